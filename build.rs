@@ -1,7 +1,5 @@
-use std::io;
-
 #[cfg(windows)]
-fn main() -> io::Result<()> {
+fn main() -> std::io::Result<()> {
     let mut res = winres::WindowsResource::new();
     res.set_icon("app_icon.ico");
     res.compile()?;
@@ -0,0 +1,30 @@
+// The vectorized frame loops against the plain ones, on one 480-sample frame:
+//
+//     cargo bench -p silentstream-core --bench simd
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use silentstream_core::audio_engine::RNNOISE_FRAME_SIZE;
+use silentstream_core::simd;
+
+fn frame() -> Vec<f32> {
+    (0..RNNOISE_FRAME_SIZE).map(|i| (i as f32 * 0.05).sin() * 0.5).collect()
+}
+
+fn scale(c: &mut Criterion) {
+    let src = frame();
+    let mut dst = vec![0.0; RNNOISE_FRAME_SIZE];
+    let mut group = c.benchmark_group("scale");
+    group.bench_function("sse2", |b| b.iter(|| simd::scale(black_box(&src), &mut dst, black_box(32768.0))));
+    group.bench_function("scalar", |b| b.iter(|| simd::scale_scalar(black_box(&src), &mut dst, black_box(32768.0))));
+    group.finish();
+}
+
+fn sum_of_squares(c: &mut Criterion) {
+    let samples = frame();
+    let mut group = c.benchmark_group("sum_of_squares");
+    group.bench_function("sse2", |b| b.iter(|| simd::sum_of_squares(black_box(&samples))));
+    group.bench_function("scalar", |b| b.iter(|| simd::sum_of_squares_scalar(black_box(&samples))));
+    group.finish();
+}
+
+criterion_group!(benches, scale, sum_of_squares);
+criterion_main!(benches);
@@ -0,0 +1,151 @@
+//! Acoustic echo cancellation: an NLMS adaptive filter that removes what the speakers
+//! play (captured via WASAPI loopback) from the microphone signal before denoising.
+use crate::audio_engine::RNNOISE_FRAME_SIZE;
+use crate::dsp::Frame;
+use std::collections::VecDeque;
+
+// Filter length: 16 ms at 48 kHz, enough to absorb the coarse delay estimate's error
+// plus early room reflections; the denoiser cleans up the rest of the tail
+const TAPS: usize = 768;
+// Echo paths longer than this (output buffering + air) aren't searched
+const MAX_DELAY_FRAMES: usize = 50;
+// Envelope history used for delay estimation: 1 s of 10 ms frames
+const ENVELOPE_FRAMES: usize = 100;
+const ESTIMATE_EVERY_FRAMES: usize = 50;
+// Reference samples kept: longest delay plus one filter span and one frame
+const HISTORY: usize = MAX_DELAY_FRAMES * RNNOISE_FRAME_SIZE + TAPS + RNNOISE_FRAME_SIZE;
+// Start the filter window this far before the estimated delay to cover estimation error
+const DELAY_MARGIN: usize = RNNOISE_FRAME_SIZE / 2;
+const STEP_SIZE: f32 = 0.3;
+// Geigel double-talk detector: mic this loud relative to the reference means someone is talking
+const DOUBLE_TALK_RATIO: f32 = 0.5;
+// Normalized envelope correlation needed before the delay estimate is trusted
+const MIN_CORRELATION: f32 = 0.5;
+const SILENCE_POWER: f32 = 1e-7;
+
+pub struct EchoCanceller {
+    // Reference at 48 kHz, oldest first; the newest frame is at the end
+    history: Vec<f32>,
+    weights: Vec<f32>,
+    mic_envelope: VecDeque<f32>,
+    ref_envelope: VecDeque<f32>,
+    frames_until_estimate: usize,
+    // Offset in samples between the newest reference sample and the start of the filter window
+    delay: usize,
+}
+
+impl EchoCanceller {
+    pub fn new() -> Self {
+        Self {
+            history: vec![0.0; HISTORY],
+            weights: vec![0.0; TAPS],
+            mic_envelope: VecDeque::with_capacity(ENVELOPE_FRAMES),
+            ref_envelope: VecDeque::with_capacity(ENVELOPE_FRAMES),
+            frames_until_estimate: ESTIMATE_EVERY_FRAMES,
+            delay: 0,
+        }
+    }
+
+    pub fn delay_ms(&self) -> f32 {
+        (self.delay + DELAY_MARGIN) as f32 * 1000.0 / 48000.0
+    }
+
+    /// `reference` is the loopback frame captured over the same period as `mic`
+    pub fn process(&mut self, mic: &Frame, reference: &Frame, out: &mut Frame) {
+        self.history.copy_within(RNNOISE_FRAME_SIZE.., 0);
+        self.history[HISTORY - RNNOISE_FRAME_SIZE..].copy_from_slice(reference);
+
+        push_bounded(&mut self.mic_envelope, energy(mic));
+        push_bounded(&mut self.ref_envelope, energy(reference));
+        self.frames_until_estimate -= 1;
+        if self.frames_until_estimate == 0 {
+            self.frames_until_estimate = ESTIMATE_EVERY_FRAMES;
+            self.update_delay();
+        }
+
+        // Window of reference samples the filter sees for the first mic sample of this frame
+        let first = HISTORY - RNNOISE_FRAME_SIZE - self.delay - TAPS + 1;
+        let window_power: f32 = self.history[first..first + TAPS].iter().map(|x| x * x).sum();
+        let ref_peak = self.history[first..first + TAPS + RNNOISE_FRAME_SIZE - 1]
+            .iter()
+            .fold(0.0f32, |m, x| m.max(x.abs()));
+        let mic_peak = mic.iter().fold(0.0f32, |m, x| m.max(x.abs()));
+
+        if ref_peak * ref_peak < SILENCE_POWER {
+            // Nothing playing, nothing to cancel
+            out.copy_from_slice(mic);
+            return;
+        }
+        let adapt = mic_peak < DOUBLE_TALK_RATIO * ref_peak;
+
+        let mut power = window_power;
+        for (n, (o, m)) in out.iter_mut().zip(mic.iter()).enumerate() {
+            let x = &self.history[first + n..first + n + TAPS];
+            let estimate: f32 = self.weights.iter().zip(x).map(|(w, x)| w * x).sum();
+            let error = m - estimate;
+            *o = error;
+
+            if adapt {
+                let gain = STEP_SIZE * error / (power + SILENCE_POWER * TAPS as f32);
+                for (w, x) in self.weights.iter_mut().zip(x) {
+                    *w += gain * x;
+                }
+            }
+            // Slide the running window power by one sample
+            let leaving = x[0];
+            let entering = self.history.get(first + n + TAPS).copied().unwrap_or(0.0);
+            power = (power - leaving * leaving + entering * entering).max(0.0);
+        }
+    }
+
+    // Cross-correlates the frame energy envelopes to find how far the mic lags the reference
+    fn update_delay(&mut self) {
+        let n = self.mic_envelope.len();
+        if n < ENVELOPE_FRAMES {
+            return;
+        }
+        let mic: Vec<f32> = normalized(self.mic_envelope.iter().copied());
+        let reference: Vec<f32> = normalized(self.ref_envelope.iter().copied());
+
+        let mut best = (0usize, 0.0f32);
+        for lag in 0..MAX_DELAY_FRAMES.min(n / 2) {
+            let overlap = n - lag;
+            let corr: f32 = (0..overlap).map(|t| mic[t + lag] * reference[t]).sum::<f32>() / overlap as f32;
+            if corr > best.1 {
+                best = (lag, corr);
+            }
+        }
+
+        let (lag, corr) = best;
+        if corr < MIN_CORRELATION {
+            return;
+        }
+        let delay = (lag * RNNOISE_FRAME_SIZE).saturating_sub(DELAY_MARGIN);
+        if delay.abs_diff(self.delay) >= RNNOISE_FRAME_SIZE {
+            log::debug!("Echo path delay re-estimated: {} ms (correlation {:.2})", lag * 10, corr);
+            self.delay = delay;
+            // The old weights belong to the previous alignment
+            self.weights.iter_mut().for_each(|w| *w = 0.0);
+        }
+    }
+}
+
+fn energy(frame: &[f32]) -> f32 {
+    frame.iter().map(|x| x * x).sum::<f32>() / frame.len() as f32
+}
+
+fn push_bounded(queue: &mut VecDeque<f32>, value: f32) {
+    if queue.len() >= ENVELOPE_FRAMES {
+        queue.pop_front();
+    }
+    queue.push_back(value);
+}
+
+// Zero mean, unit variance
+fn normalized(values: impl Iterator<Item = f32>) -> Vec<f32> {
+    let values: Vec<f32> = values.collect();
+    let mean = values.iter().sum::<f32>() / values.len() as f32;
+    let var = values.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / values.len() as f32;
+    let std = var.sqrt().max(1e-12);
+    values.iter().map(|v| (v - mean) / std).collect()
+}
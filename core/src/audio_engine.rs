@@ -0,0 +1,2215 @@
+use crate::dsp::{BoostConfig, ClickConfig, DeEsserConfig, Fade, Frame, GateConfig, LevelMeter, MusicConfig, PlosiveConfig};
+use crate::monitor;
+use crate::noise_print::{NoisePrintConfig, NoisePrintSlot};
+use crate::output_format;
+use crate::overload::{OverloadDetector, OverloadPolicy};
+use crate::pipeline::{ChainConfig, Controls, Pipeline};
+use crate::plugin_host::{PluginSlot, PluginStatus};
+use crate::replay::Recorder;
+use crate::resample::{self, FrameSource, OutputQueue, ResamplerQuality};
+use crate::settings::{ParseSettingError, Settings};
+use crate::vad_histogram::VadHistogram;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{BufferSize, SampleFormat, Stream, StreamConfig, SupportedStreamConfig};
+use ringbuf::{HeapConsumer, HeapRb};
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Constant for RNNoise frame size
+pub const RNNOISE_FRAME_SIZE: usize = 480;
+/// Capacity of the input and output ring buffers: enough for ~100ms of audio
+pub const RING_BUFFER_SIZE: usize = 8192;
+// With drop-oldest, input queued past the high-water mark is trimmed back to the low-water mark
+const INPUT_HIGH_WATER: usize = RING_BUFFER_SIZE * 3 / 4;
+const INPUT_LOW_WATER: usize = RING_BUFFER_SIZE / 4;
+// Each RNNoise frame is 10 ms at 48 kHz
+const FRAME_SECONDS: f64 = RNNOISE_FRAME_SIZE as f64 / 48000.0;
+const FRAME_MS: f32 = (FRAME_SECONDS * 1000.0) as f32;
+// Input samples at or above this magnitude count as clipped
+const CLIP_LEVEL: f32 = 0.999;
+// Fades at start and stop so the far end hears a dip rather than a click
+const FADE_IN_SECONDS: f32 = 0.01;
+const FADE_OUT_SECONDS: f32 = 0.02;
+// Longest stop() waits for the fade-out to be processed and played
+const STOP_DRAIN_TIMEOUT: Duration = Duration::from_millis(150);
+/// Frames processed per wakeup that the settings offer
+pub const BLOCK_FRAMES: [usize; 3] = [1, 2, 4];
+const MAX_BLOCK_FRAMES: usize = BLOCK_FRAMES[BLOCK_FRAMES.len() - 1];
+/// Latency caps the settings offer; 0 turns the cap off
+pub const LATENCY_CAPS_MS: [u32; 5] = [0, 100, 150, 250, 500];
+// Thread names shown in crash reports
+const PROCESSING_THREAD_NAME: &str = "audio-processing";
+const SESSION_THREAD_NAME: &str = "audio-session";
+
+/// f32 stored as its bit pattern. Relaxed ordering: readers only want a recent value.
+#[derive(Default)]
+pub struct AtomicF32(AtomicU32);
+
+impl AtomicF32 {
+    pub fn new(value: f32) -> Self {
+        Self(AtomicU32::new(value.to_bits()))
+    }
+
+    pub fn load(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn store(&self, value: f32) {
+        self.0.store(value.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// Why the engine couldn't start, or stopped, grouped by what the user can do about it.
+/// `device` is the device whose stream failed, when known.
+///
+/// ```
+/// use silentstream_core::EngineError;
+///
+/// let err = EngineError::not_found("Output device 'Speakers' not found").with_device("Speakers");
+/// assert_eq!(err.device(), Some("Speakers"));
+/// assert_eq!(err.summary(), "Device not found");
+/// assert_eq!(err.to_string(), "Device not found: Output device 'Speakers' not found");
+/// ```
+#[derive(Debug, thiserror::Error)]
+pub enum EngineError {
+    /// Not connected, disabled, or gone between listing and opening
+    #[error("Device not found: {details}")]
+    DeviceNotFound { device: Option<String>, details: String },
+    /// Another app holds it in exclusive mode
+    #[error("Device in use: {details}")]
+    DeviceInUse { device: Option<String>, details: String },
+    /// Nothing the pipeline can convert from or to
+    #[error("Audio format not supported: needs {requested}, the device offers {supported}")]
+    UnsupportedFormat { device: Option<String>, requested: String, supported: String },
+    /// cpal refused the stream for a reason not covered above
+    #[error("Could not open the audio stream: {source}")]
+    StreamBuild { device: Option<String>, source: cpal::BuildStreamError },
+    /// No resampler for the device's rate
+    #[error("Could not set up the resampler: {0}")]
+    ResamplerInit(#[from] rubato::ResamplerConstructionError),
+    /// The output stream stopped calling back without reporting an error
+    #[error("Output stopped playing")]
+    OutputStalled { device: Option<String> },
+    /// The processing thread panicked; the panic message
+    #[error("Audio processing crashed: {0}")]
+    ProcessingCrashed(String),
+    /// Anything else; the backend's message
+    #[error("Audio error: {0}")]
+    Other(String),
+}
+
+impl EngineError {
+    pub fn not_found(details: impl ToString) -> Self {
+        Self::DeviceNotFound { device: None, details: details.to_string() }
+    }
+
+    pub fn with_device(mut self, name: &str) -> Self {
+        match &mut self {
+            Self::DeviceNotFound { device, .. }
+            | Self::DeviceInUse { device, .. }
+            | Self::UnsupportedFormat { device, .. }
+            | Self::StreamBuild { device, .. }
+            | Self::OutputStalled { device } => *device = Some(name.to_string()),
+            Self::ResamplerInit(_) | Self::ProcessingCrashed(_) | Self::Other(_) => {}
+        }
+        self
+    }
+
+    pub fn device(&self) -> Option<&str> {
+        match self {
+            Self::DeviceNotFound { device, .. }
+            | Self::DeviceInUse { device, .. }
+            | Self::UnsupportedFormat { device, .. }
+            | Self::StreamBuild { device, .. }
+            | Self::OutputStalled { device } => device.as_deref(),
+            Self::ResamplerInit(_) | Self::ProcessingCrashed(_) | Self::Other(_) => None,
+        }
+    }
+
+    // A stream that cpal turned down for its parameters rather than the device
+    fn is_format_problem(&self) -> bool {
+        matches!(
+            self,
+            Self::UnsupportedFormat { .. }
+                | Self::ResamplerInit(_)
+                | Self::StreamBuild {
+                    source: cpal::BuildStreamError::StreamConfigNotSupported | cpal::BuildStreamError::InvalidArgument,
+                    ..
+                }
+        )
+    }
+
+    /// Short enough for the status line
+    pub fn summary(&self) -> &'static str {
+        match self {
+            Self::DeviceNotFound { .. } => "Device not found",
+            Self::DeviceInUse { .. } => "Device in use",
+            Self::OutputStalled { .. } => "Output stopped playing",
+            Self::ProcessingCrashed(_) => "Audio processing crashed",
+            _ if self.is_format_problem() => "Audio format not supported",
+            _ => "Audio error",
+        }
+    }
+
+    pub fn suggestion(&self) -> &'static str {
+        match self {
+            Self::DeviceNotFound { .. } if cfg!(windows) => {
+                "Check that the device is plugged in and enabled in Windows Sound settings."
+            }
+            Self::DeviceNotFound { .. } => "Check that the device is plugged in and enabled in the system's sound settings.",
+            Self::DeviceInUse { .. } if cfg!(windows) => {
+                "Close other apps using this microphone, or turn off \"Allow applications to take exclusive control\" in the device's properties."
+            }
+            Self::DeviceInUse { .. } => "Close other apps using this device.",
+            Self::OutputStalled { .. } => "The output device stopped taking audio. Start processing again, or restart the virtual cable's driver.",
+            Self::ProcessingCrashed(_) => "A crash report was saved. Start processing again; if it keeps happening, create a diagnostic report.",
+            _ if self.is_format_problem() && cfg!(windows) => {
+                "Pick another default format for the device in Windows Sound settings, or try a different device."
+            }
+            _ if self.is_format_problem() => "Pick another format for the device in the system's sound settings, or try a different device.",
+            _ => "Try restarting processing. If it keeps failing, create a diagnostic report.",
+        }
+    }
+
+    /// The underlying error's text, for the details view and bug reports
+    pub fn details(&self) -> String {
+        match self {
+            Self::DeviceNotFound { details, .. } | Self::DeviceInUse { details, .. } => details.clone(),
+            Self::UnsupportedFormat { requested, supported, .. } => format!("Needs {}, the device offers {}", requested, supported),
+            Self::StreamBuild { source, .. } => source.to_string(),
+            Self::ResamplerInit(e) => e.to_string(),
+            Self::OutputStalled { device } => format!("No output callbacks from '{}'", device.as_deref().unwrap_or("the output device")),
+            Self::ProcessingCrashed(message) | Self::Other(message) => message.clone(),
+        }
+    }
+
+    // WASAPI reports exclusive-mode conflicts only through the backend message
+    fn from_backend(err: cpal::BackendSpecificError) -> Self {
+        let text = err.description.to_lowercase();
+        if text.contains("in use") || text.contains("8889000a") {
+            Self::DeviceInUse { device: None, details: err.description }
+        } else {
+            Self::Other(err.description)
+        }
+    }
+}
+
+impl From<cpal::DevicesError> for EngineError {
+    fn from(err: cpal::DevicesError) -> Self {
+        match err {
+            cpal::DevicesError::BackendSpecific { err } => Self::from_backend(err),
+        }
+    }
+}
+
+impl From<cpal::DefaultStreamConfigError> for EngineError {
+    fn from(err: cpal::DefaultStreamConfigError) -> Self {
+        match err {
+            cpal::DefaultStreamConfigError::DeviceNotAvailable => Self::not_found(err),
+            cpal::DefaultStreamConfigError::StreamTypeNotSupported => Self::UnsupportedFormat {
+                device: None,
+                requested: "a default stream format".to_string(),
+                supported: "none".to_string(),
+            },
+            cpal::DefaultStreamConfigError::BackendSpecific { err } => Self::from_backend(err),
+        }
+    }
+}
+
+impl From<cpal::BuildStreamError> for EngineError {
+    fn from(err: cpal::BuildStreamError) -> Self {
+        match err {
+            cpal::BuildStreamError::DeviceNotAvailable => Self::not_found(err),
+            cpal::BuildStreamError::BackendSpecific { err } => match Self::from_backend(err) {
+                Self::Other(details) => Self::StreamBuild {
+                    device: None,
+                    source: cpal::BuildStreamError::BackendSpecific { err: cpal::BackendSpecificError { description: details } },
+                },
+                busy => busy,
+            },
+            source => Self::StreamBuild { device: None, source },
+        }
+    }
+}
+
+impl From<cpal::PlayStreamError> for EngineError {
+    fn from(err: cpal::PlayStreamError) -> Self {
+        match err {
+            cpal::PlayStreamError::DeviceNotAvailable => Self::not_found(err),
+            cpal::PlayStreamError::BackendSpecific { err } => Self::from_backend(err),
+        }
+    }
+}
+
+impl From<std::io::Error> for EngineError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Other(err.to_string())
+    }
+}
+
+/// Lock-free counters written from the audio callbacks and the processing thread
+#[derive(Default)]
+pub struct EngineCounters {
+    /// Output samples concealed because the processing thread had nothing ready
+    pub underruns: AtomicU64,
+    /// Input samples dropped because the input ring buffer was full, or trimmed to keep it from filling
+    pub overruns: AtomicU64,
+    /// Samples currently queued in each ring buffer
+    pub input_fill: AtomicUsize,
+    pub output_fill: AtomicUsize,
+    /// Number of successful start() calls
+    pub starts: AtomicU64,
+    /// Current echo path estimate; 0 while echo cancellation is off
+    pub echo_delay_ms: AtomicUsize,
+    /// Input frames received since the last start, and whether any of them was not exactly zero
+    pub input_samples: AtomicU64,
+    pub input_signal: AtomicBool,
+    /// Input callbacks that got a malformed buffer (partial frame) or panicked
+    pub input_errors: AtomicU64,
+    /// Frames the driver handed over in the most recent input and output callback
+    pub input_callback_frames: AtomicUsize,
+    pub output_callback_frames: AtomicUsize,
+    /// Output callbacks so far; a running engine whose count stops moving is playing into an
+    /// endpoint that no longer exists
+    pub output_callbacks: AtomicU64,
+    /// Audio waiting between the input callback and the output device, as the latency cap sees it
+    pub backlog_ms: AtomicUsize,
+    /// Processed frames dropped to bring the backlog back under the latency cap
+    pub latency_skips: AtomicU64,
+    /// Speech probability of the most recent frame that had one, and whether that frame was sent
+    pub vad_prob: AtomicF32,
+    pub gate_open: AtomicBool,
+    /// Smoothed share of real time the processing thread spends processing, and whether that
+    /// counts as overloaded (see overload)
+    pub processing_load: AtomicF32,
+    pub overloaded: AtomicBool,
+}
+
+/// What the UI, tray, toasts and diagnostics show about the engine, read in one call per frame.
+/// Every field comes from an atomic; the device names are shared with the engine, not copied.
+#[derive(Clone, Default, Debug)]
+pub struct EngineStatus {
+    pub running: bool,
+    pub bypassed: bool,
+    pub system_muted: bool,
+    pub idle: bool,
+    pub music_passthrough: bool,
+    /// Writing a replay recording (see replay)
+    pub replay_recording: bool,
+    /// The processing thread can't keep up; with OverloadPolicy::AutoBypass the mic goes out
+    /// unprocessed
+    pub overloaded: bool,
+    pub processing_load: f32,
+    /// None while stopped
+    pub input_device: Option<Arc<str>>,
+    pub output_device: Option<Arc<str>>,
+    pub input_sample_rate: u32,
+    pub output_sample_rate: u32,
+    /// Output meters, as in current_volume and peak_level
+    pub rms: f32,
+    pub peak: f32,
+    pub vad_prob: f32,
+    pub gate_open: bool,
+    pub boost_saturation: f32,
+    pub underruns: u64,
+    pub overruns: u64,
+    pub input_errors: u64,
+    pub latency_skips: u64,
+    pub input_fill: usize,
+    pub output_fill: usize,
+    pub backlog_ms: usize,
+    pub echo_delay_ms: usize,
+    /// Processing delay plus what's queued in both ring buffers; 0 while stopped
+    pub latency_ms: f32,
+}
+
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct SessionStats {
+    pub frames_forwarded: u64,
+    pub frames_gated: u64,
+    pub frames_muted: u64,
+    pub clip_events: u64,
+    pub vad_sum: f64,
+    pub vad_frames: u64,
+}
+
+impl SessionStats {
+    pub fn record(&mut self, input: &[f32], vad_prob: Option<f32>, forwarded: bool, muted: bool, clipping: &mut bool) {
+        if muted {
+            self.frames_muted += 1;
+        } else if forwarded {
+            self.frames_forwarded += 1;
+        } else {
+            self.frames_gated += 1;
+        }
+        if let Some(p) = vad_prob {
+            self.vad_sum += p as f64;
+            self.vad_frames += 1;
+        }
+        // Count clipping onsets rather than clipped frames
+        let clipped = input.iter().any(|s| s.abs() >= CLIP_LEVEL);
+        if clipped && !*clipping {
+            self.clip_events += 1;
+        }
+        *clipping = clipped;
+    }
+
+    pub fn total_frames(&self) -> u64 {
+        self.frames_forwarded + self.frames_gated + self.frames_muted
+    }
+
+    pub fn talk_seconds(&self) -> f64 {
+        self.frames_forwarded as f64 * FRAME_SECONDS
+    }
+
+    pub fn muted_seconds(&self) -> f64 {
+        self.frames_muted as f64 * FRAME_SECONDS
+    }
+
+    pub fn gated_percent(&self) -> f64 {
+        let total = self.total_frames();
+        if total == 0 { 0.0 } else { self.frames_gated as f64 * 100.0 / total as f64 }
+    }
+
+    pub fn average_vad(&self) -> f64 {
+        if self.vad_frames == 0 { 0.0 } else { self.vad_sum / self.vad_frames as f64 }
+    }
+
+    pub fn add(&self, other: &SessionStats) -> SessionStats {
+        SessionStats {
+            frames_forwarded: self.frames_forwarded + other.frames_forwarded,
+            frames_gated: self.frames_gated + other.frames_gated,
+            frames_muted: self.frames_muted + other.frames_muted,
+            clip_events: self.clip_events + other.clip_events,
+            vad_sum: self.vad_sum + other.vad_sum,
+            vad_frames: self.vad_frames + other.vad_frames,
+        }
+    }
+
+    /// Counters accumulated since `earlier` was taken from the same running total
+    pub fn since(&self, earlier: &SessionStats) -> SessionStats {
+        SessionStats {
+            frames_forwarded: self.frames_forwarded.saturating_sub(earlier.frames_forwarded),
+            frames_gated: self.frames_gated.saturating_sub(earlier.frames_gated),
+            frames_muted: self.frames_muted.saturating_sub(earlier.frames_muted),
+            clip_events: self.clip_events.saturating_sub(earlier.clip_events),
+            vad_sum: (self.vad_sum - earlier.vad_sum).max(0.0),
+            vad_frames: self.vad_frames.saturating_sub(earlier.vad_frames),
+        }
+    }
+}
+
+/// Which channel of a multichannel input device carries the mic
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum InputChannel {
+    Mix,
+    /// 0-based
+    Channel(usize),
+}
+
+impl InputChannel {
+    pub fn as_str(&self) -> String {
+        match self {
+            InputChannel::Mix => "mix".to_string(),
+            InputChannel::Channel(c) => c.to_string(),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            InputChannel::Mix => "Mix all".to_string(),
+            InputChannel::Channel(c) => format!("Channel {}", c + 1),
+        }
+    }
+}
+
+impl FromStr for InputChannel {
+    type Err = ParseSettingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "mix" {
+            Ok(InputChannel::Mix)
+        } else {
+            s.parse().map(InputChannel::Channel).map_err(|_| ParseSettingError::new("input channel", s))
+        }
+    }
+}
+
+impl Default for InputChannel {
+    fn default() -> Self {
+        InputChannel::Channel(0)
+    }
+}
+
+/// What gives way when the input ring buffer fills up
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OverflowPolicy {
+    /// Discard the oldest queued input so latency stays bounded
+    DropOldest,
+    /// Discard incoming samples; what plays afterwards is late
+    DropNewest,
+}
+
+impl OverflowPolicy {
+    pub const ALL: [OverflowPolicy; 2] = [OverflowPolicy::DropOldest, OverflowPolicy::DropNewest];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OverflowPolicy::DropOldest => "drop_oldest",
+            OverflowPolicy::DropNewest => "drop_newest",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            OverflowPolicy::DropOldest => "Drop oldest",
+            OverflowPolicy::DropNewest => "Drop newest",
+        }
+    }
+}
+
+impl FromStr for OverflowPolicy {
+    type Err = ParseSettingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.iter().copied().find(|p| p.as_str() == s).ok_or_else(|| ParseSettingError::new("overflow policy", s))
+    }
+}
+
+/// A secondary capture device. With `loopback` it names an output device whose playback is
+/// captured; an empty name means the default device.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct CaptureSource {
+    pub device: String,
+    pub loopback: bool,
+}
+
+/// Parameters of the streams opened by the last successful start()
+#[derive(Clone)]
+pub struct StreamInfo {
+    pub host: String,
+    pub input_device: String,
+    pub input_sample_rate: u32,
+    pub input_channels: usize,
+    pub input_channel: InputChannel,
+    pub input_buffer: BufferSize,
+    pub output_device: String,
+    pub output_sample_rate: u32,
+    pub output_channels: usize,
+    pub output_sample_format: SampleFormat,
+    pub output_buffer: BufferSize,
+    /// Delay of the resampler converting 48 kHz to the output rate, if any
+    pub output_resampler_delay_ms: f32,
+    /// What the output path does to fit the device, in the order applied; empty when the
+    /// device takes mono-compatible f32 at 48 kHz
+    pub output_conversions: Vec<String>,
+    /// rubato implementation converting the input to 48 kHz, if any, and its delay
+    pub resampler: Option<&'static str>,
+    pub resampler_delay_ms: f32,
+    pub block_frames: usize,
+    /// Output device used as the echo cancellation reference
+    pub echo_reference: Option<String>,
+    /// Second output playing the processed audio, and its sample rate
+    pub monitor: Option<(String, u32)>,
+    pub mix_input: Option<String>,
+}
+
+impl StreamInfo {
+    /// Delay added by the processing thread: waiting for a full block plus the resampler
+    pub fn processing_delay_ms(&self) -> f32 {
+        self.block_frames as f32 * FRAME_SECONDS as f32 * 1000.0 + self.resampler_delay_ms + self.output_resampler_delay_ms
+    }
+}
+
+/// The real-time engine: capture from an input device, run the chain, play to an output device.
+///
+/// Settings can be changed at any time (see [`Settings`]); the meters, counters and flags
+/// below are shared with the processing thread and are there to be read.
+///
+/// ```no_run
+/// use silentstream_core::{input_device_names, output_device_names, AudioEngine};
+///
+/// let inputs = input_device_names();
+/// let outputs = output_device_names();
+/// println!("inputs: {:?}\noutputs: {:?}", inputs, outputs);
+///
+/// let mut engine = AudioEngine::new();
+/// engine.set_threshold(0.7);
+/// let output = outputs.iter().position(|name| name.contains("CABLE")).unwrap_or(0);
+/// if let Err(e) = engine.start(0, output) {
+///     eprintln!("{}: {}", e.summary(), e.suggestion());
+///     return;
+/// }
+/// // Tighter while it runs; picked up on the next block
+/// engine.set_threshold(0.85);
+/// std::thread::sleep(std::time::Duration::from_secs(10));
+/// println!("level {:.2}", engine.status().rms);
+/// engine.stop_and_wait();
+/// ```
+// The streams and the processing thread live on the session thread (see Streams), so the
+// engine itself can be shared with other threads
+pub struct AudioEngine {
+    session: Option<Session>,
+    starting: Option<PendingStart>,
+    // Last session told to stop, still fading out and closing; the next one waits for it
+    closing: Option<thread::JoinHandle<()>>,
+    /// Read-only outside the engine
+    pub is_running: Arc<AtomicBool>,
+    // stop() asks the processing thread to fade out; the thread reports when it has
+    fade_out_requested: Arc<AtomicBool>,
+    faded_out: Arc<AtomicBool>,
+    // Live settings, shared with the processing thread and read once per block; see Settings
+    vad_threshold: Arc<Mutex<f32>>,
+    gate: Arc<Mutex<GateConfig>>,
+    overload_policy: Arc<Mutex<OverloadPolicy>>,
+    bypass: Arc<AtomicBool>,
+    strong_suppression: Arc<AtomicBool>,
+    // Applied after the denoiser; a disabled de-esser leaves samples untouched
+    de_esser: Arc<Mutex<DeEsserConfig>>,
+    // Applied before the denoiser so pops don't reach the VAD either
+    plosive: Arc<Mutex<PlosiveConfig>>,
+    boost: Arc<Mutex<BoostConfig>>,
+    /// Share of the last second or so in which the boost's soft clipper engaged
+    pub boost_saturation: Arc<AtomicF32>,
+    click: Arc<Mutex<ClickConfig>>,
+    music: Arc<Mutex<MusicConfig>>,
+    chain: Arc<Mutex<ChainConfig>>,
+    /// Third-party effect plugin, loaded by the processing thread
+    pub plugin: Arc<PluginSlot>,
+    noise_print: Arc<Mutex<NoisePrintConfig>>,
+    /// The learned print, or a request to learn one
+    pub noise_print_slot: Arc<NoisePrintSlot>,
+    /// Set by the processing thread while music detection has switched to passthrough
+    pub music_passthrough: Arc<AtomicBool>,
+    /// Paused because nothing records from the output: the streams keep running but the
+    /// denoiser is skipped and silence is sent. Cleared by start().
+    pub idle: Arc<AtomicBool>,
+    /// Output level meters (RMS and true peak) with ballistics applied; they decay to 0 when gated
+    pub current_volume: Arc<AtomicF32>,
+    pub peak_level: Arc<AtomicF32>,
+    /// Mirrors the Windows mute switch of the capture endpoint; read from the output callback
+    pub system_muted: Arc<AtomicBool>,
+    /// Counters since the app started; survives engine restarts
+    pub stats: Arc<Mutex<SessionStats>>,
+    /// Recent VAD probabilities; survives engine restarts, cleared from the UI
+    pub vad_histogram: Arc<Mutex<VadHistogram>>,
+    pub counters: Arc<EngineCounters>,
+    pub stream_info: Option<StreamInfo>,
+    /// Set when the processing thread panicked; taken by the UI to report the failure
+    pub fault: Arc<Mutex<Option<String>>>,
+    /// Name of the input or output device whose stream reported it's gone (unplugged, disabled,
+    /// driver restarted); taken by the supervisor
+    pub stream_lost: Arc<Mutex<Option<String>>>,
+    // Queue for a reopened output stream, picked up by the processing thread
+    output_swap: Arc<Mutex<Option<OutputQueue>>>,
+    // A replay recording to start (Some(Some)) or stop (Some(None)), picked up by the
+    // processing thread
+    replay_swap: Arc<Mutex<Option<Option<Recorder>>>>,
+    /// Set while a replay recording is being written; cleared when processing stops
+    pub replay_recording: Arc<AtomicBool>,
+    latency_cap_ms: Arc<AtomicU32>,
+    monitor_gain: Arc<AtomicF32>,
+    mix_gain: Arc<AtomicF32>,
+    // Session settings, read by start()
+    echo_reference: Option<String>,
+    resampler_quality: ResamplerQuality,
+    block_frames: usize,
+    input_channel: InputChannel,
+    overflow_policy: OverflowPolicy,
+    monitor_device: Option<String>,
+    mix_source: Option<CaptureSource>,
+    // Names of the running streams' devices, handed out by status()
+    status_devices: Option<(Arc<str>, Arc<str>)>,
+}
+
+impl AudioEngine {
+    pub fn new() -> Self {
+        let settings = Settings::default();
+        Self {
+            session: None,
+            starting: None,
+            closing: None,
+            is_running: Arc::new(AtomicBool::new(false)),
+            fade_out_requested: Arc::new(AtomicBool::new(false)),
+            faded_out: Arc::new(AtomicBool::new(false)),
+            vad_threshold: Arc::new(Mutex::new(settings.threshold)),
+            gate: Arc::new(Mutex::new(settings.gate)),
+            overload_policy: Arc::new(Mutex::new(settings.overload_policy)),
+            bypass: Arc::new(AtomicBool::new(settings.bypass)),
+            strong_suppression: Arc::new(AtomicBool::new(settings.strong_suppression)),
+            de_esser: Arc::new(Mutex::new(settings.de_esser)),
+            plosive: Arc::new(Mutex::new(settings.plosive)),
+            boost: Arc::new(Mutex::new(settings.boost)),
+            boost_saturation: Arc::new(AtomicF32::new(0.0)),
+            click: Arc::new(Mutex::new(settings.click)),
+            music: Arc::new(Mutex::new(settings.music)),
+            chain: Arc::new(Mutex::new(settings.chain)),
+            plugin: Arc::new(PluginSlot::default()),
+            noise_print: Arc::new(Mutex::new(settings.noise_print)),
+            noise_print_slot: Arc::new(NoisePrintSlot::default()),
+            music_passthrough: Arc::new(AtomicBool::new(false)),
+            idle: Arc::new(AtomicBool::new(false)),
+            current_volume: Arc::new(AtomicF32::new(0.0)),
+            peak_level: Arc::new(AtomicF32::new(0.0)),
+            system_muted: Arc::new(AtomicBool::new(false)),
+            stats: Arc::new(Mutex::new(SessionStats::default())),
+            vad_histogram: Arc::new(Mutex::new(VadHistogram::default())),
+            counters: Arc::new(EngineCounters::default()),
+            stream_info: None,
+            fault: Arc::new(Mutex::new(None)),
+            stream_lost: Arc::new(Mutex::new(None)),
+            output_swap: Arc::new(Mutex::new(None)),
+            replay_swap: Arc::new(Mutex::new(None)),
+            replay_recording: Arc::new(AtomicBool::new(false)),
+            latency_cap_ms: Arc::new(AtomicU32::new(settings.latency_cap_ms)),
+            monitor_gain: Arc::new(AtomicF32::new(settings.monitor_gain)),
+            mix_gain: Arc::new(AtomicF32::new(settings.mix_gain)),
+            echo_reference: settings.echo_reference,
+            resampler_quality: settings.resampler_quality,
+            block_frames: settings.block_frames,
+            input_channel: settings.input_channel,
+            overflow_policy: settings.overflow_policy,
+            monitor_device: settings.monitor_device,
+            mix_source: settings.mix_source,
+            status_devices: None,
+        }
+    }
+
+    /// The current settings.
+    pub fn settings(&self) -> Settings {
+        Settings {
+            threshold: self.threshold(),
+            gate: *self.gate.lock().unwrap(),
+            overload_policy: *self.overload_policy.lock().unwrap(),
+            bypass: self.bypass.load(Ordering::Relaxed),
+            strong_suppression: self.strong_suppression.load(Ordering::Relaxed),
+            de_esser: *self.de_esser.lock().unwrap(),
+            plosive: *self.plosive.lock().unwrap(),
+            boost: *self.boost.lock().unwrap(),
+            click: *self.click.lock().unwrap(),
+            music: *self.music.lock().unwrap(),
+            chain: *self.chain.lock().unwrap(),
+            noise_print: *self.noise_print.lock().unwrap(),
+            latency_cap_ms: self.latency_cap_ms.load(Ordering::Relaxed),
+            monitor_gain: self.monitor_gain.load(),
+            mix_gain: self.mix_gain.load(),
+            echo_reference: self.echo_reference.clone(),
+            resampler_quality: self.resampler_quality,
+            block_frames: self.block_frames,
+            input_channel: self.input_channel,
+            overflow_policy: self.overflow_policy,
+            monitor_device: self.monitor_device.clone(),
+            mix_source: self.mix_source.clone(),
+        }
+    }
+
+    /// Applies every setting: the live ones from the next block, the session ones at the next
+    /// start.
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        self.set_threshold(settings.threshold);
+        self.set_gate(settings.gate);
+        self.set_overload_policy(settings.overload_policy);
+        self.set_bypass(settings.bypass);
+        self.set_strong_suppression(settings.strong_suppression);
+        self.set_de_esser(settings.de_esser);
+        self.set_plosive(settings.plosive);
+        self.set_boost(settings.boost);
+        self.set_click(settings.click);
+        self.set_music(settings.music);
+        self.set_chain(settings.chain);
+        self.set_noise_print(settings.noise_print);
+        self.set_latency_cap_ms(settings.latency_cap_ms);
+        self.set_monitor_gain(settings.monitor_gain);
+        self.set_mix_gain(settings.mix_gain);
+        self.echo_reference = settings.echo_reference.clone();
+        self.resampler_quality = settings.resampler_quality;
+        self.block_frames = settings.block_frames;
+        self.input_channel = settings.input_channel;
+        self.overflow_policy = settings.overflow_policy;
+        self.monitor_device = settings.monitor_device.clone();
+        self.mix_source = settings.mix_source.clone();
+    }
+
+    /// VAD probability (0.0..1.0) below which the gate closes.
+    pub fn threshold(&self) -> f32 {
+        *self.vad_threshold.lock().unwrap()
+    }
+
+    /// Takes effect on the next block, running or not.
+    pub fn set_threshold(&self, threshold: f32) {
+        *self.vad_threshold.lock().unwrap() = threshold.clamp(0.0, 1.0);
+    }
+
+    pub fn set_gate(&self, gate: GateConfig) {
+        *self.gate.lock().unwrap() = gate;
+    }
+
+    pub fn set_overload_policy(&self, policy: OverloadPolicy) {
+        *self.overload_policy.lock().unwrap() = policy;
+    }
+
+    pub fn set_bypass(&self, bypass: bool) {
+        self.bypass.store(bypass, Ordering::Relaxed);
+    }
+
+    /// The bypass switch, for threads that report it; change it with set_bypass().
+    pub fn bypass_flag(&self) -> Arc<AtomicBool> {
+        self.bypass.clone()
+    }
+
+    pub fn set_strong_suppression(&self, strong: bool) {
+        self.strong_suppression.store(strong, Ordering::Relaxed);
+    }
+
+    pub fn set_de_esser(&self, config: DeEsserConfig) {
+        *self.de_esser.lock().unwrap() = config;
+    }
+
+    pub fn set_plosive(&self, config: PlosiveConfig) {
+        *self.plosive.lock().unwrap() = config;
+    }
+
+    pub fn set_boost(&self, config: BoostConfig) {
+        *self.boost.lock().unwrap() = config;
+    }
+
+    pub fn set_click(&self, config: ClickConfig) {
+        *self.click.lock().unwrap() = config;
+    }
+
+    pub fn set_music(&self, config: MusicConfig) {
+        *self.music.lock().unwrap() = config;
+    }
+
+    pub fn set_chain(&self, chain: ChainConfig) {
+        *self.chain.lock().unwrap() = chain;
+    }
+
+    pub fn set_noise_print(&self, config: NoisePrintConfig) {
+        *self.noise_print.lock().unwrap() = config;
+    }
+
+    pub fn set_latency_cap_ms(&self, cap_ms: u32) {
+        self.latency_cap_ms.store(cap_ms, Ordering::Relaxed);
+    }
+
+    pub fn set_monitor_gain(&self, gain: f32) {
+        self.monitor_gain.store(gain);
+    }
+
+    pub fn set_mix_gain(&self, gain: f32) {
+        self.mix_gain.store(gain);
+    }
+
+    /// Read at the next start.
+    pub fn set_echo_reference(&mut self, device: Option<String>) {
+        self.echo_reference = device;
+    }
+
+    /// Read at the next start.
+    pub fn set_resampler_quality(&mut self, quality: ResamplerQuality) {
+        self.resampler_quality = quality;
+    }
+
+    /// Read at the next start.
+    pub fn set_block_frames(&mut self, frames: usize) {
+        self.block_frames = frames;
+    }
+
+    /// Read at the next start.
+    pub fn set_input_channel(&mut self, channel: InputChannel) {
+        self.input_channel = channel;
+    }
+
+    /// Read at the next start.
+    pub fn set_overflow_policy(&mut self, policy: OverflowPolicy) {
+        self.overflow_policy = policy;
+    }
+
+    /// Read at the next start.
+    pub fn set_monitor_device(&mut self, device: Option<String>) {
+        self.monitor_device = device;
+    }
+
+    /// Read at the next start.
+    pub fn set_mix_source(&mut self, source: Option<CaptureSource>) {
+        self.mix_source = source;
+    }
+
+    /// A snapshot of the live state; no locks and no allocation
+    pub fn status(&self) -> EngineStatus {
+        let counters = &self.counters;
+        let (input_fill, output_fill) = (counters.input_fill.load(Ordering::Relaxed), counters.output_fill.load(Ordering::Relaxed));
+        let (input_sample_rate, output_sample_rate, latency_ms) = match &self.stream_info {
+            Some(info) => {
+                let queued_ms = input_fill as f32 * 1000.0 / info.input_sample_rate as f32
+                    + output_fill as f32 * 1000.0 / info.output_sample_rate as f32;
+                (info.input_sample_rate, info.output_sample_rate, info.processing_delay_ms() + queued_ms)
+            }
+            None => (0, 0, 0.0),
+        };
+        EngineStatus {
+            running: self.is_running.load(Ordering::Relaxed) && self.stream_info.is_some(),
+            bypassed: self.bypass.load(Ordering::Relaxed),
+            system_muted: self.system_muted.load(Ordering::Relaxed),
+            idle: self.idle.load(Ordering::Relaxed),
+            music_passthrough: self.music_passthrough.load(Ordering::Relaxed),
+            replay_recording: self.replay_recording.load(Ordering::Relaxed),
+            overloaded: counters.overloaded.load(Ordering::Relaxed),
+            processing_load: counters.processing_load.load(),
+            input_device: self.status_devices.as_ref().map(|(input, _)| input.clone()),
+            output_device: self.status_devices.as_ref().map(|(_, output)| output.clone()),
+            input_sample_rate,
+            output_sample_rate,
+            rms: self.current_volume.load(),
+            peak: self.peak_level.load(),
+            vad_prob: counters.vad_prob.load(),
+            gate_open: counters.gate_open.load(Ordering::Relaxed),
+            boost_saturation: self.boost_saturation.load(),
+            underruns: counters.underruns.load(Ordering::Relaxed),
+            overruns: counters.overruns.load(Ordering::Relaxed),
+            input_errors: counters.input_errors.load(Ordering::Relaxed),
+            latency_skips: counters.latency_skips.load(Ordering::Relaxed),
+            input_fill,
+            output_fill,
+            backlog_ms: counters.backlog_ms.load(Ordering::Relaxed),
+            echo_delay_ms: counters.echo_delay_ms.load(Ordering::Relaxed),
+            latency_ms,
+        }
+    }
+
+    pub fn get_input_devices(&self) -> Vec<String> {
+        input_device_names()
+    }
+
+    pub fn get_output_devices(&self) -> Vec<String> {
+        output_device_names()
+    }
+
+    /// Channel count of the input device's default format, 0 if it can't be queried
+    pub fn input_channel_count(&self, device_name: &str) -> usize {
+        let host = cpal::default_host();
+        host.input_devices()
+            .ok()
+            .and_then(|mut devices| devices.find(|d| d.name().map(|n| n == device_name).unwrap_or(false)))
+            .and_then(|d| d.default_input_config().ok())
+            .map(|c| c.channels() as usize)
+            .unwrap_or(0)
+    }
+
+    /// Blocks until the streams are open; start_async() and poll_start() don't
+    pub fn start(&mut self, input_device_index: usize, output_device_index: usize) -> Result<(), EngineError> {
+        self.start_async(input_device_index, output_device_index);
+        let Some(pending) = self.starting.take() else { return Ok(()) };
+        let result = pending.result.recv().unwrap_or_else(|_| Err(session_gone()));
+        self.finish_start(pending.session, result)
+    }
+
+    /// Opens the streams on a new session thread; poll_start() reports how it went. Stops
+    /// whatever is running or starting first, and opens nothing until that has closed.
+    pub fn start_async(&mut self, input_device_index: usize, output_device_index: usize) {
+        self.stop();
+        let previous = self.closing.take();
+        self.starting = Some(Session::spawn(self.detached(), input_device_index, output_device_index, previous));
+    }
+
+    pub fn is_starting(&self) -> bool {
+        self.starting.is_some()
+    }
+
+    /// None while the start is still under way
+    pub fn poll_start(&mut self) -> Option<Result<(), EngineError>> {
+        let result = match self.starting.as_ref()?.result.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return None,
+            Err(mpsc::TryRecvError::Disconnected) => Err(session_gone()),
+        };
+        let pending = self.starting.take()?;
+        Some(self.finish_start(pending.session, result))
+    }
+
+    fn finish_start(&mut self, session: Session, result: Result<StreamInfo, EngineError>) -> Result<(), EngineError> {
+        match result {
+            Ok(info) => {
+                self.status_devices = Some((Arc::from(info.input_device.as_str()), Arc::from(info.output_device.as_str())));
+                self.stream_info = Some(info);
+                self.session = Some(session);
+                Ok(())
+            }
+            Err(e) => {
+                self.closing = Some(session.stop());
+                Err(e)
+            }
+        }
+    }
+
+    // Copy for a session thread: shares every control and counter, owns nothing yet
+    fn detached(&self) -> Self {
+        Self {
+            session: None,
+            starting: None,
+            closing: None,
+            is_running: self.is_running.clone(),
+            fade_out_requested: self.fade_out_requested.clone(),
+            faded_out: self.faded_out.clone(),
+            vad_threshold: self.vad_threshold.clone(),
+            gate: self.gate.clone(),
+            overload_policy: self.overload_policy.clone(),
+            bypass: self.bypass.clone(),
+            strong_suppression: self.strong_suppression.clone(),
+            de_esser: self.de_esser.clone(),
+            plosive: self.plosive.clone(),
+            boost: self.boost.clone(),
+            boost_saturation: self.boost_saturation.clone(),
+            click: self.click.clone(),
+            music: self.music.clone(),
+            chain: self.chain.clone(),
+            plugin: self.plugin.clone(),
+            noise_print: self.noise_print.clone(),
+            noise_print_slot: self.noise_print_slot.clone(),
+            music_passthrough: self.music_passthrough.clone(),
+            idle: self.idle.clone(),
+            current_volume: self.current_volume.clone(),
+            peak_level: self.peak_level.clone(),
+            system_muted: self.system_muted.clone(),
+            stats: self.stats.clone(),
+            vad_histogram: self.vad_histogram.clone(),
+            counters: self.counters.clone(),
+            stream_info: None,
+            fault: self.fault.clone(),
+            stream_lost: self.stream_lost.clone(),
+            output_swap: self.output_swap.clone(),
+            replay_swap: self.replay_swap.clone(),
+            replay_recording: self.replay_recording.clone(),
+            echo_reference: self.echo_reference.clone(),
+            resampler_quality: self.resampler_quality,
+            block_frames: self.block_frames,
+            input_channel: self.input_channel,
+            overflow_policy: self.overflow_policy,
+            latency_cap_ms: self.latency_cap_ms.clone(),
+            monitor_device: self.monitor_device.clone(),
+            monitor_gain: self.monitor_gain.clone(),
+            mix_source: self.mix_source.clone(),
+            mix_gain: self.mix_gain.clone(),
+            status_devices: None,
+        }
+    }
+
+    // Runs on the session thread
+    fn open(&mut self, input_device_index: usize, output_device_index: usize) -> Result<Streams, EngineError> {
+        let host = cpal::default_host();
+        let input_devices: Vec<_> = host.input_devices()?.collect();
+        let output_devices: Vec<_> = host.output_devices()?.collect();
+
+        // Basic selection logic
+        let input_device = input_devices
+            .get(input_device_index)
+            .ok_or_else(|| EngineError::not_found("Invalid input device index"))?;
+        let output_device = output_devices
+            .get(output_device_index)
+            .ok_or_else(|| EngineError::not_found("Invalid output device index"))?;
+
+        let input_name = input_device.name().unwrap_or_default();
+        let output_name = output_device.name().unwrap_or_default();
+        if let Ok(mut lost) = self.stream_lost.lock() {
+            *lost = None;
+        }
+        if let Ok(mut swap) = self.output_swap.lock() {
+            *swap = None;
+        }
+        if let Ok(mut swap) = self.replay_swap.lock() {
+            *swap = None;
+        }
+        self.replay_recording.store(false, Ordering::Relaxed);
+
+        // Standard logic: Input -> RingBuffer -> Processing Thread -> RingBuffer -> Output
+        let rb_in = HeapRb::<f32>::new(RING_BUFFER_SIZE);
+        let (mut in_prod, mut in_cons) = rb_in.split();
+        
+
+        // Configure Input Stream
+        let input_config: StreamConfig = input_device.default_input_config().map_err(|e| EngineError::from(e).with_device(&input_name))?.into();
+        let input_channels = input_config.channels as usize;
+        if input_channels == 0 {
+            return Err(EngineError::UnsupportedFormat {
+                device: Some(input_name),
+                requested: "at least one input channel".to_string(),
+                supported: "0 channels".to_string(),
+            });
+        }
+        
+        let input_sample_rate = input_config.sample_rate.0;
+        let input_counters = self.counters.clone();
+        // The format may have changed since the channel list was shown
+        let input_channel = match self.input_channel {
+            InputChannel::Channel(c) if c >= input_channels => {
+                log::warn!("Input channel {} not available ({} channels); using channel 1", c + 1, input_channels);
+                InputChannel::Channel(0)
+            }
+            other => other,
+        };
+        
+        self.counters.input_samples.store(0, Ordering::Relaxed);
+        self.counters.input_signal.store(false, Ordering::Relaxed);
+        self.counters.input_callback_frames.store(0, Ordering::Relaxed);
+        self.idle.store(false, Ordering::Relaxed);
+        self.counters.output_callback_frames.store(0, Ordering::Relaxed);
+
+        // Input Callback. A panic here would silently kill the stream, so it's caught and
+        // counted; after one the callback only counts, since the cause is likely to repeat
+        // and each panic writes a crash report.
+        let mut input_failed = false;
+        let input_stream = input_device.build_input_stream(
+            &input_config,
+            move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                if input_failed {
+                    input_counters.input_errors.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+                let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                    let mut dropped = 0;
+                    let mut signal = false;
+                    let mut frames = 0;
+                    let leftover = deinterleave(data, input_channels, input_channel, |sample| {
+                        frames += 1;
+                        signal |= sample != 0.0;
+                        if in_prod.push(sample).is_err() {
+                            dropped += 1;
+                        }
+                    });
+                    if dropped > 0 {
+                        input_counters.overruns.fetch_add(dropped, Ordering::Relaxed);
+                    }
+                    input_counters.input_samples.fetch_add(frames, Ordering::Relaxed);
+                    input_counters.input_callback_frames.store(data.len() / input_channels, Ordering::Relaxed);
+                    if signal {
+                        input_counters.input_signal.store(true, Ordering::Relaxed);
+                    }
+                    leftover
+                }));
+                match result {
+                    Ok(0) => {}
+                    Ok(_) => {
+                        input_counters.input_errors.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Err(_) => {
+                        input_failed = true;
+                        input_counters.input_errors.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            },
+            stream_error_handler("Input", &input_name, &self.stream_lost),
+            None
+        )
+        .map_err(|e| EngineError::from(e).with_device(&input_name))?;
+
+        // Output Callback. Devices that won't take f32 at 48 kHz get the closest format they
+        // offer; the processing thread resamples and the callback converts.
+        let OutputPath { stream: output_stream, queue: mut output_queue, format: output_format } =
+            self.open_output(output_device, &output_name)?;
+        let output_config: StreamConfig = output_format.config();
+        let output_channels = output_config.channels as usize;
+        let output_conversions = output_format::conversions(
+            &output_format,
+            output_queue.is_resampling().then(|| (self.resampler_quality.implementation(), output_queue.delay_ms())),
+        );
+        let output_resampler_delay_ms = output_queue.delay_ms();
+
+        let target_sample_rate = 48000;
+        let mut resampler = if input_sample_rate != target_sample_rate {
+            Some(resample::build(self.resampler_quality, input_sample_rate, target_sample_rate, RNNOISE_FRAME_SIZE)?)
+        } else {
+            None
+        };
+        let block_frames = self.block_frames.clamp(1, *BLOCK_FRAMES.last().unwrap());
+        let overflow_policy = self.overflow_policy;
+        let resampler_name = resampler.as_ref().map(|_| self.resampler_quality.implementation());
+        let resampler_delay_ms = resampler.as_ref().map_or(0.0, |r| r.output_delay() as f32 * 1000.0 / target_sample_rate as f32);
+
+        // Echo cancellation is best effort: without a reference the engine runs as before
+        let (reference_stream, mut reference, reference_name) = match self.echo_reference.as_deref() {
+            Some(name) => match open_capture(
+                &host,
+                &CaptureSource { device: name.to_string(), loopback: true },
+                "Echo reference",
+                self.resampler_quality,
+            ) {
+                Ok((stream, source, device_name)) => (Some(stream), Some(source), Some(device_name)),
+                Err(e) => {
+                    log::warn!("Echo cancellation unavailable: {}", e);
+                    (None, None, None)
+                }
+            },
+            None => (None, None, None),
+        };
+
+        let (mix_stream, mut mix, mix_name) = match self.mix_source.as_ref() {
+            Some(source) => match open_capture(&host, source, "Mix input", self.resampler_quality) {
+                Ok((stream, source, device_name)) => (Some(stream), Some(source), Some(device_name)),
+                Err(e) => {
+                    log::warn!("Mix input unavailable: {}", e);
+                    (None, None, None)
+                }
+            },
+            None => (None, None, None),
+        };
+
+        // A monitor that fails to open doesn't stop the main path
+        let (monitor_stream, mut monitor_tap, monitor_info) = match self.monitor_device.as_deref() {
+            Some(name) => match monitor::open(&host, name, self.monitor_gain.clone(), self.resampler_quality) {
+                Ok(m) => (Some(m.stream), Some(m.tap), Some((m.device_name, m.sample_rate))),
+                Err(e) => {
+                    log::warn!("Monitor output unavailable: {}", e);
+                    (None, None, None)
+                }
+            },
+            None => (None, None, None),
+        };
+
+        // Set flag before spawning so the thread's while-loop doesn't exit immediately
+        self.is_running.store(true, Ordering::Relaxed);
+        self.fade_out_requested.store(false, Ordering::Relaxed);
+        self.faded_out.store(false, Ordering::Relaxed);
+
+        // Processing Thread
+        let is_running_clone = self.is_running.clone();
+        let vad_threshold_clone = self.vad_threshold.clone();
+        let gate_clone = self.gate.clone();
+        let overload_policy_clone = self.overload_policy.clone();
+        let bypass_clone = self.bypass.clone();
+        let strong_clone = self.strong_suppression.clone();
+        let de_esser_clone = self.de_esser.clone();
+        let plosive_clone = self.plosive.clone();
+        let boost_clone = self.boost.clone();
+        let boost_saturation_clone = self.boost_saturation.clone();
+        let click_clone = self.click.clone();
+        let music_clone = self.music.clone();
+        let chain_clone = self.chain.clone();
+        let plugin_clone = self.plugin.clone();
+        let noise_print_clone = self.noise_print.clone();
+        let noise_print_slot_clone = self.noise_print_slot.clone();
+        let music_passthrough_clone = self.music_passthrough.clone();
+        let idle_clone = self.idle.clone();
+        let current_volume_clone = self.current_volume.clone();
+        let mix_gain_clone = self.mix_gain.clone();
+        let fade_out_clone = self.fade_out_requested.clone();
+        let faded_out_clone = self.faded_out.clone();
+        let peak_level_clone = self.peak_level.clone();
+        let system_muted_clone = self.system_muted.clone();
+        let stats_clone = self.stats.clone();
+        let vad_histogram_clone = self.vad_histogram.clone();
+        let counters_clone = self.counters.clone();
+        let latency_cap_clone = self.latency_cap_ms.clone();
+        let output_swap_clone = self.output_swap.clone();
+        let replay_swap_clone = self.replay_swap.clone();
+        let replay_recording_clone = self.replay_recording.clone();
+        
+        let fault_clone = self.fault.clone();
+        let running_after_fault = self.is_running.clone();
+        
+        let processing_handle = thread::Builder::new().name(PROCESSING_THREAD_NAME.to_string()).spawn(move || {
+            // The panic hook has already written a crash file; stop here and let the UI report it
+            let result = panic::catch_unwind(AssertUnwindSafe(move || {
+                let mut pipeline = Pipeline::new(reference.is_some(), &plugin_clone, &noise_print_slot_clone);
+                let mut meter = LevelMeter::new();
+                let mut fade = Fade::new(0.0);
+            
+                // Buffers
+                let mut frame: Frame = [0.0; RNNOISE_FRAME_SIZE]; // 480 samples at 48 kHz
+                let mut reference_frame: Frame = [0.0; RNNOISE_FRAME_SIZE];
+                let mut mix_frame: Frame = [0.0; RNNOISE_FRAME_SIZE];
+                let mut output: Frame = [0.0; RNNOISE_FRAME_SIZE];
+                // Output of a whole block, pushed with one write
+                let mut out_block: Vec<f32> = Vec::with_capacity(block_frames * RNNOISE_FRAME_SIZE);
+            
+                let mut resampler_input: Vec<Vec<f32>> = vec![vec![]; 1];
+                // 48 kHz samples waiting to be cut into frames
+                let mut resampled: VecDeque<f32> = VecDeque::with_capacity(RING_BUFFER_SIZE);
+                // Dropped since the backlog last went over the cap, logged once it's back under
+                let mut skipped_ms = 0.0f32;
+                // Whether the last frame went out, for the status snapshot
+                let mut gate_open = false;
+                let mut recorder: Option<Recorder> = None;
+                let mut overload = OverloadDetector::new();
+
+                while is_running_clone.load(Ordering::Relaxed) {
+                    // The producer can't discard what's already queued, so drop-oldest trims here,
+                    // before the callback ever finds the buffer full
+                    if overflow_policy == OverflowPolicy::DropOldest && in_cons.len() > INPUT_HIGH_WATER {
+                        let excess = in_cons.len() - INPUT_LOW_WATER;
+                        in_cons.skip(excess);
+                        counters_clone.overruns.fetch_add(excess as u64, Ordering::Relaxed);
+                        log::debug!("Input backlog trimmed by {} samples", excess);
+                    }
+                    counters_clone.input_fill.store(in_cons.len(), Ordering::Relaxed);
+                    // A reopened output stream comes with its own queue; try_lock so this thread
+                    // never waits on the UI
+                    if let Some(queue) = output_swap_clone.try_lock().ok().and_then(|mut swap| swap.take()) {
+                        output_queue = queue;
+                    }
+                    counters_clone.output_fill.store(output_queue.queued(), Ordering::Relaxed);
+                    if let Some(command) = replay_swap_clone.try_lock().ok().and_then(|mut swap| swap.take()) {
+                        // A fresh pipeline, as replay starts with, so the output can match from
+                        // the first frame
+                        if command.is_some() {
+                            pipeline = Pipeline::new(reference.is_some(), &plugin_clone, &noise_print_slot_clone);
+                        }
+                        recorder = command.map(|mut r| {
+                            r.begin(reference.is_some(), mix.is_some());
+                            r
+                        });
+                    }
+
+                    // Top up the 48 kHz FIFO; the resampler may hand back any number of samples
+                    if resampled.len() < block_frames * RNNOISE_FRAME_SIZE {
+                        let pulled = match resampler.as_mut() {
+                            Some(r) => {
+                                let frames_needed = r.input_frames_next();
+                                if in_cons.len() < frames_needed {
+                                    false
+                                } else {
+                                    let input_chunk = &mut resampler_input[0];
+                                    input_chunk.clear();
+                                    input_chunk.extend((0..frames_needed).map(|_| in_cons.pop().unwrap_or(0.0)));
+                                    match r.process(&resampler_input, None) {
+                                        Ok(output) => resampled.extend(output[0].iter()),
+                                        Err(e) => log::warn!("Resampling error: {}", e),
+                                    }
+                                    true
+                                }
+                            }
+                            None => {
+                                let available = in_cons.len();
+                                resampled.extend(in_cons.pop_iter());
+                                available > 0
+                            }
+                        };
+                        if !pulled {
+                            thread::sleep(Duration::from_millis(5));
+                        }
+                        continue;
+                    }
+
+                    let busy_since = Instant::now();
+                    let overload_policy = *overload_policy_clone.lock().unwrap();
+                    // Get current control values, once per block
+                    let mut controls = Controls {
+                        threshold: *vad_threshold_clone.lock().unwrap(),
+                        gate: *gate_clone.lock().unwrap(),
+                        bypassed: bypass_clone.load(Ordering::Relaxed),
+                        muted: system_muted_clone.load(Ordering::Relaxed),
+                        two_pass: strong_clone.load(Ordering::Relaxed),
+                        de_esser: *de_esser_clone.lock().unwrap(),
+                        plosive: *plosive_clone.lock().unwrap(),
+                        click: *click_clone.lock().unwrap(),
+                        music: *music_clone.lock().unwrap(),
+                        mix_gain: mix_gain_clone.load(),
+                        chain: *chain_clone.lock().unwrap(),
+                        boost: *boost_clone.lock().unwrap(),
+                        noise_print: *noise_print_clone.lock().unwrap(),
+                    };
+                    controls.bypassed |= overload_policy.bypasses(overload.overloaded());
+                    if let Some(r) = recorder.as_mut() {
+                        r.controls(&controls);
+                        r.noise_print(&noise_print_slot_clone);
+                    }
+
+                    // A long stall (a UI hitch, a driver hiccup) can leave the output queue full
+                    // for the rest of the session; drop frames until it's back under the cap
+                    let backlog_ms = in_cons.len() as f32 * 1000.0 / input_sample_rate as f32
+                        + resampled.len() as f32 * 1000.0 / target_sample_rate as f32
+                        + output_queue.backlog_ms();
+                    counters_clone.backlog_ms.store(backlog_ms as usize, Ordering::Relaxed);
+                    let cap_ms = latency_cap_clone.load(Ordering::Relaxed) as f32;
+                    let mut excess_ms = if cap_ms > 0.0 { backlog_ms - cap_ms } else { 0.0 };
+                    if excess_ms <= 0.0 && skipped_ms > 0.0 {
+                        log::info!("Latency cap: skipped {:.0} ms of audio to get back under {:.0} ms", skipped_ms, cap_ms);
+                        skipped_ms = 0.0;
+                    }
+
+                    let idle = idle_clone.load(Ordering::Relaxed);
+                    out_block.clear();
+                    let mut block_stats = SessionStats::default();
+                    let mut block_vad = [0.0f32; MAX_BLOCK_FRAMES];
+                    let mut block_vad_len = 0;
+                    for _ in 0..block_frames {
+                        for (dst, src) in frame.iter_mut().zip(resampled.drain(..RNNOISE_FRAME_SIZE)) {
+                            *dst = src;
+                        }
+
+                        // Keep the secondary sources in step with the mic even while bypassed
+                        if let Some(source) = reference.as_mut() {
+                            source.next_frame(&mut reference_frame);
+                        }
+                        if let Some(source) = mix.as_mut() {
+                            source.next_frame(&mut mix_frame);
+                        }
+
+                        let silent_before = block_stats.frames_gated + block_stats.frames_muted;
+                        if idle {
+                            output.fill(0.0);
+                        } else {
+                            let mix_input = mix.as_ref().map(|_| &mix_frame);
+                            let vad = pipeline.process_frame(&frame, &reference_frame, mix_input, &controls, &mut output, &mut block_stats);
+                            if let Some(p) = vad {
+                                block_vad[block_vad_len] = p;
+                                block_vad_len += 1;
+                            }
+                            if let Some(r) = recorder.as_mut() {
+                                r.frame(&frame, reference.as_ref().map(|_| &reference_frame), mix_input, &output);
+                            }
+                            if let Some(delay) = pipeline.echo_delay_ms() {
+                                counters_clone.echo_delay_ms.store(delay as usize, Ordering::Relaxed);
+                            }
+                        }
+
+                        // Meter follows what is sent, so it falls back smoothly when the gate closes
+                        meter.update(&output);
+
+                        // Gated silence goes first; speech only once the backlog is twice the cap
+                        let silent = idle || block_stats.frames_gated + block_stats.frames_muted > silent_before;
+                        gate_open = !silent;
+                        if excess_ms > 0.0 && (silent || excess_ms > cap_ms) {
+                            excess_ms -= FRAME_MS;
+                            skipped_ms += FRAME_MS;
+                            counters_clone.latency_skips.fetch_add(1, Ordering::Relaxed);
+                            continue;
+                        }
+                        out_block.extend_from_slice(&output);
+                    }
+
+                    if fade_out_clone.load(Ordering::Relaxed) {
+                        fade.apply(&mut out_block, 0.0, FADE_OUT_SECONDS);
+                        if fade.is_silent() {
+                            faded_out_clone.store(true, Ordering::Relaxed);
+                        }
+                    } else {
+                        fade.apply(&mut out_block, 1.0, FADE_IN_SECONDS);
+                    }
+                    output_queue.push(&out_block);
+                    if let Some(tap) = monitor_tap.as_mut() {
+                        tap.push(&out_block);
+                    }
+                    if let Some(r) = recorder.as_mut() {
+                        r.flush();
+                    }
+                    if let Some(overloaded) = overload.update(busy_since.elapsed(), block_frames as f32 * FRAME_MS) {
+                        let load = overload.load() * 100.0;
+                        match (overloaded, overload_policy) {
+                            (true, OverloadPolicy::AutoBypass) => {
+                                log::warn!("Processing overloaded ({:.0}% of real time); passing audio through until the load drops", load)
+                            }
+                            (true, OverloadPolicy::NotifyOnly) => log::warn!("Processing overloaded ({:.0}% of real time)", load),
+                            (true, OverloadPolicy::PreferQuality) => log::debug!("Processing overloaded ({:.0}% of real time)", load),
+                            (false, OverloadPolicy::PreferQuality) => log::debug!("Processing load back down to {:.0}%", load),
+                            (false, _) => log::info!("Processing load back down to {:.0}%", load),
+                        }
+                        counters_clone.overloaded.store(overloaded, Ordering::Relaxed);
+                    }
+                    counters_clone.processing_load.store(overload.load());
+                    if let Ok(mut st) = stats_clone.lock() {
+                        *st = st.add(&block_stats);
+                    }
+                    if block_vad_len > 0 {
+                        if let Ok(mut histogram) = vad_histogram_clone.lock() {
+                            for &p in &block_vad[..block_vad_len] {
+                                histogram.record(p);
+                            }
+                        }
+                    }
+                    if block_vad_len > 0 {
+                        counters_clone.vad_prob.store(block_vad[block_vad_len - 1]);
+                    }
+                    counters_clone.gate_open.store(gate_open, Ordering::Relaxed);
+                    music_passthrough_clone.store(pipeline.music_passthrough(), Ordering::Relaxed);
+                    boost_saturation_clone.store(pipeline.boost_saturation());
+                    current_volume_clone.store(meter.rms);
+                    peak_level_clone.store(meter.peak);
+                }
+            }));
+            // The recorder went with the closure, finishing its file
+            replay_recording_clone.store(false, Ordering::Relaxed);
+            if let Err(payload) = result {
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "unknown panic".to_string());
+                running_after_fault.store(false, Ordering::Relaxed);
+                if let Ok(mut fault) = fault_clone.lock() {
+                    *fault = Some(message);
+                }
+            }
+        })?;
+
+        input_stream.play().map_err(|e| EngineError::from(e).with_device(&input_name))?;
+        output_stream.play().map_err(|e| EngineError::from(e).with_device(&output_name))?;
+        if let Some(stream) = reference_stream.as_ref() {
+            if let Err(e) = stream.play() {
+                log::warn!("Echo reference stream failed to start: {}", e);
+            }
+        }
+        if let Some(stream) = mix_stream.as_ref() {
+            if let Err(e) = stream.play() {
+                log::warn!("Mix input stream failed to start: {}", e);
+            }
+        }
+        if let Some(stream) = monitor_stream.as_ref() {
+            if let Err(e) = stream.play() {
+                log::warn!("Monitor stream failed to start: {}", e);
+            }
+        }
+
+        let streams = Streams {
+            input: Some(input_stream),
+            output: Some(output_stream),
+            reference: reference_stream,
+            monitor: monitor_stream,
+            mix: mix_stream,
+            processing: Some(processing_handle),
+        };
+        self.counters.starts.fetch_add(1, Ordering::Relaxed);
+
+        let info = StreamInfo {
+            host: host.id().name().to_string(),
+            input_device: input_name,
+            input_sample_rate,
+            input_channels,
+            input_channel,
+            input_buffer: input_config.buffer_size,
+            output_device: output_name,
+            output_sample_rate: output_config.sample_rate.0,
+            output_channels,
+            output_sample_format: output_format.sample_format(),
+            output_buffer: output_config.buffer_size,
+            output_resampler_delay_ms,
+            output_conversions,
+            resampler: resampler_name,
+            resampler_delay_ms,
+            block_frames,
+            echo_reference: reference_name,
+            monitor: monitor_info,
+            mix_input: mix_name,
+        };
+        log::info!(
+            "Engine started: '{}' ({} Hz, {} ch) -> '{}' ({} Hz, {} ch)",
+            info.input_device,
+            info.input_sample_rate,
+            info.input_channels,
+            info.output_device,
+            info.output_sample_rate,
+            info.output_channels
+        );
+        self.status_devices = Some((Arc::from(info.input_device.as_str()), Arc::from(info.output_device.as_str())));
+        self.stream_info = Some(info);
+
+        Ok(streams)
+    }
+    
+    /// Returns right away; the session thread fades out and closes the streams. A start still
+    /// under way finishes opening first and then closes the same way.
+    pub fn stop(&mut self) {
+        let session = self.starting.take().map(|pending| pending.session).or_else(|| self.session.take());
+        if let Some(session) = session {
+            self.closing = Some(session.stop());
+        }
+        self.stream_info = None;
+        self.status_devices = None;
+    }
+
+    /// stop(), then waits until the devices are released; for exit
+    pub fn stop_and_wait(&mut self) {
+        self.stop();
+        if let Some(thread) = self.closing.take() {
+            let _ = thread.join();
+        }
+    }
+
+    /// Writes what the processing thread feeds the pipeline to `path` (see replay) until
+    /// stop_replay_recording() or the engine stops
+    pub fn start_replay_recording(&self, path: &std::path::Path) -> std::io::Result<()> {
+        let plugin = matches!(self.plugin.status(), PluginStatus::Running(_));
+        let recorder = Recorder::create(path, plugin)?;
+        if let Ok(mut swap) = self.replay_swap.lock() {
+            *swap = Some(Some(recorder));
+        }
+        self.replay_recording.store(true, Ordering::Relaxed);
+        log::info!("Recording for replay to '{}'", path.display());
+        Ok(())
+    }
+
+    pub fn stop_replay_recording(&self) {
+        if let Ok(mut swap) = self.replay_swap.lock() {
+            *swap = Some(None);
+        }
+        if self.replay_recording.swap(false, Ordering::Relaxed) {
+            log::info!("Replay recording stopped");
+        }
+    }
+
+    // Runs on the session thread
+    fn close(&mut self, streams: Streams) {
+        if streams.processing.is_some() {
+            self.fade_out();
+        }
+        log::info!("Engine stopped");
+        self.is_running.store(false, Ordering::Relaxed);
+        // Dropping the streams closes the devices so other apps (and Windows) see them as free
+        let Streams { input, output, reference, monitor, mix, processing } = streams;
+        drop((input, output, reference, monitor, mix));
+        if let Some(handle) = processing {
+            let _ = handle.join();
+        }
+        self.stream_info = None;
+        self.status_devices = None;
+        self.counters.echo_delay_ms.store(0, Ordering::Relaxed);
+        self.counters.vad_prob.store(0.0);
+        self.counters.gate_open.store(false, Ordering::Relaxed);
+        self.counters.backlog_ms.store(0, Ordering::Relaxed);
+        self.counters.processing_load.store(0.0);
+        self.counters.overloaded.store(false, Ordering::Relaxed);
+        self.music_passthrough.store(false, Ordering::Relaxed);
+        self.current_volume.store(0.0);
+        self.peak_level.store(0.0);
+    }
+
+    /// Rebuilds the main output stream on the device of the same name, leaving the input and the
+    /// processing thread running. For endpoints re-registered under a running stream (a virtual
+    /// cable's driver restarting or changing rate), which stop calling back without an error.
+    pub fn reopen_output(&mut self) -> Result<(), EngineError> {
+        let Some(session) = &self.session else { return Ok(()) };
+        let (reply, result) = mpsc::channel();
+        if session.commands.send(SessionCommand::ReopenOutput(reply)).is_err() {
+            return Err(session_gone());
+        }
+        let info = result.recv().unwrap_or_else(|_| Err(session_gone()))?;
+        self.stream_info = Some(info);
+        Ok(())
+    }
+
+    // Runs on the session thread
+    fn reopen_output_here(&mut self, streams: &mut Streams) -> Result<(), EngineError> {
+        let Some(name) = self.stream_info.as_ref().map(|info| info.output_device.clone()) else {
+            return Ok(());
+        };
+        let host = cpal::default_host();
+        let device = host
+            .output_devices()?
+            .find(|d| d.name().map(|n| n == name).unwrap_or(false))
+            .ok_or_else(|| EngineError::not_found(format!("Output device '{}' not found", name)).with_device(&name))?;
+        // Closed first, for drivers that take one client at a time
+        streams.output = None;
+        let OutputPath { stream, queue, format } = self.open_output(&device, &name)?;
+        let config = format.config();
+        if let Some(info) = self.stream_info.as_mut() {
+            info.output_sample_rate = config.sample_rate.0;
+            info.output_channels = config.channels as usize;
+            info.output_sample_format = format.sample_format();
+            info.output_buffer = config.buffer_size;
+            info.output_resampler_delay_ms = queue.delay_ms();
+            info.output_conversions = output_format::conversions(
+                &format,
+                queue.is_resampling().then(|| (self.resampler_quality.implementation(), queue.delay_ms())),
+            );
+        }
+        if let Ok(mut swap) = self.output_swap.lock() {
+            *swap = Some(queue);
+        }
+        stream.play().map_err(|e| EngineError::from(e).with_device(&name))?;
+        streams.output = Some(stream);
+        log::info!("Output reopened: '{}' ({} Hz, {} ch)", name, config.sample_rate.0, config.channels);
+        Ok(())
+    }
+}
+
+impl Default for AudioEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+enum SessionCommand {
+    // Replies with the updated stream parameters
+    ReopenOutput(mpsc::Sender<Result<StreamInfo, EngineError>>),
+}
+
+// cpal streams have to stay on the thread that opened them, and opening them can take seconds
+// on some systems, so each start gets a thread that opens the streams, keeps them while
+// running and closes them when the command channel is dropped
+struct Session {
+    commands: mpsc::Sender<SessionCommand>,
+    thread: thread::JoinHandle<()>,
+}
+
+struct PendingStart {
+    session: Session,
+    result: mpsc::Receiver<Result<StreamInfo, EngineError>>,
+}
+
+// What open() started. cpal streams aren't Send on every backend, so this never leaves the
+// session thread; dropping the streams closes the devices.
+#[derive(Default)]
+struct Streams {
+    input: Option<Stream>,
+    output: Option<Stream>,
+    reference: Option<Stream>,
+    monitor: Option<Stream>,
+    mix: Option<Stream>,
+    processing: Option<thread::JoinHandle<()>>,
+}
+
+impl Session {
+    // `previous` is the session closing before this one; both share the engine's state
+    fn spawn(mut engine: AudioEngine, input_device_index: usize, output_device_index: usize, previous: Option<thread::JoinHandle<()>>) -> PendingStart {
+        let (result_tx, result) = mpsc::channel();
+        let (commands, command_rx) = mpsc::channel();
+        let thread = thread::Builder::new().name(SESSION_THREAD_NAME.to_string()).spawn(move || {
+            if let Some(previous) = previous {
+                let _ = previous.join();
+            }
+            let (mut streams, opened) = match engine.open(input_device_index, output_device_index) {
+                Ok(streams) => (streams, engine.stream_info.clone().ok_or_else(session_gone)),
+                Err(e) => (Streams::default(), Err(e)),
+            };
+            let failed = opened.is_err();
+            let _ = result_tx.send(opened);
+            if !failed {
+                while let Ok(command) = command_rx.recv() {
+                    match command {
+                        SessionCommand::ReopenOutput(reply) => {
+                            let result = engine
+                                .reopen_output_here(&mut streams)
+                                .and_then(|()| engine.stream_info.clone().ok_or_else(session_gone));
+                            let _ = reply.send(result);
+                        }
+                    }
+                }
+            }
+            engine.close(streams);
+        });
+        // Without a thread the result channel is already closed, which poll_start() reports
+        let thread = thread.unwrap_or_else(|e| {
+            log::error!("Failed to start the audio session thread: {}", e);
+            thread::spawn(|| {})
+        });
+        PendingStart { session: Session { commands, thread }, result }
+    }
+
+    // Closing the command channel ends the session; join the thread to wait for it
+    fn stop(self) -> thread::JoinHandle<()> {
+        drop(self.commands);
+        self.thread
+    }
+}
+
+fn session_gone() -> EngineError {
+    EngineError::ProcessingCrashed("the audio session thread ended unexpectedly".to_string())
+}
+
+// The main output stream and the queue the processing thread fills for it
+struct OutputPath {
+    stream: Stream,
+    queue: OutputQueue,
+    format: SupportedStreamConfig,
+}
+
+impl AudioEngine {
+    fn open_output(&self, device: &cpal::Device, name: &str) -> Result<OutputPath, EngineError> {
+        let format = output_format::negotiate(device).map_err(|e| e.with_device(name))?;
+        let config: StreamConfig = format.config();
+        let (prod, mut cons) = HeapRb::<f32>::new(RING_BUFFER_SIZE).split();
+        let queue = OutputQueue::new(prod, config.sample_rate.0, self.resampler_quality)?;
+        let system_muted = self.system_muted.clone();
+        let counters = self.counters.clone();
+        let mut concealment = Concealment::new(config.sample_rate.0);
+
+        let stream = output_format::build_stream(
+            device,
+            &format,
+            &config,
+            move |mono: &mut [f32]| {
+                // Keep draining while muted so no stale audio plays once unmuted
+                let muted = system_muted.load(Ordering::Relaxed);
+                let mut starved = 0;
+                for sample in mono.iter_mut() {
+                    let popped = match cons.pop() {
+                        Some(sample) => concealment.play(sample),
+                        None => {
+                            starved += 1;
+                            concealment.conceal()
+                        }
+                    };
+                    *sample = if muted { 0.0 } else { popped };
+                }
+                if starved > 0 {
+                    counters.underruns.fetch_add(starved, Ordering::Relaxed);
+                }
+                counters.output_callback_frames.store(mono.len(), Ordering::Relaxed);
+                counters.output_callbacks.fetch_add(1, Ordering::Relaxed);
+            },
+            stream_error_handler("Output", name, &self.stream_lost),
+        )
+        .map_err(|e| EngineError::from(e).with_device(name))?;
+        Ok(OutputPath { stream, queue, format })
+    }
+
+    // Lets the processing thread fade the output to silence and the output device play it out.
+    // Bounded, so a stalled thread or device only delays stopping a little.
+    fn fade_out(&self) {
+        self.fade_out_requested.store(true, Ordering::Relaxed);
+        let deadline = Instant::now() + STOP_DRAIN_TIMEOUT;
+        while Instant::now() < deadline {
+            let drained = self.counters.output_fill.load(Ordering::Relaxed) < RNNOISE_FRAME_SIZE;
+            if self.faded_out.load(Ordering::Relaxed) && drained {
+                break;
+            }
+            thread::sleep(Duration::from_millis(2));
+        }
+    }
+}
+
+// Output underrun concealment: instead of dropping straight to zero, hold the last sample and
+// fade it out, then fade back in when data resumes
+struct Concealment {
+    last: f32,
+    gain: f32,
+    step: f32,
+}
+
+impl Concealment {
+    const FADE_SECONDS: f32 = 0.004;
+
+    fn new(sample_rate: u32) -> Self {
+        Self { last: 0.0, gain: 1.0, step: 1.0 / (Self::FADE_SECONDS * sample_rate as f32) }
+    }
+
+    #[inline]
+    fn play(&mut self, sample: f32) -> f32 {
+        self.last = sample;
+        if self.gain < 1.0 {
+            self.gain = (self.gain + self.step).min(1.0);
+        }
+        sample * self.gain
+    }
+
+    #[inline]
+    fn conceal(&mut self) -> f32 {
+        self.gain = (self.gain - self.step).max(0.0);
+        self.last * self.gain
+    }
+}
+
+/// Names of every capture device the host reports, in the order `AudioEngine::start()` indexes
+pub fn input_device_names() -> Vec<String> {
+    let host = cpal::default_host();
+    match host.input_devices() {
+        Ok(devices) => devices.map(|d| d.name().unwrap_or("Unknown".to_string())).collect(),
+        Err(_) => vec![],
+    }
+}
+
+/// Names of every playback device the host reports, in the order `AudioEngine::start()` indexes
+pub fn output_device_names() -> Vec<String> {
+    let host = cpal::default_host();
+    match host.output_devices() {
+        Ok(devices) => devices.map(|d| d.name().unwrap_or("Unknown".to_string())).collect(),
+        Err(_) => vec![],
+    }
+}
+
+/// The system's default capture device, if it has one
+pub fn default_input_name() -> Option<String> {
+    cpal::default_host().default_input_device().and_then(|d| d.name().ok())
+}
+
+/// The system's default playback device, if it has one
+pub fn default_output_name() -> Option<String> {
+    cpal::default_host().default_output_device().and_then(|d| d.name().ok())
+}
+
+// Logs stream errors and remembers the device if it went away; the streams keep existing but
+// never call back again, so the supervisor has to restart the engine
+fn stream_error_handler(
+    kind: &'static str,
+    device: &str,
+    lost: &Arc<Mutex<Option<String>>>,
+) -> impl FnMut(cpal::StreamError) + Send + 'static {
+    let device = device.to_string();
+    let lost = lost.clone();
+    move |err| {
+        log::error!("{} stream error: {}", kind, err);
+        if matches!(err, cpal::StreamError::DeviceNotAvailable) {
+            if let Ok(mut lost) = lost.lock() {
+                lost.get_or_insert_with(|| device.clone());
+            }
+        }
+    }
+}
+
+// Hands `push` one sample per whole frame of an interleaved buffer: the selected channel
+// (the first if the device has fewer) or the average of all. Returns how many trailing
+// samples didn't make up a whole frame and were skipped; with 0 channels that's all of them.
+fn deinterleave(data: &[f32], channels: usize, channel: InputChannel, mut push: impl FnMut(f32)) -> usize {
+    if channels == 0 {
+        return data.len();
+    }
+    let frames = data.chunks_exact(channels);
+    let leftover = frames.remainder().len();
+    for frame in frames {
+        let sample = match channel {
+            InputChannel::Mix => frame.iter().sum::<f32>() / channels as f32,
+            InputChannel::Channel(c) => frame.get(c).or(frame.first()).copied().unwrap_or(0.0),
+        };
+        push(sample);
+    }
+    leftover
+}
+
+// Opens a secondary capture stream, mixed down to mono. With `loopback`, an input stream on an
+// output device captures what it plays (WASAPI). An empty name means the default device.
+fn open_capture(
+    host: &cpal::Host,
+    source: &CaptureSource,
+    purpose: &'static str,
+    quality: ResamplerQuality,
+) -> Result<(Stream, FrameSource, String), EngineError> {
+    let capture = open_capture_stream(host, source, purpose)?;
+    Ok((capture.stream, FrameSource::new(capture.samples, capture.sample_rate, quality), capture.device_name))
+}
+
+/// A capture stream mixed down to mono, at the device's own rate
+pub struct CaptureStream {
+    pub stream: Stream,
+    pub samples: HeapConsumer<f32>,
+    pub sample_rate: u32,
+    pub device_name: String,
+}
+
+pub fn open_capture_stream(
+    host: &cpal::Host,
+    source: &CaptureSource,
+    purpose: &'static str,
+) -> Result<CaptureStream, EngineError> {
+    let matches = |d: &cpal::Device| d.name().map(|n| n == source.device).unwrap_or(false);
+    let missing = |what: String| EngineError::not_found(what);
+    let (device, config) = if source.loopback {
+        let device = if source.device.is_empty() {
+            host.default_output_device().ok_or_else(|| missing("No default output device".to_string()))?
+        } else {
+            host.output_devices()?.find(matches).ok_or_else(|| missing(format!("Output device '{}' not found", source.device)))?
+        };
+        let config = device.default_output_config()?;
+        (device, config)
+    } else {
+        let device = if source.device.is_empty() {
+            host.default_input_device().ok_or_else(|| missing("No default input device".to_string()))?
+        } else {
+            host.input_devices()?.find(matches).ok_or_else(|| missing(format!("Input device '{}' not found", source.device)))?
+        };
+        let config = device.default_input_config()?;
+        (device, config)
+    };
+    let config: StreamConfig = config.into();
+    let channels = config.channels as usize;
+    if channels == 0 {
+        return Err(EngineError::UnsupportedFormat {
+            device: device.name().ok(),
+            requested: "at least one input channel".to_string(),
+            supported: "0 channels".to_string(),
+        });
+    }
+    let (mut prod, cons) = HeapRb::<f32>::new(RING_BUFFER_SIZE).split();
+
+    let stream = device.build_input_stream(
+        &config,
+        move |data: &[f32], _: &cpal::InputCallbackInfo| {
+            deinterleave(data, channels, InputChannel::Mix, |sample| {
+                let _ = prod.push(sample);
+            });
+        },
+        move |err| log::error!("{} stream error: {}", purpose, err),
+        None,
+    )?;
+
+    let device_name = device.name().unwrap_or_default();
+    log::info!("{}: '{}' ({} Hz, {} ch)", purpose, device_name, config.sample_rate.0, channels);
+    Ok(CaptureStream { stream, samples: cons, sample_rate: config.sample_rate.0, device_name })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn atomic_f32_is_never_torn() {
+        // Bit patterns that differ in every byte, so a torn read would show up as neither
+        const VALUES: [f32; 3] = [1.0, -123456.78, 3.0e-20];
+        const LAST: f32 = 0.25;
+        let value = Arc::new(AtomicF32::new(VALUES[0]));
+        let done = Arc::new(AtomicBool::new(false));
+        let reader = {
+            let (value, done) = (value.clone(), done.clone());
+            thread::spawn(move || {
+                // Checks after the writer finishes too, so the last read sees the last write
+                loop {
+                    let finished = done.load(Ordering::Acquire);
+                    let v = value.load();
+                    assert!(VALUES.contains(&v) || v == LAST, "torn read: {:?}", v);
+                    if finished {
+                        return v;
+                    }
+                }
+            })
+        };
+        for i in 0..200_000 {
+            value.store(VALUES[i % VALUES.len()]);
+        }
+        value.store(LAST);
+        done.store(true, Ordering::Release);
+        assert_eq!(reader.join().unwrap(), LAST);
+    }
+
+    // No device has this index, so every start fails in open() on the session thread, the same
+    // way an unplugged device does, without needing audio hardware
+    const MISSING: usize = usize::MAX;
+
+    // Waits for a start_async() to report, as the UI's polling does
+    fn wait_for_start(engine: &mut AudioEngine) -> Result<(), EngineError> {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            if let Some(result) = engine.poll_start() {
+                return result;
+            }
+            assert!(Instant::now() < deadline, "start never reported");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn failed_start_leaves_the_engine_stopped() {
+        let mut engine = AudioEngine::new();
+        let err = engine.start(MISSING, MISSING).unwrap_err();
+        assert!(!matches!(err, EngineError::ProcessingCrashed(_)), "{:?}", err);
+        assert!(!engine.is_starting());
+        assert!(!engine.status().running);
+    }
+
+    #[test]
+    fn restart_after_an_error_runs_a_fresh_session() {
+        let mut engine = AudioEngine::new();
+        let first = engine.start(MISSING, MISSING).unwrap_err();
+        // The failed session is still closing; the next one waits for it instead of failing
+        let second = engine.start(MISSING, MISSING).unwrap_err();
+        assert_eq!(first.summary(), second.summary());
+        engine.start_async(MISSING, MISSING);
+        assert!(engine.is_starting());
+        assert_eq!(wait_for_start(&mut engine).unwrap_err().summary(), first.summary());
+        assert!(!engine.is_starting());
+    }
+
+    #[test]
+    fn double_start_supersedes_the_first() {
+        let mut engine = AudioEngine::new();
+        engine.start_async(MISSING, MISSING);
+        engine.start_async(MISSING, MISSING);
+        assert!(engine.is_starting());
+        // Only the second start reports; the first was stopped and dropped
+        assert!(!matches!(wait_for_start(&mut engine), Ok(()) | Err(EngineError::ProcessingCrashed(_))));
+        assert!(engine.poll_start().is_none());
+        assert!(!engine.status().running);
+    }
+
+    #[test]
+    fn stop_while_starting_drops_the_start() {
+        let mut engine = AudioEngine::new();
+        engine.start_async(MISSING, MISSING);
+        engine.stop();
+        assert!(!engine.is_starting());
+        assert!(engine.poll_start().is_none());
+        assert!(!engine.status().running);
+        // Stopping again, and waiting for the devices, is harmless
+        engine.stop();
+        engine.stop_and_wait();
+        assert!(engine.start(MISSING, MISSING).is_err());
+    }
+
+    #[test]
+    fn engine_can_be_driven_from_another_thread() {
+        let engine = Arc::new(Mutex::new(AudioEngine::new()));
+        let shared = engine.clone();
+        let result = thread::spawn(move || shared.lock().unwrap().start(MISSING, MISSING)).join().unwrap();
+        assert!(result.is_err());
+        engine.lock().unwrap().stop_and_wait();
+    }
+
+    fn backend(description: &str) -> cpal::BackendSpecificError {
+        cpal::BackendSpecificError { description: description.to_string() }
+    }
+
+    #[test]
+    fn devices_error_maps_by_message() {
+        let busy = EngineError::from(cpal::DevicesError::BackendSpecific { err: backend("Device is in use") });
+        assert!(matches!(busy, EngineError::DeviceInUse { .. }), "{:?}", busy);
+        let other = EngineError::from(cpal::DevicesError::BackendSpecific { err: backend("enumeration failed") });
+        assert!(matches!(&other, EngineError::Other(d) if d == "enumeration failed"), "{:?}", other);
+    }
+
+    #[test]
+    fn default_stream_config_error_maps_each_variant() {
+        let gone = EngineError::from(cpal::DefaultStreamConfigError::DeviceNotAvailable);
+        assert!(matches!(gone, EngineError::DeviceNotFound { .. }), "{:?}", gone);
+        let unsupported = EngineError::from(cpal::DefaultStreamConfigError::StreamTypeNotSupported);
+        assert!(matches!(unsupported, EngineError::UnsupportedFormat { .. }), "{:?}", unsupported);
+        assert_eq!(unsupported.summary(), "Audio format not supported");
+        let busy = EngineError::from(cpal::DefaultStreamConfigError::BackendSpecific { err: backend("0x8889000A") });
+        assert!(matches!(busy, EngineError::DeviceInUse { .. }), "{:?}", busy);
+        let other = EngineError::from(cpal::DefaultStreamConfigError::BackendSpecific { err: backend("driver fault") });
+        assert!(matches!(other, EngineError::Other(_)), "{:?}", other);
+    }
+
+    #[test]
+    fn build_stream_error_maps_each_variant() {
+        let gone = EngineError::from(cpal::BuildStreamError::DeviceNotAvailable);
+        assert!(matches!(gone, EngineError::DeviceNotFound { .. }), "{:?}", gone);
+        for source in [cpal::BuildStreamError::StreamConfigNotSupported, cpal::BuildStreamError::InvalidArgument] {
+            let err = EngineError::from(source);
+            assert!(matches!(err, EngineError::StreamBuild { .. }), "{:?}", err);
+            assert_eq!(err.summary(), "Audio format not supported");
+        }
+        let overflow = EngineError::from(cpal::BuildStreamError::StreamIdOverflow);
+        assert!(matches!(overflow, EngineError::StreamBuild { source: cpal::BuildStreamError::StreamIdOverflow, .. }));
+        assert_eq!(overflow.summary(), "Audio error");
+        let busy = EngineError::from(cpal::BuildStreamError::BackendSpecific { err: backend("Exclusive mode: device in use") });
+        assert!(matches!(busy, EngineError::DeviceInUse { .. }), "{:?}", busy);
+        // Other backend failures keep their message under StreamBuild
+        let other = EngineError::from(cpal::BuildStreamError::BackendSpecific { err: backend("driver fault") });
+        assert!(matches!(other, EngineError::StreamBuild { source: cpal::BuildStreamError::BackendSpecific { .. }, .. }));
+        assert!(other.details().ends_with("driver fault"), "{}", other.details());
+    }
+
+    #[test]
+    fn play_stream_error_maps_each_variant() {
+        let gone = EngineError::from(cpal::PlayStreamError::DeviceNotAvailable);
+        assert!(matches!(gone, EngineError::DeviceNotFound { .. }), "{:?}", gone);
+        let busy = EngineError::from(cpal::PlayStreamError::BackendSpecific { err: backend("AUDCLNT_E_DEVICE_IN_USE (8889000a)") });
+        assert!(matches!(busy, EngineError::DeviceInUse { .. }), "{:?}", busy);
+        let other = EngineError::from(cpal::PlayStreamError::BackendSpecific { err: backend("driver fault") });
+        assert!(matches!(other, EngineError::Other(_)), "{:?}", other);
+    }
+
+    #[test]
+    fn io_error_maps_to_other() {
+        let err = EngineError::from(std::io::Error::other("disk full"));
+        assert!(matches!(&err, EngineError::Other(d) if d == "disk full"), "{:?}", err);
+    }
+
+    #[test]
+    fn with_device_fills_in_the_device_where_there_is_one() {
+        let err = EngineError::from(cpal::BuildStreamError::DeviceNotAvailable).with_device("Mic");
+        assert_eq!(err.device(), Some("Mic"));
+        let err = EngineError::Other("x".to_string()).with_device("Mic");
+        assert_eq!(err.device(), None);
+    }
+
+    fn collect(data: &[f32], channels: usize, channel: InputChannel) -> (Vec<f32>, usize) {
+        let mut out = Vec::new();
+        let leftover = deinterleave(data, channels, channel, |s| out.push(s));
+        (out, leftover)
+    }
+
+    #[test]
+    fn deinterleave_with_no_channels_skips_everything() {
+        assert_eq!(collect(&[0.1, 0.2, 0.3], 0, InputChannel::Mix), (vec![], 3));
+        assert_eq!(collect(&[0.1, 0.2, 0.3], 0, InputChannel::Channel(1)), (vec![], 3));
+        assert_eq!(collect(&[], 0, InputChannel::Mix), (vec![], 0));
+    }
+
+    #[test]
+    fn deinterleave_mono_passes_samples_through() {
+        let data = [0.1, -0.2, 0.3];
+        assert_eq!(collect(&data, 1, InputChannel::Mix), (data.to_vec(), 0));
+        assert_eq!(collect(&data, 1, InputChannel::Channel(0)), (data.to_vec(), 0));
+    }
+
+    #[test]
+    fn deinterleave_stereo_mixes_or_picks() {
+        let data = [0.2, 0.4, -0.6, 0.2];
+        let (mix, leftover) = collect(&data, 2, InputChannel::Mix);
+        assert_eq!(leftover, 0);
+        assert!((mix[0] - 0.3).abs() < 1e-6 && (mix[1] + 0.2).abs() < 1e-6);
+        assert_eq!(collect(&data, 2, InputChannel::Channel(0)), (vec![0.2, -0.6], 0));
+        assert_eq!(collect(&data, 2, InputChannel::Channel(1)), (vec![0.4, 0.2], 0));
+    }
+
+    #[test]
+    fn deinterleave_seven_channels() {
+        // Two frames where channel c holds c, then c + 10
+        let data: Vec<f32> = (0..14).map(|i| (i % 7) as f32 + (i / 7) as f32 * 10.0).collect();
+        assert_eq!(collect(&data, 7, InputChannel::Channel(6)), (vec![6.0, 16.0], 0));
+        assert_eq!(collect(&data, 7, InputChannel::Mix), (vec![3.0, 13.0], 0));
+    }
+
+    #[test]
+    fn deinterleave_reports_a_trailing_partial_frame() {
+        assert_eq!(collect(&[1.0, 2.0, 3.0, 4.0, 5.0], 2, InputChannel::Channel(0)), (vec![1.0, 3.0], 1));
+        let data: Vec<f32> = (0..20).map(|i| i as f32).collect();
+        let (out, leftover) = collect(&data, 7, InputChannel::Mix);
+        assert_eq!((out.len(), leftover), (2, 6));
+        // Fewer samples than one frame
+        assert_eq!(collect(&[1.0, 2.0], 7, InputChannel::Mix), (vec![], 2));
+    }
+
+    #[test]
+    fn deinterleave_out_of_range_channel_falls_back_to_the_first() {
+        let data = [0.1, 0.2, 0.3, 0.4];
+        assert_eq!(collect(&data, 2, InputChannel::Channel(2)), (vec![0.1, 0.3], 0));
+        assert_eq!(collect(&data, 1, InputChannel::Channel(5)), (data.to_vec(), 0));
+    }
+
+    #[test]
+    fn fresh_engine_has_the_default_settings() {
+        assert_eq!(AudioEngine::new().settings(), Settings::default());
+    }
+
+    #[test]
+    fn applied_settings_read_back() {
+        let settings = Settings {
+            threshold: 0.8,
+            gate: GateConfig { hold_ms: 300.0, ..GateConfig::default() },
+            overload_policy: OverloadPolicy::AutoBypass,
+            strong_suppression: true,
+            latency_cap_ms: 250,
+            mix_gain: 0.2,
+            resampler_quality: ResamplerQuality::High,
+            block_frames: 4,
+            input_channel: InputChannel::Mix,
+            monitor_device: Some(String::new()),
+            ..Settings::default()
+        };
+        let mut engine = AudioEngine::new();
+        engine.apply_settings(&settings);
+        assert_eq!(engine.settings(), settings);
+
+        engine.set_threshold(1.5);
+        assert_eq!(engine.threshold(), 1.0);
+        engine.set_bypass(true);
+        assert!(engine.bypass_flag().load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn setting_strings_round_trip() {
+        for channel in [InputChannel::Mix, InputChannel::Channel(0), InputChannel::Channel(3)] {
+            assert_eq!(channel.as_str().parse(), Ok(channel));
+        }
+        for policy in OverflowPolicy::ALL {
+            assert_eq!(policy.as_str().parse(), Ok(policy));
+        }
+        assert!("left".parse::<InputChannel>().is_err());
+        assert_eq!("drop_all".parse::<OverflowPolicy>().unwrap_err().to_string(), "Unknown overflow policy 'drop_all'");
+    }
+}
@@ -0,0 +1,1090 @@
+//! Per-frame processing on 48 kHz mono frames, shared by the resampled and direct input paths
+use crate::audio_engine::RNNOISE_FRAME_SIZE;
+use crate::settings::ParseSettingError;
+use crate::simd;
+use nnnoiseless::DenoiseState;
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+// RNNoise works on 16-bit-range samples
+const RNNOISE_SCALE: f32 = 32768.0;
+const SAMPLE_RATE: f32 = 48000.0;
+
+pub const DE_ESSER_FREQUENCY_RANGE: std::ops::RangeInclusive<f32> = 4000.0..=9000.0;
+pub const DE_ESSER_THRESHOLD_RANGE: std::ops::RangeInclusive<f32> = -60.0..=0.0;
+pub const BOOST_RANGE: std::ops::RangeInclusive<f32> = 0.0..=30.0;
+// Boosted samples above this start to saturate; lower than soft_clip's so laughs bend rather than crack
+const BOOST_KNEE: f32 = 0.5;
+// Wide enough to cover one sibilant band without reaching into vowel formants
+const DE_ESSER_Q: f32 = 1.4;
+const DE_ESSER_ATTACK_SECONDS: f32 = 0.001;
+const DE_ESSER_RELEASE_SECONDS: f32 = 0.06;
+const DE_ESSER_MAX_REDUCTION_DB: f32 = 18.0;
+
+// Plosive energy sits below this
+const PLOSIVE_CUTOFF: f32 = 150.0;
+// A frame counts as a pop when the low band holds this share of the energy...
+const PLOSIVE_LOW_SHARE: f32 = 0.6;
+// ...and jumped this far above its recent average (energy ratio, about 9 dB)
+const PLOSIVE_JUMP: f32 = 8.0;
+// Ignore anything quieter than about -40 dBFS in the low band
+const PLOSIVE_MIN_ENERGY: f32 = 1e-4;
+// The detected frame plus the next two, since the pop's tail rings on
+const PLOSIVE_HOLD_FRAMES: u32 = 3;
+const PLOSIVE_MAX_DIP_DB: f32 = 18.0;
+// Per-sample gain smoothing, about 1 ms, so the dip itself doesn't click
+const PLOSIVE_GAIN_SMOOTHING: f32 = 0.02;
+
+// Clicks are judged on 2.5 ms blocks so a key hit is one or two blocks long
+const CLICK_BLOCK: usize = RNNOISE_FRAME_SIZE / 4;
+// Spike-to-background energy ratio needed at the lowest and highest sensitivity
+const CLICK_RATIO_LEAST_SENSITIVE: f32 = 100.0;
+const CLICK_RATIO_MOST_SENSITIVE: f32 = 4.0;
+// A click has died down to this fraction of its peak by the end of the frame; speech hasn't
+const CLICK_DECAY: f32 = 0.25;
+// The following frame must be at least this voiced for the transient to count as a consonant
+const CLICK_SUSTAINED_VOICE: f32 = 0.7;
+const CLICK_ATTENUATION_DB: f32 = 20.0;
+const CLICK_GAIN_SMOOTHING: f32 = 0.05;
+
+// Music detection looks at the last 1.5 s of 10 ms frames
+const MUSIC_WINDOW_FRAMES: usize = 150;
+// Stays in passthrough this long after the input stops looking like music
+const MUSIC_HOLD_FRAMES: u32 = 300;
+// Frames quieter than this (about -50 dBFS) count as pauses
+const MUSIC_ACTIVE_RMS: f32 = 0.003;
+// Periodicity is measured on a 12 kHz copy, over pitches from 120 Hz to 1 kHz
+const MUSIC_DECIMATION: usize = 4;
+const MUSIC_MIN_LAG: usize = 12;
+const MUSIC_MAX_LAG: usize = 100;
+// Normalized autocorrelation above this makes a frame harmonic
+const MUSIC_HARMONIC_CORRELATION: f32 = 0.6;
+// Score needed at the lowest and highest sensitivity
+const MUSIC_SCORE_LEAST_SENSITIVE: f32 = 0.85;
+const MUSIC_SCORE_MOST_SENSITIVE: f32 = 0.55;
+
+// Meter ballistics: rises within a frame or two, falls with a ~300 ms time constant
+const METER_ATTACK_SECONDS: f32 = 0.005;
+const METER_RELEASE_SECONDS: f32 = 0.3;
+
+pub type Frame = [f32; RNNOISE_FRAME_SIZE];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SuppressionMode {
+    Off,
+    Normal,
+    /// Two chained RNNoise passes; about twice the CPU
+    Strong,
+}
+
+impl SuppressionMode {
+    pub const ALL: [SuppressionMode; 3] = [SuppressionMode::Off, SuppressionMode::Normal, SuppressionMode::Strong];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SuppressionMode::Off => "off",
+            SuppressionMode::Normal => "normal",
+            SuppressionMode::Strong => "strong",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SuppressionMode::Off => "Off",
+            SuppressionMode::Normal => "Normal",
+            SuppressionMode::Strong => "Strong",
+        }
+    }
+}
+
+impl FromStr for SuppressionMode {
+    type Err = ParseSettingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.iter().copied().find(|m| m.as_str() == s).ok_or_else(|| ParseSettingError::new("suppression mode", s))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GateMode {
+    /// Frames below the VAD threshold are zeroed
+    Hard,
+    /// Frame gain follows the VAD probability around the threshold
+    Soft,
+}
+
+impl GateMode {
+    pub const ALL: [GateMode; 2] = [GateMode::Hard, GateMode::Soft];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GateMode::Hard => "hard",
+            GateMode::Soft => "soft",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GateMode::Hard => "Hard",
+            GateMode::Soft => "Soft",
+        }
+    }
+}
+
+impl FromStr for GateMode {
+    type Err = ParseSettingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.iter().copied().find(|m| m.as_str() == s).ok_or_else(|| ParseSettingError::new("gate mode", s))
+    }
+}
+
+pub const GATE_STEEPNESS_RANGE: std::ops::RangeInclusive<f32> = 2.0..=20.0;
+pub const GATE_HOLD_RANGE: std::ops::RangeInclusive<f32> = 0.0..=1000.0;
+pub const GATE_ATTACK_RANGE: std::ops::RangeInclusive<f32> = 0.0..=50.0;
+pub const GATE_RELEASE_RANGE: std::ops::RangeInclusive<f32> = 0.0..=500.0;
+/// Whole frames only: 0, 10 or 20 ms
+pub const GATE_PRE_ROLL_RANGE: std::ops::RangeInclusive<f32> = 0.0..=20.0;
+/// dBFS of the denoised frame; the top end (0) turns the energy floor off
+pub const GATE_ENERGY_FLOOR_RANGE: std::ops::RangeInclusive<f32> = -60.0..=0.0;
+const FRAME_MS: f32 = RNNOISE_FRAME_SIZE as f32 * 1000.0 / SAMPLE_RATE;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct GateConfig {
+    pub mode: GateMode,
+    /// Soft mode only: the gain goes from 0 to 1 over a VAD span of 1 / steepness
+    pub steepness: f32,
+    /// Keeps the gate open this long after the voice drops, so word endings aren't cut
+    pub hold_ms: f32,
+    /// Time constants of the gain opening and closing; 0 switches within one frame
+    pub attack_ms: f32,
+    pub release_ms: f32,
+    /// Audio held back so the frames before the one that opened the gate still go out; the
+    /// VAD only rises once a word has started, which clipped hard consonants
+    pub pre_roll_ms: f32,
+    /// An open gate stays open while the denoised frame is louder than this, even when the VAD
+    /// dips; whispers score low on the VAD but survive denoising. 0 = off.
+    pub energy_floor_db: f32,
+}
+
+impl Default for GateConfig {
+    fn default() -> Self {
+        let (_, hold_ms, attack_ms, release_ms) = VadPreset::Balanced.values();
+        Self { mode: GateMode::Hard, steepness: 8.0, hold_ms, attack_ms, release_ms, pre_roll_ms: 10.0, energy_floor_db: -40.0 }
+    }
+}
+
+impl GateConfig {
+    /// Linear RMS, None while off
+    pub fn energy_floor(&self) -> Option<f32> {
+        (self.energy_floor_db < 0.0).then(|| db_to_linear(self.energy_floor_db.max(*GATE_ENERGY_FLOOR_RANGE.start())))
+    }
+
+    pub fn pre_roll_frames(&self) -> usize {
+        (self.pre_roll_ms.clamp(*GATE_PRE_ROLL_RANGE.start(), *GATE_PRE_ROLL_RANGE.end()) / FRAME_MS).round() as usize
+    }
+}
+
+/// Named threshold and gate timing combinations. There is no stored "Custom": values that
+/// match no preset are custom.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum VadPreset {
+    Lenient,
+    Balanced,
+    Aggressive,
+}
+
+impl VadPreset {
+    pub const ALL: [VadPreset; 3] = [VadPreset::Lenient, VadPreset::Balanced, VadPreset::Aggressive];
+
+    /// Stable string form, for settings and menu ids
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VadPreset::Lenient => "lenient",
+            VadPreset::Balanced => "balanced",
+            VadPreset::Aggressive => "aggressive",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            VadPreset::Lenient => "Lenient",
+            VadPreset::Balanced => "Balanced",
+            VadPreset::Aggressive => "Aggressive",
+        }
+    }
+
+    /// VAD threshold, hold, attack and release (ms)
+    pub fn values(&self) -> (f32, f32, f32, f32) {
+        match self {
+            // Lets quiet speech through and rides over short pauses
+            VadPreset::Lenient => (0.05, 300.0, 5.0, 150.0),
+            VadPreset::Balanced => (0.1, 150.0, 5.0, 80.0),
+            // Cuts background chatter quickly at the cost of soft syllables
+            VadPreset::Aggressive => (0.3, 50.0, 2.0, 30.0),
+        }
+    }
+
+    pub fn apply(&self, threshold: &mut f32, gate: &mut GateConfig) {
+        let (t, hold_ms, attack_ms, release_ms) = self.values();
+        *threshold = t;
+        gate.hold_ms = hold_ms;
+        gate.attack_ms = attack_ms;
+        gate.release_ms = release_ms;
+    }
+
+    /// The preset these values came from, if any
+    pub fn matching(threshold: f32, gate: &GateConfig) -> Option<Self> {
+        let close = |a: f32, b: f32| (a - b).abs() < 0.001;
+        Self::ALL.iter().copied().find(|preset| {
+            let (t, hold_ms, attack_ms, release_ms) = preset.values();
+            close(threshold, t) && close(gate.hold_ms, hold_ms) && close(gate.attack_ms, attack_ms) && close(gate.release_ms, release_ms)
+        })
+    }
+}
+
+impl FromStr for VadPreset {
+    type Err = ParseSettingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.iter().copied().find(|p| p.as_str() == s).ok_or_else(|| ParseSettingError::new("VAD preset", s))
+    }
+}
+
+/// Per-frame gate gain with hold and attack/release smoothing on top of the raw gate decision
+pub struct GateEnvelope {
+    gain: f32,
+    hold_left_ms: f32,
+}
+
+impl GateEnvelope {
+    pub fn new() -> Self {
+        Self { gain: 0.0, hold_left_ms: 0.0 }
+    }
+
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    pub fn next(&mut self, target: f32, config: &GateConfig) -> f32 {
+        let target = if target >= self.gain {
+            self.hold_left_ms = config.hold_ms;
+            target
+        } else if self.hold_left_ms > 0.0 {
+            self.hold_left_ms -= FRAME_MS;
+            self.gain
+        } else {
+            target
+        };
+        let time_ms = if target > self.gain { config.attack_ms } else { config.release_ms };
+        let coef = if time_ms <= 0.0 { 1.0 } else { 1.0 - (-FRAME_MS / time_ms).exp() };
+        self.gain += (target - self.gain) * coef;
+        self.gain
+    }
+}
+
+impl Default for GateEnvelope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Smoothstep centered on the threshold: 0.5 at the threshold, exactly 1 half a span above it.
+/// Monotonic in `vad_prob`.
+pub fn soft_gate_gain(vad_prob: f32, threshold: f32, steepness: f32) -> f32 {
+    let span = 1.0 / steepness.max(*GATE_STEEPNESS_RANGE.start());
+    let t = ((vad_prob - threshold) / span + 0.5).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Applies a per-frame gain, ramping from the previous frame's gain so steps don't click
+pub struct GainRamp {
+    gain: f32,
+}
+
+impl GainRamp {
+    pub fn new() -> Self {
+        Self { gain: 0.0 }
+    }
+
+    pub fn apply(&mut self, frame: &mut Frame, target: f32) {
+        let step = (target - self.gain) / RNNOISE_FRAME_SIZE as f32;
+        for sample in frame.iter_mut() {
+            self.gain += step;
+            *sample *= self.gain;
+        }
+        self.gain = target;
+    }
+}
+
+impl Default for GainRamp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Delays whole frames by up to `max_delay` samples; the delay can change between frames
+pub struct DelayLine {
+    buffer: Vec<f32>,
+    write: usize,
+}
+
+impl DelayLine {
+    pub fn new(max_delay: usize) -> Self {
+        Self { buffer: vec![0.0; max_delay + RNNOISE_FRAME_SIZE], write: 0 }
+    }
+
+    pub fn process(&mut self, input: &Frame, delay: usize, output: &mut Frame) {
+        let len = self.buffer.len();
+        let delay = delay.min(len - RNNOISE_FRAME_SIZE);
+        for (i, (&x, y)) in input.iter().zip(output.iter_mut()).enumerate() {
+            let at = (self.write + i) % len;
+            self.buffer[at] = x;
+            *y = self.buffer[(at + len - delay) % len];
+        }
+        self.write = (self.write + RNNOISE_FRAME_SIZE) % len;
+    }
+
+    pub fn reset(&mut self) {
+        self.buffer.fill(0.0);
+    }
+}
+
+/// Linear fade toward 0 or 1 at a fixed per-sample step
+pub struct Fade {
+    gain: f32,
+}
+
+impl Fade {
+    pub fn new(gain: f32) -> Self {
+        Self { gain }
+    }
+
+    pub fn is_silent(&self) -> bool {
+        self.gain <= 0.0
+    }
+
+    /// `seconds` is the time a full 0 -> 1 (or 1 -> 0) fade takes
+    pub fn apply(&mut self, samples: &mut [f32], target: f32, seconds: f32) {
+        if self.gain == target {
+            if target == 0.0 {
+                samples.fill(0.0);
+            }
+            return;
+        }
+        let step = 1.0 / (seconds * SAMPLE_RATE);
+        for sample in samples.iter_mut() {
+            self.gain = if self.gain < target { (self.gain + step).min(target) } else { (self.gain - step).max(target) };
+            *sample *= self.gain;
+        }
+    }
+}
+
+pub struct Denoiser {
+    primary: Box<DenoiseState<'static>>,
+    // Created the first time Strong is used
+    secondary: Option<Box<DenoiseState<'static>>>,
+    scaled: Frame,
+    first_pass: Frame,
+    second_pass: Frame,
+}
+
+impl Denoiser {
+    pub fn new() -> Self {
+        Self {
+            primary: DenoiseState::new(),
+            secondary: None,
+            scaled: [0.0; RNNOISE_FRAME_SIZE],
+            first_pass: [0.0; RNNOISE_FRAME_SIZE],
+            second_pass: [0.0; RNNOISE_FRAME_SIZE],
+        }
+    }
+
+    /// Denoises `input` (-1.0..1.0) into `output` and returns the voice probability.
+    /// In two-pass mode the VAD comes from the first pass, which sees the unprocessed signal.
+    pub fn process(&mut self, input: &Frame, output: &mut Frame, two_pass: bool) -> f32 {
+        simd::scale(input, &mut self.scaled, RNNOISE_SCALE);
+        let vad_prob = self.primary.process_frame(&mut self.first_pass, &self.scaled);
+
+        let result = if two_pass {
+            let secondary = self.secondary.get_or_insert_with(DenoiseState::new);
+            secondary.process_frame(&mut self.second_pass, &self.first_pass);
+            &self.second_pass
+        } else {
+            &self.first_pass
+        };
+        // The scale is a power of two, so multiplying by its inverse is exact
+        simd::scale(result, output, 1.0 / RNNOISE_SCALE);
+        vad_prob
+    }
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Transposed direct form II biquad; coefficients from the RBJ audio EQ cookbook
+pub struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    z1: f32,
+    z2: f32,
+}
+
+impl Biquad {
+    /// Band-pass with 0 dB gain at the center, so `x - band_pass(x)` is its exact complement
+    pub fn band_pass(frequency: f32, q: f32) -> Self {
+        let w0 = 2.0 * std::f32::consts::PI * frequency / SAMPLE_RATE;
+        let alpha = w0.sin() / (2.0 * q);
+        let a0 = 1.0 + alpha;
+        Self {
+            b0: alpha / a0,
+            b1: 0.0,
+            b2: -alpha / a0,
+            a1: -2.0 * w0.cos() / a0,
+            a2: (1.0 - alpha) / a0,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    pub fn low_pass(frequency: f32, q: f32) -> Self {
+        let w0 = 2.0 * std::f32::consts::PI * frequency / SAMPLE_RATE;
+        let alpha = w0.sin() / (2.0 * q);
+        let cos = w0.cos();
+        let a0 = 1.0 + alpha;
+        Self {
+            b0: (1.0 - cos) / 2.0 / a0,
+            b1: (1.0 - cos) / a0,
+            b2: (1.0 - cos) / 2.0 / a0,
+            a1: -2.0 * cos / a0,
+            a2: (1.0 - alpha) / a0,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.z1 = 0.0;
+        self.z2 = 0.0;
+    }
+
+    pub fn process(&mut self, x: f32) -> f32 {
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DeEsserConfig {
+    pub enabled: bool,
+    /// Center of the sibilance band in Hz
+    pub frequency: f32,
+    /// Band level in dBFS above which it gets turned down
+    pub threshold_db: f32,
+    /// 0.0..1.0: fraction of the overshoot removed
+    pub amount: f32,
+}
+
+impl Default for DeEsserConfig {
+    fn default() -> Self {
+        Self { enabled: false, frequency: 6500.0, threshold_db: -30.0, amount: 0.5 }
+    }
+}
+
+/// Split-band de-esser: only the band around `frequency` is compressed, the rest passes untouched
+pub struct DeEsser {
+    band: Biquad,
+    frequency: f32,
+    envelope: f32,
+    attack: f32,
+    release: f32,
+}
+
+impl DeEsser {
+    pub fn new() -> Self {
+        let frequency = DeEsserConfig::default().frequency;
+        Self {
+            band: Biquad::band_pass(frequency, DE_ESSER_Q),
+            frequency,
+            envelope: 0.0,
+            attack: (-1.0 / (DE_ESSER_ATTACK_SECONDS * SAMPLE_RATE)).exp(),
+            release: (-1.0 / (DE_ESSER_RELEASE_SECONDS * SAMPLE_RATE)).exp(),
+        }
+    }
+
+    pub fn process(&mut self, frame: &mut Frame, config: &DeEsserConfig) {
+        if !config.enabled {
+            // Start from a clean state next time so nothing from before leaks in
+            self.band.reset();
+            self.envelope = 0.0;
+            return;
+        }
+        if config.frequency != self.frequency {
+            self.band = Biquad::band_pass(config.frequency, DE_ESSER_Q);
+            self.frequency = config.frequency;
+        }
+
+        let threshold = db_to_linear(config.threshold_db);
+        for sample in frame.iter_mut() {
+            let band = self.band.process(*sample);
+            let level = band.abs();
+            let coef = if level > self.envelope { self.attack } else { self.release };
+            self.envelope = level + coef * (self.envelope - level);
+
+            if self.envelope > threshold {
+                let over_db = 20.0 * (self.envelope / threshold).log10();
+                let gain = db_to_linear(-(over_db * config.amount).min(DE_ESSER_MAX_REDUCTION_DB));
+                *sample += band * (gain - 1.0);
+            }
+        }
+    }
+}
+
+impl Default for DeEsser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct BoostConfig {
+    /// 0 leaves the signal untouched
+    pub gain_db: f32,
+}
+
+/// Gain for very quiet microphones, with a soft clipper so the peaks it pushes past full scale
+/// saturate instead of clipping. Returns whether any sample reached the saturating part.
+pub fn boost(frame: &mut Frame, config: &BoostConfig) -> bool {
+    if config.gain_db <= 0.0 {
+        return false;
+    }
+    let gain = db_to_linear(config.gain_db.min(*BOOST_RANGE.end()));
+    let mut saturated = false;
+    for sample in frame.iter_mut() {
+        let boosted = *sample * gain;
+        saturated |= boosted.abs() > BOOST_KNEE;
+        *sample = saturate(boosted, BOOST_KNEE);
+    }
+    saturated
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PlosiveConfig {
+    pub enabled: bool,
+    /// 0.0..1.0, scales the low-band dip
+    pub strength: f32,
+}
+
+impl Default for PlosiveConfig {
+    fn default() -> Self {
+        Self { enabled: false, strength: 0.7 }
+    }
+}
+
+/// Dips the low band for a few frames when a sudden low-frequency burst ("p", "b") arrives
+pub struct PlosiveTamer {
+    low: Biquad,
+    low_band: Frame,
+    // Running average of low-band energy over non-plosive frames
+    low_average: f32,
+    hold: u32,
+    gain: f32,
+}
+
+impl PlosiveTamer {
+    pub fn new() -> Self {
+        Self {
+            low: Biquad::low_pass(PLOSIVE_CUTOFF, std::f32::consts::FRAC_1_SQRT_2),
+            low_band: [0.0; RNNOISE_FRAME_SIZE],
+            low_average: 0.0,
+            hold: 0,
+            gain: 1.0,
+        }
+    }
+
+    pub fn process(&mut self, frame: &mut Frame, config: &PlosiveConfig) {
+        if !config.enabled {
+            self.low.reset();
+            self.low_average = 0.0;
+            self.hold = 0;
+            self.gain = 1.0;
+            return;
+        }
+
+        for (l, x) in self.low_band.iter_mut().zip(frame.iter()) {
+            *l = self.low.process(*x);
+        }
+        let low_energy = self.low_band.iter().map(|x| x * x).sum::<f32>() / RNNOISE_FRAME_SIZE as f32;
+        let total_energy = frame.iter().map(|x| x * x).sum::<f32>() / RNNOISE_FRAME_SIZE as f32;
+
+        let plosive = low_energy > PLOSIVE_MIN_ENERGY
+            && low_energy > total_energy * PLOSIVE_LOW_SHARE
+            && low_energy > self.low_average * PLOSIVE_JUMP;
+        if plosive {
+            self.hold = PLOSIVE_HOLD_FRAMES;
+        } else if self.hold == 0 {
+            // Pops don't feed the average, or a burst of them would raise the bar
+            self.low_average = 0.9 * self.low_average + 0.1 * low_energy;
+        }
+
+        let target = if self.hold > 0 {
+            self.hold -= 1;
+            db_to_linear(-PLOSIVE_MAX_DIP_DB * config.strength)
+        } else {
+            1.0
+        };
+        for (sample, low) in frame.iter_mut().zip(self.low_band.iter()) {
+            self.gain += (target - self.gain) * PLOSIVE_GAIN_SMOOTHING;
+            *sample += low * (self.gain - 1.0);
+        }
+    }
+}
+
+impl Default for PlosiveTamer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ClickConfig {
+    pub enabled: bool,
+    /// 0.0..1.0; higher flags quieter transients
+    pub sensitivity: f32,
+}
+
+impl Default for ClickConfig {
+    fn default() -> Self {
+        Self { enabled: false, sensitivity: 0.5 }
+    }
+}
+
+/// Turns down short broadband transients (key clicks) that aren't followed by voicing.
+/// Deciding that needs the next frame, so output runs one frame (10 ms) behind while enabled.
+pub struct ClickSuppressor {
+    pending: Frame,
+    pending_click: bool,
+    // Slow average of the high-passed block energy
+    background: f32,
+    previous_sample: f32,
+    gain: f32,
+}
+
+impl ClickSuppressor {
+    pub fn new() -> Self {
+        Self {
+            pending: [0.0; RNNOISE_FRAME_SIZE],
+            pending_click: false,
+            background: 0.0,
+            previous_sample: 0.0,
+            gain: 1.0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// `analysis` is the denoised frame (before gating); `output` is what would be sent and is
+    /// replaced by the previous frame, attenuated if it held a click
+    pub fn process(&mut self, analysis: &Frame, output: &mut Frame, vad_prob: f32, config: &ClickConfig) {
+        if !config.enabled {
+            if self.pending_click || self.background != 0.0 {
+                self.reset();
+            }
+            return;
+        }
+
+        let click = self.detect(analysis, config.sensitivity);
+        let attenuate = self.pending_click && vad_prob < CLICK_SUSTAINED_VOICE;
+        std::mem::swap(&mut self.pending, output);
+        self.pending_click = click;
+
+        let target = if attenuate { db_to_linear(-CLICK_ATTENUATION_DB) } else { 1.0 };
+        for sample in output.iter_mut() {
+            self.gain += (target - self.gain) * CLICK_GAIN_SMOOTHING;
+            *sample *= self.gain;
+        }
+    }
+
+    fn detect(&mut self, frame: &Frame, sensitivity: f32) -> bool {
+        // First difference as a cheap high-pass: clicks are broadband, voiced speech isn't
+        let mut blocks = [0.0f32; RNNOISE_FRAME_SIZE / CLICK_BLOCK];
+        for (energy, block) in blocks.iter_mut().zip(frame.chunks(CLICK_BLOCK)) {
+            for x in block {
+                let d = x - self.previous_sample;
+                self.previous_sample = *x;
+                *energy += d * d;
+            }
+            *energy /= CLICK_BLOCK as f32;
+        }
+
+        let sensitivity = sensitivity.clamp(0.0, 1.0);
+        let ratio = CLICK_RATIO_LEAST_SENSITIVE.powf(1.0 - sensitivity) * CLICK_RATIO_MOST_SENSITIVE.powf(sensitivity);
+        let (peak_index, peak) = blocks.iter().copied().enumerate().fold((0, 0.0f32), |m, b| if b.1 > m.1 { b } else { m });
+        let last = blocks[blocks.len() - 1];
+        let click = self.background > 0.0
+            && peak > self.background * ratio
+            && peak_index < blocks.len() - 1
+            && last < peak * CLICK_DECAY;
+
+        if !click {
+            let mean = blocks.iter().sum::<f32>() / blocks.len() as f32;
+            self.background = if self.background == 0.0 { mean } else { 0.95 * self.background + 0.05 * mean };
+        }
+        click
+    }
+}
+
+impl Default for ClickSuppressor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MusicConfig {
+    pub enabled: bool,
+    /// 0.0..1.0; higher switches to passthrough on less obvious music
+    pub sensitivity: f32,
+}
+
+impl Default for MusicConfig {
+    fn default() -> Self {
+        Self { enabled: false, sensitivity: 0.5 }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct MusicFeatures {
+    rms: f32,
+    peak: f32,
+    harmonic: bool,
+    // Share of the energy in the first difference: a rough bandwidth measure
+    brightness: f32,
+}
+
+/// Tells music (sustained, harmonic, full-band, low crest factor) from speech (pauses between
+/// words, voicing that comes and goes) on the raw input
+pub struct MusicDetector {
+    window: VecDeque<MusicFeatures>,
+    hold: u32,
+    previous_sample: f32,
+}
+
+impl MusicDetector {
+    pub fn new() -> Self {
+        Self { window: VecDeque::with_capacity(MUSIC_WINDOW_FRAMES), hold: 0, previous_sample: 0.0 }
+    }
+
+    pub fn reset(&mut self) {
+        self.window.clear();
+        self.hold = 0;
+        self.previous_sample = 0.0;
+    }
+
+    pub fn is_music(&self) -> bool {
+        self.hold > 0
+    }
+
+    /// Returns whether the input should currently pass through unprocessed
+    pub fn process(&mut self, frame: &Frame, config: &MusicConfig) -> bool {
+        let features = self.features(frame);
+        if self.window.len() == MUSIC_WINDOW_FRAMES {
+            self.window.pop_front();
+        }
+        self.window.push_back(features);
+
+        let sensitivity = config.sensitivity.clamp(0.0, 1.0);
+        let threshold = MUSIC_SCORE_LEAST_SENSITIVE + (MUSIC_SCORE_MOST_SENSITIVE - MUSIC_SCORE_LEAST_SENSITIVE) * sensitivity;
+        if self.window.len() == MUSIC_WINDOW_FRAMES && self.score() > threshold {
+            if self.hold == 0 {
+                log::info!("Music detected, passing input through");
+            }
+            self.hold = MUSIC_HOLD_FRAMES;
+        } else if self.hold > 0 {
+            self.hold -= 1;
+            if self.hold == 0 {
+                log::info!("Music ended, noise suppression resumed");
+            }
+        }
+        self.is_music()
+    }
+
+    fn features(&mut self, frame: &Frame) -> MusicFeatures {
+        let rms = rms(frame);
+        let peak = frame.iter().fold(0.0f32, |m, x| m.max(x.abs()));
+        let mut diff_energy = 0.0;
+        for x in frame {
+            let d = x - self.previous_sample;
+            self.previous_sample = *x;
+            diff_energy += d * d;
+        }
+        let energy = rms * rms * RNNOISE_FRAME_SIZE as f32;
+        let brightness = if energy > 0.0 { (diff_energy / (4.0 * energy)).min(1.0) } else { 0.0 };
+        let harmonic = rms >= MUSIC_ACTIVE_RMS && periodicity(frame) > MUSIC_HARMONIC_CORRELATION;
+        MusicFeatures { rms, peak, harmonic, brightness }
+    }
+
+    // 0..1, the mean of four cues that each lean towards music
+    fn score(&self) -> f32 {
+        let (mut active, mut harmonic, mut brightness, mut energy, mut peak) = (0usize, 0usize, 0.0f32, 0.0f32, 0.0f32);
+        for f in &self.window {
+            energy += f.rms * f.rms;
+            peak = peak.max(f.peak);
+            if f.rms >= MUSIC_ACTIVE_RMS {
+                active += 1;
+                harmonic += f.harmonic as usize;
+                brightness += f.brightness;
+            }
+        }
+        if active < MUSIC_WINDOW_FRAMES / 2 {
+            return 0.0;
+        }
+        // Speech leaves gaps between words; a played instrument rarely does
+        let sustain = active as f32 / self.window.len() as f32;
+        let harmonic = harmonic as f32 / active as f32;
+        let bandwidth = (brightness / active as f32 / 0.1).min(1.0);
+        // Peak-to-RMS over the window: ~20 dB for speech, ~10 dB for most music
+        let rms = (energy / self.window.len() as f32).sqrt().max(1e-9);
+        let crest_db = 20.0 * (peak / rms).log10();
+        let low_crest = (1.0 - (crest_db - 10.0) / 10.0).clamp(0.0, 1.0);
+        (sustain + harmonic + bandwidth + low_crest) / 4.0
+    }
+}
+
+impl Default for MusicDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Strongest normalized autocorrelation over the pitch range, on a decimated copy of the frame
+fn periodicity(frame: &Frame) -> f32 {
+    let mut decimated = [0.0f32; RNNOISE_FRAME_SIZE / MUSIC_DECIMATION];
+    for (d, chunk) in decimated.iter_mut().zip(frame.chunks(MUSIC_DECIMATION)) {
+        *d = chunk.iter().sum::<f32>() / MUSIC_DECIMATION as f32;
+    }
+    (MUSIC_MIN_LAG..=MUSIC_MAX_LAG)
+        .map(|lag| {
+            let (a, b) = (&decimated[..decimated.len() - lag], &decimated[lag..]);
+            let cross: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+            let norm = (a.iter().map(|x| x * x).sum::<f32>() * b.iter().map(|y| y * y).sum::<f32>()).sqrt();
+            if norm > 0.0 { cross / norm } else { 0.0 }
+        })
+        .fold(0.0, f32::max)
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MeterMode {
+    Rms,
+    /// Sample peak including inter-sample overs, estimated with 4x interpolation
+    Peak,
+}
+
+impl MeterMode {
+    pub const ALL: [MeterMode; 2] = [MeterMode::Rms, MeterMode::Peak];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MeterMode::Rms => "rms",
+            MeterMode::Peak => "peak",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MeterMode::Rms => "RMS",
+            MeterMode::Peak => "True peak",
+        }
+    }
+}
+
+impl FromStr for MeterMode {
+    type Err = ParseSettingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.iter().copied().find(|m| m.as_str() == s).ok_or_else(|| ParseSettingError::new("meter mode", s))
+    }
+}
+
+/// Output levels with meter ballistics, updated once per frame
+pub struct LevelMeter {
+    pub rms: f32,
+    pub peak: f32,
+    attack: f32,
+    release: f32,
+    // Last three samples of the previous frame, for interpolating across the boundary
+    history: [f32; 3],
+}
+
+impl LevelMeter {
+    pub fn new() -> Self {
+        let frame_seconds = RNNOISE_FRAME_SIZE as f32 / SAMPLE_RATE;
+        Self {
+            rms: 0.0,
+            peak: 0.0,
+            attack: (-frame_seconds / METER_ATTACK_SECONDS).exp(),
+            release: (-frame_seconds / METER_RELEASE_SECONDS).exp(),
+            history: [0.0; 3],
+        }
+    }
+
+    pub fn update(&mut self, frame: &Frame) {
+        let rms = rms(frame);
+        let peak = self.true_peak(frame);
+        self.rms = self.follow(self.rms, rms);
+        self.peak = self.follow(self.peak, peak);
+    }
+
+    fn follow(&self, current: f32, target: f32) -> f32 {
+        let coef = if target > current { self.attack } else { self.release };
+        target + coef * (current - target)
+    }
+
+    fn true_peak(&mut self, frame: &Frame) -> f32 {
+        interpolated_peak(&mut self.history, frame)
+    }
+}
+
+impl Default for LevelMeter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Largest magnitude in `samples` including inter-sample overs, estimated with 4x interpolation.
+// `history` holds the last three samples of the previous call, for interpolating across the
+// boundary.
+pub(crate) fn interpolated_peak(history: &mut [f32; 3], samples: &[f32]) -> f32 {
+    let mut peak = 0.0f32;
+    for &x3 in samples {
+        let [x0, x1, x2] = *history;
+        // Catmull-Rom between x1 and x2 at quarter steps
+        for t in [0.25, 0.5, 0.75] {
+            let t2 = t * t;
+            let t3 = t2 * t;
+            let y = 0.5
+                * (2.0 * x1
+                    + (x2 - x0) * t
+                    + (2.0 * x0 - 5.0 * x1 + 4.0 * x2 - x3) * t2
+                    + (3.0 * x1 - x0 - 3.0 * x2 + x3) * t3);
+            peak = peak.max(y.abs());
+        }
+        peak = peak.max(x3.abs());
+        *history = [x1, x2, x3];
+    }
+    peak
+}
+
+/// Leaves samples below the knee alone and bends anything above it smoothly toward full scale
+pub fn soft_clip(frame: &mut Frame) {
+    const KNEE: f32 = 0.9;
+    for sample in frame.iter_mut() {
+        *sample = saturate(*sample, KNEE);
+    }
+}
+
+// Identity up to `knee`, then a tanh curve that approaches but never reaches ±1.0.
+// Monotonic and continuous in slope at the knee.
+fn saturate(sample: f32, knee: f32) -> f32 {
+    let magnitude = sample.abs();
+    if magnitude <= knee {
+        return sample;
+    }
+    let headroom = 1.0 - knee;
+    sample.signum() * (knee + headroom * ((magnitude - knee) / headroom).tanh())
+}
+
+fn db_to_linear(db: f32) -> f32 {
+    10.0f32.powf(db / 20.0)
+}
+
+pub fn rms(frame: &[f32]) -> f32 {
+    (simd::sum_of_squares(frame) / frame.len().max(1) as f32).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn boosted(sample: f32, gain_db: f32) -> (f32, bool) {
+        let mut frame = [sample; RNNOISE_FRAME_SIZE];
+        let saturated = boost(&mut frame, &BoostConfig { gain_db });
+        (frame[0], saturated)
+    }
+
+    #[test]
+    fn boost_gain_table() {
+        // (gain dB, input, expected output, saturated)
+        let table = [
+            (0.0, 0.3, 0.3, false),
+            (-6.0, 0.3, 0.3, false),
+            (20.0, 0.01, 0.1, false),
+            (20.0, -0.04, -0.4, false),
+            (6.0206, 0.25, 0.5, false),
+            // Past the knee: bent toward full scale, not 10.0
+            (20.0, 0.1, 0.5 + 0.5 * 1.0f32.tanh(), true),
+            (20.0, -0.1, -(0.5 + 0.5 * 1.0f32.tanh()), true),
+            // Gain above the range is held at its top
+            (40.0, 0.01, 0.01 * 10.0f32.powf(1.5), false),
+        ];
+        for (gain_db, input, expected, saturates) in table {
+            let (output, saturated) = boosted(input, gain_db);
+            assert!((output - expected).abs() < 1e-4, "{} dB on {}: {} instead of {}", gain_db, input, output, expected);
+            assert_eq!(saturated, saturates, "{} dB on {}", gain_db, input);
+        }
+    }
+
+    #[test]
+    fn boost_is_monotonic_and_stays_within_full_scale() {
+        for gain_db in [0.5, 6.0, 12.0, 20.0, 30.0] {
+            let mut previous = f32::NEG_INFINITY;
+            for i in -2000..=2000 {
+                let input = i as f32 / 1000.0;
+                let (output, _) = boosted(input, gain_db);
+                assert!(output >= previous, "{} dB: {} gives {} after {}", gain_db, input, output, previous);
+                assert!((-1.0..=1.0).contains(&output), "{} dB: {} gives {}", gain_db, input, output);
+                previous = output;
+            }
+        }
+    }
+
+    #[test]
+    fn saturate_curve_table() {
+        // (knee, input, expected)
+        let table = [
+            (0.9, 0.0, 0.0),
+            (0.9, 0.9, 0.9),
+            (0.9, -0.5, -0.5),
+            (0.9, 1.0, 0.9 + 0.1 * 1.0f32.tanh()),
+            (0.5, 0.5, 0.5),
+            (0.5, 1.0, 0.5 + 0.5 * 1.0f32.tanh()),
+            (0.5, 100.0, 1.0),
+            (0.5, -100.0, -1.0),
+        ];
+        for (knee, input, expected) in table {
+            let output = saturate(input, knee);
+            assert!((output - expected).abs() < 1e-6, "knee {} on {}: {} instead of {}", knee, input, output, expected);
+        }
+    }
+
+    #[test]
+    fn soft_clip_is_monotonic_and_stays_within_full_scale() {
+        let mut previous = f32::NEG_INFINITY;
+        for i in -4000..=4000 {
+            let mut frame = [i as f32 / 1000.0; RNNOISE_FRAME_SIZE];
+            soft_clip(&mut frame);
+            assert!(frame[0] >= previous && (-1.0..=1.0).contains(&frame[0]), "{} gives {}", i as f32 / 1000.0, frame[0]);
+            previous = frame[0];
+        }
+    }
+}
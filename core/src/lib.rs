@@ -0,0 +1,53 @@
+//! SilentStream's audio side as a library: device enumeration, the real-time engine (capture ->
+//! RNNoise and the other stages -> output device) and an offline processor for audio already in
+//! memory or in files. The SilentStream app is one frontend on top of it; nothing here depends on
+//! a UI toolkit, and nothing in the public API is specific to Windows.
+//!
+//! Failures come back as [`EngineError`]. Its variant says what the user can do about it
+//! (device missing, device busy, format unsupported, anything else); the details keep the
+//! underlying error's text.
+//!
+//! List the devices, start the engine and adjust the threshold while it runs:
+//!
+//! ```no_run
+//! use silentstream_core::{input_device_names, output_device_names, AudioEngine, Settings};
+//!
+//! for name in input_device_names() {
+//!     println!("input: {}", name);
+//! }
+//! let outputs = output_device_names();
+//! let cable = outputs.iter().position(|name| name.starts_with("CABLE Input")).unwrap_or(0);
+//!
+//! let mut engine = AudioEngine::new();
+//! engine.apply_settings(&Settings { strong_suppression: true, ..Settings::default() });
+//! engine.start(0, cable)?;
+//! engine.set_threshold(0.8);
+//! assert_eq!(engine.settings().threshold, 0.8);
+//! engine.stop_and_wait();
+//! # Ok::<(), silentstream_core::EngineError>(())
+//! ```
+
+mod aec;
+pub mod audio_engine;
+pub mod dsp;
+pub mod loudness;
+pub mod monitor;
+pub mod noise_print;
+pub mod offline;
+pub mod offline_file;
+pub mod output_format;
+pub mod overload;
+pub mod pipeline;
+pub mod plugin_host;
+pub mod replay;
+pub mod resample;
+pub mod settings;
+pub mod simd;
+pub mod vad_histogram;
+
+pub use audio_engine::{
+    default_input_name, default_output_name, input_device_names, output_device_names, AudioEngine, EngineError, EngineStatus,
+};
+pub use offline::OfflineProcessor;
+pub use pipeline::Controls;
+pub use settings::{ParseSettingError, Settings};
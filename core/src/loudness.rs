@@ -0,0 +1,143 @@
+//! Integrated loudness (ITU-R BS.1770 / EBU R128) and true peak of a whole recording, fed a
+//! block at a time. K-weighted power over 400 ms blocks every 100 ms, gated at -70 LUFS and
+//! then 10 LU under the ungated average. Every channel counts with weight 1, which is right
+//! for mono and stereo; surround channels would want 1.41.
+use crate::dsp::interpolated_peak;
+use std::ops::Range;
+
+// Gating block and the step between blocks, in 100 ms units
+const STEPS_PER_BLOCK: usize = 4;
+const STEP_SECONDS: f64 = 0.1;
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const RELATIVE_GATE_LU: f64 = -10.0;
+
+pub struct LoudnessMeter {
+    channels: Vec<Channel>,
+    step_frames: usize,
+    // Frames and summed K-weighted squares of the step being filled
+    filled: usize,
+    energy: f64,
+    // The last few steps' summed squares, oldest first
+    recent: Vec<f64>,
+    // Mean power of every complete block
+    blocks: Vec<f64>,
+    peak: f32,
+}
+
+struct Channel {
+    shelf: Biquad,
+    high_pass: Biquad,
+    history: [f32; 3],
+}
+
+impl LoudnessMeter {
+    pub fn new(channels: usize, sample_rate: u32) -> Self {
+        let rate = sample_rate as f64;
+        Self {
+            channels: (0..channels)
+                .map(|_| Channel { shelf: Biquad::shelf(rate), high_pass: Biquad::high_pass(rate), history: [0.0; 3] })
+                .collect(),
+            step_frames: ((rate * STEP_SECONDS).round() as usize).max(1),
+            filled: 0,
+            energy: 0.0,
+            recent: Vec::with_capacity(STEPS_PER_BLOCK),
+            blocks: Vec::new(),
+            peak: 0.0,
+        }
+    }
+
+    /// `frames` of each of `samples`, one slice per channel
+    pub fn add(&mut self, samples: &[Vec<f32>], frames: Range<usize>) {
+        for (channel, samples) in self.channels.iter_mut().zip(samples) {
+            self.peak = self.peak.max(interpolated_peak(&mut channel.history, &samples[frames.clone()]));
+        }
+        for i in frames {
+            for (channel, samples) in self.channels.iter_mut().zip(samples) {
+                let weighted = channel.high_pass.process(channel.shelf.process(samples[i] as f64));
+                self.energy += weighted * weighted;
+            }
+            self.filled += 1;
+            if self.filled == self.step_frames {
+                self.end_step();
+            }
+        }
+    }
+
+    fn end_step(&mut self) {
+        if self.recent.len() == STEPS_PER_BLOCK {
+            self.recent.remove(0);
+        }
+        self.recent.push(self.energy);
+        if self.recent.len() == STEPS_PER_BLOCK {
+            self.blocks.push(self.recent.iter().sum::<f64>() / (STEPS_PER_BLOCK * self.step_frames) as f64);
+        }
+        self.filled = 0;
+        self.energy = 0.0;
+    }
+
+    /// None for silence or anything shorter than one block
+    pub fn integrated(&self) -> Option<f32> {
+        let gate = power(ABSOLUTE_GATE_LUFS);
+        let ungated = gated_mean(&self.blocks, gate)?;
+        let gate = gate.max(ungated * 10f64.powf(RELATIVE_GATE_LU / 10.0));
+        Some(lufs(gated_mean(&self.blocks, gate)?) as f32)
+    }
+
+    /// Highest true peak so far, in dBTP
+    pub fn true_peak_db(&self) -> f32 {
+        20.0 * self.peak.log10()
+    }
+}
+
+// Mean of the blocks above `threshold`, None when there are none
+fn gated_mean(blocks: &[f64], threshold: f64) -> Option<f64> {
+    let (sum, count) = blocks.iter().filter(|&&b| b > threshold).fold((0.0, 0), |(sum, count), b| (sum + b, count + 1));
+    (count > 0).then(|| sum / count as f64)
+}
+
+fn lufs(power: f64) -> f64 {
+    -0.691 + 10.0 * power.log10()
+}
+
+fn power(lufs: f64) -> f64 {
+    10f64.powf((lufs + 0.691) / 10.0)
+}
+
+// The two K-weighting stages, coefficients worked out for the rate as in libebur128
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    // Head-related high shelf, about +4 dB above 1.5 kHz
+    fn shelf(rate: f64) -> Self {
+        let (f0, gain, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+        let k = (std::f64::consts::PI * f0 / rate).tan();
+        let vh = 10f64.powf(gain / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        Self {
+            b: [(vh + vb * k / q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            z: [0.0; 2],
+        }
+    }
+
+    // RLB high-pass around 38 Hz
+    fn high_pass(rate: f64) -> Self {
+        let (f0, q) = (38.13547087602444, 0.5003270373238773);
+        let k = (std::f64::consts::PI * f0 / rate).tan();
+        let a0 = 1.0 + k / q + k * k;
+        Self { b: [1.0, -2.0, 1.0], a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0], z: [0.0; 2] }
+    }
+
+    // Transposed direct form II
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[0] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
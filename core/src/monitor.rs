@@ -0,0 +1,61 @@
+//! Optional second output that plays the processed stream, e.g. on headphones
+use crate::audio_engine::{AtomicF32, EngineError, RING_BUFFER_SIZE};
+use crate::output_format;
+use crate::resample::{OutputQueue, ResamplerQuality};
+use cpal::traits::{DeviceTrait, HostTrait};
+use cpal::{Stream, StreamConfig};
+use ringbuf::HeapRb;
+use std::sync::Arc;
+
+pub struct MonitorOutput {
+    pub stream: Stream,
+    /// Processed 48 kHz audio converted to the monitor device's rate
+    pub tap: OutputQueue,
+    pub device_name: String,
+    pub sample_rate: u32,
+}
+
+/// Opens the named output device (empty = default) with its own queue and resampler
+pub fn open(
+    host: &cpal::Host,
+    name: &str,
+    gain: Arc<AtomicF32>,
+    quality: ResamplerQuality,
+) -> Result<MonitorOutput, EngineError> {
+    let device = if name.is_empty() {
+        host.default_output_device().ok_or_else(|| EngineError::not_found("No default output device"))?
+    } else {
+        host.output_devices()?
+            .find(|d| d.name().map(|n| n == name).unwrap_or(false))
+            .ok_or_else(|| EngineError::not_found(format!("Output device '{}' not found", name)).with_device(name))?
+    };
+    let format = output_format::negotiate(&device)?;
+    let config: StreamConfig = format.config();
+    let sample_rate = config.sample_rate.0;
+    let (prod, mut cons) = HeapRb::<f32>::new(RING_BUFFER_SIZE).split();
+    let tap = OutputQueue::new(prod, sample_rate, quality)?;
+
+    let stream = output_format::build_stream(
+        &device,
+        &format,
+        &config,
+        move |mono: &mut [f32]| {
+            let gain = gain.load();
+            for sample in mono {
+                *sample = cons.pop().unwrap_or(0.0) * gain;
+            }
+        },
+        // Only this stream is affected; the main output keeps running
+        |err| log::error!("Monitor stream error: {}", err),
+    )?;
+
+    let device_name = device.name().unwrap_or_default();
+    log::info!(
+        "Monitor output: '{}' ({} Hz, {} ch, {})",
+        device_name,
+        sample_rate,
+        config.channels,
+        format.sample_format()
+    );
+    Ok(MonitorOutput { stream, tap, device_name, sample_rate })
+}
@@ -0,0 +1,362 @@
+//! Learned noise print and mild spectral subtraction against it, for steady tones and hums that
+//! RNNoise leaves alone (monitor whine, fan harmonics). The print is the average magnitude
+//! spectrum of two seconds of noise-only input; one is kept per input device.
+use crate::audio_engine::RNNOISE_FRAME_SIZE;
+use crate::dsp::Frame;
+use realfft::num_complex::Complex;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use std::collections::BTreeMap;
+use std::f32::consts::TAU;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+// Two frames per transform with 50% overlap, so each 10 ms frame completes one hop
+const FFT_SIZE: usize = RNNOISE_FRAME_SIZE * 2;
+pub const BINS: usize = FFT_SIZE / 2 + 1;
+/// Two seconds of 10 ms frames
+pub const LEARN_FRAMES: usize = 200;
+// At full amount the noise estimate is doubled and bins can drop by 30 dB
+const MAX_OVERSUBTRACTION: f32 = 1.0;
+const MAX_DEPTH_DB: f32 = 30.0;
+// Per-frame smoothing of the bin gains, against "musical noise" from gains flickering
+const GAIN_SMOOTHING: f32 = 0.5;
+const PRINTS_FILE: &str = "noise_prints.txt";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct NoisePrintConfig {
+    /// 0..=1: how hard the print is subtracted
+    pub amount: f32,
+}
+
+impl Default for NoisePrintConfig {
+    fn default() -> Self {
+        Self { amount: 0.7 }
+    }
+}
+
+/// Average magnitude per FFT bin
+#[derive(Clone, PartialEq, Debug)]
+pub struct NoiseProfile {
+    pub magnitudes: Vec<f32>,
+}
+
+impl NoiseProfile {
+    // Comma-separated dB values, so the file stays readable
+    fn to_line(&self) -> String {
+        self.magnitudes.iter().map(|m| format!("{:.1}", 20.0 * m.max(1e-9).log10())).collect::<Vec<_>>().join(",")
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let magnitudes: Vec<f32> = line
+            .split(',')
+            .map(|v| v.trim().parse::<f32>().ok().filter(|v| v.is_finite()).map(|db| 10f32.powf(db / 20.0)))
+            .collect::<Option<_>>()?;
+        (magnitudes.len() == BINS).then_some(Self { magnitudes })
+    }
+}
+
+fn prints_path(config_dir: &Path) -> PathBuf {
+    config_dir.join(PRINTS_FILE)
+}
+
+/// One line per device: `<dB values>:<device name>`
+pub fn load_prints(config_dir: &Path) -> BTreeMap<String, NoiseProfile> {
+    let Ok(content) = fs::read_to_string(prints_path(config_dir)) else { return BTreeMap::new() };
+    content
+        .lines()
+        .filter_map(|line| {
+            let (values, device) = line.split_once(':')?;
+            Some((device.to_string(), NoiseProfile::from_line(values)?))
+        })
+        .collect()
+}
+
+pub fn save_prints(config_dir: &Path, prints: &BTreeMap<String, NoiseProfile>) {
+    let content: String = prints.iter().map(|(device, profile)| format!("{}:{}\n", profile.to_line(), device)).collect();
+    let path = prints_path(config_dir);
+    if let Err(e) = fs::write(&path, content) {
+        log::warn!("Could not save noise prints to {}: {}", path.display(), e);
+    }
+}
+
+/// Shared between the UI and the processing thread. The thread only ever try_locks, and only
+/// after `generation` says the profile changed.
+#[derive(Default)]
+pub struct NoisePrintSlot {
+    profile: Mutex<Option<Arc<NoiseProfile>>>,
+    generation: AtomicU64,
+    learn: AtomicBool,
+    learned_frames: AtomicUsize,
+    learned: Mutex<Option<NoiseProfile>>,
+}
+
+impl NoisePrintSlot {
+    pub fn set_profile(&self, profile: Option<NoiseProfile>) {
+        if let Ok(mut current) = self.profile.lock() {
+            *current = profile.map(Arc::new);
+            self.generation.fetch_add(1, Ordering::Release);
+        }
+    }
+
+    // Bumped by every set_profile()
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    // The print in use, or None while the UI holds the lock
+    pub(crate) fn try_profile(&self) -> Option<Option<Arc<NoiseProfile>>> {
+        self.profile.try_lock().ok().map(|profile| profile.clone())
+    }
+
+    /// The next LEARN_FRAMES frames reaching the stage become the new print
+    pub fn start_learning(&self) {
+        self.learned_frames.store(0, Ordering::Relaxed);
+        self.learn.store(true, Ordering::Release);
+    }
+
+    pub fn cancel_learning(&self) {
+        self.learn.store(false, Ordering::Release);
+    }
+
+    /// 0..=1 while learning, None otherwise
+    pub fn learning_progress(&self) -> Option<f32> {
+        self.learn
+            .load(Ordering::Acquire)
+            .then(|| self.learned_frames.load(Ordering::Relaxed) as f32 / LEARN_FRAMES as f32)
+    }
+
+    /// A finished print, once; it is already in use by then
+    pub fn take_learned(&self) -> Option<NoiseProfile> {
+        self.learned.lock().ok()?.take()
+    }
+}
+
+/// Overlap-add spectral subtraction, one hop per frame. Passes audio through untouched (and
+/// without the extra frame of delay) while there is no print.
+pub struct NoisePrintFilter {
+    slot: Arc<NoisePrintSlot>,
+    generation: u64,
+    profile: Option<Arc<NoiseProfile>>,
+    forward: Arc<dyn RealToComplex<f32>>,
+    inverse: Arc<dyn ComplexToReal<f32>>,
+    // sqrt-Hann, used for both analysis and synthesis; the squares overlap-add to 1
+    window: Vec<f32>,
+    // Previous and current frame
+    input: Vec<f32>,
+    time: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+    gains: Vec<f32>,
+    // Second half of the previous synthesis frame
+    overlap: Vec<f32>,
+    learn_sum: Vec<f32>,
+    learn_frames: usize,
+}
+
+impl NoisePrintFilter {
+    pub fn new(slot: Arc<NoisePrintSlot>) -> Self {
+        let mut planner = RealFftPlanner::<f32>::new();
+        let forward = planner.plan_fft_forward(FFT_SIZE);
+        let inverse = planner.plan_fft_inverse(FFT_SIZE);
+        let scratch_len = forward.get_scratch_len().max(inverse.get_scratch_len());
+        let window = (0..FFT_SIZE).map(|i| (0.5 - 0.5 * (TAU * i as f32 / FFT_SIZE as f32).cos()).sqrt()).collect();
+        Self {
+            slot,
+            generation: 0,
+            profile: None,
+            forward,
+            inverse,
+            window,
+            input: vec![0.0; FFT_SIZE],
+            time: vec![0.0; FFT_SIZE],
+            spectrum: vec![Complex::default(); BINS],
+            scratch: vec![Complex::default(); scratch_len],
+            gains: vec![1.0; BINS],
+            overlap: vec![0.0; RNNOISE_FRAME_SIZE],
+            learn_sum: vec![0.0; BINS],
+            learn_frames: 0,
+        }
+    }
+
+    pub fn process(&mut self, frame: &mut Frame, config: &NoisePrintConfig) {
+        self.sync_profile();
+        let learning = self.slot.learn.load(Ordering::Acquire);
+        if !learning && self.learn_frames > 0 {
+            // Cancelled
+            self.learn_frames = 0;
+        }
+        if !learning && self.profile.is_none() {
+            return;
+        }
+
+        self.input.copy_within(RNNOISE_FRAME_SIZE.., 0);
+        self.input[RNNOISE_FRAME_SIZE..].copy_from_slice(frame);
+        for ((t, x), w) in self.time.iter_mut().zip(&self.input).zip(&self.window) {
+            *t = x * w;
+        }
+        if self.forward.process_with_scratch(&mut self.time, &mut self.spectrum, &mut self.scratch).is_err() {
+            return;
+        }
+
+        if learning {
+            self.learn();
+        }
+        let Some(profile) = self.profile.clone() else { return };
+
+        let amount = config.amount.clamp(0.0, 1.0);
+        let oversubtraction = 1.0 + amount * MAX_OVERSUBTRACTION;
+        let floor = 10f32.powf(-amount * MAX_DEPTH_DB / 20.0);
+        for ((bin, gain), noise) in self.spectrum.iter_mut().zip(self.gains.iter_mut()).zip(&profile.magnitudes) {
+            let magnitude = bin.norm();
+            let target = if magnitude > 0.0 { (1.0 - oversubtraction * noise / magnitude).max(floor) } else { floor };
+            *gain = *gain * GAIN_SMOOTHING + target * (1.0 - GAIN_SMOOTHING);
+            *bin *= *gain;
+        }
+        // The inverse transform rejects imaginary parts it can't represent
+        self.spectrum[0].im = 0.0;
+        self.spectrum[BINS - 1].im = 0.0;
+        if self.inverse.process_with_scratch(&mut self.spectrum, &mut self.time, &mut self.scratch).is_err() {
+            return;
+        }
+
+        let scale = 1.0 / FFT_SIZE as f32;
+        for (i, out) in frame.iter_mut().enumerate() {
+            *out = self.overlap[i] + self.time[i] * self.window[i] * scale;
+        }
+        for (i, o) in self.overlap.iter_mut().enumerate() {
+            let j = i + RNNOISE_FRAME_SIZE;
+            *o = self.time[j] * self.window[j] * scale;
+        }
+    }
+
+    /// Samples of delay: one frame while a print is in use
+    pub fn latency(&self) -> usize {
+        if self.profile.is_some() { RNNOISE_FRAME_SIZE } else { 0 }
+    }
+
+    /// Clears the overlap state; the print and any learning in progress are kept
+    pub fn reset(&mut self) {
+        self.input.fill(0.0);
+        self.overlap.fill(0.0);
+        self.gains.fill(1.0);
+    }
+
+    fn sync_profile(&mut self) {
+        let generation = self.slot.generation.load(Ordering::Acquire);
+        if generation == self.generation {
+            return;
+        }
+        let Ok(profile) = self.slot.profile.try_lock().map(|p| p.clone()) else { return };
+        self.profile = profile;
+        self.generation = generation;
+        self.reset();
+    }
+
+    fn learn(&mut self) {
+        // The first transform still holds a frame from before learning started
+        self.learn_frames += 1;
+        if self.learn_frames == 1 {
+            self.learn_sum.fill(0.0);
+            return;
+        }
+        for (sum, bin) in self.learn_sum.iter_mut().zip(&self.spectrum) {
+            *sum += bin.norm();
+        }
+        let counted = self.learn_frames - 1;
+        self.slot.learned_frames.store(counted, Ordering::Relaxed);
+        if counted < LEARN_FRAMES {
+            return;
+        }
+        let magnitudes = self.learn_sum.iter().map(|s| s / counted as f32).collect();
+        let profile = NoiseProfile { magnitudes };
+        self.learn_frames = 0;
+        self.slot.learn.store(false, Ordering::Release);
+        if let Ok(mut learned) = self.slot.learned.lock() {
+            *learned = Some(profile.clone());
+        }
+        self.slot.set_profile(Some(profile));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `frames` frames of a sine starting at frame `start`, so consecutive calls join up
+    fn tone(freq: f32, amplitude: f32, start: usize, frames: usize) -> Vec<Frame> {
+        (start..start + frames)
+            .map(|f| {
+                std::array::from_fn(|i| {
+                    let t = (f * RNNOISE_FRAME_SIZE + i) as f32 / 48000.0;
+                    amplitude * (TAU * freq * t).sin()
+                })
+            })
+            .collect()
+    }
+
+    fn rms(frames: &[Frame]) -> f32 {
+        let n = frames.len() * RNNOISE_FRAME_SIZE;
+        (frames.iter().flatten().map(|s| s * s).sum::<f32>() / n as f32).sqrt()
+    }
+
+    fn db(ratio: f32) -> f32 {
+        20.0 * ratio.log10()
+    }
+
+    // A filter that has learned a steady 15.6 kHz whine
+    fn learned_filter() -> (NoisePrintFilter, Arc<NoisePrintSlot>) {
+        let slot = Arc::new(NoisePrintSlot::default());
+        let mut filter = NoisePrintFilter::new(slot.clone());
+        slot.start_learning();
+        for mut frame in tone(15600.0, 0.1, 0, LEARN_FRAMES + 1) {
+            filter.process(&mut frame, &NoisePrintConfig::default());
+        }
+        assert!(slot.take_learned().is_some());
+        assert_eq!(slot.learning_progress(), None);
+        (filter, slot)
+    }
+
+    #[test]
+    fn learned_tone_is_attenuated_by_more_than_20_db() {
+        let (mut filter, _slot) = learned_filter();
+        let input = tone(15600.0, 0.1, LEARN_FRAMES + 1, 100);
+        let mut output = input.clone();
+        for frame in &mut output {
+            filter.process(frame, &NoisePrintConfig { amount: 1.0 });
+        }
+        // Past the gain smoothing
+        let attenuation = db(rms(&input[20..]) / rms(&output[20..]));
+        assert!(attenuation > 20.0, "attenuated by {:.1} dB", attenuation);
+    }
+
+    #[test]
+    fn other_tones_pass_through_the_print() {
+        let (mut filter, _slot) = learned_filter();
+        let voice = tone(1000.0, 0.1, 0, 100);
+        let mut output: Vec<Frame> = tone(15600.0, 0.1, LEARN_FRAMES + 1, 100)
+            .iter()
+            .zip(&voice)
+            .map(|(a, b)| std::array::from_fn(|i| a[i] + b[i]))
+            .collect();
+        for frame in &mut output {
+            filter.process(frame, &NoisePrintConfig::default());
+        }
+        // The whine is mostly gone, so what's left is the 1 kHz tone, one frame late
+        let change = db(rms(&output[21..]) / rms(&voice[20..99]));
+        assert!(change.abs() < 1.0, "1 kHz tone changed by {:.1} dB", change);
+    }
+
+    #[test]
+    fn without_a_print_audio_passes_untouched() {
+        let mut filter = NoisePrintFilter::new(Arc::new(NoisePrintSlot::default()));
+        let input = tone(15600.0, 0.1, 0, 10);
+        let mut output = input.clone();
+        for frame in &mut output {
+            filter.process(frame, &NoisePrintConfig::default());
+        }
+        assert_eq!(output, input);
+        assert_eq!(filter.latency(), 0);
+    }
+}
@@ -0,0 +1,95 @@
+//! The processing chain run over audio that's already in memory (a recording, a test signal),
+//! with no devices or threads. Input and output are mono at 48 kHz; samples that don't fill a
+//! whole frame wait for the next call. The output lags the input by the chain's latency, the
+//! same as live, and finish() pushes the remaining tail out.
+use crate::audio_engine::{SessionStats, RNNOISE_FRAME_SIZE};
+use crate::dsp::Frame;
+use crate::noise_print::NoisePrintSlot;
+use crate::pipeline::{Controls, Pipeline};
+use crate::plugin_host::PluginSlot;
+use std::sync::Arc;
+
+/// The only rate the chain runs at
+pub const SAMPLE_RATE: u32 = 48000;
+
+/// See the module docs.
+///
+/// ```
+/// use silentstream_core::{Controls, OfflineProcessor};
+///
+/// let mut processor = OfflineProcessor::new(Controls { threshold: 0.6, ..Controls::default() });
+/// let mut output = Vec::new();
+/// processor.process_block(&vec![0.0; 1000], &mut output);
+/// processor.finish(&mut output);
+/// assert!(output.len() >= 1000);
+/// ```
+pub struct OfflineProcessor {
+    pipeline: Pipeline,
+    /// Applied from the next frame on
+    pub controls: Controls,
+    pending: Vec<f32>,
+    frame: Frame,
+    output: Frame,
+    // Stands in for the echo reference and mix input, which offline processing doesn't have
+    silence: Frame,
+    stats: SessionStats,
+}
+
+impl OfflineProcessor {
+    pub fn new(controls: Controls) -> Self {
+        Self {
+            pipeline: Pipeline::new(false, &Arc::new(PluginSlot::default()), &Arc::new(NoisePrintSlot::default())),
+            controls,
+            pending: Vec::with_capacity(RNNOISE_FRAME_SIZE),
+            frame: [0.0; RNNOISE_FRAME_SIZE],
+            output: [0.0; RNNOISE_FRAME_SIZE],
+            silence: [0.0; RNNOISE_FRAME_SIZE],
+            stats: SessionStats::default(),
+        }
+    }
+
+    /// Processes `input` (-1.0..1.0) and appends every completed frame to `output`
+    pub fn process_block(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        let mut input = input;
+        while !input.is_empty() {
+            let take = (RNNOISE_FRAME_SIZE - self.pending.len()).min(input.len());
+            self.pending.extend_from_slice(&input[..take]);
+            input = &input[take..];
+            if self.pending.len() == RNNOISE_FRAME_SIZE {
+                self.frame.copy_from_slice(&self.pending);
+                self.pending.clear();
+                self.process_frame(output);
+            }
+        }
+    }
+
+    /// Pads the last partial frame with silence and runs enough silence after it to push the
+    /// chain's latency out
+    pub fn finish(&mut self, output: &mut Vec<f32>) {
+        if !self.pending.is_empty() {
+            self.pending.resize(RNNOISE_FRAME_SIZE, 0.0);
+            self.frame.copy_from_slice(&self.pending);
+            self.pending.clear();
+            self.process_frame(output);
+        }
+        for _ in 0..self.pipeline.latency().div_ceil(RNNOISE_FRAME_SIZE) {
+            self.frame.fill(0.0);
+            self.process_frame(output);
+        }
+    }
+
+    /// Samples the output currently lags the input by
+    pub fn latency(&self) -> usize {
+        self.pipeline.latency()
+    }
+
+    /// Frame counts and VAD average so far
+    pub fn stats(&self) -> SessionStats {
+        self.stats
+    }
+
+    fn process_frame(&mut self, output: &mut Vec<f32>) {
+        self.pipeline.process_frame(&self.frame, &self.silence, None, &self.controls, &mut self.output, &mut self.stats);
+        output.extend_from_slice(&self.output);
+    }
+}
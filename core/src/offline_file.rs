@@ -0,0 +1,461 @@
+//! Recordings through OfflineProcessor, streamed so long tracks don't have to fit in memory.
+//! WAV, FLAC, MP3 and Ogg Vorbis are decoded with symphonia; the result is always a WAV file.
+//! Each channel gets its own chain at 48 kHz and is resampled back, so by default the result
+//! has the input's rate and channel count, lined up sample for sample with the input. Loudness
+//! is measured going in and coming out, and can be brought to a target in a second pass.
+use crate::audio_engine::RNNOISE_FRAME_SIZE;
+use crate::loudness::LoudnessMeter;
+use crate::offline::{OfflineProcessor, SAMPLE_RATE};
+use crate::pipeline::Controls;
+use crate::resample::{self, ResamplerQuality};
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+use rubato::VecResampler;
+use std::fs::{self, File};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{Decoder, DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use symphonia::core::sample::SampleFormat as Sample;
+
+// Frames read per step; progress and cancelling are checked this often
+const READ_BLOCK: usize = 4800;
+// Extensions tried at all; anything else is skipped without being opened
+const EXTENSIONS: [&str; 5] = ["wav", "flac", "mp3", "ogg", "oga"];
+// Lossy sources have no sample size of their own
+const LOSSY_BITS: u16 = 16;
+// Share of the progress bar for the chain when normalizing; the gain pass is just a copy
+const DENOISE_SHARE: f32 = 0.95;
+/// A common target for spoken word, with headroom for lossy encoding afterwards
+pub const DEFAULT_TARGET_LUFS: f32 = -16.0;
+pub const DEFAULT_CEILING_DB: f32 = -1.0;
+pub const TARGET_LUFS_RANGE: std::ops::RangeInclusive<f32> = -30.0..=-10.0;
+
+#[derive(thiserror::Error, Debug)]
+pub enum FileError {
+    /// Not something this can read; nothing was written
+    #[error("{0}")]
+    Unsupported(String),
+    #[error("Could not decode: {0}")]
+    Decode(symphonia::core::errors::Error),
+    #[error("{0}")]
+    Wav(#[from] hound::Error),
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not set up the resampler: {0}")]
+    ResamplerInit(#[from] rubato::ResamplerConstructionError),
+    #[error("Resampling failed: {0}")]
+    Resample(#[from] rubato::ResampleError),
+    #[error("Cancelled")]
+    Cancelled,
+}
+
+impl From<symphonia::core::errors::Error> for FileError {
+    fn from(error: symphonia::core::errors::Error) -> Self {
+        use symphonia::core::errors::Error;
+        match error {
+            Error::Unsupported(what) => Self::Unsupported(format!("Format not supported ({})", what)),
+            Error::IoError(e) => Self::Io(e),
+            error => Self::Decode(error),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum OutputFormat {
+    /// Same rate and channels as the input
+    #[default]
+    Original,
+    /// Channels mixed down, at the chain's own rate
+    Mono48k,
+}
+
+impl OutputFormat {
+    pub const ALL: [OutputFormat; 2] = [OutputFormat::Original, OutputFormat::Mono48k];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            OutputFormat::Original => "Original rate",
+            OutputFormat::Mono48k => "48 kHz mono",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Normalize {
+    pub target_lufs: f32,
+    /// True-peak ceiling in dBTP; the gain stops short of the target rather than go over it
+    pub ceiling_db: f32,
+}
+
+impl Default for Normalize {
+    fn default() -> Self {
+        Self { target_lufs: DEFAULT_TARGET_LUFS, ceiling_db: DEFAULT_CEILING_DB }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct OutputOptions {
+    pub format: OutputFormat,
+    /// After denoising; None leaves the level as the chain made it
+    pub normalize: Option<Normalize>,
+}
+
+/// Integrated loudness measured on the way through; None for silence or under 400 ms
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FileReport {
+    pub input_lufs: Option<f32>,
+    pub output_lufs: Option<f32>,
+}
+
+/// What probe() found out about a recording
+#[derive(Clone, Copy, Debug)]
+pub struct SourceInfo {
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// None when the container doesn't say (some MP3s)
+    pub frames: Option<u64>,
+    /// Sample format written for it: the input's own for WAV and FLAC, 16-bit for lossy ones
+    pub spec: WavSpec,
+}
+
+/// Checks the file can be read without processing it
+pub fn probe(path: &Path) -> Result<SourceInfo, FileError> {
+    Ok(Source::open(path)?.info)
+}
+
+/// Writes the processed `input` to `output` as WAV, replacing it only once complete. `progress`
+/// gets the share done (0..1), when the input's length is known; setting `cancel` stops at the
+/// next block and leaves no output.
+pub fn process_file(
+    input: &Path,
+    output: &Path,
+    options: OutputOptions,
+    controls: Controls,
+    cancel: &AtomicBool,
+    mut progress: impl FnMut(f32),
+) -> Result<FileReport, FileError> {
+    let mut source = Source::open(input)?;
+    let info = source.info;
+    let (channels, rate) = match options.format {
+        OutputFormat::Original => (info.channels as usize, info.sample_rate),
+        OutputFormat::Mono48k => (1, SAMPLE_RATE),
+    };
+    let spec = WavSpec { channels: channels as u16, sample_rate: rate, ..info.spec };
+    let mut chains =
+        (0..channels).map(|_| ChannelChain::new(controls, info.sample_rate, rate)).collect::<Result<Vec<_>, _>>()?;
+
+    let temp = output.with_extension("wav.part");
+    // Normalizing keeps the chain's output as float until the gain is known
+    let unscaled = output.with_extension("unscaled.part");
+    let result = (|| match options.normalize {
+        None => {
+            let (before, after) = denoise(&mut source, &mut chains, &temp, spec, cancel, &mut progress)?;
+            progress(1.0);
+            Ok(FileReport { input_lufs: before.integrated(), output_lufs: after.integrated() })
+        }
+        Some(normalize) => {
+            let float = WavSpec { bits_per_sample: 32, sample_format: SampleFormat::Float, ..spec };
+            let (before, after) = denoise(&mut source, &mut chains, &unscaled, float, cancel, &mut |done| progress(done * DENOISE_SHARE))?;
+            // Silence stays as it is; otherwise the ceiling wins over the target
+            let loudness = after.integrated();
+            let gain = loudness.map_or(0.0, |loudness| (normalize.target_lufs - loudness).min(normalize.ceiling_db - after.true_peak_db()));
+            log::debug!("Normalizing '{}' by {:+.1} dB", input.display(), gain);
+            apply_gain(&unscaled, &temp, spec, gain, cancel, |done| progress(DENOISE_SHARE + done * (1.0 - DENOISE_SHARE)))?;
+            progress(1.0);
+            Ok(FileReport { input_lufs: before.integrated(), output_lufs: loudness.map(|loudness| loudness + gain) })
+        }
+    })();
+    let _ = fs::remove_file(&unscaled);
+    match result {
+        Ok(report) => {
+            fs::rename(&temp, output)?;
+            Ok(report)
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&temp);
+            Err(e)
+        }
+    }
+}
+
+// The chain over the whole source into `path`, measuring what goes in and what comes out
+fn denoise(
+    source: &mut Source,
+    chains: &mut [ChannelChain],
+    path: &Path,
+    spec: WavSpec,
+    cancel: &AtomicBool,
+    progress: &mut dyn FnMut(f32),
+) -> Result<(LoudnessMeter, LoudnessMeter), FileError> {
+    let info = source.info;
+    let channels = chains.len();
+    // Output frames for a number of input frames
+    let output_frames = |frames: usize| (frames as f64 * spec.sample_rate as f64 / info.sample_rate as f64).round() as usize;
+    let mut input_meter = LoudnessMeter::new(channels, info.sample_rate);
+    let mut output_meter = LoudnessMeter::new(channels, spec.sample_rate);
+    let mut writer = WavWriter::create(path, spec)?;
+    let mut block = vec![Vec::with_capacity(READ_BLOCK); channels];
+    let mut processed = vec![Vec::new(); channels];
+    // Output frames still to drop for the lag, once known
+    let mut skip = None;
+    let (mut read, mut written, mut ended) = (0, 0, false);
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(FileError::Cancelled);
+        }
+        for channel in block.iter_mut() {
+            channel.clear();
+        }
+        let got = if ended { 0 } else { source.read(&mut block, READ_BLOCK)? };
+        input_meter.add(&block, 0..got);
+        read += got;
+        ended |= got < READ_BLOCK;
+        // Past the end, silence pushes the lagging tail out
+        for channel in block.iter_mut() {
+            channel.resize(READ_BLOCK, 0.0);
+        }
+        for ((chain, input), output) in chains.iter_mut().zip(&block).zip(processed.iter_mut()) {
+            chain.push(input, output)?;
+        }
+        // The chain reports its latency once it has processed a frame. Every chain is built
+        // the same way, so they all lag by the same amount.
+        let skip = skip.get_or_insert_with(|| chains.first().map_or(0, ChannelChain::lag));
+        let available = processed.iter().map(Vec::len).min().unwrap_or(0);
+        let dropped = (*skip).min(available);
+        *skip -= dropped;
+        let target = output_frames(read);
+        let take = (available - dropped).min(target - written);
+        for i in dropped..dropped + take {
+            for channel in &processed {
+                write_sample(&mut writer, spec, channel[i])?;
+            }
+        }
+        output_meter.add(&processed, dropped..dropped + take);
+        written += take;
+        for channel in processed.iter_mut() {
+            channel.drain(..dropped + take);
+        }
+        if ended && written == target {
+            break;
+        }
+        if let Some(frames) = info.frames {
+            progress((written as f64 / output_frames(frames as usize).max(1) as f64).min(1.0) as f32);
+        }
+    }
+    writer.finalize()?;
+    Ok((input_meter, output_meter))
+}
+
+// Copies the float file at `from` to `to` in `spec`, `gain_db` louder
+fn apply_gain(from: &Path, to: &Path, spec: WavSpec, gain_db: f32, cancel: &AtomicBool, mut progress: impl FnMut(f32)) -> Result<(), FileError> {
+    let mut reader = WavReader::open(from)?;
+    let total = reader.len().max(1) as usize;
+    let gain = 10f32.powf(gain_db / 20.0);
+    let mut writer = WavWriter::create(to, spec)?;
+    for (i, sample) in reader.samples::<f32>().enumerate() {
+        if i % (READ_BLOCK * spec.channels as usize) == 0 {
+            if cancel.load(Ordering::Relaxed) {
+                return Err(FileError::Cancelled);
+            }
+            progress(i as f32 / total as f32);
+        }
+        write_sample(&mut writer, spec, sample? * gain)?;
+    }
+    writer.finalize()?;
+    Ok(())
+}
+
+fn write_sample<W: std::io::Write + std::io::Seek>(writer: &mut WavWriter<W>, spec: WavSpec, sample: f32) -> Result<(), hound::Error> {
+    match spec.sample_format {
+        SampleFormat::Float => writer.write_sample(sample),
+        SampleFormat::Int => {
+            let max = ((1u64 << (spec.bits_per_sample - 1)) - 1) as f32;
+            writer.write_sample((sample * max).round().clamp(-max - 1.0, max) as i32)
+        }
+    }
+}
+
+// The input's first audio track, decoded a packet at a time
+struct Source {
+    info: SourceInfo,
+    reader: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track: u32,
+    buffer: Option<SampleBuffer<f32>>,
+    // Interleaved samples from the last packet not yet handed out
+    pending: Vec<f32>,
+    position: usize,
+}
+
+impl Source {
+    fn open(path: &Path) -> Result<Self, FileError> {
+        let extension = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).unwrap_or_default();
+        if !EXTENSIONS.contains(&extension.as_str()) {
+            return Err(FileError::Unsupported("Not a WAV, FLAC, MP3 or Ogg Vorbis file".to_string()));
+        }
+        let stream = MediaSourceStream::new(Box::new(File::open(path)?), Default::default());
+        let mut hint = Hint::new();
+        hint.with_extension(&extension);
+        // Gapless trims encoder padding, so MP3 and Vorbis come out as long as what was encoded
+        let options = FormatOptions { enable_gapless: true, ..Default::default() };
+        let reader = symphonia::default::get_probe().format(&hint, stream, &options, &MetadataOptions::default())?.format;
+        let track = reader
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or_else(|| FileError::Unsupported("No audio in the file".to_string()))?;
+        let params = &track.codec_params;
+        let (Some(sample_rate), Some(channels)) = (params.sample_rate, params.channels) else {
+            return Err(FileError::Unsupported("Sample rate or channels not given".to_string()));
+        };
+        let (bits_per_sample, sample_format) = match (params.sample_format, params.bits_per_sample) {
+            (Some(Sample::F32 | Sample::F64), _) => (32, SampleFormat::Float),
+            (_, Some(bits @ 8..=32)) => (bits as u16, SampleFormat::Int),
+            _ => (LOSSY_BITS, SampleFormat::Int),
+        };
+        let channels = channels.count() as u16;
+        let spec = WavSpec { channels, sample_rate, bits_per_sample, sample_format };
+        let info = SourceInfo { sample_rate, channels, frames: params.n_frames, spec };
+        let decoder = symphonia::default::get_codecs().make(params, &DecoderOptions::default())?;
+        let track = track.id;
+        Ok(Self { info, reader, decoder, track, buffer: None, pending: Vec::new(), position: 0 })
+    }
+
+    // Appends up to `frames` frames to `block`, one Vec per channel, or the channels averaged
+    // when `block` has just one. Fewer than `frames` means the end.
+    fn read(&mut self, block: &mut [Vec<f32>], frames: usize) -> Result<usize, FileError> {
+        let channels = self.info.channels as usize;
+        let mut got = 0;
+        while got < frames {
+            if self.position + channels > self.pending.len() {
+                if !self.decode_next()? {
+                    break;
+                }
+                continue;
+            }
+            let frame = &self.pending[self.position..self.position + channels];
+            if block.len() == 1 && channels > 1 {
+                block[0].push(frame.iter().sum::<f32>() / channels as f32);
+            } else {
+                for (channel, &sample) in block.iter_mut().zip(frame) {
+                    channel.push(sample);
+                }
+            }
+            self.position += channels;
+            got += 1;
+        }
+        Ok(got)
+    }
+
+    // False at the end of the stream
+    fn decode_next(&mut self) -> Result<bool, FileError> {
+        use symphonia::core::errors::Error;
+        loop {
+            let packet = match self.reader.next_packet() {
+                Ok(packet) => packet,
+                Err(Error::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(false),
+                Err(Error::ResetRequired) => return Ok(false),
+                Err(e) => return Err(e.into()),
+            };
+            if packet.track_id() != self.track {
+                continue;
+            }
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                // A damaged packet costs its own samples, not the file
+                Err(Error::DecodeError(what)) => {
+                    log::debug!("Skipped a damaged packet: {}", what);
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            if decoded.spec().channels.count() != self.info.channels as usize {
+                return Err(FileError::Unsupported("Channel count changes partway through".to_string()));
+            }
+            let capacity = decoded.capacity() as u64;
+            if self.buffer.as_ref().is_none_or(|b| (b.capacity() as u64) < capacity * self.info.channels as u64) {
+                self.buffer = Some(SampleBuffer::new(capacity, *decoded.spec()));
+            }
+            let Some(buffer) = self.buffer.as_mut() else { continue };
+            buffer.copy_interleaved_ref(decoded);
+            self.pending.drain(..self.position);
+            self.position = 0;
+            self.pending.extend_from_slice(buffer.samples());
+            return Ok(true);
+        }
+    }
+}
+
+// One channel: to 48 kHz, through the chain, on to the output's rate
+struct ChannelChain {
+    to_chain: Option<Resampler>,
+    processor: OfflineProcessor,
+    from_chain: Option<Resampler>,
+    rate: u32,
+    at_48k: Vec<f32>,
+    chain_out: Vec<f32>,
+}
+
+impl ChannelChain {
+    fn new(controls: Controls, input_rate: u32, rate: u32) -> Result<Self, FileError> {
+        let to_chain = if input_rate == SAMPLE_RATE { None } else { Some(Resampler::new(input_rate, SAMPLE_RATE)?) };
+        let from_chain = if rate == SAMPLE_RATE { None } else { Some(Resampler::new(SAMPLE_RATE, rate)?) };
+        Ok(Self { to_chain, processor: OfflineProcessor::new(controls), from_chain, rate, at_48k: Vec::new(), chain_out: Vec::new() })
+    }
+
+    // Output samples (at the output's rate) that come before the first input sample's. The
+    // resamplers start out primed, so only the chain's own latency counts.
+    fn lag(&self) -> usize {
+        (self.processor.latency() as f64 * self.rate as f64 / SAMPLE_RATE as f64).round() as usize
+    }
+
+    fn push(&mut self, input: &[f32], output: &mut Vec<f32>) -> Result<(), FileError> {
+        self.at_48k.clear();
+        match self.to_chain.as_mut() {
+            Some(resampler) => resampler.push(input, &mut self.at_48k)?,
+            None => self.at_48k.extend_from_slice(input),
+        }
+        self.chain_out.clear();
+        self.processor.process_block(&self.at_48k, &mut self.chain_out);
+        match self.from_chain.as_mut() {
+            Some(resampler) => resampler.push(&self.chain_out, output)?,
+            None => output.extend_from_slice(&self.chain_out),
+        }
+        Ok(())
+    }
+}
+
+// Fixed-output resampler fed arbitrary amounts at a time
+struct Resampler {
+    inner: Box<dyn VecResampler<f32>>,
+    pending: Vec<f32>,
+    chunk: Vec<Vec<f32>>,
+}
+
+impl Resampler {
+    fn new(from: u32, to: u32) -> Result<Self, FileError> {
+        let inner = resample::build(ResamplerQuality::High, from, to, RNNOISE_FRAME_SIZE)?;
+        Ok(Self { inner, pending: Vec::new(), chunk: vec![Vec::new()] })
+    }
+
+    fn push(&mut self, input: &[f32], output: &mut Vec<f32>) -> Result<(), FileError> {
+        self.pending.extend_from_slice(input);
+        let mut used = 0;
+        loop {
+            let needed = self.inner.input_frames_next();
+            if self.pending.len() - used < needed {
+                break;
+            }
+            self.chunk[0].clear();
+            self.chunk[0].extend_from_slice(&self.pending[used..used + needed]);
+            used += needed;
+            output.extend_from_slice(&self.inner.process(&self.chunk, None)?[0]);
+        }
+        self.pending.drain(..used);
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+//! Output devices that won't take mono f32 at 48 kHz. The processing thread resamples to the
+//! device rate (see resample::OutputQueue); the callback here fans the mono signal out to every
+//! channel and converts it to the device's sample type, with TPDF dither for 8- and 16-bit
+//! formats.
+use crate::audio_engine::EngineError;
+use cpal::traits::DeviceTrait;
+use cpal::{BuildStreamError, FromSample, SampleFormat, SampleRate, SizedSample, Stream, StreamConfig, SupportedStreamConfig};
+use std::cmp::Reverse;
+
+// The rate the pipeline runs at; other rates are picked only when the device offers nothing else
+const PIPELINE_RATE: u32 = 48000;
+// Callback buffers up to this size never allocate
+const SCRATCH_FRAMES: usize = 8192;
+
+// Sample types the callback can convert to
+fn convertible(format: SampleFormat) -> bool {
+    matches!(
+        format,
+        SampleFormat::F32
+            | SampleFormat::F64
+            | SampleFormat::I8
+            | SampleFormat::I16
+            | SampleFormat::I32
+            | SampleFormat::I64
+            | SampleFormat::U8
+            | SampleFormat::U16
+            | SampleFormat::U32
+            | SampleFormat::U64
+    )
+}
+
+// Float first, then the most bits
+fn format_rank(format: SampleFormat) -> (bool, Reverse<usize>) {
+    (!format.is_float(), Reverse(format.sample_size()))
+}
+
+// One step of the integer format; dither is skipped above 16 bits, where it's far below the noise
+fn dither_step(format: SampleFormat) -> f32 {
+    let bits = format.sample_size() * 8;
+    if format.is_float() || bits > 16 {
+        0.0
+    } else {
+        1.0 / (1u32 << (bits - 1)) as f32
+    }
+}
+
+/// Picks the output format: the device default when it's f32, otherwise f32 at the default rate
+/// if the device lists it, otherwise the default as is, otherwise whatever listed format is
+/// closest to 48 kHz
+pub fn negotiate(device: &cpal::Device) -> Result<SupportedStreamConfig, EngineError> {
+    let default = device.default_output_config();
+    if let Ok(config) = &default {
+        if config.sample_format() == SampleFormat::F32 {
+            return Ok(config.clone());
+        }
+    }
+    let ranges: Vec<_> = device.supported_output_configs().map(|r| r.collect()).unwrap_or_default();
+
+    if let Ok(config) = &default {
+        let rate = config.sample_rate();
+        let float = ranges
+            .iter()
+            .filter(|r| r.sample_format() == SampleFormat::F32 && r.min_sample_rate() <= rate && rate <= r.max_sample_rate())
+            .min_by_key(|r| r.channels().abs_diff(config.channels()));
+        if let Some(&range) = float {
+            return Ok(range.with_sample_rate(rate));
+        }
+        if convertible(config.sample_format()) {
+            return Ok(config.clone());
+        }
+    }
+
+    let listed = ranges
+        .into_iter()
+        .filter(|r| convertible(r.sample_format()) && r.channels() > 0)
+        .map(|r| {
+            let rate = PIPELINE_RATE.clamp(r.min_sample_rate().0, r.max_sample_rate().0);
+            r.with_sample_rate(SampleRate(rate))
+        })
+        .min_by_key(|c| (format_rank(c.sample_format()), c.sample_rate().0.abs_diff(PIPELINE_RATE)));
+    match (listed, default) {
+        (Some(config), _) => Ok(config),
+        (None, Err(e)) => Err(e.into()),
+        (None, Ok(config)) => Err(EngineError::UnsupportedFormat {
+            device: None,
+            requested: "integer or float PCM samples".to_string(),
+            supported: format!("{:?}", config.sample_format()),
+        }),
+    }
+}
+
+/// What happens between the pipeline's mono f32 at 48 kHz and the device, for the diagnostics.
+/// `resampler` is the implementation and delay of the output-side resampler, if one runs.
+pub fn conversions(format: &SupportedStreamConfig, resampler: Option<(&str, f32)>) -> Vec<String> {
+    let mut steps = Vec::new();
+    if let Some((name, delay_ms)) = resampler {
+        steps.push(format!("{} Hz -> {} Hz, {} ({:.1} ms)", PIPELINE_RATE, format.sample_rate().0, name, delay_ms));
+    }
+    if format.sample_format() != SampleFormat::F32 {
+        let dither = if dither_step(format.sample_format()) > 0.0 { " with TPDF dither" } else { "" };
+        steps.push(format!("f32 -> {}{}", format.sample_format(), dither));
+    }
+    if format.channels() > 1 {
+        steps.push(format!("mono -> {} channels", format.channels()));
+    }
+    steps
+}
+
+/// Builds an output stream in `format`. `render` fills one mono sample per device frame, already
+/// at the device rate.
+pub fn build_stream<R, E>(
+    device: &cpal::Device,
+    format: &SupportedStreamConfig,
+    config: &StreamConfig,
+    render: R,
+    on_error: E,
+) -> Result<Stream, BuildStreamError>
+where
+    R: FnMut(&mut [f32]) + Send + 'static,
+    E: FnMut(cpal::StreamError) + Send + 'static,
+{
+    let sample_format = format.sample_format();
+    match sample_format {
+        SampleFormat::F32 => build::<f32, R, E>(device, config, sample_format, render, on_error),
+        SampleFormat::F64 => build::<f64, R, E>(device, config, sample_format, render, on_error),
+        SampleFormat::I8 => build::<i8, R, E>(device, config, sample_format, render, on_error),
+        SampleFormat::I16 => build::<i16, R, E>(device, config, sample_format, render, on_error),
+        SampleFormat::I32 => build::<i32, R, E>(device, config, sample_format, render, on_error),
+        SampleFormat::I64 => build::<i64, R, E>(device, config, sample_format, render, on_error),
+        SampleFormat::U8 => build::<u8, R, E>(device, config, sample_format, render, on_error),
+        SampleFormat::U16 => build::<u16, R, E>(device, config, sample_format, render, on_error),
+        SampleFormat::U32 => build::<u32, R, E>(device, config, sample_format, render, on_error),
+        SampleFormat::U64 => build::<u64, R, E>(device, config, sample_format, render, on_error),
+        _ => Err(BuildStreamError::StreamConfigNotSupported),
+    }
+}
+
+fn build<T, R, E>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    sample_format: SampleFormat,
+    mut render: R,
+    on_error: E,
+) -> Result<Stream, BuildStreamError>
+where
+    T: SizedSample + FromSample<f32>,
+    R: FnMut(&mut [f32]) + Send + 'static,
+    E: FnMut(cpal::StreamError) + Send + 'static,
+{
+    let channels = (config.channels as usize).max(1);
+    let integer = !sample_format.is_float();
+    let step = dither_step(sample_format);
+    let mut dither = Tpdf::new();
+    let mut mono: Vec<f32> = Vec::with_capacity(SCRATCH_FRAMES);
+    device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+            mono.resize(data.len() / channels, 0.0);
+            render(&mut mono);
+            for (frame, &sample) in data.chunks_mut(channels).zip(mono.iter()) {
+                // Clamped so dither can't push a full-scale sample past the integer range
+                let sample = if integer { (sample + dither.sample() * step).clamp(-1.0, 1.0) } else { sample };
+                frame.fill(T::from_sample(sample));
+            }
+        },
+        on_error,
+        None,
+    )
+}
+
+// Triangular noise in [-1, 1): the sum of two uniform values, from a xorshift generator
+struct Tpdf {
+    state: u32,
+}
+
+impl Tpdf {
+    fn new() -> Self {
+        Self { state: 0x9e37_79b9 }
+    }
+
+    fn uniform(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        (self.state >> 8) as f32 / (1u32 << 24) as f32 - 0.5
+    }
+
+    fn sample(&mut self) -> f32 {
+        self.uniform() + self.uniform()
+    }
+}
@@ -0,0 +1,231 @@
+//! Detects a processing thread that can't keep up: the time spent processing a block against
+//! the audio it covers, smoothed over about half a second. Above ENTER_LOAD for ENTER_HOLD_MS
+//! counts as overloaded; it takes a stretch below EXIT_LOAD to count as recovered. That stretch
+//! doubles each time the load comes back soon after a recovery, so a machine hovering at the
+//! limit settles instead of switching every few seconds.
+use crate::settings::ParseSettingError;
+use std::str::FromStr;
+use std::time::Duration;
+
+// Share of real time spent processing
+const ENTER_LOAD: f32 = 0.85;
+const EXIT_LOAD: f32 = 0.5;
+const SMOOTHING_MS: f32 = 500.0;
+const ENTER_HOLD_MS: f32 = 500.0;
+const RECOVER_MS: f32 = 3_000.0;
+const MAX_RECOVER_MS: f32 = 60_000.0;
+// Overloaded again within this long of recovering doubles the next recovery time
+const RELAPSE_MS: f32 = 30_000.0;
+
+/// What the engine does while overloaded
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OverloadPolicy {
+    /// Keep processing; the output may stutter
+    PreferQuality,
+    /// Pass the mic through unprocessed until the load drops
+    AutoBypass,
+    /// Keep processing, but say so
+    NotifyOnly,
+}
+
+impl OverloadPolicy {
+    pub const ALL: [OverloadPolicy; 3] = [OverloadPolicy::PreferQuality, OverloadPolicy::AutoBypass, OverloadPolicy::NotifyOnly];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OverloadPolicy::PreferQuality => "prefer_quality",
+            OverloadPolicy::AutoBypass => "auto_bypass",
+            OverloadPolicy::NotifyOnly => "notify_only",
+        }
+    }
+
+    /// Whether the processing thread passes audio through in this state
+    pub fn bypasses(&self, overloaded: bool) -> bool {
+        overloaded && *self == OverloadPolicy::AutoBypass
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            OverloadPolicy::PreferQuality => "Prefer quality",
+            OverloadPolicy::AutoBypass => "Auto-bypass",
+            OverloadPolicy::NotifyOnly => "Notify only",
+        }
+    }
+}
+
+impl FromStr for OverloadPolicy {
+    type Err = ParseSettingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.iter().copied().find(|p| p.as_str() == s).ok_or_else(|| ParseSettingError::new("overload policy", s))
+    }
+}
+
+pub struct OverloadDetector {
+    load: f32,
+    overloaded: bool,
+    // How long the load has been past the threshold for the next transition
+    past_ms: f32,
+    recover_ms: f32,
+    since_recovery_ms: f32,
+}
+
+impl OverloadDetector {
+    pub fn new() -> Self {
+        Self { load: 0.0, overloaded: false, past_ms: 0.0, recover_ms: RECOVER_MS, since_recovery_ms: f32::INFINITY }
+    }
+
+    /// Called once per block with the time spent on it and the audio it covered; returns the new
+    /// state when it changes
+    pub fn update(&mut self, busy: Duration, audio_ms: f32) -> Option<bool> {
+        let load = busy.as_secs_f32() * 1000.0 / audio_ms;
+        self.load += (load - self.load) * (audio_ms / SMOOTHING_MS).min(1.0);
+        self.since_recovery_ms += audio_ms;
+
+        let past = if self.overloaded { self.load < EXIT_LOAD } else { self.load > ENTER_LOAD };
+        self.past_ms = if past { self.past_ms + audio_ms } else { 0.0 };
+        let hold_ms = if self.overloaded { self.recover_ms } else { ENTER_HOLD_MS };
+        if self.past_ms < hold_ms {
+            return None;
+        }
+
+        self.past_ms = 0.0;
+        self.overloaded = !self.overloaded;
+        if self.overloaded {
+            self.recover_ms = if self.since_recovery_ms < RELAPSE_MS { (self.recover_ms * 2.0).min(MAX_RECOVER_MS) } else { RECOVER_MS };
+        } else {
+            self.since_recovery_ms = 0.0;
+        }
+        Some(self.overloaded)
+    }
+
+    /// Smoothed share of real time spent processing
+    pub fn load(&self) -> f32 {
+        self.load
+    }
+
+    pub fn overloaded(&self) -> bool {
+        self.overloaded
+    }
+}
+
+impl Default for OverloadDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK_MS: f32 = 10.0;
+
+    // Feeds `ms` of blocks that each took `load` of real time; returns the state changes with
+    // the time they happened at, counted from the start of the run
+    fn run(detector: &mut OverloadDetector, load: f32, ms: f32) -> Vec<(f32, bool)> {
+        let busy = Duration::from_secs_f32(BLOCK_MS * load / 1000.0);
+        let mut changes = Vec::new();
+        for block in 0..(ms / BLOCK_MS) as usize {
+            if let Some(state) = detector.update(busy, BLOCK_MS) {
+                changes.push(((block + 1) as f32 * BLOCK_MS, state));
+            }
+        }
+        changes
+    }
+
+    #[test]
+    fn load_under_the_threshold_never_overloads() {
+        let mut detector = OverloadDetector::new();
+        assert!(run(&mut detector, 0.8, 60_000.0).is_empty());
+        assert!((detector.load() - 0.8).abs() < 0.01);
+    }
+
+    #[test]
+    fn sustained_overload_is_reported_once_then_recovers() {
+        let mut detector = OverloadDetector::new();
+        let changes = run(&mut detector, 1.2, 5_000.0);
+        assert_eq!(changes.len(), 1);
+        let (at, state) = changes[0];
+        assert!(state && detector.overloaded());
+        // Smoothing to past ENTER_LOAD, then the hold
+        assert!((ENTER_HOLD_MS..2.0 * SMOOTHING_MS + ENTER_HOLD_MS).contains(&at), "overloaded after {} ms", at);
+
+        // Between the two thresholds nothing changes
+        assert!(run(&mut detector, 0.7, 10_000.0).is_empty());
+        let changes = run(&mut detector, 0.2, 10_000.0);
+        assert_eq!(changes.len(), 1);
+        assert!(!changes[0].1 && !detector.overloaded());
+        assert!(changes[0].0 >= RECOVER_MS, "recovered after {} ms", changes[0].0);
+    }
+
+    #[test]
+    fn short_spikes_are_ignored() {
+        let mut detector = OverloadDetector::new();
+        for _ in 0..20 {
+            assert!(run(&mut detector, 3.0, 100.0).is_empty());
+            assert!(run(&mut detector, 0.3, 900.0).is_empty());
+        }
+    }
+
+    #[test]
+    fn relapse_doubles_the_recovery_time() {
+        let mut detector = OverloadDetector::new();
+        let recovery = |detector: &mut OverloadDetector| {
+            run(detector, 1.5, 3_000.0);
+            assert!(detector.overloaded());
+            // Stops at the recovery, so the next overload counts as a relapse
+            let busy = Duration::from_secs_f32(BLOCK_MS * 0.1 / 1000.0);
+            let mut ms = 0.0;
+            while detector.update(busy, BLOCK_MS).is_none() {
+                ms += BLOCK_MS;
+            }
+            ms + BLOCK_MS
+        };
+        let first = recovery(&mut detector);
+        let second = recovery(&mut detector);
+        let third = recovery(&mut detector);
+        assert!(second > first * 1.8 && third > second * 1.8, "{} / {} / {} ms", first, second, third);
+        // A long quiet stretch resets it
+        run(&mut detector, 0.1, RELAPSE_MS + 1_000.0);
+        assert!((recovery(&mut detector) - first).abs() < 100.0);
+    }
+
+    // The processing thread's use of the detector: bypassed for the next block when the
+    // policy says so while overloaded
+    fn bypassed_blocks(policy: OverloadPolicy) -> (usize, usize, usize) {
+        let mut detector = OverloadDetector::new();
+        let mut bypassed = [0; 3];
+        for (phase, load) in [0.3, 1.3, 0.1].into_iter().enumerate() {
+            let busy = Duration::from_secs_f32(BLOCK_MS * load / 1000.0);
+            for _ in 0..1_000 {
+                if policy.bypasses(detector.overloaded()) {
+                    bypassed[phase] += 1;
+                }
+                detector.update(busy, BLOCK_MS);
+            }
+        }
+        (bypassed[0], bypassed[1], bypassed[2])
+    }
+
+    #[test]
+    fn each_policy_takes_effect() {
+        let (before, during, after) = bypassed_blocks(OverloadPolicy::AutoBypass);
+        assert_eq!(before, 0);
+        // Everything past the detection delay
+        assert!(during > 850, "{} of 1000 blocks bypassed while overloaded", during);
+        // Until it has recovered
+        assert!(after > 0 && after < 1_000 - 250, "{} of 1000 blocks bypassed after the load dropped", after);
+        for policy in [OverloadPolicy::PreferQuality, OverloadPolicy::NotifyOnly] {
+            assert_eq!(bypassed_blocks(policy), (0, 0, 0), "{:?}", policy);
+        }
+    }
+
+    #[test]
+    fn policy_strings_round_trip() {
+        for policy in OverloadPolicy::ALL {
+            assert_eq!(policy.as_str().parse(), Ok(policy));
+        }
+        assert_eq!("bogus".parse::<OverloadPolicy>().unwrap_err().to_string(), "Unknown overload policy 'bogus'");
+    }
+}
@@ -0,0 +1,707 @@
+//! Per-frame processing chain, independent of devices and threads:
+//! echo cancellation -> reorderable stages -> mix, or straight through while bypassed or while
+//! the input sounds like music. The stages default to boost -> plosives -> noise print ->
+//! denoise -> de-ess -> gate -> clicks -> effect plugin; ChainConfig keeps any other order
+//! workable. The straight path is delayed by the chain's latency so switching between the two
+//! crossfades between aligned signals instead of jumping in time.
+use crate::aec::EchoCanceller;
+use crate::audio_engine::{SessionStats, RNNOISE_FRAME_SIZE};
+use crate::dsp::{
+    self, BoostConfig, ClickConfig, ClickSuppressor, DeEsser, DeEsserConfig, DelayLine, Denoiser, Frame, GainRamp, GateConfig, GateEnvelope,
+    GateMode, MusicConfig, MusicDetector, PlosiveConfig, PlosiveTamer,
+};
+use crate::noise_print::{NoisePrintConfig, NoisePrintFilter, NoisePrintSlot};
+use crate::plugin_host::{PluginRunner, PluginSlot};
+use crate::settings::ParseSettingError;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Control values read once per block and applied to every frame in it
+#[derive(Clone, Copy, PartialEq)]
+pub struct Controls {
+    /// VAD probability (0.0..1.0) below which the gate closes
+    pub threshold: f32,
+    pub gate: GateConfig,
+    /// Straight through, unprocessed
+    pub bypassed: bool,
+    /// Silence out, e.g. while the system mutes the microphone
+    pub muted: bool,
+    /// A second denoiser pass ("Strong" suppression)
+    pub two_pass: bool,
+    pub de_esser: DeEsserConfig,
+    pub plosive: PlosiveConfig,
+    pub click: ClickConfig,
+    pub music: MusicConfig,
+    /// Level of the mix input under the processed voice, 0.0..1.0
+    pub mix_gain: f32,
+    /// Stage order and on/off switches
+    pub chain: ChainConfig,
+    pub boost: BoostConfig,
+    pub noise_print: NoisePrintConfig,
+}
+
+// What a fresh engine starts with
+impl Default for Controls {
+    fn default() -> Self {
+        Self {
+            threshold: 0.5,
+            gate: GateConfig::default(),
+            bypassed: false,
+            muted: false,
+            two_pass: false,
+            de_esser: DeEsserConfig::default(),
+            plosive: PlosiveConfig::default(),
+            click: ClickConfig::default(),
+            music: MusicConfig::default(),
+            mix_gain: 0.5,
+            chain: ChainConfig::default(),
+            boost: BoostConfig::default(),
+            noise_print: NoisePrintConfig::default(),
+        }
+    }
+}
+
+// Smoothing of the saturation share, per frame; about a second at 100 frames/s
+const SATURATION_SMOOTHING: f32 = 0.01;
+// Most the chain can delay by: two denoiser passes, the noise print, the gate's pre-roll and
+// the click lookahead
+const MAX_LATENCY: usize = 4 * RNNOISE_FRAME_SIZE + MAX_PRE_ROLL;
+const MAX_PRE_ROLL: usize = 2 * RNNOISE_FRAME_SIZE;
+// Crossfade between the processed and the straight path: one frame, 10 ms
+const CROSSFADE_STEP: f32 = 1.0 / RNNOISE_FRAME_SIZE as f32;
+
+/// A reorderable stage of the chain; `as_str()` is its name in a ChainConfig setting
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum StageKind {
+    Boost,
+    Plosive,
+    NoisePrint,
+    Denoise,
+    DeEss,
+    Gate,
+    Click,
+    Plugin,
+}
+
+pub const STAGE_COUNT: usize = 8;
+
+impl StageKind {
+    /// Also the default order, and the index of each stage in Pipeline::stages
+    pub const ALL: [StageKind; STAGE_COUNT] = [
+        StageKind::Boost,
+        StageKind::Plosive,
+        StageKind::NoisePrint,
+        StageKind::Denoise,
+        StageKind::DeEss,
+        StageKind::Gate,
+        StageKind::Click,
+        StageKind::Plugin,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StageKind::Boost => "boost",
+            StageKind::Plosive => "plosive",
+            StageKind::NoisePrint => "noise_print",
+            StageKind::Denoise => "denoise",
+            StageKind::DeEss => "de_ess",
+            StageKind::Gate => "gate",
+            StageKind::Click => "click",
+            StageKind::Plugin => "plugin",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            StageKind::Boost => "Mic boost",
+            StageKind::Plosive => "Plosive reduction",
+            StageKind::NoisePrint => "Noise print",
+            StageKind::Denoise => "Noise suppression",
+            StageKind::DeEss => "De-esser",
+            StageKind::Gate => "Voice gate",
+            StageKind::Click => "Keyboard clicks",
+            StageKind::Plugin => "Effect plugin",
+        }
+    }
+
+    /// Stages that read the VAD probability, which only exists once the denoiser has run
+    pub fn needs_vad(&self) -> bool {
+        matches!(self, StageKind::Gate | StageKind::Click)
+    }
+}
+
+impl FromStr for StageKind {
+    type Err = ParseSettingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.iter().copied().find(|k| k.as_str() == s).ok_or_else(|| ParseSettingError::new("stage", s))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ChainEntry {
+    pub kind: StageKind,
+    pub enabled: bool,
+}
+
+/// Stage order and on/off switches. Every stage appears exactly once; the denoiser is always
+/// on and comes before the stages that need its VAD output.
+///
+/// ```
+/// use silentstream_core::pipeline::{ChainConfig, StageKind};
+///
+/// // The gate needs the denoiser's VAD, so the denoiser is moved ahead of it
+/// let chain = ChainConfig::from_setting("gate,denoise,-click");
+/// let position = |kind| chain.entries.iter().position(|e| e.kind == kind).unwrap();
+/// assert!(position(StageKind::Denoise) < position(StageKind::Gate));
+/// assert!(!chain.entries[position(StageKind::Click)].enabled);
+/// assert_eq!(ChainConfig::from_setting(&chain.as_setting()), chain);
+/// ```
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ChainConfig {
+    pub entries: [ChainEntry; STAGE_COUNT],
+}
+
+impl Default for ChainConfig {
+    fn default() -> Self {
+        Self { entries: StageKind::ALL.map(|kind| ChainEntry { kind, enabled: true }) }
+    }
+}
+
+impl ChainConfig {
+    /// Comma-separated stage names in order, disabled ones prefixed with '-'. Unknown names are
+    /// dropped, missing stages (added in a newer version) go in ahead of the stage that follows
+    /// them by default, and the result is made valid.
+    pub fn from_setting(value: &str) -> Self {
+        let mut entries: Vec<ChainEntry> = Vec::with_capacity(STAGE_COUNT);
+        for token in value.split(',').map(str::trim) {
+            let (name, enabled) = match token.strip_prefix('-') {
+                Some(name) => (name, false),
+                None => (token, true),
+            };
+            if let Ok(kind) = name.parse::<StageKind>() {
+                if !entries.iter().any(|e| e.kind == kind) {
+                    entries.push(ChainEntry { kind, enabled });
+                }
+            }
+        }
+        for (default_index, kind) in StageKind::ALL.into_iter().enumerate() {
+            if !entries.iter().any(|e| e.kind == kind) {
+                let later = &StageKind::ALL[default_index + 1..];
+                let at = entries.iter().position(|e| later.contains(&e.kind)).unwrap_or(entries.len());
+                entries.insert(at, ChainEntry { kind, enabled: true });
+            }
+        }
+        let mut config = Self::default();
+        config.entries.copy_from_slice(&entries);
+        config.enforce();
+        config
+    }
+
+    pub fn as_setting(&self) -> String {
+        self.entries
+            .iter()
+            .map(|e| if e.enabled { e.kind.as_str().to_string() } else { format!("-{}", e.kind.as_str()) })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    fn is_valid(entries: &[ChainEntry; STAGE_COUNT]) -> bool {
+        let Some(denoise) = entries.iter().position(|e| e.kind == StageKind::Denoise) else { return false };
+        entries[denoise].enabled && entries[..denoise].iter().all(|e| !e.kind.needs_vad())
+    }
+
+    // Turns the denoiser on and moves it ahead of the first stage that needs the VAD
+    fn enforce(&mut self) {
+        let Some(denoise) = self.entries.iter().position(|e| e.kind == StageKind::Denoise) else { return };
+        self.entries[denoise].enabled = true;
+        if let Some(first) = self.entries[..denoise].iter().position(|e| e.kind.needs_vad()) {
+            self.entries[first..=denoise].rotate_right(1);
+        }
+    }
+
+    /// Whether the stage at `index` can swap places with its neighbour without breaking the rules
+    pub fn can_move(&self, index: usize, up: bool) -> bool {
+        let Some(other) = (if up { index.checked_sub(1) } else { Some(index + 1) }) else { return false };
+        if other >= STAGE_COUNT {
+            return false;
+        }
+        let mut entries = self.entries;
+        entries.swap(index, other);
+        Self::is_valid(&entries)
+    }
+
+    pub fn move_stage(&mut self, index: usize, up: bool) {
+        if self.can_move(index, up) {
+            let other = if up { index - 1 } else { index + 1 };
+            self.entries.swap(index, other);
+        }
+    }
+}
+
+/// Written by the stages as a frame moves through the chain
+pub struct Analysis {
+    /// Set by the denoiser stage
+    pub vad_prob: Option<f32>,
+    /// Denoiser output before later stages changed it; the click detector listens to this
+    pub denoised: Frame,
+    /// Set by the boost stage when its soft clipper engaged
+    pub saturated: bool,
+}
+
+/// One reorderable step of the chain. configure() hands over the current controls before
+/// each frame; reset() runs while the chain is skipped so nothing stale is replayed later.
+/// latency() is how many samples the stage currently holds audio back.
+pub trait DspStage {
+    fn configure(&mut self, _controls: &Controls) {}
+    fn process(&mut self, frame: &mut Frame, analysis: &mut Analysis);
+    fn reset(&mut self) {}
+    fn latency(&self) -> usize {
+        0
+    }
+}
+
+struct BoostStage {
+    config: BoostConfig,
+}
+
+impl DspStage for BoostStage {
+    fn configure(&mut self, controls: &Controls) {
+        self.config = controls.boost;
+    }
+
+    fn process(&mut self, frame: &mut Frame, analysis: &mut Analysis) {
+        analysis.saturated = dsp::boost(frame, &self.config);
+    }
+}
+
+struct PlosiveStage {
+    tamer: PlosiveTamer,
+    config: PlosiveConfig,
+}
+
+impl DspStage for PlosiveStage {
+    fn configure(&mut self, controls: &Controls) {
+        self.config = controls.plosive;
+    }
+
+    fn process(&mut self, frame: &mut Frame, _analysis: &mut Analysis) {
+        self.tamer.process(frame, &self.config);
+    }
+}
+
+// Does nothing until a print has been learned for the input device
+struct NoisePrintStage {
+    filter: NoisePrintFilter,
+    config: NoisePrintConfig,
+}
+
+impl DspStage for NoisePrintStage {
+    fn configure(&mut self, controls: &Controls) {
+        self.config = controls.noise_print;
+    }
+
+    fn process(&mut self, frame: &mut Frame, _analysis: &mut Analysis) {
+        self.filter.process(frame, &self.config);
+    }
+
+    fn reset(&mut self) {
+        self.filter.reset();
+    }
+
+    fn latency(&self) -> usize {
+        self.filter.latency()
+    }
+}
+
+// Needs 48 kHz 480-sample frames, which is all the pipeline ever sees
+struct DenoiseStage {
+    denoiser: Denoiser,
+    two_pass: bool,
+}
+
+impl DspStage for DenoiseStage {
+    fn configure(&mut self, controls: &Controls) {
+        self.two_pass = controls.two_pass;
+    }
+
+    fn process(&mut self, frame: &mut Frame, analysis: &mut Analysis) {
+        let input = *frame;
+        analysis.vad_prob = Some(self.denoiser.process(&input, frame, self.two_pass));
+        analysis.denoised = *frame;
+    }
+
+    // RNNoise overlap-adds half a window per frame, so each pass is a frame late
+    fn latency(&self) -> usize {
+        if self.two_pass { 2 * RNNOISE_FRAME_SIZE } else { RNNOISE_FRAME_SIZE }
+    }
+}
+
+struct DeEssStage {
+    de_esser: DeEsser,
+    config: DeEsserConfig,
+}
+
+impl DspStage for DeEssStage {
+    fn configure(&mut self, controls: &Controls) {
+        self.config = controls.de_esser;
+    }
+
+    fn process(&mut self, frame: &mut Frame, _analysis: &mut Analysis) {
+        self.de_esser.process(frame, &self.config);
+    }
+}
+
+struct GateStage {
+    envelope: GateEnvelope,
+    ramp: GainRamp,
+    threshold: f32,
+    config: GateConfig,
+    // The gain worked out from a frame's VAD is applied to the audio from the pre-roll before
+    // it, so the gate is already open when the onset comes out. The denoised copy is delayed
+    // along with it to keep the click detector lined up.
+    audio: DelayLine,
+    denoised: DelayLine,
+}
+
+impl DspStage for GateStage {
+    fn configure(&mut self, controls: &Controls) {
+        self.threshold = controls.threshold;
+        self.config = controls.gate;
+    }
+
+    fn process(&mut self, frame: &mut Frame, analysis: &mut Analysis) {
+        let vad_prob = analysis.vad_prob.unwrap_or(1.0);
+        let target = match self.config.mode {
+            GateMode::Hard => {
+                if vad_prob >= self.threshold {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            GateMode::Soft => dsp::soft_gate_gain(vad_prob, self.threshold, self.config.steepness),
+        };
+        // Enough energy left after denoising holds the gain where it is, which also restarts
+        // the hold time; it never opens a closed gate, so loud noise bursts stay out
+        let target = match self.config.energy_floor() {
+            Some(floor) if dsp::rms(&analysis.denoised) >= floor => target.max(self.envelope.gain()),
+            _ => target,
+        };
+        // The gain now lands on older audio, so it has to stay open that much longer too
+        let envelope = GateConfig { hold_ms: self.config.hold_ms + self.config.pre_roll_ms, ..self.config };
+        let gain = self.envelope.next(target, &envelope);
+
+        let delay = self.latency();
+        let input = *frame;
+        self.audio.process(&input, delay, frame);
+        let denoised = analysis.denoised;
+        self.denoised.process(&denoised, delay, &mut analysis.denoised);
+        self.ramp.apply(frame, gain);
+    }
+
+    fn reset(&mut self) {
+        self.audio.reset();
+        self.denoised.reset();
+    }
+
+    fn latency(&self) -> usize {
+        self.config.pre_roll_frames() * RNNOISE_FRAME_SIZE
+    }
+}
+
+struct ClickStage {
+    suppressor: ClickSuppressor,
+    config: ClickConfig,
+}
+
+impl DspStage for ClickStage {
+    fn configure(&mut self, controls: &Controls) {
+        self.config = controls.click;
+    }
+
+    fn process(&mut self, frame: &mut Frame, analysis: &mut Analysis) {
+        let vad_prob = analysis.vad_prob.unwrap_or(1.0);
+        self.suppressor.process(&analysis.denoised, frame, vad_prob, &self.config);
+    }
+
+    fn reset(&mut self) {
+        self.suppressor.reset();
+    }
+
+    // Holds one frame back to look ahead
+    fn latency(&self) -> usize {
+        if self.config.enabled { RNNOISE_FRAME_SIZE } else { 0 }
+    }
+}
+
+// Passes audio through while no plugin is loaded
+struct PluginStage {
+    runner: PluginRunner,
+}
+
+impl DspStage for PluginStage {
+    fn process(&mut self, frame: &mut Frame, _analysis: &mut Analysis) {
+        self.runner.process(frame);
+    }
+}
+
+fn new_stage(kind: StageKind, plugin: &Arc<PluginSlot>, noise_print: &Arc<NoisePrintSlot>) -> Box<dyn DspStage> {
+    match kind {
+        StageKind::Boost => Box::new(BoostStage { config: BoostConfig::default() }),
+        StageKind::Plosive => Box::new(PlosiveStage { tamer: PlosiveTamer::new(), config: PlosiveConfig::default() }),
+        StageKind::NoisePrint => Box::new(NoisePrintStage {
+            filter: NoisePrintFilter::new(noise_print.clone()),
+            config: NoisePrintConfig::default(),
+        }),
+        StageKind::Denoise => Box::new(DenoiseStage { denoiser: Denoiser::new(), two_pass: false }),
+        StageKind::DeEss => Box::new(DeEssStage { de_esser: DeEsser::new(), config: DeEsserConfig::default() }),
+        StageKind::Gate => Box::new(GateStage {
+            envelope: GateEnvelope::new(),
+            ramp: GainRamp::new(),
+            threshold: 0.0,
+            config: GateConfig::default(),
+            audio: DelayLine::new(MAX_PRE_ROLL),
+            denoised: DelayLine::new(MAX_PRE_ROLL),
+        }),
+        StageKind::Click => Box::new(ClickStage { suppressor: ClickSuppressor::new(), config: ClickConfig::default() }),
+        StageKind::Plugin => Box::new(PluginStage { runner: PluginRunner::new(plugin.clone()) }),
+    }
+}
+
+/// The processing chain for one stream of 48 kHz mono frames.
+///
+/// ```
+/// use silentstream_core::audio_engine::{SessionStats, RNNOISE_FRAME_SIZE};
+/// use silentstream_core::noise_print::NoisePrintSlot;
+/// use silentstream_core::pipeline::Pipeline;
+/// use silentstream_core::plugin_host::PluginSlot;
+/// use silentstream_core::Controls;
+/// use std::sync::Arc;
+///
+/// let mut pipeline = Pipeline::new(false, &Arc::new(PluginSlot::default()), &Arc::new(NoisePrintSlot::default()));
+/// let (input, reference) = ([0.0; RNNOISE_FRAME_SIZE], [0.0; RNNOISE_FRAME_SIZE]);
+/// let mut output = [0.0; RNNOISE_FRAME_SIZE];
+/// let mut stats = SessionStats::default();
+/// let vad = pipeline.process_frame(&input, &reference, None, &Controls::default(), &mut output, &mut stats);
+/// assert!(vad.is_some_and(|p| (0.0..=1.0).contains(&p)));
+/// assert!(output.iter().all(|s| s.is_finite()));
+/// ```
+pub struct Pipeline {
+    // In StageKind::ALL order; the processing order comes from Controls::chain
+    stages: Vec<Box<dyn DspStage>>,
+    music: MusicDetector,
+    echo_canceller: Option<EchoCanceller>,
+    analysis: Analysis,
+    // Share of recent frames where the boost saturated
+    saturation: f32,
+    clipping: bool,
+    // The unprocessed input, delayed to line up with the chain's output
+    dry: DelayLine,
+    dry_frame: Frame,
+    // Chain latency as of the last processed frame; the straight path keeps it while bypassed
+    latency: usize,
+    // Share of the processed path in the output, 0..=1
+    wet: f32,
+    // Set while the chain is skipped; the reset stages then need their latency's worth of
+    // input before their output is used
+    resuming: bool,
+    warmup: usize,
+}
+
+impl Pipeline {
+    pub fn new(echo_cancellation: bool, plugin: &Arc<PluginSlot>, noise_print: &Arc<NoisePrintSlot>) -> Self {
+        Self {
+            stages: StageKind::ALL.iter().map(|&kind| new_stage(kind, plugin, noise_print)).collect(),
+            music: MusicDetector::new(),
+            echo_canceller: echo_cancellation.then(EchoCanceller::new),
+            analysis: Analysis { vad_prob: None, denoised: [0.0; RNNOISE_FRAME_SIZE], saturated: false },
+            saturation: 0.0,
+            clipping: false,
+            dry: DelayLine::new(MAX_LATENCY),
+            dry_frame: [0.0; RNNOISE_FRAME_SIZE],
+            latency: RNNOISE_FRAME_SIZE,
+            wet: 1.0,
+            resuming: false,
+            warmup: 0,
+        }
+    }
+
+    fn chain_latency(&self, controls: &Controls) -> usize {
+        controls.chain.entries.iter().filter(|e| e.enabled).map(|e| self.stages[e.kind as usize].latency()).sum()
+    }
+
+    // Moves the processed share toward `target`, mixing `output` (processed) with the straight path
+    fn crossfade(&mut self, output: &mut Frame, target: f32) {
+        if self.wet == target {
+            return;
+        }
+        for (o, d) in output.iter_mut().zip(self.dry_frame.iter()) {
+            self.wet = if self.wet < target { (self.wet + CROSSFADE_STEP).min(target) } else { (self.wet - CROSSFADE_STEP).max(target) };
+            *o = *o * self.wet + d * (1.0 - self.wet);
+        }
+    }
+
+    /// Current echo path estimate, if echo cancellation is on
+    pub fn echo_delay_ms(&self) -> Option<f32> {
+        self.echo_canceller.as_ref().map(|aec| aec.delay_ms())
+    }
+
+    /// True while the input is passed through because it sounds like music
+    pub fn music_passthrough(&self) -> bool {
+        self.music.is_music()
+    }
+
+    pub fn boost_saturation(&self) -> f32 {
+        self.saturation
+    }
+
+    /// Samples the output lags the input by, as of the last frame
+    pub fn latency(&self) -> usize {
+        self.latency
+    }
+
+    /// `reference` is the loopback frame for the same period (ignored without echo cancellation);
+    /// `mix` goes under the voice after the gate, never through the denoiser. Returns the VAD
+    /// probability, or None when the denoiser was skipped.
+    pub fn process_frame(
+        &mut self,
+        frame: &Frame,
+        reference: &Frame,
+        mix: Option<&Frame>,
+        controls: &Controls,
+        output: &mut Frame,
+        stats: &mut SessionStats,
+    ) -> Option<f32> {
+        // Manual bypass wins; the detector starts over once it's released
+        let music = if controls.music.enabled && !controls.bypassed {
+            self.music.process(frame, &controls.music)
+        } else {
+            self.music.reset();
+            false
+        };
+
+        let straight = controls.bypassed || music;
+        self.dry.process(frame, self.latency, &mut self.dry_frame);
+
+        let vad = if straight && self.wet == 0.0 {
+            *output = self.dry_frame;
+            stats.record(frame, None, true, controls.muted, &mut self.clipping);
+            for stage in &mut self.stages {
+                stage.reset();
+            }
+            self.resuming = true;
+            None
+        } else {
+            match self.echo_canceller.as_mut() {
+                Some(aec) => aec.process(frame, reference, output),
+                None => *output = *frame,
+            }
+            self.analysis.vad_prob = None;
+            self.analysis.saturated = false;
+            for entry in controls.chain.entries.iter().filter(|e| e.enabled) {
+                let stage = &mut self.stages[entry.kind as usize];
+                stage.configure(controls);
+                stage.process(output, &mut self.analysis);
+            }
+            let saturated = if self.analysis.saturated { 1.0 } else { 0.0 };
+            self.saturation += (saturated - self.saturation) * SATURATION_SMOOTHING;
+            let vad_prob = self.analysis.vad_prob;
+            let forwarded = vad_prob.is_none_or(|p| p >= controls.threshold);
+            // The raw frame, so clipping is judged before any stage changed the level
+            stats.record(frame, vad_prob, forwarded, controls.muted, &mut self.clipping);
+
+            self.latency = self.chain_latency(controls).min(MAX_LATENCY);
+            if self.resuming {
+                self.resuming = false;
+                self.warmup = self.latency.div_ceil(RNNOISE_FRAME_SIZE);
+            }
+            if straight {
+                self.crossfade(output, 0.0);
+            } else if self.warmup > 0 {
+                self.warmup -= 1;
+                *output = self.dry_frame;
+            } else {
+                self.crossfade(output, 1.0);
+            }
+            vad_prob
+        };
+
+        if let Some(mix) = mix {
+            for (o, m) in output.iter_mut().zip(mix.iter()) {
+                *o += m * controls.mix_gain;
+            }
+            dsp::soft_clip(output);
+        }
+        vad
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+
+    const AMPLITUDE: f32 = 0.3;
+    const FREQ: f32 = 440.0;
+    // Makes the processed path about four times louder than the bypass path, so switching
+    // between them without a crossfade would jump
+    const BOOST_DB: f32 = 12.0;
+
+    // Output of a sine through the pipeline, with bypass toggled at the given frames
+    fn run(frames: usize, toggles: &[usize]) -> Vec<f32> {
+        let mut pipeline = Pipeline::new(false, &Arc::new(PluginSlot::default()), &Arc::new(NoisePrintSlot::default()));
+        let mut controls = Controls { boost: BoostConfig { gain_db: BOOST_DB }, ..Controls::default() };
+        let mut stats = SessionStats::default();
+        let reference = [0.0; RNNOISE_FRAME_SIZE];
+        let mut output = Vec::new();
+        for f in 0..frames {
+            if toggles.contains(&f) {
+                controls.bypassed = !controls.bypassed;
+            }
+            let frame: Frame =
+                std::array::from_fn(|i| AMPLITUDE * (TAU * FREQ * (f * RNNOISE_FRAME_SIZE + i) as f32 / 48000.0).sin());
+            let mut out = [0.0; RNNOISE_FRAME_SIZE];
+            pipeline.process_frame(&frame, &reference, None, &controls, &mut out, &mut stats);
+            output.extend_from_slice(&out);
+        }
+        output
+    }
+
+    fn max_step(samples: &[f32]) -> f32 {
+        samples.windows(2).map(|w| (w[1] - w[0]).abs()).fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn toggling_bypass_mid_sine_crossfades() {
+        // The boosted sine's own steepest step, plus what a one-frame fade across full scale adds
+        let gain = 10f32.powf(BOOST_DB / 20.0);
+        let bound = TAU * FREQ / 48000.0 * AMPLITUDE * gain + 2.0 / RNNOISE_FRAME_SIZE as f32 + 0.01;
+        // A hard switch would step by up to this much
+        let (processed, bypassed) = (run(60, &[]), run(60, &[0]));
+        let gap = processed[40 * RNNOISE_FRAME_SIZE..].iter().zip(&bypassed[40 * RNNOISE_FRAME_SIZE..]).map(|(a, b)| (a - b).abs());
+        assert!(gap.fold(0.0, f32::max) > 5.0 * bound);
+
+        let output = run(200, &[50, 100, 101, 150]);
+        // Past the denoiser's first frames
+        let steps = max_step(&output[10 * RNNOISE_FRAME_SIZE..]);
+        assert!(steps < bound, "largest sample-to-sample step {} (bound {})", steps, bound);
+    }
+
+    #[test]
+    fn bypassed_output_is_the_delayed_input() {
+        let output = run(100, &[50]);
+        let latency = {
+            let mut pipeline = Pipeline::new(false, &Arc::new(PluginSlot::default()), &Arc::new(NoisePrintSlot::default()));
+            let mut out = [0.0; RNNOISE_FRAME_SIZE];
+            let zero = [0.0; RNNOISE_FRAME_SIZE];
+            pipeline.process_frame(&zero, &zero, None, &Controls::default(), &mut out, &mut SessionStats::default());
+            pipeline.latency()
+        };
+        // Once the crossfade is over, bypass plays the input `latency` samples late
+        for (i, sample) in output.iter().enumerate().skip(52 * RNNOISE_FRAME_SIZE) {
+            let expected = AMPLITUDE * (TAU * FREQ * (i - latency) as f32 / 48000.0).sin();
+            assert!((sample - expected).abs() < 1e-3, "sample {}: {} instead of {}", i, sample, expected);
+        }
+    }
+}
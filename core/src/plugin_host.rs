@@ -0,0 +1,545 @@
+//! Hosts one CLAP effect plugin as a stage of the processing chain. The plugin is loaded,
+//! activated and run on the processing thread at 48 kHz with 480-sample blocks; the UI talks
+//! to it only through the shared PluginSlot. A plugin that fails to load, reports an error
+//! or produces invalid samples is dropped and its stage passes audio through unchanged.
+use crate::audio_engine::RNNOISE_FRAME_SIZE;
+use crate::dsp::Frame;
+use clap_sys::audio_buffer::clap_audio_buffer;
+use clap_sys::entry::clap_plugin_entry;
+use clap_sys::events::{
+    clap_event_header, clap_event_param_value, clap_input_events, clap_output_events, CLAP_CORE_EVENT_SPACE_ID,
+    CLAP_EVENT_PARAM_VALUE,
+};
+use clap_sys::ext::audio_ports::{clap_audio_port_info, clap_plugin_audio_ports, CLAP_EXT_AUDIO_PORTS};
+use clap_sys::ext::params::{
+    clap_param_info, clap_plugin_params, CLAP_EXT_PARAMS, CLAP_PARAM_IS_HIDDEN, CLAP_PARAM_IS_READONLY, CLAP_PARAM_IS_STEPPED,
+};
+use clap_sys::factory::plugin_factory::{clap_plugin_factory, CLAP_PLUGIN_FACTORY_ID};
+use clap_sys::host::clap_host;
+use clap_sys::plugin::clap_plugin;
+use clap_sys::process::{clap_process, CLAP_PROCESS_ERROR};
+use clap_sys::version::{clap_version_is_compatible, CLAP_VERSION};
+use libloading::Library;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+pub const PLUGIN_EXTENSION: &str = "clap";
+const SAMPLE_RATE: f64 = 48000.0;
+// Parameter changes delivered with one frame; more wait for the next
+const EVENT_CAPACITY: usize = 64;
+// Installed plugins are looked for this many folders deep
+const SCAN_DEPTH: usize = 4;
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct PluginConfig {
+    pub enabled: bool,
+    /// The .clap file (a bundle folder on macOS)
+    pub path: String,
+    /// Values the user set, by CLAP parameter id; applied after loading
+    pub params: Vec<(u32, f64)>,
+}
+
+impl PluginConfig {
+    /// "id=value" pairs separated by ';'
+    pub fn params_setting(&self) -> String {
+        self.params.iter().map(|(id, value)| format!("{}={}", id, value)).collect::<Vec<_>>().join(";")
+    }
+
+    pub fn parse_params(value: &str) -> Vec<(u32, f64)> {
+        value
+            .split(';')
+            .filter_map(|pair| {
+                let (id, value) = pair.split_once('=')?;
+                let value: f64 = value.trim().parse().ok()?;
+                Some((id.trim().parse().ok()?, value)).filter(|_| value.is_finite())
+            })
+            .collect()
+    }
+
+    pub fn set_param(&mut self, id: u32, value: f64) {
+        match self.params.iter_mut().find(|(i, _)| *i == id) {
+            Some(entry) => entry.1 = value,
+            None => self.params.push((id, value)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ParamInfo {
+    pub id: u32,
+    pub name: String,
+    pub min: f64,
+    pub max: f64,
+    pub default: f64,
+    pub value: f64,
+    pub stepped: bool,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum PluginStatus {
+    Off,
+    Running(String),
+    Failed(String),
+}
+
+struct SlotState {
+    // Bumped whenever the plugin should be loaded again or removed
+    generation: u64,
+    path: Option<PathBuf>,
+    initial_params: Vec<(u32, f64)>,
+    // Changes from the UI waiting for the processing thread
+    pending: Vec<(u32, f64)>,
+    status: PluginStatus,
+    params: Vec<ParamInfo>,
+}
+
+/// Shared between the UI and the processing thread; survives engine restarts
+pub struct PluginSlot {
+    state: Mutex<SlotState>,
+}
+
+impl Default for PluginSlot {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(SlotState {
+                generation: 0,
+                path: None,
+                initial_params: Vec::new(),
+                pending: Vec::with_capacity(EVENT_CAPACITY),
+                status: PluginStatus::Off,
+                params: Vec::new(),
+            }),
+        }
+    }
+}
+
+impl PluginSlot {
+    /// Has the processing thread load (or drop) the plugin on its next frame
+    pub fn configure(&self, config: &PluginConfig) {
+        if let Ok(mut state) = self.state.lock() {
+            state.generation += 1;
+            state.path = (config.enabled && !config.path.trim().is_empty()).then(|| PathBuf::from(config.path.trim()));
+            state.initial_params = config.params.clone();
+            state.pending.clear();
+            state.params.clear();
+            state.status = PluginStatus::Off;
+        }
+    }
+
+    pub fn set_param(&self, id: u32, value: f64) {
+        if let Ok(mut state) = self.state.lock() {
+            if let Some(param) = state.params.iter_mut().find(|p| p.id == id) {
+                param.value = value;
+            }
+            state.pending.retain(|(i, _)| *i != id);
+            state.pending.push((id, value));
+        }
+    }
+
+    pub fn status(&self) -> PluginStatus {
+        self.state.lock().map(|s| s.status.clone()).unwrap_or(PluginStatus::Off)
+    }
+
+    pub fn params(&self) -> Vec<ParamInfo> {
+        self.state.lock().map(|s| s.params.clone()).unwrap_or_default()
+    }
+}
+
+/// The processing thread's side of the slot
+pub struct PluginRunner {
+    slot: Arc<PluginSlot>,
+    generation: Option<u64>,
+    plugin: Option<ClapPlugin>,
+    changes: Vec<(u32, f64)>,
+}
+
+impl PluginRunner {
+    pub fn new(slot: Arc<PluginSlot>) -> Self {
+        Self { slot, generation: None, plugin: None, changes: Vec::with_capacity(EVENT_CAPACITY) }
+    }
+
+    pub fn process(&mut self, frame: &mut Frame) {
+        self.sync();
+        let Some(plugin) = self.plugin.as_mut() else { return };
+        let input = *frame;
+        if let Err(e) = plugin.process(frame, &self.changes) {
+            log::error!("Effect plugin bypassed: {}", e);
+            *frame = input;
+            self.plugin = None;
+            if let Ok(mut state) = self.slot.state.lock() {
+                state.status = PluginStatus::Failed(e);
+                state.params.clear();
+            }
+        }
+        self.changes.clear();
+    }
+
+    // Never waits for the UI: a busy slot is looked at again on the next frame
+    fn sync(&mut self) {
+        let Ok(mut state) = self.slot.state.try_lock() else { return };
+        if self.generation != Some(state.generation) {
+            self.generation = Some(state.generation);
+            self.plugin = None;
+            self.changes.clear();
+            let Some(path) = state.path.clone() else { return };
+            let initial = state.initial_params.clone();
+            drop(state);
+
+            // Loading can take a while; the UI stays free meanwhile
+            let loaded = ClapPlugin::load(&path);
+            let Ok(mut state) = self.slot.state.lock() else { return };
+            match loaded {
+                Ok((plugin, name, mut params)) => {
+                    log::info!("Effect plugin '{}' loaded from {}", name, path.display());
+                    for (id, value) in initial {
+                        if let Some(param) = params.iter_mut().find(|p| p.id == id) {
+                            param.value = value.clamp(param.min, param.max);
+                            self.changes.push((id, param.value));
+                        }
+                    }
+                    state.status = PluginStatus::Running(name);
+                    state.params = params;
+                    self.plugin = Some(plugin);
+                }
+                Err(e) => {
+                    log::error!("Failed to load effect plugin {}: {}", path.display(), e);
+                    state.status = PluginStatus::Failed(e);
+                }
+            }
+            return;
+        }
+        let room = EVENT_CAPACITY.saturating_sub(self.changes.len()).min(state.pending.len());
+        self.changes.extend(state.pending.drain(..room));
+    }
+}
+
+// Fixed list handed to the plugin as its input events
+struct EventList {
+    events: [clap_event_param_value; EVENT_CAPACITY],
+    len: usize,
+}
+
+const EMPTY_EVENT: clap_event_param_value = clap_event_param_value {
+    header: clap_event_header {
+        size: std::mem::size_of::<clap_event_param_value>() as u32,
+        time: 0,
+        space_id: CLAP_CORE_EVENT_SPACE_ID,
+        type_: CLAP_EVENT_PARAM_VALUE,
+        flags: 0,
+    },
+    param_id: 0,
+    cookie: std::ptr::null_mut(),
+    note_id: -1,
+    port_index: -1,
+    channel: -1,
+    key: -1,
+    value: 0.0,
+};
+
+unsafe extern "C" fn events_size(list: *const clap_input_events) -> u32 {
+    let events = &*((*list).ctx as *const EventList);
+    events.len as u32
+}
+
+unsafe extern "C" fn events_get(list: *const clap_input_events, index: u32) -> *const clap_event_header {
+    let events = &*((*list).ctx as *const EventList);
+    match events.events[..events.len].get(index as usize) {
+        Some(event) => &event.header,
+        None => std::ptr::null(),
+    }
+}
+
+// Nothing reads what the plugin sends back
+unsafe extern "C" fn discard_event(_list: *const clap_output_events, _event: *const clap_event_header) -> bool {
+    true
+}
+
+unsafe extern "C" fn host_get_extension(_host: *const clap_host, _id: *const c_char) -> *const c_void {
+    std::ptr::null()
+}
+
+unsafe extern "C" fn host_request(_host: *const clap_host) {}
+
+struct ClapPlugin {
+    entry: *const clap_plugin_entry,
+    plugin: *const clap_plugin,
+    activated: bool,
+    processing: bool,
+    // Must outlive the plugin, which keeps a pointer to it
+    _host: Box<clap_host>,
+    channels: usize,
+    inputs: Box<[Frame; 2]>,
+    outputs: Box<[Frame; 2]>,
+    events: Box<EventList>,
+    steady_time: i64,
+    // Dropped last, after Drop has shut the plugin down
+    _library: Library,
+}
+
+impl ClapPlugin {
+    // Instantiates the first plugin in the file and starts it
+    fn load(path: &Path) -> Result<(Self, String, Vec<ParamInfo>), String> {
+        let c_path = CString::new(path.to_string_lossy().as_bytes()).map_err(|_| "Invalid plugin path".to_string())?;
+        unsafe {
+            let library = Library::new(binary_path(path)).map_err(|e| format!("Cannot open the plugin: {}", e))?;
+            let entry: *const clap_plugin_entry = *library
+                .get::<*const clap_plugin_entry>(b"clap_entry\0")
+                .map_err(|_| "Not a CLAP plugin (no clap_entry)".to_string())?;
+            if entry.is_null() || !clap_version_is_compatible((*entry).clap_version) {
+                return Err("Unsupported CLAP version".to_string());
+            }
+            let (Some(init), Some(get_factory)) = ((*entry).init, (*entry).get_factory) else {
+                return Err("Incomplete CLAP entry point".to_string());
+            };
+            if !init(c_path.as_ptr()) {
+                return Err("The plugin failed to initialize".to_string());
+            }
+
+            let host = Box::new(clap_host {
+                clap_version: CLAP_VERSION,
+                host_data: std::ptr::null_mut(),
+                name: c"SilentStream".as_ptr(),
+                vendor: c"yyyutakaaa".as_ptr(),
+                url: c"https://github.com/yyyutakaaa/SilentStream".as_ptr(),
+                version: c"1.0.0".as_ptr(),
+                get_extension: Some(host_get_extension),
+                request_restart: Some(host_request),
+                request_process: Some(host_request),
+                request_callback: Some(host_request),
+            });
+            // From here on Drop cleans up whatever got started
+            let mut loaded = Self {
+                entry,
+                plugin: std::ptr::null(),
+                activated: false,
+                processing: false,
+                _host: host,
+                channels: 1,
+                inputs: Box::new([[0.0; RNNOISE_FRAME_SIZE]; 2]),
+                outputs: Box::new([[0.0; RNNOISE_FRAME_SIZE]; 2]),
+                events: Box::new(EventList { events: [EMPTY_EVENT; EVENT_CAPACITY], len: 0 }),
+                steady_time: 0,
+                _library: library,
+            };
+
+            let factory = get_factory(CLAP_PLUGIN_FACTORY_ID.as_ptr()) as *const clap_plugin_factory;
+            let Some(factory) = factory.as_ref() else { return Err("The file contains no plugins".to_string()) };
+            let (Some(count), Some(descriptor), Some(create)) =
+                (factory.get_plugin_count, factory.get_plugin_descriptor, factory.create_plugin)
+            else {
+                return Err("Incomplete plugin factory".to_string());
+            };
+            if count(factory) == 0 {
+                return Err("The file contains no plugins".to_string());
+            }
+            let desc = descriptor(factory, 0);
+            if desc.is_null() {
+                return Err("The plugin has no descriptor".to_string());
+            }
+            let name = c_string((*desc).name).unwrap_or_else(|| "Unnamed plugin".to_string());
+            loaded.plugin = create(factory, &*loaded._host, (*desc).id);
+            let Some(plugin) = loaded.plugin.as_ref() else { return Err(format!("'{}' could not be created", name)) };
+            if !plugin.init.is_some_and(|f| f(plugin)) {
+                return Err(format!("'{}' failed to initialize", name));
+            }
+
+            loaded.channels = input_channels(plugin).ok_or_else(|| format!("'{}' has no mono or stereo audio input", name))?;
+            let params = query_params(plugin);
+            if !plugin.activate.is_some_and(|f| f(plugin, SAMPLE_RATE, 1, RNNOISE_FRAME_SIZE as u32)) {
+                return Err(format!("'{}' cannot run at 48 kHz", name));
+            }
+            loaded.activated = true;
+            if !plugin.start_processing.is_some_and(|f| f(plugin)) {
+                return Err(format!("'{}' failed to start", name));
+            }
+            loaded.processing = true;
+            Ok((loaded, name, params))
+        }
+    }
+
+    fn process(&mut self, frame: &mut Frame, changes: &[(u32, f64)]) -> Result<(), String> {
+        for input in self.inputs.iter_mut().take(self.channels) {
+            *input = *frame;
+        }
+        self.events.len = changes.len().min(EVENT_CAPACITY);
+        for (event, &(id, value)) in self.events.events.iter_mut().zip(changes) {
+            event.param_id = id;
+            event.value = value;
+        }
+
+        let [in_l, in_r] = &mut *self.inputs;
+        let [out_l, out_r] = &mut *self.outputs;
+        let mut input_ptrs = [in_l.as_mut_ptr(), in_r.as_mut_ptr()];
+        let mut output_ptrs = [out_l.as_mut_ptr(), out_r.as_mut_ptr()];
+        let input = clap_audio_buffer {
+            data32: input_ptrs.as_mut_ptr(),
+            data64: std::ptr::null_mut(),
+            channel_count: self.channels as u32,
+            latency: 0,
+            constant_mask: 0,
+        };
+        let mut output = clap_audio_buffer { data32: output_ptrs.as_mut_ptr(), ..input };
+        let in_events = clap_input_events {
+            ctx: &*self.events as *const EventList as *mut c_void,
+            size: Some(events_size),
+            get: Some(events_get),
+        };
+        let out_events = clap_output_events { ctx: std::ptr::null_mut(), try_push: Some(discard_event) };
+        let process = clap_process {
+            steady_time: self.steady_time,
+            frames_count: RNNOISE_FRAME_SIZE as u32,
+            transport: std::ptr::null(),
+            audio_inputs: &input,
+            audio_outputs: &mut output,
+            audio_inputs_count: 1,
+            audio_outputs_count: 1,
+            in_events: &in_events,
+            out_events: &out_events,
+        };
+        let status = unsafe {
+            let plugin = &*self.plugin;
+            match plugin.process {
+                Some(f) => f(plugin, &process),
+                None => CLAP_PROCESS_ERROR,
+            }
+        };
+        self.steady_time += RNNOISE_FRAME_SIZE as i64;
+        if status == CLAP_PROCESS_ERROR {
+            return Err("the plugin reported a processing error".to_string());
+        }
+
+        // Back to mono
+        let scale = 1.0 / self.channels as f32;
+        for (i, sample) in frame.iter_mut().enumerate() {
+            *sample = self.outputs[..self.channels].iter().map(|out| out[i]).sum::<f32>() * scale;
+        }
+        if frame.iter().any(|s| !s.is_finite()) {
+            return Err("the plugin produced invalid samples".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ClapPlugin {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(plugin) = self.plugin.as_ref() {
+                if self.processing {
+                    if let Some(stop) = plugin.stop_processing {
+                        stop(plugin);
+                    }
+                }
+                if self.activated {
+                    if let Some(deactivate) = plugin.deactivate {
+                        deactivate(plugin);
+                    }
+                }
+                if let Some(destroy) = plugin.destroy {
+                    destroy(plugin);
+                }
+            }
+            if let Some(deinit) = (*self.entry).deinit {
+                deinit();
+            }
+        }
+    }
+}
+
+// On macOS a .clap is a bundle with the binary inside
+fn binary_path(path: &Path) -> PathBuf {
+    if path.is_dir() {
+        if let Some(stem) = path.file_stem() {
+            return path.join("Contents").join("MacOS").join(stem);
+        }
+    }
+    path.to_path_buf()
+}
+
+unsafe fn c_string(ptr: *const c_char) -> Option<String> {
+    (!ptr.is_null()).then(|| CStr::from_ptr(ptr).to_string_lossy().into_owned())
+}
+
+// Channels of the main input, if it's one the mono pipeline can feed
+unsafe fn input_channels(plugin: &clap_plugin) -> Option<usize> {
+    let ports = plugin.get_extension?(plugin, CLAP_EXT_AUDIO_PORTS.as_ptr()) as *const clap_plugin_audio_ports;
+    let ports = ports.as_ref()?;
+    if ports.count?(plugin, true) == 0 {
+        return None;
+    }
+    let mut info: clap_audio_port_info = std::mem::zeroed();
+    if !ports.get?(plugin, 0, true, &mut info) {
+        return None;
+    }
+    matches!(info.channel_count, 1 | 2).then_some(info.channel_count as usize)
+}
+
+unsafe fn query_params(plugin: &clap_plugin) -> Vec<ParamInfo> {
+    let Some(get_extension) = plugin.get_extension else { return Vec::new() };
+    let Some(params) = (get_extension(plugin, CLAP_EXT_PARAMS.as_ptr()) as *const clap_plugin_params).as_ref() else {
+        return Vec::new();
+    };
+    let (Some(count), Some(get_info)) = (params.count, params.get_info) else { return Vec::new() };
+    (0..count(plugin))
+        .filter_map(|index| {
+            let mut info: clap_param_info = std::mem::zeroed();
+            if !get_info(plugin, index, &mut info) || info.flags & (CLAP_PARAM_IS_HIDDEN | CLAP_PARAM_IS_READONLY) != 0 {
+                return None;
+            }
+            let mut value = info.default_value;
+            if let Some(get_value) = params.get_value {
+                get_value(plugin, info.id, &mut value);
+            }
+            Some(ParamInfo {
+                id: info.id,
+                name: CStr::from_ptr(info.name.as_ptr()).to_string_lossy().into_owned(),
+                min: info.min_value,
+                max: info.max_value,
+                default: info.default_value,
+                value,
+                stepped: info.flags & CLAP_PARAM_IS_STEPPED != 0,
+            })
+        })
+        .collect()
+}
+
+/// .clap files in the standard CLAP folders and CLAP_PATH
+pub fn installed_plugins() -> Vec<PathBuf> {
+    let mut folders: Vec<PathBuf> = std::env::var_os("CLAP_PATH").map(|p| std::env::split_paths(&p).collect()).unwrap_or_default();
+    if cfg!(windows) {
+        if let Some(common) = std::env::var_os("COMMONPROGRAMFILES") {
+            folders.push(PathBuf::from(common).join("CLAP"));
+        }
+        if let Some(local) = std::env::var_os("LOCALAPPDATA") {
+            folders.push(PathBuf::from(local).join("Programs").join("Common").join("CLAP"));
+        }
+    } else if cfg!(target_os = "macos") {
+        folders.push(PathBuf::from("/Library/Audio/Plug-Ins/CLAP"));
+        if let Some(home) = std::env::var_os("HOME") {
+            folders.push(PathBuf::from(home).join("Library/Audio/Plug-Ins/CLAP"));
+        }
+    } else {
+        if let Some(home) = std::env::var_os("HOME") {
+            folders.push(PathBuf::from(home).join(".clap"));
+        }
+        folders.push(PathBuf::from("/usr/lib/clap"));
+    }
+    let mut found = Vec::new();
+    for folder in folders {
+        scan_folder(&folder, SCAN_DEPTH, &mut found);
+    }
+    found.sort();
+    found.dedup();
+    found
+}
+
+fn scan_folder(folder: &Path, depth: usize, found: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(folder) else { return };
+    for path in entries.flatten().map(|e| e.path()) {
+        if path.extension().is_some_and(|e| e.eq_ignore_ascii_case(PLUGIN_EXTENSION)) {
+            found.push(path);
+        } else if depth > 0 && path.is_dir() {
+            scan_folder(&path, depth - 1, found);
+        }
+    }
+}
@@ -0,0 +1,434 @@
+//! Record and replay of what reached the pipeline, for reproducing a reported artifact exactly.
+//! While recording, the processing thread hands over every frame it processes (the mic, plus the
+//! echo reference and mix input when those are on) and the controls whenever they change; a
+//! writer thread puts them on disk. replay() runs a fresh pipeline over the same frames with the
+//! same controls. Each second of live output is stored as a hash, so a replay can tell whether
+//! it came out identical and, if not, from where. Live processing starts a fresh pipeline when
+//! a recording begins, as replay does.
+//!
+//! Not captured: effect plugins (a recording made with one says so and replays without it), and
+//! a noise print learned while recording, which live processing picks up partway through a
+//! block. Floating-point results can differ between CPUs, so a mismatch on another machine
+//! doesn't have to mean much.
+//!
+//! Layout, little-endian: magic, version, start time (Unix seconds), flags, then records, each
+//! a tag byte and its payload. Frames are raw f32, about 190 KB per second of mic audio.
+use crate::audio_engine::{SessionStats, RNNOISE_FRAME_SIZE};
+use crate::dsp::Frame;
+use crate::noise_print::{NoisePrintSlot, NoiseProfile, BINS};
+use crate::offline::SAMPLE_RATE;
+use crate::pipeline::{ChainConfig, Controls, Pipeline};
+use crate::plugin_host::PluginSlot;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::mpsc::{channel, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+pub const EXTENSION: &str = "ssreplay";
+const MAGIC: &[u8; 8] = b"SSREPLAY";
+const VERSION: u8 = 1;
+const WRITER_THREAD_NAME: &str = "replay-writer";
+
+const FLAG_ECHO: u8 = 1;
+const FLAG_MIX: u8 = 2;
+const FLAG_PLUGIN: u8 = 4;
+
+// Milliseconds since the start, then the controls as text (see encode_controls)
+const TAG_CONTROLS: u8 = b'C';
+// Whether a print is set, then its magnitudes
+const TAG_PRINT: u8 = b'P';
+// Mic, then the reference and mix frames when the flags say so
+const TAG_FRAME: u8 = b'F';
+// Milliseconds since the start at its first frame, then the frame count and hash of the
+// output since the previous hash
+const TAG_HASH: u8 = b'H';
+
+// One hash per second of output
+const HASH_FRAMES: u32 = 100;
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// The processing thread's side: encodes records into a buffer sent off once per block
+pub struct Recorder {
+    sender: Sender<Vec<u8>>,
+    buffer: Vec<u8>,
+    since: Instant,
+    started: u64,
+    plugin: bool,
+    last_controls: Option<Controls>,
+    print_generation: Option<u64>,
+    hash: u64,
+    hashed: u32,
+    hash_since_ms: u64,
+}
+
+impl Recorder {
+    /// Creates the file and its writer thread. `plugin` marks a recording made with an effect
+    /// plugin running, which replay can't reproduce.
+    pub fn create(path: &Path, plugin: bool) -> io::Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        let (sender, blocks) = channel::<Vec<u8>>();
+        let name = path.display().to_string();
+        thread::Builder::new().name(WRITER_THREAD_NAME.to_string()).spawn(move || {
+            for block in blocks {
+                if let Err(e) = file.write_all(&block) {
+                    log::warn!("Replay recording '{}' stopped: {}", name, e);
+                    return;
+                }
+            }
+            if let Err(e) = file.flush() {
+                log::warn!("Replay recording '{}' may be incomplete: {}", name, e);
+            }
+        })?;
+        let started = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        Ok(Self {
+            sender,
+            buffer: Vec::new(),
+            since: Instant::now(),
+            started,
+            plugin,
+            last_controls: None,
+            print_generation: None,
+            hash: FNV_OFFSET,
+            hashed: 0,
+            hash_since_ms: 0,
+        })
+    }
+
+    // Writes the header once the processing thread knows which sources are open
+    pub(crate) fn begin(&mut self, echo: bool, mix: bool) {
+        let flags = if echo { FLAG_ECHO } else { 0 } | if mix { FLAG_MIX } else { 0 } | if self.plugin { FLAG_PLUGIN } else { 0 };
+        self.buffer.extend_from_slice(MAGIC);
+        self.buffer.push(VERSION);
+        self.buffer.extend_from_slice(&self.started.to_le_bytes());
+        self.buffer.push(flags);
+    }
+
+    // Recorded only when something changed; takes effect from the next frame
+    pub(crate) fn controls(&mut self, controls: &Controls) {
+        if self.last_controls.as_ref() == Some(controls) {
+            return;
+        }
+        self.last_controls = Some(*controls);
+        let text = encode_controls(controls);
+        self.buffer.push(TAG_CONTROLS);
+        self.buffer.extend_from_slice(&self.elapsed_ms().to_le_bytes());
+        self.buffer.extend_from_slice(&(text.len() as u32).to_le_bytes());
+        self.buffer.extend_from_slice(text.as_bytes());
+    }
+
+    pub(crate) fn noise_print(&mut self, slot: &NoisePrintSlot) {
+        let generation = slot.generation();
+        if self.print_generation == Some(generation) {
+            return;
+        }
+        // Tried again next block
+        let Some(profile) = slot.try_profile() else { return };
+        self.print_generation = Some(generation);
+        self.buffer.push(TAG_PRINT);
+        self.buffer.push(profile.is_some() as u8);
+        for magnitude in profile.iter().flat_map(|p| &p.magnitudes) {
+            self.buffer.extend_from_slice(&magnitude.to_le_bytes());
+        }
+    }
+
+    pub(crate) fn frame(&mut self, input: &Frame, reference: Option<&Frame>, mix: Option<&Frame>, output: &Frame) {
+        self.buffer.push(TAG_FRAME);
+        for frame in [Some(input), reference, mix].into_iter().flatten() {
+            for sample in frame {
+                self.buffer.extend_from_slice(&sample.to_le_bytes());
+            }
+        }
+        if self.hashed == 0 {
+            self.hash_since_ms = self.elapsed_ms();
+        }
+        self.hash = hash_frame(self.hash, output);
+        self.hashed += 1;
+        if self.hashed == HASH_FRAMES {
+            self.end_hash();
+        }
+    }
+
+    fn end_hash(&mut self) {
+        self.buffer.push(TAG_HASH);
+        self.buffer.extend_from_slice(&self.hash_since_ms.to_le_bytes());
+        self.buffer.extend_from_slice(&self.hashed.to_le_bytes());
+        self.buffer.extend_from_slice(&self.hash.to_le_bytes());
+        self.hash = FNV_OFFSET;
+        self.hashed = 0;
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.since.elapsed().as_millis() as u64
+    }
+
+    // Once per block
+    pub(crate) fn flush(&mut self) {
+        if !self.buffer.is_empty() {
+            let _ = self.sender.send(std::mem::take(&mut self.buffer));
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if self.hashed > 0 {
+            self.end_hash();
+        }
+        self.flush();
+    }
+}
+
+fn hash_frame(mut hash: u64, frame: &Frame) -> u64 {
+    for sample in frame {
+        for byte in sample.to_bits().to_le_bytes() {
+            hash = (hash ^ byte as u64).wrapping_mul(FNV_PRIME);
+        }
+    }
+    hash
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReplayError {
+    #[error("Not a SilentStream replay recording")]
+    NotARecording,
+    #[error("Recorded by a newer version (format {0})")]
+    Version(u8),
+    #[error("Damaged recording: {0}")]
+    Damaged(&'static str),
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("{0}")]
+    Wav(#[from] hound::Error),
+}
+
+#[derive(Clone, Debug)]
+pub struct ReplayReport {
+    /// Unix seconds
+    pub started: u64,
+    pub frames: u64,
+    /// Milliseconds into the recording and the controls from then on
+    pub control_changes: Vec<(u64, String)>,
+    /// Milliseconds into the recording of the first second whose output differs; None when
+    /// identical
+    pub first_difference: Option<u64>,
+    /// Recorded with an effect plugin, which the replay ran without
+    pub plugin: bool,
+    /// Ended partway through a record, as when the app was killed while recording
+    pub truncated: bool,
+}
+
+impl ReplayReport {
+    pub fn seconds(&self) -> f64 {
+        self.frames as f64 * RNNOISE_FRAME_SIZE as f64 / SAMPLE_RATE as f64
+    }
+}
+
+/// Runs the recording at `path` through a fresh pipeline and writes the output to `output` as
+/// 48 kHz mono float WAV, lagging the input by the chain's latency as live output does
+pub fn replay(path: &Path, output: &Path) -> Result<ReplayReport, ReplayError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic).map_err(|_| ReplayError::NotARecording)?;
+    if &magic != MAGIC {
+        return Err(ReplayError::NotARecording);
+    }
+    let version = read_u8(&mut reader)?;
+    if version > VERSION {
+        return Err(ReplayError::Version(version));
+    }
+    let started = read_u64(&mut reader)?;
+    let flags = read_u8(&mut reader)?;
+    let (echo, mix) = (flags & FLAG_ECHO != 0, flags & FLAG_MIX != 0);
+
+    let slot = Arc::new(NoisePrintSlot::default());
+    let mut pipeline = Pipeline::new(echo, &Arc::new(PluginSlot::default()), &slot);
+    let mut controls = Controls::default();
+    let mut stats = SessionStats::default();
+    let (mut input, mut reference, mut mix_frame, mut processed): (Frame, Frame, Frame, Frame) =
+        ([0.0; RNNOISE_FRAME_SIZE], [0.0; RNNOISE_FRAME_SIZE], [0.0; RNNOISE_FRAME_SIZE], [0.0; RNNOISE_FRAME_SIZE]);
+    let spec = hound::WavSpec { channels: 1, sample_rate: SAMPLE_RATE, bits_per_sample: 32, sample_format: hound::SampleFormat::Float };
+    let mut writer = hound::WavWriter::create(output, spec)?;
+    let mut report = ReplayReport {
+        started,
+        frames: 0,
+        control_changes: Vec::new(),
+        first_difference: None,
+        plugin: flags & FLAG_PLUGIN != 0,
+        truncated: false,
+    };
+    let (mut hash, mut hashed) = (FNV_OFFSET, 0u32);
+
+    loop {
+        let mut tag = [0u8; 1];
+        if reader.read(&mut tag)? == 0 {
+            break;
+        }
+        let record = (|| -> Result<(), ReplayError> {
+            match tag[0] {
+                TAG_CONTROLS => {
+                    let ms = read_u64(&mut reader)?;
+                    let mut text = vec![0u8; read_u32(&mut reader)? as usize];
+                    reader.read_exact(&mut text)?;
+                    let text = String::from_utf8(text).map_err(|_| ReplayError::Damaged("controls are not text"))?;
+                    controls = decode_controls(&text);
+                    report.control_changes.push((ms, text));
+                }
+                TAG_PRINT => {
+                    let profile = if read_u8(&mut reader)? != 0 {
+                        let mut magnitudes = Vec::with_capacity(BINS);
+                        for _ in 0..BINS {
+                            magnitudes.push(read_f32(&mut reader)?);
+                        }
+                        Some(NoiseProfile { magnitudes })
+                    } else {
+                        None
+                    };
+                    slot.set_profile(profile);
+                }
+                TAG_FRAME => {
+                    read_frame(&mut reader, &mut input)?;
+                    if echo {
+                        read_frame(&mut reader, &mut reference)?;
+                    }
+                    if mix {
+                        read_frame(&mut reader, &mut mix_frame)?;
+                    }
+                    pipeline.process_frame(&input, &reference, mix.then_some(&mix_frame), &controls, &mut processed, &mut stats);
+                    for &sample in &processed {
+                        writer.write_sample(sample)?;
+                    }
+                    hash = hash_frame(hash, &processed);
+                    hashed += 1;
+                    report.frames += 1;
+                }
+                TAG_HASH => {
+                    let (since_ms, frames, recorded) = (read_u64(&mut reader)?, read_u32(&mut reader)?, read_u64(&mut reader)?);
+                    if (frames != hashed || recorded != hash) && report.first_difference.is_none() {
+                        report.first_difference = Some(since_ms);
+                    }
+                    (hash, hashed) = (FNV_OFFSET, 0);
+                }
+                _ => return Err(ReplayError::Damaged("unknown record")),
+            }
+            Ok(())
+        })();
+        match record {
+            Ok(()) => {}
+            Err(ReplayError::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                report.truncated = true;
+                break;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    writer.finalize()?;
+    Ok(report)
+}
+
+fn read_frame(reader: &mut impl Read, frame: &mut Frame) -> io::Result<()> {
+    for sample in frame.iter_mut() {
+        *sample = read_f32(reader)?;
+    }
+    Ok(())
+}
+
+fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
+    let mut bytes = [0u8; 1];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f32(reader: &mut impl Read) -> io::Result<f32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(f32::from_le_bytes(bytes))
+}
+
+// Space-separated key=value pairs. f32 Display round-trips exactly, so replay sees the same
+// values. Destructured so a new field can't be forgotten here.
+fn encode_controls(controls: &Controls) -> String {
+    let Controls { threshold, gate, bypassed, muted, two_pass, de_esser, plosive, click, music, mix_gain, chain, boost, noise_print } =
+        *controls;
+    [
+        format!("threshold={}", threshold),
+        format!("gate_mode={}", gate.mode.as_str()),
+        format!("gate_steepness={}", gate.steepness),
+        format!("gate_hold_ms={}", gate.hold_ms),
+        format!("gate_attack_ms={}", gate.attack_ms),
+        format!("gate_release_ms={}", gate.release_ms),
+        format!("gate_pre_roll_ms={}", gate.pre_roll_ms),
+        format!("gate_energy_floor_db={}", gate.energy_floor_db),
+        format!("bypassed={}", bypassed),
+        format!("muted={}", muted),
+        format!("two_pass={}", two_pass),
+        format!("de_esser_enabled={}", de_esser.enabled),
+        format!("de_esser_frequency={}", de_esser.frequency),
+        format!("de_esser_threshold_db={}", de_esser.threshold_db),
+        format!("de_esser_amount={}", de_esser.amount),
+        format!("plosive_enabled={}", plosive.enabled),
+        format!("plosive_strength={}", plosive.strength),
+        format!("click_enabled={}", click.enabled),
+        format!("click_sensitivity={}", click.sensitivity),
+        format!("music_enabled={}", music.enabled),
+        format!("music_sensitivity={}", music.sensitivity),
+        format!("mix_gain={}", mix_gain),
+        format!("chain={}", chain.as_setting()),
+        format!("boost_gain_db={}", boost.gain_db),
+        format!("noise_print_amount={}", noise_print.amount),
+    ]
+    .join(" ")
+}
+
+// Unknown keys and bad values keep the defaults
+fn decode_controls(text: &str) -> Controls {
+    let mut c = Controls::default();
+    for (key, value) in text.split(' ').filter_map(|pair| pair.split_once('=')) {
+        let float = |current: f32| value.parse().unwrap_or(current);
+        let flag = |current: bool| value.parse().unwrap_or(current);
+        match key {
+            "threshold" => c.threshold = float(c.threshold),
+            "gate_mode" => c.gate.mode = value.parse().unwrap_or(c.gate.mode),
+            "gate_steepness" => c.gate.steepness = float(c.gate.steepness),
+            "gate_hold_ms" => c.gate.hold_ms = float(c.gate.hold_ms),
+            "gate_attack_ms" => c.gate.attack_ms = float(c.gate.attack_ms),
+            "gate_release_ms" => c.gate.release_ms = float(c.gate.release_ms),
+            "gate_pre_roll_ms" => c.gate.pre_roll_ms = float(c.gate.pre_roll_ms),
+            "gate_energy_floor_db" => c.gate.energy_floor_db = float(c.gate.energy_floor_db),
+            "bypassed" => c.bypassed = flag(c.bypassed),
+            "muted" => c.muted = flag(c.muted),
+            "two_pass" => c.two_pass = flag(c.two_pass),
+            "de_esser_enabled" => c.de_esser.enabled = flag(c.de_esser.enabled),
+            "de_esser_frequency" => c.de_esser.frequency = float(c.de_esser.frequency),
+            "de_esser_threshold_db" => c.de_esser.threshold_db = float(c.de_esser.threshold_db),
+            "de_esser_amount" => c.de_esser.amount = float(c.de_esser.amount),
+            "plosive_enabled" => c.plosive.enabled = flag(c.plosive.enabled),
+            "plosive_strength" => c.plosive.strength = float(c.plosive.strength),
+            "click_enabled" => c.click.enabled = flag(c.click.enabled),
+            "click_sensitivity" => c.click.sensitivity = float(c.click.sensitivity),
+            "music_enabled" => c.music.enabled = flag(c.music.enabled),
+            "music_sensitivity" => c.music.sensitivity = float(c.music.sensitivity),
+            "mix_gain" => c.mix_gain = float(c.mix_gain),
+            "chain" => c.chain = ChainConfig::from_setting(value),
+            "boost_gain_db" => c.boost.gain_db = float(c.boost.gain_db),
+            "noise_print_amount" => c.noise_print.amount = float(c.noise_print.amount),
+            _ => {}
+        }
+    }
+    c
+}
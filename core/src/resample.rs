@@ -0,0 +1,264 @@
+//! Resampler choice for devices that don't run at 48 kHz
+use crate::audio_engine::RNNOISE_FRAME_SIZE;
+use crate::dsp::Frame;
+use crate::settings::ParseSettingError;
+use ringbuf::{HeapConsumer, HeapProducer};
+use rubato::{
+    FastFixedOut, FftFixedOut, PolynomialDegree, ResamplerConstructionError, SincFixedOut, SincInterpolationParameters,
+    SincInterpolationType, VecResampler, WindowFunction,
+};
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+// Audio queued beyond this many frames is dropped so a secondary source never lags far behind
+const MAX_SOURCE_BACKLOG: usize = 4;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ResamplerQuality {
+    /// Linear interpolation: cheapest, some aliasing
+    Fast,
+    /// FFT-based, exact for fixed ratios; the long-standing default
+    Balanced,
+    /// Band-limited sinc interpolation
+    High,
+}
+
+impl ResamplerQuality {
+    pub const ALL: [ResamplerQuality; 3] = [ResamplerQuality::Fast, ResamplerQuality::Balanced, ResamplerQuality::High];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResamplerQuality::Fast => "fast",
+            ResamplerQuality::Balanced => "balanced",
+            ResamplerQuality::High => "high",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ResamplerQuality::Fast => "Fast",
+            ResamplerQuality::Balanced => "Balanced",
+            ResamplerQuality::High => "High",
+        }
+    }
+
+    /// Name of the rubato implementation behind each setting
+    pub fn implementation(&self) -> &'static str {
+        match self {
+            ResamplerQuality::Fast => "FastFixedOut (linear)",
+            ResamplerQuality::Balanced => "FftFixedOut",
+            ResamplerQuality::High => "SincFixedOut (256 taps, cubic)",
+        }
+    }
+}
+
+impl FromStr for ResamplerQuality {
+    type Err = ParseSettingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.iter().copied().find(|q| q.as_str() == s).ok_or_else(|| ParseSettingError::new("resampler quality", s))
+    }
+}
+
+/// Mono resampler producing `chunk_size` output frames per call
+pub fn build(
+    quality: ResamplerQuality,
+    from_rate: u32,
+    to_rate: u32,
+    chunk_size: usize,
+) -> Result<Box<dyn VecResampler<f32>>, ResamplerConstructionError> {
+    let ratio = to_rate as f64 / from_rate as f64;
+    Ok(match quality {
+        ResamplerQuality::Fast => Box::new(FastFixedOut::<f32>::new(ratio, 1.0, PolynomialDegree::Linear, chunk_size, 1)?),
+        ResamplerQuality::Balanced => {
+            Box::new(FftFixedOut::<f32>::new(from_rate as usize, to_rate as usize, chunk_size, 2, 1)?)
+        }
+        ResamplerQuality::High => {
+            let params = SincInterpolationParameters {
+                sinc_len: 256,
+                f_cutoff: 0.95,
+                oversampling_factor: 128,
+                interpolation: SincInterpolationType::Cubic,
+                window: WindowFunction::BlackmanHarris2,
+            };
+            Box::new(SincFixedOut::<f32>::new(ratio, 1.0, params, chunk_size, 1)?)
+        }
+    })
+}
+
+/// Processed 48 kHz audio converted to an output device's rate and queued for its callback
+pub struct OutputQueue {
+    samples: HeapProducer<f32>,
+    resampler: Option<Box<dyn VecResampler<f32>>>,
+    sample_rate: u32,
+    chunk: Vec<Vec<f32>>,
+    // 48 kHz samples waiting for a full resampler chunk
+    pending: VecDeque<f32>,
+}
+
+impl OutputQueue {
+    pub fn new(samples: HeapProducer<f32>, sample_rate: u32, quality: ResamplerQuality) -> Result<Self, ResamplerConstructionError> {
+        let resampler = if sample_rate != 48000 { Some(build(quality, 48000, sample_rate, RNNOISE_FRAME_SIZE)?) } else { None };
+        Ok(Self { samples, resampler, sample_rate, chunk: vec![vec![]; 1], pending: VecDeque::new() })
+    }
+
+    pub fn is_resampling(&self) -> bool {
+        self.resampler.is_some()
+    }
+
+    pub fn delay_ms(&self) -> f32 {
+        self.resampler.as_ref().map_or(0.0, |r| r.output_delay() as f32 * 1000.0 / self.sample_rate as f32)
+    }
+
+    /// Device-rate samples waiting for the callback
+    pub fn queued(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Device-rate samples plus 48 kHz ones still waiting for a resampler chunk, as time
+    pub fn backlog_ms(&self) -> f32 {
+        self.samples.len() as f32 * 1000.0 / self.sample_rate as f32 + self.pending.len() as f32 * 1000.0 / 48000.0
+    }
+
+    /// Never blocks: if the device stalls, its queue fills and samples are dropped
+    pub fn push(&mut self, block: &[f32]) {
+        let Some(r) = self.resampler.as_mut() else {
+            self.samples.push_slice(block);
+            return;
+        };
+        self.pending.extend(block.iter());
+        loop {
+            let needed = r.input_frames_next();
+            if self.pending.len() < needed {
+                break;
+            }
+            let chunk = &mut self.chunk[0];
+            chunk.clear();
+            chunk.extend(self.pending.drain(..needed));
+            match r.process(&self.chunk, None) {
+                Ok(resampled) => {
+                    self.samples.push_slice(&resampled[0]);
+                }
+                Err(e) => {
+                    log::warn!("Output resampling error: {}", e);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Samples from a secondary capture stream (mono, device rate) turned into 48 kHz frames,
+/// pulled one per mic frame
+pub struct FrameSource {
+    samples: HeapConsumer<f32>,
+    resampler: Option<Box<dyn VecResampler<f32>>>,
+    chunk: Vec<Vec<f32>>,
+    // 48 kHz samples not yet handed out as a frame
+    pending: VecDeque<f32>,
+}
+
+impl FrameSource {
+    pub fn new(samples: HeapConsumer<f32>, sample_rate: u32, quality: ResamplerQuality) -> Self {
+        let resampler = if sample_rate != 48000 {
+            match build(quality, sample_rate, 48000, RNNOISE_FRAME_SIZE) {
+                Ok(r) => Some(r),
+                Err(e) => {
+                    log::warn!("Capture resampler init failed ({} Hz): {}", sample_rate, e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        Self { samples, resampler, chunk: vec![vec![]; 1], pending: VecDeque::new() }
+    }
+
+    /// Fills `out` with the next frame, or silence if the device hasn't caught up
+    pub fn next_frame(&mut self, out: &mut Frame) {
+        let max_backlog = RNNOISE_FRAME_SIZE * MAX_SOURCE_BACKLOG;
+        let backlog = self.samples.len();
+        if backlog > max_backlog {
+            self.samples.skip(backlog - max_backlog);
+        }
+
+        while self.pending.len() < RNNOISE_FRAME_SIZE {
+            match self.resampler.as_mut() {
+                Some(r) => {
+                    let needed = r.input_frames_next();
+                    if self.samples.len() < needed {
+                        break;
+                    }
+                    let chunk = &mut self.chunk[0];
+                    chunk.clear();
+                    chunk.extend((0..needed).map(|_| self.samples.pop().unwrap_or(0.0)));
+                    match r.process(&self.chunk, None) {
+                        Ok(resampled) => self.pending.extend(resampled[0].iter()),
+                        Err(_) => break,
+                    }
+                }
+                None => {
+                    if self.samples.is_empty() {
+                        break;
+                    }
+                    self.pending.extend(self.samples.pop_iter());
+                }
+            }
+        }
+
+        if self.pending.len() < RNNOISE_FRAME_SIZE {
+            out.fill(0.0);
+            return;
+        }
+        for (dst, src) in out.iter_mut().zip(self.pending.drain(..RNNOISE_FRAME_SIZE)) {
+            *dst = src;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ringbuf::HeapRb;
+
+    // A 44.1 kHz device delivering 10 ms callbacks while the processing thread pulls one frame
+    // per 10 ms: every tick after the resampler fills should produce a real frame, and the
+    // queues must not grow, however long it runs.
+    #[test]
+    fn frame_source_keeps_pace_with_a_44100_hz_device() {
+        const RATE: usize = 44100;
+        const SECONDS: usize = 20;
+        const TICKS: usize = SECONDS * 100;
+        // Ticks allowed to come up short while the resampler's first chunk is pending
+        const WARM_UP: usize = 3;
+        for quality in ResamplerQuality::ALL {
+            let (mut device, samples) = HeapRb::<f32>::new(RATE).split();
+            let mut source = FrameSource::new(samples, RATE as u32, quality);
+            let mut frame = [0.0; RNNOISE_FRAME_SIZE];
+            let mut missed = Vec::new();
+            for tick in 0..TICKS {
+                // DC, so a frame of exact zeros can only be the fill-in for a missing one
+                device.push_slice(&[0.5; RATE / 100]);
+                source.next_frame(&mut frame);
+                if frame.iter().all(|&s| s == 0.0) {
+                    missed.push(tick);
+                }
+            }
+            assert!(missed.iter().all(|&tick| tick < WARM_UP), "{:?}: missed frames at ticks {:?}", quality, missed);
+
+            // Everything pushed has been turned into frames, apart from what is still queued
+            let delivered = (TICKS - missed.len()) * RNNOISE_FRAME_SIZE;
+            let queued = source.samples.len() as f64 * 48000.0 / RATE as f64 + source.pending.len() as f64;
+            let expected = (RATE * SECONDS) as f64 * 48000.0 / RATE as f64;
+            assert!(
+                (delivered as f64 + queued - expected).abs() < RNNOISE_FRAME_SIZE as f64,
+                "{:?}: {} samples delivered and {} queued, {} expected",
+                quality,
+                delivered,
+                queued,
+                expected
+            );
+            assert!(queued < (WARM_UP + 1) as f64 * RNNOISE_FRAME_SIZE as f64, "{:?}: {} samples queued", quality, queued);
+        }
+    }
+}
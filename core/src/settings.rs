@@ -0,0 +1,109 @@
+//! Everything a frontend configures on an [`AudioEngine`](crate::AudioEngine), as one value.
+//!
+//! Live settings (the threshold, the stages, the gains) take effect on the next processed
+//! block. Session settings (devices besides the main two, resampling, block size, channel
+//! and overflow handling) are read when the engine starts, so changing them needs a restart.
+//!
+//! ```
+//! use silentstream_core::dsp::GateMode;
+//! use silentstream_core::Settings;
+//!
+//! let mut settings = Settings { threshold: 0.7, ..Settings::default() };
+//! settings.gate.mode = "soft".parse().unwrap();
+//! assert_eq!(settings.gate.mode, GateMode::Soft);
+//! assert!("loud".parse::<GateMode>().is_err());
+//! ```
+use crate::audio_engine::{CaptureSource, InputChannel, OverflowPolicy};
+use crate::dsp::{BoostConfig, ClickConfig, DeEsserConfig, GateConfig, MusicConfig, PlosiveConfig};
+use crate::noise_print::NoisePrintConfig;
+use crate::overload::OverloadPolicy;
+use crate::pipeline::ChainConfig;
+use crate::resample::ResamplerQuality;
+
+/// Engine configuration; `Settings::default()` is what a fresh engine starts with.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Settings {
+    /// VAD probability (0.0..1.0) below which the gate closes.
+    pub threshold: f32,
+    pub gate: GateConfig,
+    /// What happens when processing can't keep up.
+    pub overload_policy: OverloadPolicy,
+    /// Passes the microphone through unprocessed.
+    pub bypass: bool,
+    /// Chains a second denoiser pass ("Strong" suppression).
+    pub strong_suppression: bool,
+    pub de_esser: DeEsserConfig,
+    pub plosive: PlosiveConfig,
+    pub boost: BoostConfig,
+    /// Keyboard-click assist on top of the gate.
+    pub click: ClickConfig,
+    pub music: MusicConfig,
+    /// Stage order and which stages are on.
+    pub chain: ChainConfig,
+    pub noise_print: NoisePrintConfig,
+    /// Backlog above which processed frames are dropped, gated ones first; 0 = no cap.
+    pub latency_cap_ms: u32,
+    /// Volume of the live monitor, 0.0..1.0.
+    pub monitor_gain: f32,
+    /// Level of `mix_source` under the processed voice, 0.0..1.0.
+    pub mix_gain: f32,
+    /// Output device whose playback is cancelled from the mic (`Some("")` = default output);
+    /// None turns echo cancellation off. Read at start.
+    pub echo_reference: Option<String>,
+    /// Read at start.
+    pub resampler_quality: ResamplerQuality,
+    /// RNNoise frames processed per wakeup, one of `BLOCK_FRAMES`. Read at start.
+    pub block_frames: usize,
+    /// Falls back to the first channel if the device has fewer. Read at start.
+    pub input_channel: InputChannel,
+    /// Read at start.
+    pub overflow_policy: OverflowPolicy,
+    /// Output device that also gets the processed audio (`Some("")` = default output). Read
+    /// at start.
+    pub monitor_device: Option<String>,
+    /// Extra input summed under the processed voice, bypassing the denoiser. Read at start.
+    pub mix_source: Option<CaptureSource>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            threshold: 0.5,
+            gate: GateConfig::default(),
+            overload_policy: OverloadPolicy::PreferQuality,
+            bypass: false,
+            strong_suppression: false,
+            de_esser: DeEsserConfig::default(),
+            plosive: PlosiveConfig::default(),
+            boost: BoostConfig::default(),
+            click: ClickConfig::default(),
+            music: MusicConfig::default(),
+            chain: ChainConfig::default(),
+            noise_print: NoisePrintConfig::default(),
+            latency_cap_ms: 150,
+            monitor_gain: 1.0,
+            mix_gain: 0.5,
+            echo_reference: None,
+            resampler_quality: ResamplerQuality::Balanced,
+            block_frames: 1,
+            input_channel: InputChannel::default(),
+            overflow_policy: OverflowPolicy::DropOldest,
+            monitor_device: None,
+            mix_source: None,
+        }
+    }
+}
+
+/// A settings string that isn't one of the type's `as_str()` forms.
+#[derive(Clone, PartialEq, Debug, thiserror::Error)]
+#[error("Unknown {kind} '{value}'")]
+pub struct ParseSettingError {
+    kind: &'static str,
+    value: String,
+}
+
+impl ParseSettingError {
+    pub(crate) fn new(kind: &'static str, value: &str) -> Self {
+        Self { kind, value: value.to_string() }
+    }
+}
@@ -0,0 +1,111 @@
+//! Vectorized versions of the per-sample loops that run on every frame. SSE2 is part of the
+//! x86_64 baseline, so no runtime detection is needed; other targets use the plain loops.
+//! Lengths that aren't a multiple of four finish the tail with the scalar loop.
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+/// `dst[i] = src[i] * factor`, over the shorter of the two
+pub fn scale(src: &[f32], dst: &mut [f32], factor: f32) {
+    let len = src.len().min(dst.len());
+    let (src, dst) = (&src[..len], &mut dst[..len]);
+    #[cfg(target_arch = "x86_64")]
+    let done = unsafe { scale_sse2(src, dst, factor) };
+    #[cfg(not(target_arch = "x86_64"))]
+    let done = 0;
+    scale_scalar(&src[done..], &mut dst[done..], factor);
+}
+
+pub fn sum_of_squares(samples: &[f32]) -> f32 {
+    #[cfg(target_arch = "x86_64")]
+    let (sum, done) = unsafe { sum_of_squares_sse2(samples) };
+    #[cfg(not(target_arch = "x86_64"))]
+    let (sum, done) = (0.0f32, 0);
+    sum + sum_of_squares_scalar(&samples[done..])
+}
+
+/// The plain loops: the tails above, other targets, and the reference in tests and benches/simd.rs
+pub fn scale_scalar(src: &[f32], dst: &mut [f32], factor: f32) {
+    for (d, s) in dst.iter_mut().zip(src) {
+        *d = s * factor;
+    }
+}
+
+pub fn sum_of_squares_scalar(samples: &[f32]) -> f32 {
+    samples.iter().map(|s| s * s).sum()
+}
+
+// Returns how many samples were handled
+#[cfg(target_arch = "x86_64")]
+unsafe fn scale_sse2(src: &[f32], dst: &mut [f32], factor: f32) -> usize {
+    let chunks = src.len() / 4;
+    let factor = _mm_set1_ps(factor);
+    for i in 0..chunks {
+        let v = _mm_loadu_ps(src.as_ptr().add(i * 4));
+        _mm_storeu_ps(dst.as_mut_ptr().add(i * 4), _mm_mul_ps(v, factor));
+    }
+    chunks * 4
+}
+
+// Four running sums, added together at the end
+#[cfg(target_arch = "x86_64")]
+unsafe fn sum_of_squares_sse2(samples: &[f32]) -> (f32, usize) {
+    let chunks = samples.len() / 4;
+    let mut acc = _mm_setzero_ps();
+    for i in 0..chunks {
+        let v = _mm_loadu_ps(samples.as_ptr().add(i * 4));
+        acc = _mm_add_ps(acc, _mm_mul_ps(v, v));
+    }
+    let mut lanes = [0.0f32; 4];
+    _mm_storeu_ps(lanes.as_mut_ptr(), acc);
+    (lanes.iter().sum(), chunks * 4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LENGTHS: [usize; 6] = [0, 1, 3, 4, 5, 480];
+
+    // Deterministic, covering both signs and the range RNNoise sees after scaling
+    fn signal(len: usize) -> Vec<f32> {
+        (0..len).map(|i| ((i as f32 * 0.731).sin() * 0.9 + (i % 7) as f32 * 0.01) * if i % 3 == 0 { -1.0 } else { 1.0 }).collect()
+    }
+
+    #[test]
+    fn scale_matches_scalar() {
+        for len in LENGTHS {
+            // Offsets 1..3 start the slices off the 16-byte alignment of the buffer
+            for offset in 0..4 {
+                let src = signal(len + offset);
+                let (mut simd, mut scalar) = (vec![0.0; len + offset], vec![0.0; len + offset]);
+                scale(&src[offset..], &mut simd[offset..], 32768.0);
+                scale_scalar(&src[offset..], &mut scalar[offset..], 32768.0);
+                // One multiply per sample either way, so the results are bit-identical
+                assert_eq!(simd, scalar, "length {} offset {}", len, offset);
+            }
+        }
+    }
+
+    #[test]
+    fn scale_stops_at_the_shorter_slice() {
+        let src = signal(10);
+        let mut dst = vec![7.0; 6];
+        scale(&src, &mut dst, 2.0);
+        assert!(dst.iter().zip(&src).all(|(d, s)| *d == s * 2.0));
+        let mut dst = vec![7.0; 10];
+        scale(&src[..5], &mut dst, 2.0);
+        assert!(dst[5..].iter().all(|&d| d == 7.0));
+    }
+
+    #[test]
+    fn sum_of_squares_matches_scalar() {
+        for len in LENGTHS {
+            for offset in 0..4 {
+                let samples = signal(len + offset);
+                let (simd, scalar) = (sum_of_squares(&samples[offset..]), sum_of_squares_scalar(&samples[offset..]));
+                // The four lanes add in a different order
+                assert!((simd - scalar).abs() <= scalar * 1e-5 + f32::EPSILON, "length {} offset {}: {} vs {}", len, offset, simd, scalar);
+            }
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Distribution of recent VAD probabilities, for placing the threshold. Noise and speech
+//! usually form two humps; the threshold belongs in the valley between them. Filled from the
+//! processing thread, so memory is fixed and recording never allocates.
+
+pub const VAD_BUCKETS: usize = 64;
+// About a minute of 10 ms frames
+const HISTORY_FRAMES: usize = 6000;
+// Below this a suggestion would mostly reflect the first few words
+const MIN_FRAMES_FOR_SUGGESTION: u32 = 500;
+// The second hump must be this far from the first to count as separate
+const MIN_PEAK_DISTANCE: usize = 8;
+// ... and hold at least this share of the frames
+const MIN_PEAK_SHARE: f32 = 0.02;
+
+pub struct VadHistogram {
+    counts: [u32; VAD_BUCKETS],
+    // Bucket of each frame in the window, oldest at `next` once full
+    history: [u8; HISTORY_FRAMES],
+    next: usize,
+    len: usize,
+}
+
+impl Default for VadHistogram {
+    fn default() -> Self {
+        Self { counts: [0; VAD_BUCKETS], history: [0; HISTORY_FRAMES], next: 0, len: 0 }
+    }
+}
+
+impl VadHistogram {
+    pub fn record(&mut self, prob: f32) {
+        let bucket = ((prob.clamp(0.0, 1.0) * VAD_BUCKETS as f32) as usize).min(VAD_BUCKETS - 1);
+        if self.len == HISTORY_FRAMES {
+            let oldest = self.history[self.next] as usize;
+            self.counts[oldest] -= 1;
+        } else {
+            self.len += 1;
+        }
+        self.history[self.next] = bucket as u8;
+        self.next = (self.next + 1) % HISTORY_FRAMES;
+        self.counts[bucket] += 1;
+    }
+
+    pub fn reset(&mut self) {
+        self.counts = [0; VAD_BUCKETS];
+        self.next = 0;
+        self.len = 0;
+    }
+
+    pub fn counts(&self) -> [u32; VAD_BUCKETS] {
+        self.counts
+    }
+
+    pub fn total(&self) -> u32 {
+        self.len as u32
+    }
+
+    /// Middle of the deepest bucket between the two largest humps; None until there is enough
+    /// history or while only one hump stands out (e.g. nobody has spoken yet)
+    pub fn suggest_threshold(&self) -> Option<f32> {
+        let total = self.total();
+        if total < MIN_FRAMES_FOR_SUGGESTION {
+            return None;
+        }
+        // Three-bucket average so single noisy buckets don't count as humps
+        let mut smoothed = [0.0f32; VAD_BUCKETS];
+        for (i, s) in smoothed.iter_mut().enumerate() {
+            let lo = i.saturating_sub(1);
+            let hi = (i + 1).min(VAD_BUCKETS - 1);
+            *s = self.counts[lo..=hi].iter().sum::<u32>() as f32 / (hi - lo + 1) as f32;
+        }
+        let first = (0..VAD_BUCKETS).max_by(|&a, &b| smoothed[a].total_cmp(&smoothed[b]))?;
+        let is_local_max = |i: usize| {
+            (i == 0 || smoothed[i] >= smoothed[i - 1]) && (i == VAD_BUCKETS - 1 || smoothed[i] >= smoothed[i + 1])
+        };
+        let second = (0..VAD_BUCKETS)
+            .filter(|&i| i.abs_diff(first) >= MIN_PEAK_DISTANCE && is_local_max(i))
+            .max_by(|&a, &b| smoothed[a].total_cmp(&smoothed[b]))?;
+        if smoothed[second] * 3.0 < total as f32 * MIN_PEAK_SHARE {
+            return None;
+        }
+        let (lo, hi) = (first.min(second), first.max(second));
+        let valley = (lo..=hi).min_by(|&a, &b| smoothed[a].total_cmp(&smoothed[b]))?;
+        Some((valley as f32 + 0.5) / VAD_BUCKETS as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filled(frames: &[(f32, usize)]) -> VadHistogram {
+        let mut histogram = VadHistogram::default();
+        for &(prob, count) in frames {
+            for _ in 0..count {
+                histogram.record(prob);
+            }
+        }
+        histogram
+    }
+
+    #[test]
+    fn window_holds_the_last_6000_frames() {
+        let histogram = filled(&[(0.1, HISTORY_FRAMES), (0.9, 2500)]);
+        assert_eq!(histogram.total(), 6000);
+        assert_eq!(histogram.counts().iter().sum::<u32>(), 6000);
+        let counts = histogram.counts();
+        assert_eq!(counts[(0.1 * VAD_BUCKETS as f32) as usize], 3500);
+        assert_eq!(counts[(0.9 * VAD_BUCKETS as f32) as usize], 2500);
+
+        // The 0.1 frames are pushed out entirely
+        let histogram = filled(&[(0.1, HISTORY_FRAMES), (0.9, HISTORY_FRAMES + 10)]);
+        assert_eq!(histogram.total(), 6000);
+        assert_eq!(histogram.counts()[(0.1 * VAD_BUCKETS as f32) as usize], 0);
+    }
+
+    #[test]
+    fn out_of_range_probabilities_land_in_the_end_buckets() {
+        let counts = filled(&[(-0.5, 1), (1.0, 1), (7.0, 1)]).counts();
+        assert_eq!(counts[0], 1);
+        assert_eq!(counts[VAD_BUCKETS - 1], 2);
+    }
+
+    #[test]
+    fn reset_empties_the_window() {
+        let mut histogram = filled(&[(0.1, 3000), (0.9, 1000)]);
+        histogram.reset();
+        assert_eq!(histogram.total(), 0);
+        assert!(histogram.counts().iter().all(|&c| c == 0));
+        assert_eq!(histogram.suggest_threshold(), None);
+
+        // And fills again from scratch
+        histogram.record(0.5);
+        assert_eq!(histogram.total(), 1);
+        assert_eq!(histogram.counts().iter().sum::<u32>(), 1);
+    }
+
+    #[test]
+    fn bimodal_input_suggests_a_threshold_between_the_modes() {
+        let histogram = filled(&[(0.05, 2000), (0.1, 1500), (0.15, 500), (0.85, 300), (0.9, 700)]);
+        let threshold = histogram.suggest_threshold().unwrap();
+        assert!(threshold > 0.15 && threshold < 0.85, "{threshold}");
+    }
+
+    #[test]
+    fn single_mode_suggests_nothing() {
+        assert_eq!(filled(&[(0.05, 2000), (0.1, 2000)]).suggest_threshold(), None);
+    }
+
+    #[test]
+    fn humps_closer_than_min_peak_distance_count_as_one() {
+        let apart = (MIN_PEAK_DISTANCE / 2) as f32 / VAD_BUCKETS as f32;
+        assert_eq!(filled(&[(0.3, 2000), (0.3 + apart, 1500)]).suggest_threshold(), None);
+    }
+
+    #[test]
+    fn second_hump_needs_min_peak_share() {
+        // 50 of 5050 frames is under 2%
+        assert_eq!(filled(&[(0.1, 5000), (0.9, 50)]).suggest_threshold(), None);
+        assert!(filled(&[(0.1, 5000), (0.9, 200)]).suggest_threshold().is_some());
+    }
+
+    #[test]
+    fn fewer_than_500_frames_suggests_nothing() {
+        assert_eq!(filled(&[(0.1, 300), (0.9, 199)]).suggest_threshold(), None);
+        assert!(filled(&[(0.1, 300), (0.9, 200)]).suggest_threshold().is_some());
+    }
+}
@@ -0,0 +1,60 @@
+// Helpers shared by the integration tests: fixtures from scripts/make_fixtures.py and the
+// offline chain. Each test binary compiles its own copy and uses only some of them.
+#![allow(dead_code)]
+
+use silentstream_core::{Controls, OfflineProcessor};
+use std::path::{Path, PathBuf};
+
+pub fn fixture_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+}
+
+// 16-bit samples as f32
+pub fn read_wav(path: &Path) -> Vec<f32> {
+    let mut reader = hound::WavReader::open(path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+    reader.samples::<i16>().map(|s| s.unwrap() as f32 / 32768.0).collect()
+}
+
+pub fn read_fixture(name: &str) -> Vec<f32> {
+    read_wav(&fixture_path(name))
+}
+
+pub fn rms(samples: &[f32]) -> f32 {
+    (samples.iter().map(|s| s * s).sum::<f32>() / samples.len().max(1) as f32).sqrt()
+}
+
+pub fn peak(samples: &[f32]) -> f32 {
+    samples.iter().fold(0.0, |m, s| m.max(s.abs()))
+}
+
+pub fn db(ratio: f32) -> f32 {
+    20.0 * ratio.log10()
+}
+
+// Largest sample-by-sample difference
+pub fn max_difference(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max)
+}
+
+// The chain's output lined up with the input: the latency trimmed off the front and the tail
+// cut to the input's length
+pub fn process(input: &[f32], controls: Controls) -> Vec<f32> {
+    let mut processor = OfflineProcessor::new(controls);
+    let mut output = Vec::new();
+    processor.process_block(input, &mut output);
+    let latency = processor.latency();
+    processor.finish(&mut output);
+    output.drain(..latency);
+    output.truncate(input.len());
+    output
+}
+
+// The output with one stage off and then on, everything else as in `controls`
+pub fn process_off_on(input: &[f32], controls: Controls, stage: fn(&mut Controls, bool)) -> (Vec<f32>, Vec<f32>) {
+    let with = |enabled| {
+        let mut controls = controls;
+        stage(&mut controls, enabled);
+        process(input, controls)
+    };
+    (with(false), with(true))
+}
@@ -0,0 +1,87 @@
+// Decoding of the formats offline_file reads, through process_file() as batch processing uses
+// it. The FLAC fixture holds the same samples as its WAV twin, so both must come out the same;
+// the MP3 and Ogg Vorbis fixtures are silence with gapless trim information, which checks the
+// containers and the lengths rather than the codecs. See scripts/make_fixtures.py.
+//
+// Output is always WAV; there is no FLAC encoder among the dependencies.
+mod common;
+
+use common::fixture_path;
+use silentstream_core::offline_file::{self, FileError, OutputFormat, OutputOptions};
+use silentstream_core::Controls;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+
+// Processes a fixture into a temporary WAV and reads it back: the spec and the samples
+fn process(name: &str, format: OutputFormat) -> (hound::WavSpec, Vec<i32>) {
+    let output = std::env::temp_dir().join(format!("silentstream-formats-{}-{}-{:?}.wav", std::process::id(), name, format));
+    let options = OutputOptions { format, normalize: None };
+    offline_file::process_file(&fixture_path(name), &output, options, Controls::default(), &AtomicBool::new(false), |_| {})
+        .unwrap_or_else(|e| panic!("{}: {}", name, e));
+    let mut reader = hound::WavReader::open(&output).unwrap();
+    let samples = reader.samples::<i32>().map(Result::unwrap).collect();
+    let spec = reader.spec();
+    drop(reader);
+    std::fs::remove_file(&output).unwrap();
+    (spec, samples)
+}
+
+fn probe(name: &str) -> offline_file::SourceInfo {
+    offline_file::probe(&fixture_path(name)).unwrap_or_else(|e| panic!("{}: {}", name, e))
+}
+
+#[test]
+fn flac_reads_like_the_same_wav() {
+    let (wav, flac) = (probe("speech_44k_stereo.wav"), probe("speech_44k_stereo.flac"));
+    assert_eq!((flac.sample_rate, flac.channels, flac.frames), (44100, 2, Some(22050)));
+    assert_eq!((flac.sample_rate, flac.channels, flac.frames), (wav.sample_rate, wav.channels, wav.frames));
+    assert_eq!(flac.spec, wav.spec);
+}
+
+#[test]
+fn flac_round_trips_through_the_chain_like_wav() {
+    for format in OutputFormat::ALL {
+        let (wav_spec, from_wav) = process("speech_44k_stereo.wav", format);
+        let (flac_spec, from_flac) = process("speech_44k_stereo.flac", format);
+        assert_eq!(flac_spec, wav_spec, "{:?}", format);
+        assert!(from_flac == from_wav, "{:?}: FLAC and WAV input gave different output", format);
+    }
+}
+
+#[test]
+fn output_format_sets_rate_channels_and_length() {
+    let (spec, samples) = process("speech_44k_stereo.flac", OutputFormat::Original);
+    assert_eq!((spec.sample_rate, spec.channels, spec.bits_per_sample), (44100, 2, 16));
+    assert_eq!(samples.len(), 22050 * 2);
+    let (spec, samples) = process("speech_44k_stereo.flac", OutputFormat::Mono48k);
+    assert_eq!((spec.sample_rate, spec.channels), (48000, 1));
+    assert_eq!(samples.len(), 24000);
+}
+
+#[test]
+fn mp3_encoder_delay_and_padding_are_trimmed() {
+    let info = probe("silence.mp3");
+    assert_eq!((info.sample_rate, info.channels, info.spec.bits_per_sample), (48000, 1, 16));
+    // 50 frames of 1152 samples, less 1105 of delay and 2495 of padding
+    assert_eq!(info.frames, Some(54000));
+    let (spec, samples) = process("silence.mp3", OutputFormat::Original);
+    assert_eq!((spec.sample_rate, spec.channels), (48000, 1));
+    assert_eq!(samples.len(), 54000);
+    assert!(samples.iter().all(|&s| s == 0));
+}
+
+#[test]
+fn vorbis_is_trimmed_to_the_last_granule_position() {
+    let info = probe("silence.ogg");
+    assert_eq!((info.sample_rate, info.channels, info.spec.bits_per_sample), (48000, 1, 16));
+    let (spec, samples) = process("silence.ogg", OutputFormat::Original);
+    assert_eq!((spec.sample_rate, spec.channels), (48000, 1));
+    assert_eq!(samples.len(), 48000);
+    assert!(samples.iter().all(|&s| s == 0));
+}
+
+#[test]
+fn other_files_are_turned_down_unopened() {
+    let result = offline_file::probe(Path::new("recording.aiff"));
+    assert!(matches!(result, Err(FileError::Unsupported(_))));
+}
@@ -0,0 +1,96 @@
+// The gate's pre-roll and energy floor on fixtures from scripts/make_fixtures.py: word onsets
+// that start before the VAD notices them, and whispers that the VAD scores low.
+mod common;
+
+use common::{process, read_fixture, rms};
+use silentstream_core::dsp::GateConfig;
+use silentstream_core::Controls;
+
+const FRAME: usize = 480;
+
+// Start of each stretch that rises above `level` after at least 100 ms below it
+fn onsets(samples: &[f32], level: f32) -> Vec<usize> {
+    let mut found = Vec::new();
+    let mut quiet = usize::MAX;
+    for (i, s) in samples.iter().enumerate() {
+        if s.abs() > level {
+            if quiet >= 4800 {
+                found.push(i);
+            }
+            quiet = 0;
+        } else {
+            quiet = quiet.saturating_add(1);
+        }
+    }
+    found
+}
+
+// Milliseconds from each word's first sample in the input to the same word in the output
+fn onset_delays(pre_roll_ms: f32, threshold: f32) -> Vec<f32> {
+    let input = read_fixture("onsets.wav");
+    let output = process(&input, Controls { threshold, gate: GateConfig { pre_roll_ms, ..GateConfig::default() }, ..Controls::default() });
+    let (words, heard) = (onsets(&input, 0.02), onsets(&output, 0.02));
+    assert_eq!(words.len(), 3, "{:?}", words);
+    assert_eq!(heard.len(), words.len(), "pre-roll {} ms: words heard at {:?}, spoken at {:?}", pre_roll_ms, heard, words);
+    words.iter().zip(&heard).map(|(w, h)| (*h as f32 - *w as f32) / 48.0).collect()
+}
+
+#[test]
+fn pre_roll_brings_word_onsets_forward_at_high_thresholds() {
+    for threshold in [0.8, 0.9] {
+        let (none, short, long) = (onset_delays(0.0, threshold), onset_delays(10.0, threshold), onset_delays(20.0, threshold));
+        for word in 0..none.len() {
+            assert!(
+                none[word] > short[word] && short[word] > long[word],
+                "threshold {}, word {}: {} / {} / {} ms late with 0 / 10 / 20 ms of pre-roll",
+                threshold,
+                word,
+                none[word],
+                short[word],
+                long[word]
+            );
+            // Most of the 20 ms comes back; the attack ramp takes the rest
+            assert!(none[word] - long[word] > 15.0, "threshold {}, word {}: {} vs {} ms", threshold, word, none[word], long[word]);
+        }
+    }
+}
+
+#[test]
+fn full_pre_roll_keeps_onsets_at_the_default_threshold() {
+    for (word, delay) in onset_delays(20.0, 0.5).into_iter().enumerate() {
+        assert!(delay < 5.0, "word {} starts {} ms late", word, delay);
+    }
+}
+
+// Speech frames the gate shut (output 20 dB under the input), and the same for the gaps
+fn closed_frames(threshold: f32, energy_floor_db: f32) -> ((usize, usize), (usize, usize)) {
+    let input = read_fixture("whisper.wav");
+    let output = process(&input, Controls { threshold, gate: GateConfig { energy_floor_db, ..GateConfig::default() }, ..Controls::default() });
+    let (mut speech, mut gaps) = ((0, 0), (0, 0));
+    for (i, o) in input.chunks(FRAME).zip(output.chunks(FRAME)) {
+        let counts = if rms(i) > 0.005 { &mut speech } else { &mut gaps };
+        counts.1 += 1;
+        if rms(o) < rms(i) * 0.1 {
+            counts.0 += 1;
+        }
+    }
+    (speech, gaps)
+}
+
+#[test]
+fn energy_floor_gates_fewer_whisper_frames_than_the_vad_alone() {
+    for threshold in [0.5, 0.7] {
+        let (vad_only, _) = closed_frames(threshold, 0.0);
+        let (with_floor, (gaps_closed, gaps)) = closed_frames(threshold, GateConfig::default().energy_floor_db);
+        assert!(
+            with_floor.0 < vad_only.0,
+            "threshold {}: {} of {} whisper frames gated with the floor, {} without",
+            threshold,
+            with_floor.0,
+            with_floor.1,
+            vad_only.0
+        );
+        // The floor only holds an open gate; it still shuts between phrases
+        assert!(gaps_closed * 10 > gaps * 8, "threshold {}: {} of {} gap frames gated", threshold, gaps_closed, gaps);
+    }
+}
@@ -0,0 +1,117 @@
+// Golden-file regression tests for the processing chain. Each fixture under tests/fixtures
+// goes through OfflineProcessor, and the output is compared with the one stored under
+// tests/fixtures/golden within a small tolerance, so a refactor that changes what comes out
+// shows up here. Properties that must hold at any threshold are checked separately.
+//
+// The fixtures come from scripts/make_fixtures.py. After an intended change to the output,
+// regenerate the golden files with
+//
+//     SILENTSTREAM_BLESS=1 cargo test -p silentstream-core --test golden
+//
+// and listen to the difference before committing them.
+mod common;
+
+use common::{fixture_path, process, read_fixture, read_wav, rms};
+use silentstream_core::{Controls, OfflineProcessor};
+use std::path::Path;
+
+const FIXTURES: [&str; 4] = ["clean_speech", "speech_fan", "noise", "silence"];
+const THRESHOLDS: [f32; 3] = [0.2, 0.5, 0.9];
+// Golden outputs are stored at the default threshold
+const GOLDEN_THRESHOLD: f32 = 0.5;
+// A few 16-bit steps, plus room for floating-point differences between CPUs
+const TOLERANCE: f32 = 2e-3;
+
+fn write_wav(path: &Path, samples: &[f32]) {
+    let spec = hound::WavSpec { channels: 1, sample_rate: 48000, bits_per_sample: 16, sample_format: hound::SampleFormat::Int };
+    let mut writer = hound::WavWriter::create(path, spec).unwrap();
+    for &s in samples {
+        writer.write_sample((s * 32768.0).round().clamp(-32768.0, 32767.0) as i16).unwrap();
+    }
+    writer.finalize().unwrap();
+}
+
+fn at(threshold: f32) -> Controls {
+    Controls { threshold, ..Controls::default() }
+}
+
+#[test]
+fn output_matches_golden_files() {
+    let bless = std::env::var_os("SILENTSTREAM_BLESS").is_some();
+    for name in FIXTURES {
+        let input = read_fixture(&format!("{}.wav", name));
+        let output = process(&input, at(GOLDEN_THRESHOLD));
+        let golden_path = fixture_path(&format!("golden/{}.wav", name));
+        if bless {
+            std::fs::create_dir_all(golden_path.parent().unwrap()).unwrap();
+            write_wav(&golden_path, &output);
+            continue;
+        }
+        let golden = read_wav(&golden_path);
+        assert_eq!(output.len(), golden.len(), "{}: length differs from the golden output", name);
+        if let Some((i, (a, b))) = output.iter().zip(&golden).enumerate().find(|(_, (a, b))| (*a - *b).abs() > TOLERANCE) {
+            panic!("{}: sample {} is {} instead of {} (see the header to regenerate)", name, i, a, b);
+        }
+    }
+}
+
+#[test]
+fn output_length_matches_input() {
+    for name in FIXTURES {
+        let input = read_fixture(&format!("{}.wav", name));
+        for threshold in THRESHOLDS {
+            let mut processor = OfflineProcessor::new(Controls { threshold, ..Controls::default() });
+            let mut output = Vec::new();
+            processor.process_block(&input, &mut output);
+            let latency = processor.latency();
+            processor.finish(&mut output);
+            assert!(output.len() >= input.len() + latency, "{} at {}: {} samples for {}", name, threshold, output.len(), input.len());
+            assert!(output.len() < input.len() + latency + 2 * 480, "{} at {}: {} samples for {}", name, threshold, output.len(), input.len());
+        }
+    }
+}
+
+#[test]
+fn output_stays_within_full_scale() {
+    for name in FIXTURES {
+        let input = read_fixture(&format!("{}.wav", name));
+        for threshold in THRESHOLDS {
+            let output = process(&input, at(threshold));
+            assert!(output.iter().all(|s| s.is_finite() && s.abs() <= 1.0), "{} at {}", name, threshold);
+        }
+    }
+}
+
+#[test]
+fn pure_noise_is_silenced() {
+    let input = read_fixture("noise.wav");
+    let output = process(&input, at(0.9));
+    // Past the first frames, where the denoiser is still adapting
+    let settled = &output[4800..];
+    assert!(rms(settled) < rms(&input) * 0.05, "noise RMS {} left of {}", rms(settled), rms(&input));
+}
+
+#[test]
+fn silence_stays_silent() {
+    let output = process(&read_fixture("silence.wav"), at(GOLDEN_THRESHOLD));
+    assert!(output.iter().all(|s| s.abs() < 1e-4));
+}
+
+#[test]
+fn clean_speech_passes() {
+    let input = read_fixture("clean_speech.wav");
+    for threshold in [0.2, 0.5] {
+        let output = process(&input, at(threshold));
+        let ratio = rms(&output) / rms(&input);
+        assert!((0.8..=1.2).contains(&ratio), "speech RMS ratio {} at threshold {}", ratio, threshold);
+    }
+}
+
+#[test]
+fn fan_noise_is_reduced_under_speech() {
+    let speech = read_fixture("clean_speech.wav");
+    let noisy = read_fixture("speech_fan.wav");
+    let output = process(&noisy, at(GOLDEN_THRESHOLD));
+    let error = |x: &[f32]| rms(&x.iter().zip(&speech).map(|(a, b)| a - b).collect::<Vec<_>>());
+    assert!(error(&output) < error(&noisy), "residual {} vs {} before", error(&output), error(&noisy));
+}
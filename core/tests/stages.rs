@@ -0,0 +1,108 @@
+// Optional stages on fixtures from scripts/make_fixtures.py, each against the same chain with
+// the stage off.
+mod common;
+
+use common::{db, max_difference, peak, process_off_on, read_fixture, rms};
+use silentstream_core::dsp::ClickConfig;
+use silentstream_core::Controls;
+
+// Under about 150 Hz: two one-pole low-passes
+fn low_band(samples: &[f32]) -> Vec<f32> {
+    let coef = 1.0 - (-std::f32::consts::TAU * 150.0 / 48000.0).exp();
+    let (mut a, mut b) = (0.0, 0.0);
+    samples
+        .iter()
+        .map(|s| {
+            a += (s - a) * coef;
+            b += (a - b) * coef;
+            b
+        })
+        .collect()
+}
+
+// Strong mode: a second denoiser pass
+#[test]
+fn strong_mode_leaves_less_residual_noise() {
+    // Threshold 0 keeps the gate open, so only the denoiser passes are compared
+    let noise = read_fixture("noise.wav");
+    let (normal, strong) = process_off_on(&noise, Controls { threshold: 0.0, ..Controls::default() }, |c, on| c.two_pass = on);
+    let (normal, strong) = (rms(&normal[4800..]), rms(&strong[4800..]));
+    assert!(db(normal / strong) > 3.0, "fan noise left: {} normal, {} strong", normal, strong);
+}
+
+#[test]
+fn strong_mode_is_quieter_between_words_and_keeps_the_voice() {
+    let clean = read_fixture("clean_speech.wav");
+    let noisy = read_fixture("speech_fan.wav");
+    // Output where the clean speech is silent, past the first frames, and where it is loud
+    let (normal, strong) = process_off_on(&noisy, Controls::default(), |c, on| c.two_pass = on);
+    let split = |output: &[f32]| {
+        let pick = |keep: fn(f32) -> bool| -> Vec<f32> {
+            output.iter().zip(&clean).skip(4800).filter(|(_, c)| keep(**c)).map(|(o, _)| *o).collect()
+        };
+        (rms(&pick(|c| c == 0.0)), rms(&pick(|c| c.abs() > 0.05)))
+    };
+    let ((normal_gaps, normal_speech), (strong_gaps, strong_speech)) = (split(&normal), split(&strong));
+    assert!(strong_gaps < normal_gaps, "between words: {} normal, {} strong", normal_gaps, strong_gaps);
+    assert!(db(strong_speech / normal_speech).abs() < 0.5, "speech: {} normal, {} strong", normal_speech, strong_speech);
+}
+
+#[test]
+fn plosive_tamer_reduces_the_low_band_peak() {
+    let input = read_fixture("plosives.wav");
+    let (off, on) = process_off_on(&input, Controls::default(), |c, on| c.plosive.enabled = on);
+    let (off, on) = (peak(&low_band(&off)), peak(&low_band(&on)));
+    assert!(db(off / on) > 4.0, "low-band peak {} with the tamer, {} without", on, off);
+}
+
+#[test]
+fn plosive_tamer_leaves_normal_speech_alone() {
+    let input = read_fixture("clean_speech.wav");
+    let (off, on) = process_off_on(&input, Controls::default(), |c, on| c.plosive.enabled = on);
+    let difference = max_difference(&off, &on);
+    assert!(difference < 1e-4, "speech without pops changed by up to {}", difference);
+}
+
+// Key clicks in typing_talking.wav, found against the same take without typing
+fn click_positions(typing: &[f32], talking: &[f32]) -> Vec<usize> {
+    let mut clicks: Vec<usize> = Vec::new();
+    for (i, (a, b)) in typing.iter().zip(talking).enumerate() {
+        if (a - b).abs() > 0.05 && clicks.last().is_none_or(|&last| i > last + 480) {
+            clicks.push(i);
+        }
+    }
+    clicks
+}
+
+fn click_assist(controls: &mut Controls, enabled: bool) {
+    controls.click = ClickConfig { enabled, sensitivity: 1.0 };
+}
+
+#[test]
+fn click_assist_turns_down_clicks_between_words() {
+    let talking = read_fixture("talking.wav");
+    let typing = read_fixture("typing_talking.wav");
+    let (off, on) = process_off_on(&typing, Controls::default(), click_assist);
+
+    let mut caught = 0;
+    for p in click_positions(&typing, &talking) {
+        let speech = rms(&talking[p.saturating_sub(240)..p + 240]);
+        let before = db(rms(&off[p..p + 240]).max(1e-6));
+        let after = db(rms(&on[p..p + 240]).max(1e-6));
+        if speech > 0.005 {
+            // On a word, including its "s": left to the gate
+            assert!((after - before).abs() < 0.5, "click at {p} over speech changed by {:.1} dB", after - before);
+        } else if before > -45.0 && after < before - 15.0 {
+            caught += 1;
+        }
+    }
+    assert!(caught >= 5, "only {caught} clicks between words were turned down");
+}
+
+#[test]
+fn click_assist_does_not_dull_consonants() {
+    let talking = read_fixture("talking.wav");
+    let (off, on) = process_off_on(&talking, Controls::default(), click_assist);
+    let diff = max_difference(&off, &on);
+    assert!(diff < 1e-6, "speech without typing changed by up to {diff}");
+}
@@ -0,0 +1,387 @@
+"""Writes the audio fixtures under core/tests/fixtures.
+
+The fixtures are synthetic so they can be regenerated exactly: "speech" is a glottal pulse
+train through three formant resonators, with syllable-length envelopes and pauses, which the
+denoiser's VAD takes for a voice. Everything uses a seeded generator, so running this again
+gives byte-identical files. 16-bit mono WAV at 48 kHz unless noted.
+
+There is no FLAC, MP3 or Vorbis encoder to call, so those fixtures are put together here by
+hand: the FLAC file stores a WAV fixture's samples verbatim, and the MP3 and Ogg Vorbis files
+are minimal valid streams of silence with gapless trim information. They exercise the
+decoding path and the length handling rather than the codecs.
+
+    python scripts/make_fixtures.py
+"""
+import math
+import os
+import random
+import struct
+import wave
+
+RATE = 48000
+OUT = os.path.join(os.path.dirname(__file__), "..", "core", "tests", "fixtures")
+
+# (F1, F2, F3) in Hz for a few vowels
+VOWELS = [(730, 1090, 2440), (270, 2290, 3010), (530, 1840, 2480), (570, 840, 2410), (300, 870, 2240)]
+
+
+def resonator(freq, bandwidth):
+    r = math.exp(-math.pi * bandwidth / RATE)
+    a1 = 2 * r * math.cos(2 * math.pi * freq / RATE)
+    a2 = -r * r
+    gain = 1 - r
+    return a1, a2, gain
+
+
+def voiced(duration, f0, vowel, amplitude, breath=0.0, rng=None):
+    """One syllable: pulses at f0 with a little vibrato, shaped by the vowel's formants"""
+    n = int(duration * RATE)
+    out = [0.0] * n
+    filters = [resonator(f, 60 + f * 0.05) for f in vowel]
+    states = [[0.0, 0.0] for _ in filters]
+    phase = 0.0
+    for i in range(n):
+        t = i / RATE
+        pitch = f0 * (1 + 0.03 * math.sin(2 * math.pi * 5 * t))
+        phase += pitch / RATE
+        source = 0.0
+        if phase >= 1.0:
+            phase -= 1.0
+            source = 1.0
+        if rng is not None and breath > 0:
+            source += breath * (rng.random() * 2 - 1)
+        y = 0.0
+        for (a1, a2, gain), state in zip(filters, states):
+            v = gain * source + a1 * state[0] + a2 * state[1]
+            state[1], state[0] = state[0], v
+            y += v
+        # Raised-cosine attack and release, 25 ms each
+        edge = min(i, n - 1 - i) / (0.025 * RATE)
+        envelope = 0.5 - 0.5 * math.cos(math.pi * min(edge, 1.0))
+        out[i] = y * envelope
+    peak = max(abs(s) for s in out) or 1.0
+    return [s / peak * amplitude for s in out]
+
+
+def whispered(duration, vowel, amplitude, rng):
+    """One whispered syllable: noise instead of pulses through the vowel's formants"""
+    n = int(duration * RATE)
+    filters = [resonator(f, 80 + f * 0.08) for f in vowel]
+    states = [[0.0, 0.0] for _ in filters]
+    out = []
+    for i in range(n):
+        source = rng.random() * 2 - 1
+        y = 0.0
+        for (a1, a2, gain), state in zip(filters, states):
+            v = gain * source + a1 * state[0] + a2 * state[1]
+            state[1], state[0] = state[0], v
+            y += v
+        edge = min(i, n - 1 - i) / (0.04 * RATE)
+        out.append(y * (0.5 - 0.5 * math.cos(math.pi * min(edge, 1.0))))
+    rms = math.sqrt(sum(s * s for s in out) / n) or 1.0
+    return [s / rms * amplitude for s in out]
+
+
+def fricative(duration, amplitude, rng):
+    """An "s": white noise through a first difference, so it is mostly above 5 kHz"""
+    n = int(duration * RATE)
+    noise = [rng.random() * 2 - 1 for _ in range(n + 1)]
+    out = []
+    for i in range(n):
+        edge = min(i, n - 1 - i) / (0.015 * RATE)
+        out.append((noise[i + 1] - noise[i]) * (0.5 - 0.5 * math.cos(math.pi * min(edge, 1.0))))
+    peak = max(abs(s) for s in out)
+    return [s / peak * amplitude for s in out]
+
+
+def key_click(rng):
+    """A few milliseconds of broadband noise with a 1 ms decay"""
+    return [(rng.random() * 2 - 1) * 0.4 * math.exp(-i / (0.001 * RATE)) for i in range(int(0.004 * RATE))]
+
+
+def background(duration, rng, rms):
+    """Faint white noise, so the quiet parts aren't digital silence"""
+    return [(rng.random() * 2 - 1) * rms * math.sqrt(3) for _ in range(int(duration * RATE))]
+
+
+def speech(duration, rng, amplitude=0.4, breath=0.0):
+    """Syllables of 120-260 ms with short gaps, up to `duration` seconds"""
+    out = []
+    while len(out) < duration * RATE:
+        syllable = voiced(rng.uniform(0.12, 0.26), rng.uniform(110, 180), rng.choice(VOWELS), amplitude, breath, rng)
+        out += syllable + [0.0] * int(rng.uniform(0.03, 0.09) * RATE)
+    return out[: int(duration * RATE)]
+
+
+def fan(duration, rng, amplitude):
+    """Low rumble plus broadband hiss and a 120 Hz hum"""
+    out = []
+    low = 0.0
+    for i in range(int(duration * RATE)):
+        white = rng.random() * 2 - 1
+        low += (white - low) * 0.02
+        out.append(low * 3 + white * 0.3 + 0.2 * math.sin(2 * math.pi * 120 * i / RATE))
+    peak = max(abs(s) for s in out)
+    return [s / peak * amplitude for s in out]
+
+
+def mix(*signals):
+    return [sum(samples) for samples in zip(*signals)]
+
+
+def pcm16(samples):
+    return [max(-32768, min(32767, round(s * 32767))) for s in samples]
+
+
+def write(name, samples, rate=RATE, channels=1):
+    """`samples` interleaved when there is more than one channel"""
+    path = os.path.join(OUT, name)
+    with wave.open(path, "wb") as w:
+        w.setnchannels(channels)
+        w.setsampwidth(2)
+        w.setframerate(rate)
+        w.writeframes(b"".join(struct.pack("<h", s) for s in pcm16(samples)))
+    print(os.path.normpath(path))
+
+
+def write_bytes(name, data):
+    path = os.path.join(OUT, name)
+    with open(path, "wb") as f:
+        f.write(data)
+    print(os.path.normpath(path))
+
+
+class BitWriter:
+    """MSB-first by default (FLAC, MP3); Vorbis packs LSB-first"""
+
+    def __init__(self, lsb_first=False):
+        self.bits = []
+        self.lsb_first = lsb_first
+
+    def put(self, value, count):
+        order = range(count) if self.lsb_first else reversed(range(count))
+        self.bits += [(value >> i) & 1 for i in order]
+
+    def data(self):
+        bits = self.bits + [0] * (-len(self.bits) % 8)
+        out = bytearray()
+        for i in range(0, len(bits), 8):
+            byte = bits[i : i + 8]
+            if self.lsb_first:
+                byte = byte[::-1]
+            out.append(int("".join(map(str, byte)), 2))
+        return bytes(out)
+
+
+def crc(data, poly, width, reflect_in=False):
+    """Plain (non-reflected) CRC with zero init, as FLAC and Ogg use"""
+    top = 1 << (width - 1)
+    mask = (1 << width) - 1
+    value = 0
+    for byte in data:
+        value ^= byte << (width - 8)
+        for _ in range(8):
+            value = ((value << 1) ^ poly) & mask if value & top else (value << 1) & mask
+    return value
+
+
+def flac(samples, rate, channels, block=4096):
+    """16-bit FLAC with verbatim subframes; `samples` interleaved floats"""
+    ints = pcm16(samples)
+    frames = len(ints) // channels
+    info = BitWriter()
+    info.put(block, 16)
+    info.put(block, 16)
+    info.put(0, 24)
+    info.put(0, 24)
+    info.put(rate, 20)
+    info.put(channels - 1, 3)
+    info.put(15, 5)
+    info.put(frames, 36)
+    info.put(0, 128)  # MD5 unknown
+    out = bytearray(b"fLaC")
+    out += bytes([0x80]) + (34).to_bytes(3, "big") + info.data()
+    for number, start in enumerate(range(0, frames, block)):
+        size = min(block, frames - start)
+        header = BitWriter()
+        header.put(0x3FFE, 14)
+        header.put(0, 2)  # reserved, fixed block size
+        header.put(0b0111, 4)  # 16-bit block size at the end of the header
+        header.put(0b0000, 4)  # rate from STREAMINFO
+        header.put(channels - 1, 4)  # independent channels
+        header.put(0b100, 3)  # 16 bits per sample
+        header.put(0, 1)
+        assert number < 128
+        header.put(number, 8)
+        header.put(size - 1, 16)
+        frame = bytearray(header.data())
+        frame.append(crc(frame, 0x07, 8))
+        for channel in range(channels):
+            frame.append(0x02)  # verbatim, no wasted bits
+            for i in range(start, start + size):
+                frame += struct.pack(">h", ints[i * channels + channel])
+        frame += crc(frame, 0x8005, 16).to_bytes(2, "big")
+        out += frame
+    return bytes(out)
+
+
+def mp3_silence(frames, delay, padding):
+    """MPEG-1 Layer III, 48 kHz mono, 64 kbit/s: an Info frame with a LAME-style gapless trim,
+    then `frames` frames whose side information and main data are all zero"""
+    size = 144 * 64000 // 48000
+    header = bytes([0xFF, 0xFB, 0x54, 0xC0])
+    side_info = bytes(17)
+    # The trim counts the decoder's own 529-sample delay, as LAME does
+    trim = ((delay - 529) << 12) | (padding + 529)
+    info = b"Info" + struct.pack(">II", 1, frames) + b"Lavf58.76" + bytes(12) + trim.to_bytes(3, "big")
+    first = header + side_info + info
+    out = first + bytes(size - len(first))
+    out += (header + bytes(size - 4)) * frames
+    return out
+
+
+def ogg_page(packets, granule, sequence, first=False, last=False):
+    segments = []
+    for packet in packets:
+        segments += [255] * (len(packet) // 255) + [len(packet) % 255]
+    assert len(segments) <= 255
+    flags = (2 if first else 0) | (4 if last else 0)
+    page = b"OggS" + bytes([0, flags]) + struct.pack("<qII", granule, 1, sequence) + bytes(4)
+    page += bytes([len(segments)]) + bytes(segments) + b"".join(packets)
+    checksum = crc(page, 0x04C11DB7, 32)
+    return page[:22] + struct.pack("<I", checksum) + page[26:]
+
+
+def vorbis_silence(packets, length):
+    """Ogg Vorbis, 48 kHz mono, short 256-sample blocks whose floor is marked unused, so every
+    packet decodes to silence. The last page's granule position trims the stream to `length`."""
+    ident = b"\x01vorbis" + struct.pack("<IBIiiiBB", 0, 1, 48000, 0, 0, 0, 0x88, 1)
+    vendor = b"make_fixtures.py"
+    comment = b"\x03vorbis" + struct.pack("<I", len(vendor)) + vendor + struct.pack("<I", 0) + b"\x01"
+
+    setup = BitWriter(lsb_first=True)
+    for byte in b"\x05vorbis":
+        setup.put(byte, 8)
+    setup.put(0, 8)  # one codebook: two entries of length 1, no lookup
+    setup.put(0x564342, 24)
+    setup.put(1, 16)
+    setup.put(2, 24)
+    setup.put(0, 1)
+    setup.put(0, 1)
+    setup.put(0, 5)
+    setup.put(0, 5)
+    setup.put(0, 4)
+    setup.put(0, 6)  # one time-domain transform, a placeholder
+    setup.put(0, 16)
+    setup.put(0, 6)  # one floor, type 1 without partitions
+    setup.put(1, 16)
+    setup.put(0, 5)
+    setup.put(1, 2)
+    setup.put(8, 4)
+    setup.put(0, 6)  # one residue covering nothing
+    setup.put(0, 16)
+    setup.put(0, 24)
+    setup.put(0, 24)
+    setup.put(15, 24)
+    setup.put(0, 6)
+    setup.put(0, 8)
+    setup.put(0, 3)
+    setup.put(0, 1)
+    setup.put(0, 6)  # one mapping
+    setup.put(0, 16)
+    setup.put(0, 1)
+    setup.put(0, 1)
+    setup.put(0, 2)
+    setup.put(0, 8)
+    setup.put(0, 8)
+    setup.put(0, 8)
+    setup.put(0, 6)  # one mode, short blocks
+    setup.put(0, 1)
+    setup.put(0, 16)
+    setup.put(0, 16)
+    setup.put(0, 8)
+    setup.put(1, 1)
+
+    # Packet type 0 and the floor's "unused" bit
+    audio = b"\x00"
+    out = ogg_page([ident], 0, 0, first=True) + ogg_page([comment, setup.data()], 0, 1)
+    sequence = 2
+    per_page = 50
+    for start in range(0, packets, per_page):
+        count = min(per_page, packets - start)
+        end = start + count
+        # The first packet only primes the overlap; each later one completes 128 samples
+        granule = (end - 1) * 128
+        last = end == packets
+        out += ogg_page([audio] * count, length if last else granule, sequence, last=last)
+        sequence += 1
+    return out
+
+
+def main():
+    os.makedirs(OUT, exist_ok=True)
+    clean = speech(1.5, random.Random(1))
+    write("clean_speech.wav", clean)
+    write("speech_fan.wav", mix(clean, fan(1.5, random.Random(2), 0.12)))
+    write("noise.wav", fan(1.5, random.Random(3), 0.12))
+    write("silence.wav", [0.0] * (RATE // 2))
+
+    # Words that start on a 20 ms noise burst, like a hard consonant, before the vowel the VAD
+    # picks up on; 0.4 s of near-silence before each
+    rng = random.Random(5)
+    words = []
+    for vowel in VOWELS[:3]:
+        burst = [(rng.random() * 2 - 1) * 0.2 for _ in range(int(0.02 * RATE))]
+        words += [0.0] * int(0.4 * RATE) + burst + voiced(0.25, 130, vowel, 0.4)
+    words += [0.0] * int(0.3 * RATE)
+    write("onsets.wav", mix(words, background(len(words) / RATE, random.Random(6), 0.0005)))
+
+    # Words opening on a plosive: a damped 60 Hz pop, as a "p" blowing into the capsule
+    # sounds, straight into the vowel
+    plosives = []
+    for vowel in VOWELS[1:4]:
+        pop = [0.6 * math.exp(-i / (0.025 * RATE)) * math.sin(2 * math.pi * 60 * i / RATE) for i in range(int(0.06 * RATE))]
+        plosives += [0.0] * int(0.3 * RATE) + mix(pop + [0.0] * int(0.2 * RATE), [0.0] * int(0.03 * RATE) + voiced(0.23, 140, vowel, 0.3))
+    plosives += [0.0] * int(0.3 * RATE)
+    write("plosives.wav", mix(plosives, background(len(plosives) / RATE, random.Random(9), 0.0005)))
+
+    # "s" + vowel words, then the same take with a key click every 110-190 ms, landing in the
+    # gaps and on the words alike
+    rng = random.Random(10)
+    talking = []
+    for vowel in VOWELS * 2:
+        talking += [0.0] * int(0.25 * RATE) + fricative(0.08, 0.1, rng) + voiced(0.2, 120, vowel, 0.35)
+    talking += [0.0] * int(0.25 * RATE)
+    talking = mix(talking, background(len(talking) / RATE, random.Random(11), 0.0005))
+    typing = list(talking)
+    rng = random.Random(12)
+    position = int(0.05 * RATE)
+    while position < len(typing) - RATE // 10:
+        for i, s in enumerate(key_click(rng)):
+            typing[position + i] += s
+        position += int(rng.uniform(0.11, 0.19) * RATE)
+    write("talking.wav", talking)
+    write("typing_talking.wav", typing)
+
+    # Three whispered phrases of four syllables, about -29 dBFS RMS, 0.6 s apart
+    rng = random.Random(7)
+    whisper = [0.0] * int(0.6 * RATE)
+    for _ in range(3):
+        for _ in range(4):
+            whisper += whispered(rng.uniform(0.14, 0.22), rng.choice(VOWELS), 0.035, rng) + [0.0] * int(0.03 * RATE)
+        whisper += [0.0] * int(0.6 * RATE)
+    write("whisper.wav", mix(whisper, background(len(whisper) / RATE, random.Random(8), 0.0005)))
+
+    # Stereo at 44.1 kHz, with different content per channel, in WAV and FLAC
+    left = speech(0.5, random.Random(4), 0.3)
+    right = [math.sin(2 * math.pi * 440 * i / RATE) * 0.25 for i in range(len(left))]
+    stereo = [s for frame in zip(left, right) for s in frame][: 2 * 22050]
+    write("speech_44k_stereo.wav", stereo, rate=44100, channels=2)
+    write_bytes("speech_44k_stereo.flac", flac(stereo, 44100, 2))
+    # 50 frames of 1152 samples less the trim: 54000 samples, 1.125 s
+    write_bytes("silence.mp3", mp3_silence(50, 1105, 2495))
+    write_bytes("silence.ogg", vorbis_silence(400, 48000))
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,389 @@
+use chrono::{Datelike, Timelike};
+use eframe::egui;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use sysinfo::{ProcessRefreshKind, System};
+
+// What an automatic trigger (OBS, watched apps, ...) does to the engine.
+// Triggers only fire on transitions, so a manual start/stop stays in effect
+// until the next one.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TriggerAction {
+    Nothing,
+    StartProcessing,
+    StopProcessing,
+    Bypass,
+}
+
+impl TriggerAction {
+    pub const ALL: [TriggerAction; 4] = [
+        TriggerAction::Nothing,
+        TriggerAction::StartProcessing,
+        TriggerAction::StopProcessing,
+        TriggerAction::Bypass,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TriggerAction::Nothing => "nothing",
+            TriggerAction::StartProcessing => "start",
+            TriggerAction::StopProcessing => "stop",
+            TriggerAction::Bypass => "bypass",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|a| a.as_str() == s)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TriggerAction::Nothing => "Do nothing",
+            TriggerAction::StartProcessing => "Start processing",
+            TriggerAction::StopProcessing => "Stop processing",
+            TriggerAction::Bypass => "Bypass suppression",
+        }
+    }
+}
+
+const APP_SCAN_INTERVAL: Duration = Duration::from_secs(3);
+// Watched apps must be gone this long before we react, so restarts/updates don't flap the engine
+const APP_EXIT_GRACE: Duration = Duration::from_secs(30);
+
+#[derive(Clone, PartialEq)]
+pub struct AppWatchConfig {
+    pub enabled: bool,
+    // Executable names, matched case-insensitively with or without ".exe"
+    pub apps: Vec<String>,
+    pub on_exit: TriggerAction,
+}
+
+impl Default for AppWatchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            apps: vec!["Discord".to_string(), "Zoom".to_string(), "obs64".to_string()],
+            on_exit: TriggerAction::StopProcessing,
+        }
+    }
+}
+
+pub struct AppWatcher {
+    apps: Arc<Mutex<Vec<String>>>,
+    running_app: Arc<Mutex<Option<String>>>,
+    stop_flag: Option<Arc<AtomicBool>>,
+    // Carries "is any watched app running" on every transition
+    events: Option<Receiver<bool>>,
+}
+
+impl AppWatcher {
+    pub fn new() -> Self {
+        Self {
+            apps: Arc::new(Mutex::new(Vec::new())),
+            running_app: Arc::new(Mutex::new(None)),
+            stop_flag: None,
+            events: None,
+        }
+    }
+
+    pub fn start(&mut self, config: &AppWatchConfig, ctx: &egui::Context) {
+        self.stop();
+        self.set_apps(&config.apps);
+        if !config.enabled {
+            return;
+        }
+
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = channel();
+        let stop = stop_flag.clone();
+        let apps = self.apps.clone();
+        let running_app = self.running_app.clone();
+        let ctx = ctx.clone();
+
+        thread::spawn(move || {
+            let mut system = System::new();
+            let mut active: Option<bool> = None;
+            let mut gone_since: Option<Instant> = None;
+
+            while !stop.load(Ordering::SeqCst) {
+                system.refresh_processes_specifics(ProcessRefreshKind::new());
+                let watched = apps.lock().map(|a| a.clone()).unwrap_or_default();
+                let found = system
+                    .processes()
+                    .values()
+                    .map(|p| p.name())
+                    .find(|name| watched.iter().any(|w| exe_matches(name, w)))
+                    .map(|name| name.to_string());
+
+                if let Ok(mut r) = running_app.lock() {
+                    *r = found.clone();
+                }
+
+                let next = if found.is_some() {
+                    gone_since = None;
+                    Some(true)
+                } else if active == Some(true) {
+                    // Hysteresis: only report the exit once the grace period has passed
+                    let since = *gone_since.get_or_insert_with(Instant::now);
+                    if since.elapsed() >= APP_EXIT_GRACE { Some(false) } else { None }
+                } else {
+                    Some(false)
+                };
+
+                if let Some(next) = next {
+                    if active != Some(next) {
+                        active = Some(next);
+                        let _ = tx.send(next);
+                        ctx.request_repaint();
+                    }
+                }
+
+                // Sleep in small steps so stop() takes effect quickly
+                let wake = Instant::now() + APP_SCAN_INTERVAL;
+                while Instant::now() < wake && !stop.load(Ordering::SeqCst) {
+                    thread::sleep(Duration::from_millis(100));
+                }
+            }
+        });
+
+        self.stop_flag = Some(stop_flag);
+        self.events = Some(rx);
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(flag) = self.stop_flag.take() {
+            flag.store(true, Ordering::SeqCst);
+        }
+        self.events = None;
+        if let Ok(mut r) = self.running_app.lock() {
+            *r = None;
+        }
+    }
+
+    // Update the watch list without restarting the scan thread
+    pub fn set_apps(&self, apps: &[String]) {
+        if let Ok(mut a) = self.apps.lock() {
+            *a = apps.to_vec();
+        }
+    }
+
+    pub fn running_app(&self) -> Option<String> {
+        self.running_app.lock().ok().and_then(|r| r.clone())
+    }
+
+    // Latest transition reported since the last poll, if any
+    pub fn poll(&self) -> Option<bool> {
+        self.events.as_ref().and_then(|rx| rx.try_iter().last())
+    }
+}
+
+impl Drop for AppWatcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn exe_matches(process_name: &str, watched: &str) -> bool {
+    let normalize = |s: &str| {
+        let s = s.trim().to_lowercase();
+        s.strip_suffix(".exe").map(|s| s.to_string()).unwrap_or(s)
+    };
+    let watched = normalize(watched);
+    !watched.is_empty() && normalize(process_name) == watched
+}
+
+pub const WEEKDAY_LABELS: [&str; 7] = ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"];
+
+#[derive(Clone, PartialEq)]
+pub struct ScheduleConfig {
+    pub enabled: bool,
+    // Minutes since midnight; an end before the start runs past midnight
+    pub start: u32,
+    pub end: u32,
+    // Monday first; a window that crosses midnight belongs to the day it starts on
+    pub days: [bool; 7],
+}
+
+impl Default for ScheduleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            start: 9 * 60,
+            end: 17 * 60,
+            days: [true, true, true, true, true, false, false],
+        }
+    }
+}
+
+impl ScheduleConfig {
+    // `weekday` counts from Monday = 0
+    pub fn is_active_at(&self, weekday: usize, minute: u32) -> bool {
+        let yesterday = (weekday + 6) % 7;
+        if self.start == self.end {
+            // Whole selected days
+            self.days[weekday]
+        } else if self.start < self.end {
+            self.days[weekday] && minute >= self.start && minute < self.end
+        } else {
+            (self.days[weekday] && minute >= self.start) || (self.days[yesterday] && minute < self.end)
+        }
+    }
+
+    pub fn is_active_now(&self) -> bool {
+        let now = chrono::Local::now();
+        self.is_active_at(now.weekday().num_days_from_monday() as usize, now.hour() * 60 + now.minute())
+    }
+
+    pub fn days_to_string(&self) -> String {
+        self.days.iter().map(|d| if *d { '1' } else { '0' }).collect()
+    }
+
+    pub fn days_from_str(s: &str) -> Option<[bool; 7]> {
+        let chars: Vec<char> = s.trim().chars().collect();
+        if chars.len() != 7 {
+            return None;
+        }
+        let mut days = [false; 7];
+        for (day, c) in days.iter_mut().zip(chars) {
+            *day = c == '1';
+        }
+        Some(days)
+    }
+}
+
+pub fn format_time_of_day(minutes: u32) -> String {
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+// "9:30" / "09:30" -> minutes since midnight
+pub fn parse_time_of_day(s: &str) -> Option<u32> {
+    let (h, m) = s.trim().split_once(':')?;
+    let (h, m): (u32, u32) = (h.trim().parse().ok()?, m.trim().parse().ok()?);
+    if h < 24 && m < 60 { Some(h * 60 + m) } else { None }
+}
+
+const LISTENER_SCAN_INTERVAL: Duration = Duration::from_secs(2);
+
+// Pause processing while no app records from the output (the capture side of a virtual cable)
+#[derive(Clone, PartialEq)]
+pub struct IdlePauseConfig {
+    pub enabled: bool,
+    // How long nothing may be recording before pausing
+    pub minutes: u32,
+    // Close the audio devices while paused instead of only skipping the denoiser
+    pub release_devices: bool,
+}
+
+impl Default for IdlePauseConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            minutes: 5,
+            release_devices: false,
+        }
+    }
+}
+
+// Watches the audio sessions on one endpoint. Reports true as soon as another app has an
+// active session there and false once none has had one for the configured time.
+pub struct ListenerWatcher {
+    endpoint: Arc<Mutex<Option<String>>>,
+    stop_flag: Option<Arc<AtomicBool>>,
+    events: Option<Receiver<bool>>,
+}
+
+impl ListenerWatcher {
+    pub fn new() -> Self {
+        Self {
+            endpoint: Arc::new(Mutex::new(None)),
+            stop_flag: None,
+            events: None,
+        }
+    }
+
+    pub fn start(&mut self, config: &IdlePauseConfig, ctx: &egui::Context) {
+        self.stop();
+        if !config.enabled {
+            return;
+        }
+        // Session enumeration is Windows-only; elsewhere nothing would ever look active
+        if !cfg!(windows) {
+            log::info!("Pausing when unused is not available on this platform");
+            return;
+        }
+
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = channel();
+        let stop = stop_flag.clone();
+        let endpoint = self.endpoint.clone();
+        let idle_after = Duration::from_secs(u64::from(config.minutes) * 60);
+        let ctx = ctx.clone();
+
+        thread::spawn(move || {
+            let mut watched: Option<String> = None;
+            let mut listening: Option<bool> = None;
+            let mut unused_since = Instant::now();
+
+            while !stop.load(Ordering::SeqCst) {
+                let wanted = endpoint.lock().ok().and_then(|e| e.clone());
+                if wanted != watched {
+                    watched = wanted;
+                    unused_since = Instant::now();
+                }
+
+                let next = match watched.as_deref() {
+                    Some(name) if !crate::core_audio::session_processes(name).is_empty() => {
+                        unused_since = Instant::now();
+                        Some(true)
+                    }
+                    Some(_) if unused_since.elapsed() >= idle_after => Some(false),
+                    _ => None,
+                };
+                if let Some(next) = next {
+                    if listening != Some(next) {
+                        listening = Some(next);
+                        let _ = tx.send(next);
+                        ctx.request_repaint();
+                    }
+                }
+
+                let wake = Instant::now() + LISTENER_SCAN_INTERVAL;
+                while Instant::now() < wake && !stop.load(Ordering::SeqCst) {
+                    thread::sleep(Duration::from_millis(100));
+                }
+            }
+        });
+
+        self.stop_flag = Some(stop_flag);
+        self.events = Some(rx);
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(flag) = self.stop_flag.take() {
+            flag.store(true, Ordering::SeqCst);
+        }
+        self.events = None;
+    }
+
+    pub fn set_endpoint(&self, name: Option<&str>) {
+        if let Ok(mut e) = self.endpoint.lock() {
+            if e.as_deref() != name {
+                *e = name.map(|n| n.to_string());
+            }
+        }
+    }
+
+    // Latest transition reported since the last poll, if any
+    pub fn poll(&self) -> Option<bool> {
+        self.events.as_ref().and_then(|rx| rx.try_iter().last())
+    }
+}
+
+impl Drop for ListenerWatcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
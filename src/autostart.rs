@@ -0,0 +1,328 @@
+// Launch at login. On Windows either through the HKCU Run key or a logon-triggered scheduled
+// task for machines where policy blocks Run entries. Elsewhere the backend setting is ignored:
+// macOS gets a LaunchAgent plist and other systems an XDG autostart .desktop file.
+#[cfg(windows)]
+use {
+    std::os::windows::process::CommandExt,
+    std::process::Command,
+    winreg::enums::*,
+    winreg::RegKey,
+};
+
+// Passed by the autostart entry so a login launch can be told apart from a manual one
+pub const AUTOSTART_FLAG: &str = "--autostart";
+const AUTOSTART_ARGS: &str = "--autostart --minimized";
+#[cfg(windows)]
+pub const AUTOSTART_LABEL: &str = "Start with Windows";
+#[cfg(target_os = "macos")]
+pub const AUTOSTART_LABEL: &str = "Open at login";
+#[cfg(not(any(windows, target_os = "macos")))]
+pub const AUTOSTART_LABEL: &str = "Start at login";
+#[cfg(windows)]
+const RUN_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Run";
+#[cfg(windows)]
+const ENTRY_NAME: &str = "SilentStream";
+// Keeps schtasks.exe from flashing a console window
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+#[cfg(target_os = "macos")]
+const AGENT_LABEL: &str = "com.yyyutakaaa.silentstream";
+#[cfg(not(any(windows, target_os = "macos")))]
+const DESKTOP_FILE: &str = "silentstream.desktop";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AutostartBackend {
+    Registry,
+    ScheduledTask,
+}
+
+impl AutostartBackend {
+    pub const ALL: [AutostartBackend; 2] = [AutostartBackend::Registry, AutostartBackend::ScheduledTask];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AutostartBackend::Registry => "registry",
+            AutostartBackend::ScheduledTask => "scheduled_task",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|b| b.as_str() == s)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AutostartBackend::Registry => "Registry (Run key)",
+            AutostartBackend::ScheduledTask => "Scheduled task",
+        }
+    }
+}
+
+#[cfg(windows)]
+pub fn set(backend: AutostartBackend, enable: bool) -> Result<(), String> {
+    let result = match (backend, enable) {
+        (AutostartBackend::Registry, true) => launch_command().and_then(|command| {
+            run_key(KEY_SET_VALUE)?.set_value(ENTRY_NAME, &command).map_err(|e| format!("Failed to write the Run key: {}", e))
+        }),
+        (AutostartBackend::Registry, false) => match run_key(KEY_SET_VALUE)?.delete_value(ENTRY_NAME) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(format!("Failed to remove the Run key entry: {}", e)),
+            _ => Ok(()),
+        },
+        (AutostartBackend::ScheduledTask, true) => launch_command().and_then(|command| {
+            schtasks(&["/Create", "/TN", ENTRY_NAME, "/TR", &command, "/SC", "ONLOGON", "/RL", "LIMITED", "/F"])
+        }),
+        (AutostartBackend::ScheduledTask, false) => {
+            if is_enabled(backend) {
+                schtasks(&["/Delete", "/TN", ENTRY_NAME, "/F"])
+            } else {
+                Ok(())
+            }
+        }
+    };
+    match &result {
+        Ok(()) => log::info!("Autostart {} via {}", if enable { "enabled" } else { "disabled" }, backend.label()),
+        Err(e) => log::error!("{}", e),
+    }
+    result
+}
+
+#[cfg(windows)]
+pub fn is_enabled(backend: AutostartBackend) -> bool {
+    match backend {
+        AutostartBackend::Registry => {
+            run_key(KEY_QUERY_VALUE).map(|key| key.get_value::<String, _>(ENTRY_NAME).is_ok()).unwrap_or(false)
+        }
+        AutostartBackend::ScheduledTask => Command::new("schtasks")
+            .args(["/Query", "/TN", ENTRY_NAME])
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+            .map(|output| output.status.success())
+            .unwrap_or(false),
+    }
+}
+
+// Rewrites the Run key entry if it launches a different executable (the exe was moved or
+// copied) or predates the autostart flag. Returns whether anything was changed.
+#[cfg(windows)]
+pub fn repair(backend: AutostartBackend) -> Result<bool, String> {
+    if backend != AutostartBackend::Registry {
+        return Ok(false);
+    }
+    let Ok(current) = run_key(KEY_QUERY_VALUE)?.get_value::<String, _>(ENTRY_NAME) else { return Ok(false) };
+    let exe = std::env::current_exe().map_err(|e| format!("Executable path unknown: {}", e))?;
+    let exe = exe.to_string_lossy();
+    if same_path(command_exe(&current), &exe) && current.contains(AUTOSTART_FLAG) {
+        return Ok(false);
+    }
+    log::info!("Autostart entry '{}' is outdated, pointing it at '{}'", current, exe);
+    set(backend, true)?;
+    Ok(true)
+}
+
+// Executable at the start of a command line: the quoted part, or for old unquoted entries
+// everything up to ".exe" so paths with spaces still come out whole. Plain string handling,
+// built everywhere so the tests run on any platform.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn command_exe(command: &str) -> &str {
+    let command = command.trim();
+    if let Some(rest) = command.strip_prefix('"') {
+        return rest.split('"').next().unwrap_or(rest);
+    }
+    match command.to_ascii_lowercase().find(".exe") {
+        Some(i) => &command[..i + ".exe".len()],
+        None => command,
+    }
+}
+
+// Windows paths are case-insensitive and accept either slash
+#[cfg_attr(not(windows), allow(dead_code))]
+fn same_path(a: &str, b: &str) -> bool {
+    let normalize = |p: &str| p.trim().replace('/', "\\").to_lowercase();
+    normalize(a) == normalize(b)
+}
+
+#[cfg(windows)]
+fn launch_command() -> Result<String, String> {
+    let exe = std::env::current_exe().map_err(|e| format!("Executable path unknown: {}", e))?;
+    Ok(format!("\"{}\" {}", exe.to_string_lossy(), AUTOSTART_ARGS))
+}
+
+// Exec= value; the desktop entry spec wants these four characters escaped inside quotes
+#[cfg(not(any(windows, target_os = "macos")))]
+fn launch_command() -> Result<String, String> {
+    let exe = std::env::current_exe().map_err(|e| format!("Executable path unknown: {}", e))?;
+    let mut quoted = String::new();
+    for c in exe.to_string_lossy().chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    Ok(format!("\"{}\" {}", quoted, AUTOSTART_ARGS))
+}
+
+#[cfg(windows)]
+fn run_key(access: u32) -> Result<RegKey, String> {
+    RegKey::predef(HKEY_CURRENT_USER)
+        .open_subkey_with_flags(RUN_KEY, access)
+        .map_err(|e| format!("Cannot open the Run registry key: {}", e))
+}
+
+#[cfg(windows)]
+fn schtasks(args: &[&str]) -> Result<(), String> {
+    let output = Command::new("schtasks")
+        .args(args)
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .map_err(|e| format!("Failed to run schtasks: {}", e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        Err(format!("schtasks failed: {}", message))
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn entry_path() -> Result<std::path::PathBuf, String> {
+    std::env::var_os("HOME")
+        .map(|home| std::path::PathBuf::from(home).join("Library/LaunchAgents").join(format!("{}.plist", AGENT_LABEL)))
+        .ok_or_else(|| "Cannot find the LaunchAgents folder: HOME is not set".to_string())
+}
+
+// launchd runs the executable directly, so the arguments are listed one by one
+#[cfg(target_os = "macos")]
+fn entry_contents() -> Result<String, String> {
+    let exe = std::env::current_exe().map_err(|e| format!("Executable path unknown: {}", e))?;
+    let escape = |s: &str| s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+    let arguments: String = std::iter::once(escape(&exe.to_string_lossy()))
+        .chain(AUTOSTART_ARGS.split_whitespace().map(escape))
+        .map(|arg| format!("        <string>{}</string>\n", arg))
+        .collect();
+    Ok(format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n",
+            "<plist version=\"1.0\">\n",
+            "<dict>\n",
+            "    <key>Label</key>\n",
+            "    <string>{}</string>\n",
+            "    <key>ProgramArguments</key>\n",
+            "    <array>\n",
+            "{}",
+            "    </array>\n",
+            "    <key>RunAtLoad</key>\n",
+            "    <true/>\n",
+            "</dict>\n",
+            "</plist>\n",
+        ),
+        AGENT_LABEL, arguments
+    ))
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn entry_path() -> Result<std::path::PathBuf, String> {
+    crate::settings::xdg_config_home()
+        .map(|dir| dir.join("autostart").join(DESKTOP_FILE))
+        .ok_or_else(|| "Cannot find the autostart folder: HOME is not set".to_string())
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn entry_contents() -> Result<String, String> {
+    Ok(format!(
+        "[Desktop Entry]\nType=Application\nName=SilentStream\nComment=Microphone noise suppression\nExec={}\nTerminal=false\nX-GNOME-Autostart-enabled=true\n",
+        launch_command()?
+    ))
+}
+
+#[cfg(not(windows))]
+pub fn set(_backend: AutostartBackend, enable: bool) -> Result<(), String> {
+    let path = entry_path()?;
+    let result = if enable {
+        entry_contents().and_then(|entry| {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+            }
+            std::fs::write(&path, entry).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+        })
+    } else {
+        match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(format!("Failed to remove {}: {}", path.display(), e)),
+            _ => Ok(()),
+        }
+    };
+    match &result {
+        Ok(()) => log::info!("Autostart {} via {}", if enable { "enabled" } else { "disabled" }, path.display()),
+        Err(e) => log::error!("{}", e),
+    }
+    result
+}
+
+#[cfg(not(windows))]
+pub fn is_enabled(_backend: AutostartBackend) -> bool {
+    entry_path().map(|path| path.exists()).unwrap_or(false)
+}
+
+// Rewrites the entry file if it differs from what this executable would write, which covers
+// a moved executable as well as entries from older versions
+#[cfg(not(windows))]
+pub fn repair(backend: AutostartBackend) -> Result<bool, String> {
+    let Ok(current) = std::fs::read_to_string(entry_path()?) else { return Ok(false) };
+    if current == entry_contents()? {
+        return Ok(false);
+    }
+    log::info!("Autostart entry is outdated, pointing it at the current executable");
+    set(backend, true)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_exe_takes_the_quoted_path() {
+        let table = [
+            (r#""C:\Program Files\SilentStream\SilentStream.exe" --autostart --minimized"#, r"C:\Program Files\SilentStream\SilentStream.exe"),
+            (r#""C:\Apps\SilentStream.exe""#, r"C:\Apps\SilentStream.exe"),
+            (r#"  "C:\Apps\SilentStream.exe" --minimized  "#, r"C:\Apps\SilentStream.exe"),
+            // Unterminated quote: the rest of the line
+            (r#""C:\Apps\SilentStream.exe --minimized"#, r"C:\Apps\SilentStream.exe --minimized"),
+        ];
+        for (command, exe) in table {
+            assert_eq!(command_exe(command), exe, "{}", command);
+        }
+    }
+
+    #[test]
+    fn command_exe_handles_old_unquoted_entries() {
+        let table = [
+            (r"C:\Program Files\SilentStream\SilentStream.exe --minimized", r"C:\Program Files\SilentStream\SilentStream.exe"),
+            (r"C:\Apps\SILENTSTREAM.EXE --autostart", r"C:\Apps\SILENTSTREAM.EXE"),
+            (r"C:\Apps\SilentStream.exe", r"C:\Apps\SilentStream.exe"),
+            // No ".exe" at all: the whole line
+            (r"C:\Apps\SilentStream --minimized", r"C:\Apps\SilentStream --minimized"),
+            ("", ""),
+        ];
+        for (command, exe) in table {
+            assert_eq!(command_exe(command), exe, "{}", command);
+        }
+    }
+
+    #[test]
+    fn same_path_ignores_case_and_separators() {
+        assert!(same_path(r"C:\Apps\SilentStream.exe", r"c:\apps\silentstream.EXE"));
+        assert!(same_path("C:/Apps/SilentStream.exe", r"C:\Apps\SilentStream.exe"));
+        assert!(same_path(r" C:\Apps\SilentStream.exe ", r"C:\Apps\SilentStream.exe"));
+        assert!(!same_path(r"C:\Apps\SilentStream.exe", r"D:\Apps\SilentStream.exe"));
+        assert!(!same_path(r"C:\Apps\SilentStream.exe", r"C:\Apps\Old\SilentStream.exe"));
+    }
+
+    #[test]
+    fn registry_entry_round_trips_through_command_exe() {
+        let exe = r"C:\Users\Jo Doe\AppData\Local\SilentStream\SilentStream.exe";
+        let command = format!("\"{}\" {}", exe, AUTOSTART_ARGS);
+        assert!(same_path(command_exe(&command), exe));
+        assert!(command.contains(AUTOSTART_FLAG));
+    }
+}
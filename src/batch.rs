@@ -0,0 +1,243 @@
+// "Batch process": recordings through the same chain as live processing, a few files at a
+// time. Each worker takes whole files, so a long track never holds up the others for more
+// than its own length. Results go to a folder next to each file's own, named after it plus
+// a suffix, as WAV files under the same names.
+use eframe::egui;
+use silentstream_core::dsp::SuppressionMode;
+use silentstream_core::offline_file::{self, FileError, FileReport, Normalize, OutputFormat, OutputOptions, DEFAULT_TARGET_LUFS};
+use silentstream_core::Controls;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+pub const DEFAULT_SUFFIX: &str = "_denoised";
+// Leaves cores free for live processing and the UI
+const MAX_WORKERS: usize = 4;
+// Smallest progress step worth a repaint
+const PROGRESS_STEP: f32 = 0.01;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum FileState {
+    Queued,
+    // Share done
+    Running(f32),
+    Done(FileReport),
+    // Not a format the chain can read; the reason
+    Skipped(String),
+    Failed(String),
+    Cancelled,
+}
+
+impl FileState {
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, FileState::Queued | FileState::Running(_))
+    }
+}
+
+// What the window shows between runs
+pub struct BatchDialog {
+    pub inputs: Vec<PathBuf>,
+    // Typed folder or file, added with the button
+    pub path: String,
+    pub path_missing: bool,
+    pub threshold: f32,
+    // Normal or Strong
+    pub mode: SuppressionMode,
+    pub suffix: String,
+    pub format: OutputFormat,
+    pub normalize: bool,
+    pub target_lufs: f32,
+    pub job: Option<BatchJob>,
+}
+
+impl BatchDialog {
+    pub fn new(threshold: f32) -> Self {
+        Self {
+            inputs: Vec::new(),
+            path: String::new(),
+            path_missing: false,
+            threshold,
+            mode: SuppressionMode::Normal,
+            suffix: DEFAULT_SUFFIX.to_string(),
+            format: OutputFormat::default(),
+            normalize: false,
+            target_lufs: DEFAULT_TARGET_LUFS,
+            job: None,
+        }
+    }
+
+    pub fn options(&self) -> OutputOptions {
+        let normalize = self.normalize.then(|| Normalize { target_lufs: self.target_lufs, ..Normalize::default() });
+        OutputOptions { format: self.format, normalize }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.job.as_ref().is_some_and(|job| !job.is_finished())
+    }
+
+    // Folders become the files directly inside them; anything already listed is left out
+    pub fn add(&mut self, paths: &[PathBuf]) {
+        for file in expand(paths) {
+            if !self.inputs.contains(&file) {
+                self.inputs.push(file);
+            }
+        }
+        // The list shows the inputs again instead of the last run
+        self.job = None;
+    }
+}
+
+pub struct BatchFile {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub state: FileState,
+}
+
+enum Event {
+    Progress(usize, f32),
+    Finished(usize, FileState),
+}
+
+pub struct BatchJob {
+    pub files: Vec<BatchFile>,
+    events: Receiver<Event>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl BatchJob {
+    pub fn start(inputs: Vec<PathBuf>, suffix: &str, options: OutputOptions, controls: Controls, ctx: &egui::Context) -> Self {
+        let files: Vec<BatchFile> = inputs
+            .into_iter()
+            .map(|input| {
+                let output = output_path(&input, suffix);
+                let state = if output.is_some() { FileState::Queued } else { FileState::Skipped("No folder to write next to".to_string()) };
+                BatchFile { output: output.unwrap_or_default(), input, state }
+            })
+            .collect();
+        let queue: VecDeque<(usize, PathBuf, PathBuf)> = files
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| f.state == FileState::Queued)
+            .map(|(i, f)| (i, f.input.clone(), f.output.clone()))
+            .collect();
+        let workers = thread::available_parallelism().map_or(1, |n| n.get().saturating_sub(1)).clamp(1, MAX_WORKERS).min(queue.len());
+        log::info!("Batch processing {} file(s) on {} worker(s)", queue.len(), workers);
+
+        let (tx, events) = channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let queue = Arc::new(Mutex::new(queue));
+        for _ in 0..workers {
+            let (queue, tx, cancel, ctx) = (queue.clone(), tx.clone(), cancel.clone(), ctx.clone());
+            thread::spawn(move || work(&queue, options, controls, &cancel, &tx, &ctx));
+        }
+        Self { files, events, cancel }
+    }
+
+    pub fn poll(&mut self) {
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                Event::Progress(i, done) => self.files[i].state = FileState::Running(done),
+                Event::Finished(i, state) => self.files[i].state = state,
+            }
+        }
+    }
+
+    // Files already running stop at their next block and leave nothing behind
+    pub fn cancel(&self) {
+        log::info!("Batch processing cancelled");
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.files.iter().all(|f| f.state.is_finished())
+    }
+
+    // Done, and all files
+    pub fn counts(&self) -> (usize, usize) {
+        (self.files.iter().filter(|f| matches!(f.state, FileState::Done(_))).count(), self.files.len())
+    }
+}
+
+fn work(
+    queue: &Mutex<VecDeque<(usize, PathBuf, PathBuf)>>,
+    options: OutputOptions,
+    controls: Controls,
+    cancel: &AtomicBool,
+    tx: &Sender<Event>,
+    ctx: &egui::Context,
+) {
+    loop {
+        let Some((i, input, output)) = queue.lock().ok().and_then(|mut q| q.pop_front()) else { return };
+        let state = if cancel.load(Ordering::Relaxed) {
+            FileState::Cancelled
+        } else {
+            let _ = tx.send(Event::Progress(i, 0.0));
+            ctx.request_repaint();
+            let mut reported = 0.0;
+            let result = output
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .map_err(FileError::from)
+                .and_then(|()| {
+                    offline_file::process_file(&input, &output, options, controls, cancel, |done| {
+                        if done - reported >= PROGRESS_STEP {
+                            reported = done;
+                            let _ = tx.send(Event::Progress(i, done));
+                            ctx.request_repaint();
+                        }
+                    })
+                });
+            match result {
+                Ok(report) => {
+                    log::info!("Batch: '{}' -> '{}' ({})", input.display(), output.display(), loudness_text(&report));
+                    FileState::Done(report)
+                }
+                Err(FileError::Cancelled) => FileState::Cancelled,
+                Err(FileError::Unsupported(reason)) => {
+                    log::info!("Batch: skipped '{}': {}", input.display(), reason);
+                    FileState::Skipped(reason)
+                }
+                Err(e) => {
+                    log::warn!("Batch: '{}' failed: {}", input.display(), e);
+                    FileState::Failed(e.to_string())
+                }
+            }
+        };
+        let _ = tx.send(Event::Finished(i, state));
+        ctx.request_repaint();
+    }
+}
+
+// "-23.4 -> -16.0 LUFS"; silence has no loudness to show
+pub fn loudness_text(report: &FileReport) -> String {
+    let lufs = |value: Option<f32>| value.map_or_else(|| "-".to_string(), |v| format!("{:.1}", v));
+    format!("{} -> {} LUFS", lufs(report.input_lufs), lufs(report.output_lufs))
+}
+
+// Folders become the files directly inside them, sorted; files are kept as given
+fn expand(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut inside: Vec<PathBuf> = fs::read_dir(path)
+                .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.is_file()).collect())
+                .unwrap_or_default();
+            inside.sort();
+            files.extend(inside);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    files
+}
+
+// rec/take1.flac -> rec_denoised/take1.wav
+pub fn output_path(input: &Path, suffix: &str) -> Option<PathBuf> {
+    let folder = input.parent()?;
+    let name = folder.file_name()?.to_string_lossy();
+    Some(folder.with_file_name(format!("{}{}", name, suffix)).join(input.file_name()?).with_extension("wav"))
+}
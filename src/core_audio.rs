@@ -0,0 +1,266 @@
+// Thin helpers over the Windows Core Audio (MMDevice) COM API for things cpal doesn't expose.
+// Other platforms get no-op stand-ins at the bottom.
+use eframe::egui;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+#[cfg(windows)]
+use {
+    std::ffi::c_void,
+    std::sync::atomic::Ordering,
+    std::sync::Mutex,
+    std::thread,
+    std::time::Duration,
+    windows::core::{ComInterface, IUnknown, GUID, HRESULT, PCWSTR, PWSTR},
+    windows::Win32::Devices::FunctionDiscovery::PKEY_Device_FriendlyName,
+    windows::Win32::Media::Audio::Endpoints::IAudioEndpointVolume,
+    windows::Win32::Media::Audio::{
+        eAll, eCapture, AudioSessionStateActive, EDataFlow, ERole, IAudioSessionControl2, IAudioSessionManager2, IMMDevice,
+        IMMDeviceEnumerator, MMDeviceEnumerator, DEVICE_STATE_ACTIVE,
+    },
+    windows::Win32::System::Com::StructuredStorage::{PropVariantClear, PropVariantToStringAlloc},
+    windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CoTaskMemFree, CLSCTX_ALL, COINIT_MULTITHREADED, STGM_READ},
+};
+
+#[cfg(windows)]
+const MUTE_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// Safe to call repeatedly; fails harmlessly if the thread already uses another apartment
+#[cfg(windows)]
+pub fn init_com() {
+    unsafe {
+        let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+    }
+}
+
+#[cfg(windows)]
+unsafe fn take_pwstr(p: PWSTR) -> String {
+    let s = p.to_string().unwrap_or_default();
+    CoTaskMemFree(Some(p.0 as *const _));
+    s
+}
+
+// Same friendly name cpal reports for WASAPI devices
+#[cfg(windows)]
+unsafe fn device_name(device: &IMMDevice) -> windows::core::Result<String> {
+    let store = device.OpenPropertyStore(STGM_READ)?;
+    let mut value = store.GetValue(&PKEY_Device_FriendlyName)?;
+    let name = PropVariantToStringAlloc(&value).map(|p| take_pwstr(p));
+    let _ = PropVariantClear(&mut value);
+    name
+}
+
+#[cfg(windows)]
+pub fn find_endpoint(flow: EDataFlow, name: &str) -> Option<IMMDevice> {
+    unsafe {
+        let enumerator: IMMDeviceEnumerator = CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL).ok()?;
+        let collection = enumerator.EnumAudioEndpoints(flow, DEVICE_STATE_ACTIVE).ok()?;
+        let count = collection.GetCount().ok()?;
+        (0..count)
+            .filter_map(|i| collection.Item(i).ok())
+            .find(|d| device_name(d).map(|n| n == name).unwrap_or(false))
+    }
+}
+
+#[cfg(windows)]
+pub fn default_endpoint_name(flow: EDataFlow, role: ERole) -> Option<String> {
+    init_com();
+    unsafe {
+        let enumerator: IMMDeviceEnumerator = CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL).ok()?;
+        let device = enumerator.GetDefaultAudioEndpoint(flow, role).ok()?;
+        device_name(&device).ok()
+    }
+}
+
+// IPolicyConfig is undocumented (it's what the Sound control panel uses), so it is
+// called through a hand-written vtable and every failure is treated as "not available".
+#[cfg(windows)]
+const CLSID_POLICY_CONFIG: GUID = GUID::from_u128(0x870af99c_171d_4f9e_af0d_e63df40c2bc9);
+#[cfg(windows)]
+const IID_POLICY_CONFIG: GUID = GUID::from_u128(0xf8679f50_850a_41cf_9c72_430f290290c8);
+
+#[cfg(windows)]
+#[repr(C)]
+struct PolicyConfigVtbl {
+    _query_interface: usize,
+    _add_ref: usize,
+    release: unsafe extern "system" fn(*mut c_void) -> u32,
+    // GetMixFormat .. SetPropertyValue
+    _unused: [usize; 10],
+    set_default_endpoint: unsafe extern "system" fn(*mut c_void, PCWSTR, ERole) -> HRESULT,
+}
+
+#[cfg(windows)]
+struct PolicyConfig(*mut c_void);
+
+#[cfg(windows)]
+impl PolicyConfig {
+    fn create() -> Option<Self> {
+        init_com();
+        unsafe {
+            let unknown: IUnknown = CoCreateInstance(&CLSID_POLICY_CONFIG, None, CLSCTX_ALL).ok()?;
+            let mut raw = std::ptr::null_mut();
+            if unknown.query(&IID_POLICY_CONFIG, &mut raw).is_err() || raw.is_null() {
+                return None;
+            }
+            Some(Self(raw))
+        }
+    }
+
+    unsafe fn vtable(&self) -> &PolicyConfigVtbl {
+        &**(self.0 as *const *const PolicyConfigVtbl)
+    }
+
+    fn set_default_endpoint(&self, device_id: PCWSTR, role: ERole) -> windows::core::Result<()> {
+        unsafe { (self.vtable().set_default_endpoint)(self.0, device_id, role).ok() }
+    }
+}
+
+#[cfg(windows)]
+impl Drop for PolicyConfig {
+    fn drop(&mut self) {
+        unsafe {
+            (self.vtable().release)(self.0);
+        }
+    }
+}
+
+#[cfg(windows)]
+pub fn policy_config_available() -> bool {
+    PolicyConfig::create().is_some()
+}
+
+#[cfg(windows)]
+pub fn set_default_endpoint(flow: EDataFlow, name: &str, role: ERole) -> Result<(), String> {
+    let policy = PolicyConfig::create().ok_or("Changing default devices is not supported on this system")?;
+    let device = find_endpoint(flow, name).ok_or_else(|| format!("'{}' was not found", name))?;
+    unsafe {
+        let id = device.GetId().map_err(|e| e.to_string())?;
+        let result = policy.set_default_endpoint(PCWSTR(id.0), role);
+        CoTaskMemFree(Some(id.0 as *const _));
+        result.map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(windows)]
+fn endpoint_volume(flow: EDataFlow, name: &str) -> Option<IAudioEndpointVolume> {
+    let device = find_endpoint(flow, name)?;
+    unsafe { device.Activate(CLSCTX_ALL, None).ok() }
+}
+
+// Toggles the Windows-level mute of a capture endpoint (same switch as hardware mute keys)
+#[cfg(windows)]
+pub fn set_capture_mute(name: &str, mute: bool) -> bool {
+    init_com();
+    match endpoint_volume(eCapture, name) {
+        Some(volume) => unsafe { volume.SetMute(mute, std::ptr::null()).is_ok() },
+        None => false,
+    }
+}
+
+// Processes with an active audio session on the named endpoint (capture or render), other
+// than this one. Exclusive-mode holders show up here too while they stream.
+#[cfg(windows)]
+pub fn session_processes(name: &str) -> Vec<u32> {
+    init_com();
+    let Some(device) = find_endpoint(eAll, name) else { return Vec::new() };
+    let own = std::process::id();
+    unsafe {
+        let Ok(manager) = device.Activate::<IAudioSessionManager2>(CLSCTX_ALL, None) else { return Vec::new() };
+        let Ok(sessions) = manager.GetSessionEnumerator() else { return Vec::new() };
+        let mut pids: Vec<u32> = (0..sessions.GetCount().unwrap_or(0))
+            .filter_map(|i| sessions.GetSession(i).ok())
+            .filter(|session| session.GetState().map(|state| state == AudioSessionStateActive).unwrap_or(false))
+            .filter_map(|session| session.cast::<IAudioSessionControl2>().ok()?.GetProcessId().ok())
+            // Process 0 is the system sounds session
+            .filter(|&pid| pid != 0 && pid != own)
+            .collect();
+        pids.dedup();
+        pids
+    }
+}
+
+// Polls the selected capture endpoint's mute state and mirrors it into a shared flag
+#[cfg(windows)]
+pub struct MuteWatcher {
+    device_name: Arc<Mutex<Option<String>>>,
+}
+
+#[cfg(windows)]
+impl MuteWatcher {
+    pub fn start(muted: Arc<AtomicBool>, ctx: &egui::Context) -> Self {
+        let device_name: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
+        let name_store = device_name.clone();
+        let ctx = ctx.clone();
+
+        thread::spawn(move || {
+            init_com();
+            let mut watched: Option<String> = None;
+            let mut volume: Option<IAudioEndpointVolume> = None;
+
+            loop {
+                let wanted = name_store.lock().ok().and_then(|n| n.clone());
+                if wanted != watched {
+                    watched = wanted;
+                    volume = None;
+                }
+                if volume.is_none() {
+                    volume = watched.as_deref().and_then(|name| endpoint_volume(eCapture, name));
+                }
+
+                let state = match &volume {
+                    Some(v) => match unsafe { v.GetMute() } {
+                        Ok(m) => m.as_bool(),
+                        Err(_) => {
+                            // Endpoint went away; re-resolve on the next tick
+                            volume = None;
+                            false
+                        }
+                    },
+                    None => false,
+                };
+
+                if muted.swap(state, Ordering::Relaxed) != state {
+                    ctx.request_repaint();
+                }
+
+                thread::sleep(MUTE_POLL_INTERVAL);
+            }
+        });
+
+        Self { device_name }
+    }
+
+    pub fn set_device(&self, name: Option<&str>) {
+        if let Ok(mut n) = self.device_name.lock() {
+            *n = name.map(|s| s.to_string());
+        }
+    }
+}
+
+#[cfg(not(windows))]
+pub fn policy_config_available() -> bool {
+    false
+}
+
+#[cfg(not(windows))]
+pub fn session_processes(_name: &str) -> Vec<u32> {
+    Vec::new()
+}
+
+#[cfg(not(windows))]
+pub fn set_capture_mute(_name: &str, _mute: bool) -> bool {
+    false
+}
+
+// There's no endpoint mute to mirror elsewhere, so the flag just stays false
+#[cfg(not(windows))]
+pub struct MuteWatcher;
+
+#[cfg(not(windows))]
+impl MuteWatcher {
+    pub fn start(_muted: Arc<AtomicBool>, _ctx: &egui::Context) -> Self {
+        Self
+    }
+
+    pub fn set_device(&self, _name: Option<&str>) {}
+}
@@ -0,0 +1,139 @@
+// Panic hook: every panic leaves a crash file behind, and a panic on the UI thread
+// additionally tells the user where it is and offers to relaunch the app.
+use crate::diagnostics;
+use crate::settings::{config_dir_override, load_settings, settings_to_string, CONFIG_DIR_FLAG};
+use std::backtrace::Backtrace;
+use std::fmt::Write as _;
+use std::fs;
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+
+pub fn crash_dir(config_dir: &Path) -> PathBuf {
+    config_dir.join("crashes")
+}
+
+pub fn install(config_dir: Option<PathBuf>) {
+    std::panic::set_hook(Box::new(move |info| {
+        let thread = std::thread::current();
+        let thread_name = thread.name().unwrap_or("unnamed").to_string();
+        let message = panic_message(info);
+        log::error!("Panic on thread '{}': {}", thread_name, message);
+
+        let report = build_crash_report(&thread_name, &message);
+        let path = config_dir.as_deref().and_then(|dir| write_crash_file(dir, &report));
+        if path.is_none() {
+            eprintln!("{}", report);
+        }
+
+        // Background threads are either recovered (audio) or not worth killing the app for
+        if thread_name == "main" {
+            if offer_restart(path.as_deref()) {
+                relaunch();
+            }
+            std::process::exit(1);
+        }
+    }));
+}
+
+fn panic_message(info: &PanicHookInfo) -> String {
+    let payload = if let Some(s) = info.payload().downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = info.payload().downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic payload".to_string()
+    };
+    match info.location() {
+        Some(loc) => format!("{} ({}:{})", payload, loc.file(), loc.line()),
+        None => payload,
+    }
+}
+
+fn build_crash_report(thread_name: &str, message: &str) -> String {
+    let mut report = String::new();
+    let _ = writeln!(report, "SilentStream {} crash report", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(report, "Time: {}", chrono::Local::now().format("%Y-%m-%d %H:%M:%S %z"));
+    let _ = writeln!(report, "Thread: {}", thread_name);
+    let _ = writeln!(report, "Panic: {}", message);
+    let _ = writeln!(report, "\n== Backtrace ==\n{}", Backtrace::force_capture());
+    // Settings as last saved; the live UI state isn't reachable from here
+    let _ = writeln!(report, "\n== Settings ==");
+    for line in settings_to_string(&load_settings()).lines() {
+        let _ = writeln!(report, "{}", diagnostics::redact(line));
+    }
+    report
+}
+
+fn write_crash_file(config_dir: &Path, report: &str) -> Option<PathBuf> {
+    let dir = crash_dir(config_dir);
+    fs::create_dir_all(&dir).ok()?;
+    let path = dir.join(format!("crash-{}.txt", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+    fs::write(&path, report).ok()?;
+    Some(path)
+}
+
+#[cfg(windows)]
+fn wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+// Returns true if the user asked for a restart
+#[cfg(windows)]
+fn offer_restart(path: Option<&Path>) -> bool {
+    use windows_sys::Win32::UI::WindowsAndMessaging::{MessageBoxW, IDYES, MB_ICONERROR, MB_YESNO};
+
+    let location = match path {
+        Some(p) => format!("A crash report was saved to:\n{}", p.display()),
+        None => "The crash report could not be saved.".to_string(),
+    };
+    let text = wide(&format!(
+        "SilentStream stopped unexpectedly and noise suppression is no longer active.\n\n{}\n\nRestart SilentStream now?",
+        location
+    ));
+    let caption = wide("SilentStream crashed");
+    unsafe { MessageBoxW(0, text.as_ptr(), caption.as_ptr(), MB_YESNO | MB_ICONERROR) == IDYES }
+}
+
+// No dialog without a window elsewhere; the report path goes to stderr and the app stays down
+#[cfg(not(windows))]
+fn offer_restart(path: Option<&Path>) -> bool {
+    match path {
+        Some(p) => eprintln!("SilentStream crashed. A crash report was saved to {}", p.display()),
+        None => eprintln!("SilentStream crashed. The crash report could not be saved."),
+    }
+    false
+}
+
+// Startup errors that happen before there is a window to show them in
+#[cfg(windows)]
+pub fn show_error(caption: &str, message: &str) {
+    use windows_sys::Win32::UI::WindowsAndMessaging::{MessageBoxW, MB_ICONERROR, MB_OK};
+
+    let text = wide(message);
+    let caption = wide(caption);
+    unsafe {
+        MessageBoxW(0, text.as_ptr(), caption.as_ptr(), MB_OK | MB_ICONERROR);
+    }
+}
+
+#[cfg(not(windows))]
+pub fn show_error(caption: &str, message: &str) {
+    eprintln!("{}: {}", caption, message);
+}
+
+fn relaunch() {
+    match std::env::current_exe() {
+        Ok(exe) => {
+            let mut command = std::process::Command::new(exe);
+            command.arg("--minimized");
+            // Keep using the same config directory if one was given
+            if let Some(dir) = config_dir_override() {
+                command.arg(CONFIG_DIR_FLAG).arg(dir);
+            }
+            if let Err(e) = command.spawn() {
+                log::error!("Failed to relaunch after crash: {}", e);
+            }
+        }
+        Err(e) => log::error!("Failed to relaunch after crash, executable path unknown: {}", e),
+    }
+}
@@ -0,0 +1,111 @@
+use crate::core_audio;
+use std::time::{Duration, Instant};
+#[cfg(windows)]
+use windows::Win32::Media::Audio::{eCapture, eCommunications};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Clone, PartialEq, Default)]
+pub struct DefaultDeviceConfig {
+    pub enabled: bool,
+    // Capture endpoint that should stay the default communication device.
+    // Empty means "the capture side of the selected output" (e.g. CABLE Output).
+    pub device: String,
+    pub auto_fix: bool,
+}
+
+#[derive(Clone, PartialEq)]
+pub enum DefaultDeviceState {
+    Unknown,
+    Ok,
+    Drifted { current: Option<String> },
+}
+
+// VB-Cable and VoiceMeeter name the two ends of a cable "X Input" (playback) and "X Output" (capture)
+pub fn companion_capture_device(output: &str, inputs: &[String]) -> Option<String> {
+    cable_capture_side(output, inputs).or_else(|| inputs.iter().find(|i| i.starts_with("CABLE Output")).cloned())
+}
+
+// The capture end of `output` if it is one end of a cable, with no fallback
+pub fn cable_capture_side(output: &str, inputs: &[String]) -> Option<String> {
+    let candidate = output.replacen("Input", "Output", 1);
+    (candidate != output && inputs.contains(&candidate)).then_some(candidate)
+}
+
+pub struct DefaultDeviceGuard {
+    state: DefaultDeviceState,
+    last_check: Option<Instant>,
+    policy_available: Option<bool>,
+}
+
+impl DefaultDeviceGuard {
+    pub fn new() -> Self {
+        Self { state: DefaultDeviceState::Unknown, last_check: None, policy_available: None }
+    }
+
+    pub fn state(&self) -> &DefaultDeviceState {
+        &self.state
+    }
+
+    // False once PolicyConfig turned out to be unavailable; drift is then only reported
+    pub fn can_fix(&self) -> bool {
+        self.policy_available != Some(false)
+    }
+
+    // Rate-limited; returns true when the state changed
+    pub fn check(&mut self, expected: &str) -> bool {
+        if self.last_check.map(|t| t.elapsed() < CHECK_INTERVAL).unwrap_or(false) {
+            return false;
+        }
+        self.last_check = Some(Instant::now());
+        if self.policy_available.is_none() {
+            self.policy_available = Some(core_audio::policy_config_available());
+        }
+
+        let current = current_default();
+        let state = if current.as_deref() == Some(expected) {
+            DefaultDeviceState::Ok
+        } else {
+            DefaultDeviceState::Drifted { current }
+        };
+        let changed = state != self.state;
+        self.state = state;
+        changed
+    }
+
+    pub fn fix(&mut self, expected: &str) -> Result<(), String> {
+        let result = set_default(expected);
+        if result.is_err() && !core_audio::policy_config_available() {
+            self.policy_available = Some(false);
+        }
+        // Re-check on the next frame
+        self.last_check = None;
+        result
+    }
+
+    pub fn reset(&mut self) {
+        self.state = DefaultDeviceState::Unknown;
+        self.last_check = None;
+    }
+}
+
+// The default communication device is a Windows setting; elsewhere the guard stays idle
+#[cfg(windows)]
+fn current_default() -> Option<String> {
+    core_audio::default_endpoint_name(eCapture, eCommunications)
+}
+
+#[cfg(not(windows))]
+fn current_default() -> Option<String> {
+    None
+}
+
+#[cfg(windows)]
+fn set_default(name: &str) -> Result<(), String> {
+    core_audio::set_default_endpoint(eCapture, name, eCommunications)
+}
+
+#[cfg(not(windows))]
+fn set_default(_name: &str) -> Result<(), String> {
+    Err("Changing default devices is not supported on this system".to_string())
+}
@@ -0,0 +1,41 @@
+// Keeps virtual capture endpoints (the far end of audio cables, mixer buses, stereo mix) out
+// of the input list. Picking one as the microphone usually feeds SilentStream's own output
+// back into it. Only the input list is filtered; virtual outputs are the usual target.
+
+// Matched case-insensitively anywhere in the device name
+pub const DEFAULT_PATTERNS: [&str; 10] = [
+    "CABLE Output",
+    "CABLE-A Output",
+    "CABLE-B Output",
+    "VoiceMeeter Out",
+    "VoiceMeeter Aux Out",
+    "VoiceMeeter VAIO3 Out",
+    "Stereo Mix",
+    "What U Hear",
+    "Wave Out Mix",
+    "Monitor of",
+];
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct VirtualInputFilter {
+    pub enabled: bool,
+    pub patterns: Vec<String>,
+}
+
+impl Default for VirtualInputFilter {
+    fn default() -> Self {
+        Self { enabled: true, patterns: DEFAULT_PATTERNS.iter().map(|p| p.to_string()).collect() }
+    }
+}
+
+impl VirtualInputFilter {
+    pub fn is_virtual(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.patterns.iter().any(|p| !p.is_empty() && name.contains(&p.to_lowercase()))
+    }
+
+    // Whether the input dropdown should list `name`
+    pub fn shows(&self, name: &str) -> bool {
+        !self.enabled || !self.is_virtual(name)
+    }
+}
@@ -0,0 +1,169 @@
+// Device dropdown with a search field. egui's ComboBox closes on any click outside its
+// button, including clicks into a text field in the popup, so the popup is drawn here.
+// Typing filters, arrows move the highlight, Enter picks and Escape closes.
+use eframe::egui;
+
+const DEFAULT_MARKER: &str = "★";
+const LIST_HEIGHT: f32 = 240.0;
+
+#[derive(Clone, Default)]
+struct PickerState {
+    open: bool,
+    // Focus the search field on the first frame after opening
+    just_opened: bool,
+    query: String,
+    // Position in the filtered list, not a device index
+    highlight: usize,
+}
+
+// Shows `devices` sorted default-first then alphabetically, skipping those `listed` rejects
+// (the selected device is always listed). Returns true when the selection changed.
+pub fn device_picker(
+    ui: &mut egui::Ui,
+    id_source: &str,
+    devices: &[String],
+    selected: &mut usize,
+    default: Option<&str>,
+    listed: impl Fn(&str) -> bool,
+) -> bool {
+    let id = ui.make_persistent_id(id_source);
+    let mut state = ui.data(|d| d.get_temp::<PickerState>(id)).unwrap_or_default();
+    let label = |name: &str| {
+        if Some(name) == default {
+            format!("{} {}", DEFAULT_MARKER, name)
+        } else {
+            name.to_string()
+        }
+    };
+
+    let width = ui.available_width() - 8.0;
+    let current = devices.get(*selected).map(|name| label(name)).unwrap_or_else(|| "No device".to_string());
+    let button = picker_button(ui, &current, width, state.open);
+    if button.clicked() {
+        state.open = !state.open;
+        state.just_opened = state.open;
+        state.query.clear();
+    }
+
+    let mut changed = false;
+    if state.open {
+        let query = state.query.to_lowercase();
+        let mut entries: Vec<usize> = (0..devices.len())
+            .filter(|&i| i == *selected || listed(&devices[i]))
+            .filter(|&i| query.is_empty() || devices[i].to_lowercase().contains(&query))
+            .collect();
+        entries.sort_by_key(|&i| (Some(devices[i].as_str()) != default, devices[i].to_lowercase()));
+        if state.just_opened {
+            state.highlight = entries.iter().position(|&i| i == *selected).unwrap_or(0);
+        }
+
+        let popup = egui::Area::new(id.with("popup"))
+            .order(egui::Order::Foreground)
+            .constrain(true)
+            .fixed_pos(button.rect.left_bottom())
+            .show(ui.ctx(), |ui| {
+                let frame = egui::Frame::popup(ui.style());
+                let margin = frame.total_margin().sum().x;
+                frame.show(ui, |ui| {
+                    ui.set_width(width - margin);
+                    // Taken before the search field sees them; it would move its cursor instead
+                    let (up, down, enter) = ui.input_mut(|i| {
+                        (
+                            i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                            i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                            i.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+                        )
+                    });
+                    let search = ui.add(
+                        egui::TextEdit::singleline(&mut state.query)
+                            .hint_text("Search devices")
+                            .desired_width(f32::INFINITY),
+                    );
+                    if state.just_opened {
+                        search.request_focus();
+                        state.just_opened = false;
+                    }
+                    if search.changed() {
+                        state.highlight = 0;
+                    }
+                    if down {
+                        state.highlight = (state.highlight + 1).min(entries.len().saturating_sub(1));
+                    }
+                    if up {
+                        state.highlight = state.highlight.saturating_sub(1);
+                    }
+                    if enter {
+                        if let Some(&i) = entries.get(state.highlight) {
+                            changed = *selected != i;
+                            *selected = i;
+                        }
+                        state.open = false;
+                    }
+
+                    ui.add_space(4.0);
+                    egui::ScrollArea::vertical().max_height(LIST_HEIGHT).show(ui, |ui| {
+                        ui.with_layout(egui::Layout::top_down_justified(egui::Align::LEFT), |ui| {
+                            if entries.is_empty() {
+                                ui.label(egui::RichText::new("No matching devices").size(11.0).weak());
+                            }
+                            for (position, &i) in entries.iter().enumerate() {
+                                let mut item = ui.selectable_label(i == *selected, label(&devices[i]));
+                                if position == state.highlight {
+                                    item = item.highlight();
+                                    if up || down {
+                                        item.scroll_to_me(None);
+                                    }
+                                }
+                                if item.clicked() {
+                                    changed = *selected != i;
+                                    *selected = i;
+                                    state.open = false;
+                                }
+                            }
+                        });
+                    });
+                });
+            });
+
+        let clicked_outside = ui.input(|i| {
+            i.pointer.any_click()
+                && i.pointer
+                    .interact_pos()
+                    .is_some_and(|pos| !popup.response.rect.contains(pos) && !button.rect.contains(pos))
+        });
+        if clicked_outside || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+            state.open = false;
+        }
+    }
+
+    ui.data_mut(|d| d.insert_temp(id, state));
+    changed
+}
+
+// Looks like a closed ComboBox: the current device, truncated, and an arrow on the right
+fn picker_button(ui: &mut egui::Ui, text: &str, width: f32, open: bool) -> egui::Response {
+    let height = ui.spacing().interact_size.y;
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(width, height), egui::Sense::click());
+    if ui.is_rect_visible(rect) {
+        let visuals = ui.style().interact_selectable(&response, open);
+        let padding = ui.spacing().button_padding.x;
+        let painter = ui.painter();
+        painter.rect(rect, visuals.rounding, visuals.weak_bg_fill, visuals.bg_stroke);
+        let arrow = painter.text(
+            rect.right_center() - egui::vec2(padding, 0.0),
+            egui::Align2::RIGHT_CENTER,
+            "⏷",
+            egui::TextStyle::Button.resolve(ui.style()),
+            visuals.text_color(),
+        );
+        let text_rect = egui::Rect::from_min_max(rect.min, egui::pos2(arrow.left() - padding, rect.max.y));
+        painter.with_clip_rect(text_rect).text(
+            rect.left_center() + egui::vec2(padding, 0.0),
+            egui::Align2::LEFT_CENTER,
+            text,
+            egui::TextStyle::Button.resolve(ui.style()),
+            visuals.text_color(),
+        );
+    }
+    response
+}
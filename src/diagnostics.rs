@@ -0,0 +1,404 @@
+// Builds the plain-text report behind "Create diagnostic report"; nothing is written
+// until the user has seen the text and chosen to save it.
+use silentstream_core::audio_engine::{EngineCounters, EngineStatus, SessionStats, StreamInfo, RING_BUFFER_SIZE};
+use silentstream_core::overload::OverloadPolicy;
+use cpal::BufferSize;
+use crate::logging;
+use crate::settings::{settings_to_string, Settings};
+use crate::uptime::Uptime;
+use cpal::traits::{DeviceTrait, HostTrait};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use sysinfo::System;
+
+const LOG_TAIL_LINES: usize = 200;
+// Warnings and errors included in the copied status
+const STATUS_RECENT_ERRORS: usize = 5;
+// Keys whose values never leave the machine
+const REDACTED_KEYS: [&str; 1] = ["obs_password"];
+
+pub fn diagnostics_dir(config_dir: &Path) -> PathBuf {
+    config_dir.join("diagnostics")
+}
+
+pub fn build_report(
+    settings: &Settings,
+    stream: Option<&StreamInfo>,
+    counters: &EngineCounters,
+    stats: &SessionStats,
+    uptime: &Uptime,
+    config_dir: Option<&Path>,
+) -> String {
+    let mut report = String::new();
+    let _ = writeln!(report, "SilentStream diagnostic report");
+    let _ = writeln!(report, "Created: {}", chrono::Local::now().format("%Y-%m-%d %H:%M:%S %z"));
+
+    section(&mut report, "Build");
+    let _ = writeln!(report, "Version: {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(
+        report,
+        "Profile: {}",
+        if cfg!(debug_assertions) { "debug" } else { "release" }
+    );
+    let _ = writeln!(report, "Target: {} / {}", std::env::consts::OS, std::env::consts::ARCH);
+
+    section(&mut report, "System");
+    let _ = writeln!(report, "OS: {}", System::long_os_version().unwrap_or_else(|| "unknown".to_string()));
+    let _ = writeln!(report, "Kernel: {}", System::kernel_version().unwrap_or_else(|| "unknown".to_string()));
+    let mut system = System::new();
+    system.refresh_cpu();
+    let _ = writeln!(
+        report,
+        "CPU: {} ({} logical cores)",
+        system.cpus().first().map(|c| c.brand().trim()).unwrap_or("unknown"),
+        system.cpus().len()
+    );
+
+    section(&mut report, "Active streams");
+    match stream {
+        Some(info) => {
+            let _ = writeln!(report, "Host: {}", info.host);
+            let _ = writeln!(
+                report,
+                "Input: '{}' {} Hz, {} ch ({})",
+                info.input_device,
+                info.input_sample_rate,
+                info.input_channels,
+                info.input_channel.label()
+            );
+            let _ = writeln!(
+                report,
+                "Output: '{}' {} Hz, {} ch, {}",
+                info.output_device, info.output_sample_rate, info.output_channels, info.output_sample_format
+            );
+            let _ = writeln!(report, "Output conversion: {}", output_conversion(info));
+            let _ = writeln!(
+                report,
+                "Callback buffers: input {}, output {}",
+                callback_buffer(info.input_buffer, counters.input_callback_frames.load(Ordering::Relaxed)),
+                callback_buffer(info.output_buffer, counters.output_callback_frames.load(Ordering::Relaxed))
+            );
+            match info.resampler {
+                Some(name) => {
+                    let _ = writeln!(report, "Resampling to 48000 Hz: {} ({:.1} ms delay)", name, info.resampler_delay_ms);
+                }
+                None => {
+                    let _ = writeln!(report, "Resampling to 48000 Hz: no");
+                }
+            }
+            let _ = writeln!(
+                report,
+                "Processing block: {} frame(s); processing delay {:.1} ms",
+                info.block_frames,
+                info.processing_delay_ms()
+            );
+            match &info.monitor {
+                Some((device, rate)) => {
+                    let _ = writeln!(report, "Monitor: '{}' {} Hz", device, rate);
+                }
+                None => {
+                    let _ = writeln!(report, "Monitor: off");
+                }
+            }
+            match &info.mix_input {
+                Some(device) => {
+                    let _ = writeln!(report, "Mix input: '{}'", device);
+                }
+                None => {
+                    let _ = writeln!(report, "Mix input: off");
+                }
+            }
+            match &info.echo_reference {
+                Some(device) => {
+                    let delay = counters.echo_delay_ms.load(Ordering::Relaxed);
+                    let _ = writeln!(report, "Echo cancellation: reference '{}', estimated delay {} ms", device, delay);
+                }
+                None => {
+                    let _ = writeln!(report, "Echo cancellation: off");
+                }
+            }
+        }
+        None => {
+            let _ = writeln!(report, "Engine not running");
+        }
+    }
+
+    section(&mut report, "Uptime");
+    let _ = writeln!(report, "{}", uptime.summary());
+    for restart in uptime.recent() {
+        let _ = writeln!(report, "{}  {}", restart.at.format("%Y-%m-%d %H:%M:%S"), restart.reason.label());
+    }
+
+    section(&mut report, "Engine counters");
+    let _ = writeln!(report, "Starts: {}", counters.starts.load(Ordering::Relaxed));
+    let _ = writeln!(report, "Underrun samples: {}", counters.underruns.load(Ordering::Relaxed));
+    let _ = writeln!(report, "Overrun samples: {}", counters.overruns.load(Ordering::Relaxed));
+    let _ = writeln!(report, "Input callback errors: {}", counters.input_errors.load(Ordering::Relaxed));
+    let _ = writeln!(
+        report,
+        "Backlog: {} ms; {} frames skipped by the latency cap",
+        counters.backlog_ms.load(Ordering::Relaxed),
+        counters.latency_skips.load(Ordering::Relaxed)
+    );
+    let _ = writeln!(
+        report,
+        "Ring buffer fill: input {} / output {} of {} samples",
+        counters.input_fill.load(Ordering::Relaxed),
+        counters.output_fill.load(Ordering::Relaxed),
+        RING_BUFFER_SIZE
+    );
+    let _ = writeln!(
+        report,
+        "Session frames: {} forwarded, {} gated, {} muted; {} clip events; average VAD {:.3}",
+        stats.frames_forwarded,
+        stats.frames_gated,
+        stats.frames_muted,
+        stats.clip_events,
+        stats.average_vad()
+    );
+
+    section(&mut report, "Devices");
+    write_devices(&mut report);
+
+    section(&mut report, "Settings");
+    for line in settings_to_string(settings).lines() {
+        let _ = writeln!(report, "{}", redact(line));
+    }
+
+    section(&mut report, "Recent log");
+    match config_dir.map(|dir| log_tail(&logging::log_dir(dir))) {
+        Some(lines) if !lines.is_empty() => {
+            for line in lines {
+                let _ = writeln!(report, "{}", line);
+            }
+        }
+        _ => {
+            let _ = writeln!(report, "(no log file)");
+        }
+    }
+
+    report
+}
+
+// The negotiated stream parameters as shown in the Diagnostics panel
+pub fn stream_parameters(info: &StreamInfo, counters: &EngineCounters) -> Vec<(&'static str, String)> {
+    vec![
+        ("Host", info.host.clone()),
+        ("Input device", info.input_device.clone()),
+        ("Input format", format!("{} Hz, {} ch ({})", info.input_sample_rate, info.input_channels, info.input_channel.label())),
+        ("Input buffer", callback_buffer(info.input_buffer, counters.input_callback_frames.load(Ordering::Relaxed))),
+        ("Output device", info.output_device.clone()),
+        ("Output format", format!("{} Hz, {} ch, {}", info.output_sample_rate, info.output_channels, info.output_sample_format)),
+        ("Output conversion", output_conversion(info)),
+        ("Output buffer", callback_buffer(info.output_buffer, counters.output_callback_frames.load(Ordering::Relaxed))),
+        (
+            "Resampler",
+            match info.resampler {
+                Some(name) => format!("{} Hz -> 48000 Hz, {} ({:.1} ms)", info.input_sample_rate, name, info.resampler_delay_ms),
+                None => "not needed (input is 48000 Hz)".to_string(),
+            },
+        ),
+        ("Processing block", format!("{} frame(s), {:.1} ms", info.block_frames, info.processing_delay_ms())),
+    ]
+}
+
+// Markdown table for pasting into a GitHub issue
+pub fn stream_parameters_markdown(info: &StreamInfo, counters: &EngineCounters) -> String {
+    let mut text = format!("**SilentStream {} stream parameters** ({} / {})\n\n", env!("CARGO_PKG_VERSION"), std::env::consts::OS, std::env::consts::ARCH);
+    text.push_str("| Parameter | Value |\n|---|---|\n");
+    for (name, value) in stream_parameters(info, counters) {
+        let _ = writeln!(text, "| {} | {} |", name, value.replace('|', "\\|"));
+    }
+    text
+}
+
+// Short status block for the clipboard (Ctrl+Shift+C), fenced so it pastes cleanly into an issue
+pub fn status_text(status: &EngineStatus, stream: Option<&StreamInfo>, counters: &EngineCounters, settings: &Settings, uptime: &Uptime) -> String {
+    let on_off = |on: bool| if on { "on" } else { "off" };
+    let mut text = format!("**SilentStream {} status** ({} / {})\n\n```\n", env!("CARGO_PKG_VERSION"), std::env::consts::OS, std::env::consts::ARCH);
+    let state = if !status.running {
+        "stopped"
+    } else if status.system_muted {
+        "system-muted"
+    } else if status.bypassed {
+        "bypassed"
+    } else if status.idle {
+        "idle"
+    } else if status.overloaded && settings.overload_policy == OverloadPolicy::AutoBypass {
+        "overloaded, passing through"
+    } else if status.music_passthrough {
+        "music passthrough"
+    } else {
+        "processing"
+    };
+    let _ = writeln!(text, "State: {}", state);
+    match stream {
+        Some(info) => {
+            for (name, value) in stream_parameters(info, counters) {
+                let _ = writeln!(text, "{}: {}", name, value);
+            }
+        }
+        None => {
+            let _ = writeln!(text, "Engine not running");
+        }
+    }
+    let _ = writeln!(
+        text,
+        "Suppression: {}, VAD threshold {:.2}, gate {} (hold {:.0} ms, pre-roll {:.0} ms)",
+        settings.suppression_mode.label(),
+        settings.vad_threshold,
+        settings.gate.mode.label(),
+        settings.gate.hold_ms,
+        settings.gate.pre_roll_ms
+    );
+    let _ = writeln!(
+        text,
+        "De-esser {}, plosives {}, clicks {}, music passthrough {}, boost {:+.1} dB",
+        on_off(settings.de_esser.enabled),
+        on_off(settings.plosive.enabled),
+        on_off(settings.click.enabled),
+        on_off(settings.music.enabled),
+        settings.boost.gain_db
+    );
+    let _ = writeln!(
+        text,
+        "Echo cancellation {}, monitor {}, mix input {}",
+        on_off(settings.aec_enabled),
+        on_off(settings.monitor_enabled),
+        on_off(settings.mix_enabled)
+    );
+    let _ = writeln!(text, "{}", uptime.summary());
+    let _ = writeln!(
+        text,
+        "Latency {:.0} ms, backlog {} ms; underruns {}, overruns {}, input errors {}, latency skips {}",
+        status.latency_ms, status.backlog_ms, status.underruns, status.overruns, status.input_errors, status.latency_skips
+    );
+    let errors: Vec<_> = logging::recent_entries()
+        .into_iter()
+        .filter(|e| e.level <= log::Level::Warn)
+        .collect();
+    if errors.is_empty() {
+        let _ = writeln!(text, "Recent errors: none");
+    } else {
+        let _ = writeln!(text, "Recent errors:");
+        for entry in &errors[errors.len().saturating_sub(STATUS_RECENT_ERRORS)..] {
+            let _ = writeln!(text, "  {} {} {}", entry.time, entry.level, entry.message);
+        }
+    }
+    text.push_str("```\n");
+    text
+}
+
+fn output_conversion(info: &StreamInfo) -> String {
+    if info.output_conversions.is_empty() {
+        "none (device takes f32 at 48000 Hz)".to_string()
+    } else {
+        info.output_conversions.join("; ")
+    }
+}
+
+// Requested buffer size plus what the driver actually delivered in the last callback
+fn callback_buffer(requested: BufferSize, observed: usize) -> String {
+    let requested = match requested {
+        BufferSize::Default => "driver default".to_string(),
+        BufferSize::Fixed(frames) => format!("{} frames requested", frames),
+    };
+    if observed == 0 {
+        format!("{}, no callback yet", requested)
+    } else {
+        format!("{}, {} frames per callback", requested, observed)
+    }
+}
+
+fn section(report: &mut String, title: &str) {
+    let _ = writeln!(report, "\n== {} ==", title);
+}
+
+pub fn redact(line: &str) -> String {
+    match line.split_once('=') {
+        Some((key, value)) if REDACTED_KEYS.contains(&key) && !value.is_empty() => format!("{}=<redacted>", key),
+        _ => line.to_string(),
+    }
+}
+
+fn write_devices(report: &mut String) {
+    let host = cpal::default_host();
+    let _ = writeln!(report, "Host: {:?}", host.id());
+
+    match host.input_devices() {
+        Ok(devices) => {
+            for device in devices {
+                let name = device.name().unwrap_or_else(|_| "Unknown".to_string());
+                let default = device
+                    .default_input_config()
+                    .map(|c| format!("{} Hz, {} ch, {:?}", c.sample_rate().0, c.channels(), c.sample_format()))
+                    .unwrap_or_else(|e| format!("error: {}", e));
+                let _ = writeln!(report, "[in]  {} — default {}", name, default);
+                if let Ok(configs) = device.supported_input_configs() {
+                    for c in configs {
+                        let _ = writeln!(
+                            report,
+                            "        {}-{} Hz, {} ch, {:?}",
+                            c.min_sample_rate().0,
+                            c.max_sample_rate().0,
+                            c.channels(),
+                            c.sample_format()
+                        );
+                    }
+                }
+            }
+        }
+        Err(e) => {
+            let _ = writeln!(report, "Input devices unavailable: {}", e);
+        }
+    }
+
+    match host.output_devices() {
+        Ok(devices) => {
+            for device in devices {
+                let name = device.name().unwrap_or_else(|_| "Unknown".to_string());
+                let default = device
+                    .default_output_config()
+                    .map(|c| format!("{} Hz, {} ch, {:?}", c.sample_rate().0, c.channels(), c.sample_format()))
+                    .unwrap_or_else(|e| format!("error: {}", e));
+                let _ = writeln!(report, "[out] {} — default {}", name, default);
+                if let Ok(configs) = device.supported_output_configs() {
+                    for c in configs {
+                        let _ = writeln!(
+                            report,
+                            "        {}-{} Hz, {} ch, {:?}",
+                            c.min_sample_rate().0,
+                            c.max_sample_rate().0,
+                            c.channels(),
+                            c.sample_format()
+                        );
+                    }
+                }
+            }
+        }
+        Err(e) => {
+            let _ = writeln!(report, "Output devices unavailable: {}", e);
+        }
+    }
+}
+
+fn log_tail(dir: &Path) -> Vec<String> {
+    let Ok(content) = fs::read_to_string(dir.join(logging::LOG_FILE)) else { return Vec::new() };
+    let lines: Vec<&str> = content.lines().collect();
+    let start = lines.len().saturating_sub(LOG_TAIL_LINES);
+    lines[start..].iter().map(|l| l.to_string()).collect()
+}
+
+// Writes the report next to earlier ones and returns its path
+pub fn save_report(config_dir: &Path, report: &str) -> std::io::Result<PathBuf> {
+    let dir = diagnostics_dir(config_dir);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!(
+        "silentstream-report-{}.txt",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    fs::write(&path, report)?;
+    Ok(path)
+}
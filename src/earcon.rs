@@ -0,0 +1,107 @@
+// Short tones confirming that the mic went live or quiet, for when the window isn't in view.
+// Each cue gets its own brief output stream on the monitoring device (or the default
+// playback device), never on the processed output that other people hear.
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use std::f32::consts::TAU;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+const NOTE_SECONDS: f32 = 0.07;
+// Fade at both ends of each note so they don't click
+const NOTE_FADE_SECONDS: f32 = 0.005;
+const LOW_HZ: f32 = 660.0;
+const HIGH_HZ: f32 = 880.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Cue {
+    // Rising: processed audio is going out again
+    Live,
+    // Falling: muted or bypassed
+    Quiet,
+}
+
+impl Cue {
+    fn notes(&self) -> [f32; 2] {
+        match self {
+            Cue::Live => [LOW_HZ, HIGH_HZ],
+            Cue::Quiet => [HIGH_HZ, LOW_HZ],
+        }
+    }
+}
+
+// The two notes at `sample_rate`, mono
+fn render(cue: Cue, sample_rate: u32, volume: f32) -> Vec<f32> {
+    cue.notes().iter().flat_map(|&frequency| tone(frequency, NOTE_SECONDS, sample_rate, volume)).collect()
+}
+
+// A sine with short fades at both ends, mono
+pub fn tone(frequency: f32, seconds: f32, sample_rate: u32, volume: f32) -> Vec<f32> {
+    let len = (seconds * sample_rate as f32) as usize;
+    let fade_len = ((NOTE_FADE_SECONDS * sample_rate as f32) as usize).max(1);
+    (0..len)
+        .map(|i| {
+            let edge = i.min(len - 1 - i);
+            let envelope = (edge as f32 / fade_len as f32).min(1.0);
+            let phase = TAU * frequency * i as f32 / sample_rate as f32;
+            phase.sin() * envelope * volume
+        })
+        .collect()
+}
+
+// Plays `cue` on `device` (empty = default output) without blocking. Skipped when that device
+// is `processed_output`, so a cue can't reach the far end.
+pub fn play(cue: Cue, volume: f32, device: &str, processed_output: &str) {
+    let device = device.to_string();
+    let processed_output = processed_output.to_string();
+    let spawned = thread::Builder::new().name("earcon".to_string()).spawn(move || {
+        if let Err(e) = play_blocking(cue, volume, &device, &processed_output) {
+            log::warn!("Could not play the {:?} cue: {}", cue, e);
+        }
+    });
+    if let Err(e) = spawned {
+        log::warn!("Could not start the cue thread: {}", e);
+    }
+}
+
+fn play_blocking(cue: Cue, volume: f32, name: &str, processed_output: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let host = cpal::default_host();
+    let device = if name.is_empty() {
+        host.default_output_device().ok_or("No default output device")?
+    } else {
+        host.output_devices()?
+            .find(|d| d.name().map(|n| n == name).unwrap_or(false))
+            .ok_or_else(|| format!("Output device '{}' not found", name))?
+    };
+    let device_name = device.name().unwrap_or_default();
+    if device_name == processed_output {
+        log::info!("Cue skipped: '{}' is the processed output", device_name);
+        return Ok(());
+    }
+
+    let config: cpal::StreamConfig = device.default_output_config()?.into();
+    let channels = config.channels as usize;
+    let samples = Arc::new(render(cue, config.sample_rate.0, volume.clamp(0.0, 1.0)));
+    let duration = Duration::from_secs_f32(samples.len() as f32 / config.sample_rate.0 as f32);
+    let position = Arc::new(AtomicUsize::new(0));
+    let stream = {
+        let samples = samples.clone();
+        device.build_output_stream(
+            &config,
+            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                for frame in data.chunks_mut(channels) {
+                    let i = position.fetch_add(1, Ordering::Relaxed);
+                    let sample = samples.get(i).copied().unwrap_or(0.0);
+                    frame.fill(sample);
+                }
+            },
+            |err| log::warn!("Cue stream error: {}", err),
+            None,
+        )?
+    };
+    stream.play()?;
+    // Leave room for the device's buffer to drain before the stream is dropped
+    thread::sleep(duration + Duration::from_millis(100));
+    Ok(())
+}
@@ -0,0 +1,59 @@
+// A start or restart handed to the engine's session thread, kept by the supervisor until
+// poll_start() sees how it went. Stops go through right away: the engine closes the old
+// session in the background and a later start waits for it, so a stop while starting just
+// drops the request here.
+use crate::uptime::RestartReason;
+use std::time::Instant;
+
+pub struct StartRequest {
+    // None for a plain start
+    pub restart: Option<RestartReason>,
+    pub since: Instant,
+    // Uptime keeps counting across the restart instead of starting over
+    pub was_processing: bool,
+    // A missing device waits for the device lists to change instead of showing an error
+    pub wait_for_devices: bool,
+    // Status line once running, instead of "Processing audio"
+    pub running_message: Option<String>,
+}
+
+impl StartRequest {
+    pub fn start() -> Self {
+        Self { restart: None, since: Instant::now(), was_processing: false, wait_for_devices: true, running_message: None }
+    }
+
+    pub fn restart(reason: RestartReason, was_processing: bool) -> Self {
+        Self { restart: Some(reason), since: Instant::now(), was_processing, wait_for_devices: false, running_message: None }
+    }
+
+    pub fn status(&self) -> &'static str {
+        if self.restart.is_some() {
+            "Restarting audio…"
+        } else {
+            "Starting audio…"
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_start_waits_for_devices_and_counts_uptime_afresh() {
+        let request = StartRequest::start();
+        assert_eq!(request.status(), "Starting audio…");
+        assert!(request.restart.is_none());
+        assert!(request.wait_for_devices);
+        assert!(!request.was_processing);
+    }
+
+    #[test]
+    fn restart_reports_its_errors_and_keeps_uptime() {
+        let request = StartRequest::restart(RestartReason::DeviceChange, true);
+        assert_eq!(request.status(), "Restarting audio…");
+        assert!(request.restart.is_some());
+        assert!(!request.wait_for_devices);
+        assert!(request.was_processing);
+    }
+}
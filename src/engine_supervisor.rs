@@ -0,0 +1,859 @@
+// Runs the engine from its own thread: starts and restarts, recovery from a crashed processing
+// thread, a lost device or an output that stopped calling back without an error (how a
+// re-registered virtual cable endpoint looks), waiting for missing devices, switching back
+// from a fallback device, the delayed start at login, the schedule, and pausing while the
+// session is locked or asleep. eframe only calls update() for a hidden window when something
+// asks for a repaint, so none of this waits for it; it works the same visible, hidden in the
+// tray or before the window is first drawn. The UI asks for starts and stops through here and
+// shows the snapshot published here, and is woken only when that changes.
+use crate::automation::ScheduleConfig;
+use crate::engine_start::StartRequest;
+use crate::session::{SessionEvent, SessionWatcher};
+use crate::settings::DevicePair;
+use crate::uptime::{RestartReason, Uptime};
+use eframe::egui;
+use silentstream_core::audio_engine::{default_input_name, default_output_name, input_device_names, output_device_names, InputChannel};
+use silentstream_core::{AudioEngine, EngineError};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+// While the session thread is opening the streams
+const START_POLL_INTERVAL: Duration = Duration::from_millis(50);
+// A running output that hasn't called back for this long is playing into a dead endpoint
+const OUTPUT_STALL: Duration = Duration::from_secs(2);
+// A second stall this soon after reopening the output restarts the whole engine
+const OUTPUT_REOPEN_WINDOW: Duration = Duration::from_secs(10);
+// How often the device lists are read while waiting for a device, or for a saved one to return
+const DEVICE_SCAN_INTERVAL: Duration = Duration::from_secs(3);
+const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Phase {
+    Stopped,
+    // Launched at login; starts at the given time
+    Delayed(Instant),
+    // The session thread is opening the streams
+    Starting,
+    Running,
+    // Starts once the device lists change
+    WaitingForDevices,
+    // Stopped for a locked or sleeping session; starts again when it's back
+    Paused,
+}
+
+// What the UI shows, replaced whenever something changes
+#[derive(Clone)]
+pub struct EngineSnapshot {
+    // Goes up with every change
+    pub generation: u64,
+    pub phase: Phase,
+    // Status line, unless there's an error
+    pub status: String,
+    // Why processing stopped or didn't start
+    pub error: Option<Arc<EngineError>>,
+    pub uptime: Uptime,
+    // What runs, or runs next; while waiting, the devices waited for
+    pub devices: DevicePair,
+    // Saved devices that weren't found, replaced by the system defaults; empty for a side that
+    // was found
+    pub fallback: Option<DevicePair>,
+}
+
+impl EngineSnapshot {
+    pub fn is_processing(&self) -> bool {
+        self.phase == Phase::Running
+    }
+
+    // Running, or on its way there
+    pub fn is_active(&self) -> bool {
+        matches!(self.phase, Phase::Running | Phase::Starting)
+    }
+}
+
+// For the UI to act on once, besides showing the snapshot
+pub enum Notice {
+    // Processing is running; `restart` when it was restarted rather than started from stopped
+    Started { restart: bool },
+    // Processing stopped on its own; the error is in the snapshot
+    Stopped,
+    // The device lists were read again, and `selected` is what's picked in them
+    Devices { inputs: Vec<String>, outputs: Vec<String>, selected: DevicePair },
+    // Entering (true) or leaving the scheduled hours
+    Schedule(bool),
+    // Shell restarts and the end of the session, which only the UI can act on
+    Session(SessionEvent),
+}
+
+// The settings the supervisor acts on by itself
+#[derive(Clone, PartialEq, Default)]
+pub struct Policy {
+    pub pause_when_locked: bool,
+    pub return_to_saved_devices: bool,
+    pub schedule: ScheduleConfig,
+    // Read at start, so a start on another input than the UI had picked gets that input's channel
+    pub input_channels: BTreeMap<String, InputChannel>,
+}
+
+pub struct EngineSupervisor {
+    state: Arc<Mutex<State>>,
+    notices: Receiver<Notice>,
+    stop: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl EngineSupervisor {
+    // `inputs`/`outputs` are the device lists the UI shows and `devices` what's picked in them
+    pub fn start(
+        engine: Arc<Mutex<AudioEngine>>,
+        (inputs, outputs): (Vec<String>, Vec<String>),
+        devices: DevicePair,
+        fallback: Option<DevicePair>,
+        ctx: &egui::Context,
+    ) -> Self {
+        let (tx, rx) = channel();
+        let state = Arc::new(Mutex::new(State::new(engine, inputs, outputs, devices, fallback, tx, ctx)));
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_state = state.clone();
+        let thread_stop = stop.clone();
+        let ctx = ctx.clone();
+
+        let spawned = thread::Builder::new().name("engine supervisor".to_string()).spawn(move || {
+            let sessions = SessionWatcher::start(&ctx);
+            while !thread_stop.load(Ordering::Relaxed) {
+                let interval = {
+                    let mut state = thread_state.lock().unwrap_or_else(PoisonError::into_inner);
+                    for event in sessions.poll() {
+                        state.session_event(event);
+                    }
+                    state.tick()
+                };
+                // Commands from the UI unpark it early
+                thread::park_timeout(interval);
+            }
+        });
+        let thread = spawned.map_err(|e| log::error!("Could not start the engine supervisor: {}", e)).ok();
+
+        Self { state, notices: rx, stop, thread }
+    }
+
+    pub fn snapshot(&self) -> EngineSnapshot {
+        self.state().snapshot.clone()
+    }
+
+    pub fn poll(&self) -> Vec<Notice> {
+        self.notices.try_iter().collect()
+    }
+
+    // Opens `devices` on the next tick; the snapshot says Starting right away
+    pub fn start_processing(&self, devices: DevicePair, request: StartRequest) {
+        self.command(|state| {
+            state.snapshot.devices = devices;
+            state.begin(request);
+        });
+    }
+
+    pub fn restart_processing(&self, devices: DevicePair, reason: RestartReason) {
+        self.command(|state| {
+            state.snapshot.devices = devices;
+            state.restart(reason);
+        });
+    }
+
+    pub fn stop_processing(&self) {
+        self.command(|state| state.stop("Stopped"));
+    }
+
+    pub fn delay_start(&self, devices: DevicePair, delay: Duration) {
+        log::info!("Launched at login, starting processing in {} s", delay.as_secs());
+        self.command(|state| {
+            state.snapshot.devices = devices;
+            state.set_phase(Phase::Delayed(Instant::now() + delay), &format!("Starting in {} s…", delay.as_secs()));
+        });
+    }
+
+    // What the next start the supervisor makes by itself opens
+    pub fn select(&self, devices: DevicePair) {
+        let mut state = self.state();
+        if state.snapshot.devices != devices {
+            state.snapshot.devices = devices;
+            state.changed = true;
+            state.publish();
+        }
+    }
+
+    pub fn set_policy(&self, policy: Policy) {
+        self.command(|state| {
+            if policy.schedule != state.policy.schedule {
+                // Reports the current state of the new schedule, as on startup
+                state.schedule_active = None;
+                state.schedule_checked = None;
+            }
+            state.policy = policy;
+        });
+    }
+
+    pub fn return_to_saved_devices(&self) {
+        self.command(State::return_to_saved_devices);
+    }
+
+    // A device picked by hand replaces the missing one for that side
+    pub fn forget_fallback(&self, input: bool) {
+        self.command(|state| {
+            if let Some(saved) = state.snapshot.fallback.as_mut() {
+                if input { saved.input.clear() } else { saved.output.clear() }
+                if saved.input.is_empty() && saved.output.is_empty() {
+                    state.snapshot.fallback = None;
+                }
+                state.changed = true;
+            }
+        });
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn command(&self, f: impl FnOnce(&mut State)) {
+        {
+            let mut state = self.state();
+            f(&mut state);
+            state.publish();
+        }
+        if let Some(thread) = &self.thread {
+            thread.thread().unpark();
+        }
+    }
+
+    // Waits for the thread to finish its round, so nothing starts the engine after this
+    pub fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for EngineSupervisor {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+struct State {
+    engine: Arc<Mutex<AudioEngine>>,
+    ctx: egui::Context,
+    notices: Sender<Notice>,
+    snapshot: EngineSnapshot,
+    // Set by every change to the snapshot until it's published
+    changed: bool,
+    policy: Policy,
+    // A start the engine hasn't been given yet, and the one it's working on
+    pending: Option<StartRequest>,
+    starting: Option<StartRequest>,
+    // The device lists last read
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    last_scan: Instant,
+    // When the output was last reopened after it stalled
+    output_reopened: Option<Instant>,
+    // Output callback count, and when it last moved
+    callbacks: u64,
+    last_callback: Instant,
+    schedule_active: Option<bool>,
+    schedule_checked: Option<Instant>,
+    session_locked: bool,
+    session_suspended: bool,
+}
+
+impl State {
+    fn new(
+        engine: Arc<Mutex<AudioEngine>>,
+        inputs: Vec<String>,
+        outputs: Vec<String>,
+        devices: DevicePair,
+        fallback: Option<DevicePair>,
+        notices: Sender<Notice>,
+        ctx: &egui::Context,
+    ) -> Self {
+        Self {
+            engine,
+            ctx: ctx.clone(),
+            notices,
+            snapshot: EngineSnapshot {
+                generation: 0,
+                phase: Phase::Stopped,
+                status: "Stopped".to_string(),
+                error: None,
+                uptime: Uptime::default(),
+                devices,
+                fallback,
+            },
+            changed: false,
+            policy: Policy::default(),
+            pending: None,
+            starting: None,
+            inputs,
+            outputs,
+            last_scan: Instant::now(),
+            output_reopened: None,
+            callbacks: 0,
+            last_callback: Instant::now(),
+            schedule_active: None,
+            schedule_checked: None,
+            session_locked: false,
+            session_suspended: false,
+        }
+    }
+
+    fn engine(&self) -> MutexGuard<'_, AudioEngine> {
+        self.engine.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // One round on the supervisor thread; returns how long to sleep
+    fn tick(&mut self) -> Duration {
+        self.watch_engine();
+        self.launch();
+        self.poll_start();
+        self.scan_devices();
+        self.check_delayed_start();
+        self.check_schedule();
+        self.publish();
+        if self.pending.is_some() || self.starting.is_some() { START_POLL_INTERVAL } else { POLL_INTERVAL }
+    }
+
+    fn publish(&mut self) {
+        if self.changed {
+            self.changed = false;
+            self.snapshot.generation += 1;
+            self.ctx.request_repaint();
+        }
+    }
+
+    fn notify(&mut self, notice: Notice) {
+        let _ = self.notices.send(notice);
+        self.changed = true;
+    }
+
+    fn set_phase(&mut self, phase: Phase, status: &str) {
+        self.snapshot.phase = phase;
+        self.snapshot.status = status.to_string();
+        self.snapshot.error = None;
+        self.changed = true;
+    }
+
+    fn begin(&mut self, request: StartRequest) {
+        self.set_phase(Phase::Starting, request.status());
+        self.starting = None;
+        self.pending = Some(request);
+    }
+
+    fn restart(&mut self, reason: RestartReason) {
+        let was_processing = self.snapshot.is_processing()
+            || self.pending.iter().chain(&self.starting).any(|request| request.was_processing);
+        self.begin(StartRequest::restart(reason, was_processing));
+    }
+
+    fn stop(&mut self, status: &str) {
+        self.pending = None;
+        self.starting = None;
+        self.engine().stop();
+        self.snapshot.uptime.stop();
+        self.set_phase(Phase::Stopped, status);
+    }
+
+    // Hands a start to the engine, by name in fresh device lists
+    fn launch(&mut self) {
+        let Some(request) = self.pending.take() else { return };
+        let (inputs, outputs) = (input_device_names(), output_device_names());
+        if inputs.is_empty() || outputs.is_empty() {
+            log::warn!("No audio devices found");
+            (self.inputs, self.outputs) = (inputs, outputs);
+            self.wait_for_devices();
+            return;
+        }
+        if inputs != self.inputs || outputs != self.outputs {
+            (self.inputs, self.outputs) = (inputs, outputs);
+            self.notify_devices();
+        }
+        let devices = &self.snapshot.devices;
+        let input = self.inputs.iter().position(|d| *d == devices.input);
+        let output = self.outputs.iter().position(|d| *d == devices.output);
+        match (input, output) {
+            (Some(input), Some(output)) => {
+                let channel = self.policy.input_channels.get(&devices.input).copied().unwrap_or_default();
+                let mut engine = self.engine();
+                engine.set_input_channel(channel);
+                engine.start_async(input, output);
+                drop(engine);
+                self.starting = Some(request);
+            }
+            (input, _) => {
+                let name = if input.is_none() { devices.input.clone() } else { devices.output.clone() };
+                let error = EngineError::not_found(format!("'{}' is not connected", name)).with_device(&name);
+                self.finish(request, Err(error));
+            }
+        }
+    }
+
+    fn poll_start(&mut self) {
+        if self.starting.is_none() {
+            return;
+        }
+        let mut engine = self.engine();
+        let result = match engine.poll_start() {
+            Some(result) => result,
+            None if engine.is_starting() => return,
+            // Stopped or started over some other way in the meantime
+            None => {
+                drop(engine);
+                self.starting = None;
+                self.set_phase(Phase::Stopped, "Stopped");
+                return;
+            }
+        };
+        drop(engine);
+        if let Some(request) = self.starting.take() {
+            self.finish(request, result);
+        }
+    }
+
+    fn finish(&mut self, request: StartRequest, result: Result<(), EngineError>) {
+        let took = request.since.elapsed().as_millis();
+        match (result, request.restart) {
+            (Ok(()), restart) => {
+                match restart {
+                    Some(reason) => log::info!("Audio restarted in {} ms ({})", took, reason.label()),
+                    None => log::info!("Audio started in {} ms", took),
+                }
+                let status = request.running_message.unwrap_or_else(|| "Processing audio".to_string());
+                self.set_phase(Phase::Running, &status);
+                match restart {
+                    Some(reason) if request.was_processing => self.snapshot.uptime.restarted(reason),
+                    _ => self.snapshot.uptime.start(),
+                }
+                self.notify(Notice::Started { restart: restart.is_some() });
+            }
+            (Err(e @ EngineError::DeviceNotFound { .. }), _) if request.wait_for_devices => {
+                log::warn!("Failed to start audio engine, device unavailable: {}", e);
+                self.wait_for_devices();
+            }
+            (Err(e), _) => {
+                log::error!("Failed to start audio engine after {} ms: {}", took, e);
+                self.failed(e);
+            }
+        }
+    }
+
+    fn failed(&mut self, error: EngineError) {
+        self.snapshot.uptime.stop();
+        self.set_phase(Phase::Stopped, &format!("Error: {}", error.summary()));
+        self.snapshot.error = Some(Arc::new(error));
+    }
+
+    // Starts over from scan_devices() once the device lists change. The uptime keeps running,
+    // so a start after an interruption counts as recovering from a disconnect.
+    fn wait_for_devices(&mut self) {
+        if self.snapshot.phase != Phase::WaitingForDevices {
+            log::info!("Waiting for audio devices");
+        }
+        self.last_scan = Instant::now();
+        self.set_phase(Phase::WaitingForDevices, "Waiting for audio devices…");
+    }
+
+    // A panic in the processing thread, a stream whose device went away, and an output that
+    // stopped calling back
+    fn watch_engine(&mut self) {
+        let (fault, lost, count, running) = {
+            let engine = self.engine();
+            (
+                engine.fault.lock().ok().and_then(|mut f| f.take()),
+                engine.stream_lost.lock().ok().and_then(|mut l| l.take()),
+                engine.counters.output_callbacks.load(Ordering::Relaxed),
+                engine.is_running.load(Ordering::Relaxed),
+            )
+        };
+        let fault = fault.map(EngineError::ProcessingCrashed);
+        let lost = lost.map(|device| EngineError::not_found(format!("'{}' went away while processing", device)).with_device(&device));
+        if count != self.callbacks || !running {
+            self.callbacks = count;
+            self.last_callback = Instant::now();
+        }
+        let stalled = (self.last_callback.elapsed() >= OUTPUT_STALL).then(|| {
+            // Reported once per stall; the next report needs another quiet period
+            self.last_callback = Instant::now();
+            EngineError::OutputStalled { device: None }
+        });
+        for error in fault.into_iter().chain(lost).chain(stalled) {
+            log::info!("Supervisor: {}", error);
+            self.recover(error);
+        }
+    }
+
+    fn recover(&mut self, error: EngineError) {
+        let processing = self.snapshot.is_processing();
+        let error = match error {
+            EngineError::OutputStalled { .. } if processing => match self.reopen_output() {
+                Ok(()) => return,
+                Err(e) => e,
+            },
+            error => error,
+        };
+        match error {
+            EngineError::OutputStalled { .. } => {}
+            EngineError::DeviceNotFound { .. } => {
+                if !processing {
+                    return;
+                }
+                log::warn!("{}", error);
+                self.engine().stop();
+                self.wait_for_devices();
+            }
+            error => {
+                log::error!("Audio processing stopped: {}", error);
+                self.pending = None;
+                self.starting = None;
+                self.engine().stop();
+                self.failed(error);
+                self.notify(Notice::Stopped);
+            }
+        }
+    }
+
+    // Only the output side is rebuilt, so the mic stays open. A second stall soon after means the
+    // stream wasn't the problem, and the whole engine restarts instead.
+    fn reopen_output(&mut self) -> Result<(), EngineError> {
+        if self.output_reopened.is_some_and(|at| at.elapsed() < OUTPUT_REOPEN_WINDOW) {
+            log::warn!("Output stalled again right after reopening it; restarting the engine");
+            self.output_reopened = None;
+            self.restart(RestartReason::DeviceDisconnect);
+            return Ok(());
+        }
+        log::warn!("Output stopped calling back; reopening it");
+        self.output_reopened = Some(Instant::now());
+        self.engine().reopen_output()?;
+        self.snapshot.status = "Output device re-initialized".to_string();
+        self.changed = true;
+        Ok(())
+    }
+
+    // Reads the device lists while waiting for devices, or while a fallback is in use so the
+    // saved device can come back
+    fn scan_devices(&mut self) {
+        let waiting = self.snapshot.phase == Phase::WaitingForDevices;
+        if !waiting && (self.snapshot.fallback.is_none() || self.saved_devices_connected()) {
+            return;
+        }
+        if self.last_scan.elapsed() < DEVICE_SCAN_INTERVAL {
+            return;
+        }
+        self.last_scan = Instant::now();
+        let (inputs, outputs) = (input_device_names(), output_device_names());
+        if inputs == self.inputs && outputs == self.outputs {
+            return;
+        }
+        let usable = !inputs.is_empty() && !outputs.is_empty();
+        (self.inputs, self.outputs) = (inputs, outputs);
+        if !usable {
+            return;
+        }
+        self.reselect();
+        if waiting {
+            log::info!("Audio devices changed, retrying start");
+            let request = if self.snapshot.uptime.is_running() {
+                StartRequest { wait_for_devices: true, ..StartRequest::restart(RestartReason::DeviceDisconnect, true) }
+            } else {
+                StartRequest::start()
+            };
+            self.begin(request);
+        } else if self.saved_devices_connected() {
+            log::info!("Saved device is connected again");
+            if self.policy.return_to_saved_devices {
+                self.return_to_saved_devices();
+            }
+        }
+    }
+
+    // Device lists can change while waiting at login or while the PC sleeps
+    fn rescan(&mut self) {
+        (self.inputs, self.outputs) = (input_device_names(), output_device_names());
+        self.reselect();
+    }
+
+    // Keeps the devices by name in fresh lists. A name that's gone falls back to the system
+    // default and is remembered so the UI can offer it back.
+    fn reselect(&mut self) {
+        let devices = &mut self.snapshot.devices;
+        let missing_input = pick(&self.inputs, &mut devices.input, default_input_name());
+        let missing_output = pick(&self.outputs, &mut devices.output, default_output_name());
+        if missing_input.is_some() || missing_output.is_some() {
+            let fallback = self.snapshot.fallback.get_or_insert_with(DevicePair::default);
+            if let Some(input) = missing_input.filter(|_| fallback.input.is_empty()) {
+                fallback.input = input;
+            }
+            if let Some(output) = missing_output.filter(|_| fallback.output.is_empty()) {
+                fallback.output = output;
+            }
+            log::warn!("Saved device missing, using the system default instead");
+        }
+        self.notify_devices();
+    }
+
+    fn notify_devices(&mut self) {
+        let notice = Notice::Devices { inputs: self.inputs.clone(), outputs: self.outputs.clone(), selected: self.snapshot.devices.clone() };
+        self.notify(notice);
+    }
+
+    fn saved_devices_connected(&self) -> bool {
+        let Some(saved) = &self.snapshot.fallback else { return false };
+        (saved.input.is_empty() || self.inputs.contains(&saved.input)) && (saved.output.is_empty() || self.outputs.contains(&saved.output))
+    }
+
+    fn return_to_saved_devices(&mut self) {
+        let Some(saved) = self.snapshot.fallback.take() else { return };
+        if self.inputs.contains(&saved.input) {
+            self.snapshot.devices.input = saved.input;
+        }
+        if self.outputs.contains(&saved.output) {
+            self.snapshot.devices.output = saved.output;
+        }
+        log::info!("Switching back to the saved devices");
+        self.notify_devices();
+        if self.snapshot.is_active() {
+            self.restart(RestartReason::DeviceChange);
+        }
+    }
+
+    fn check_delayed_start(&mut self) {
+        let Phase::Delayed(due) = self.snapshot.phase else { return };
+        if Instant::now() >= due {
+            self.rescan();
+            self.begin(StartRequest::start());
+        }
+    }
+
+    // Like the other triggers it only acts on transitions, so a manual start or stop holds until
+    // the next boundary
+    fn check_schedule(&mut self) {
+        if !self.policy.schedule.enabled {
+            self.schedule_active = None;
+            return;
+        }
+        if self.schedule_checked.is_some_and(|at| at.elapsed() < SCHEDULE_CHECK_INTERVAL) {
+            return;
+        }
+        self.schedule_checked = Some(Instant::now());
+        let active = self.policy.schedule.is_active_now();
+        if self.schedule_active == Some(active) {
+            return;
+        }
+        self.schedule_active = Some(active);
+        log::info!("{} scheduled hours", if active { "Entering" } else { "Leaving" });
+        self.notify(Notice::Schedule(active));
+        if active && !self.snapshot.is_active() {
+            self.begin(StartRequest::start());
+        } else if !active && self.snapshot.is_active() {
+            self.stop("Stopped");
+        }
+    }
+
+    fn session_event(&mut self, event: SessionEvent) {
+        log::info!("Session event: {:?}", event);
+        match event {
+            SessionEvent::Locked => self.session_locked = true,
+            SessionEvent::Unlocked => self.session_locked = false,
+            SessionEvent::Suspending => self.session_suspended = true,
+            SessionEvent::Resumed => self.session_suspended = false,
+            SessionEvent::ShellRestarted | SessionEvent::EndingSession => {
+                self.notify(Notice::Session(event));
+                self.publish();
+                return;
+            }
+        }
+
+        let away = self.session_locked || self.session_suspended;
+        if away && self.policy.pause_when_locked && self.snapshot.is_active() {
+            self.stop(if self.session_suspended { "Paused for sleep" } else { "Paused while locked" });
+            self.snapshot.phase = Phase::Paused;
+        } else if !away && self.snapshot.phase == Phase::Paused {
+            self.rescan();
+            let reason = if event == SessionEvent::Resumed { "resume" } else { "unlock" };
+            self.begin(StartRequest {
+                running_message: Some(format!("Processing audio (restarted after {})", reason)),
+                ..StartRequest::start()
+            });
+        }
+        self.publish();
+    }
+}
+
+// Switches `name` to the system default, or the first device, if it isn't in `devices`;
+// returns the name it replaced. An empty name picks the default without counting as missing.
+fn pick(devices: &[String], name: &mut String, default: Option<String>) -> Option<String> {
+    if devices.contains(name) {
+        return None;
+    }
+    let replacement = default.filter(|d| devices.contains(d)).or_else(|| devices.first().cloned()).unwrap_or_default();
+    let missing = std::mem::replace(name, replacement);
+    Some(missing).filter(|m| !m.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MISSING: &str = "No such device (supervisor test)";
+
+    fn state(devices: &[&str]) -> (State, Receiver<Notice>) {
+        let (tx, rx) = channel();
+        let list: Vec<String> = devices.iter().map(|d| d.to_string()).collect();
+        let selected = DevicePair { input: list.first().cloned().unwrap_or_default(), output: list.first().cloned().unwrap_or_default() };
+        let engine = Arc::new(Mutex::new(AudioEngine::new()));
+        let state = State::new(engine, list.clone(), list, selected, None, tx, &egui::Context::default());
+        (state, rx)
+    }
+
+    fn running(state: &mut State) {
+        state.set_phase(Phase::Running, "Processing audio");
+        state.snapshot.uptime.start();
+    }
+
+    #[test]
+    fn pick_keeps_a_connected_device_and_falls_back_otherwise() {
+        let devices = vec!["Mic".to_string(), "Headset".to_string()];
+        let mut name = "Headset".to_string();
+        assert_eq!(pick(&devices, &mut name, None), None);
+        assert_eq!(name, "Headset");
+
+        let mut name = "USB".to_string();
+        assert_eq!(pick(&devices, &mut name, Some("Headset".to_string())), Some("USB".to_string()));
+        assert_eq!(name, "Headset");
+        let mut name = "USB".to_string();
+        assert_eq!(pick(&devices, &mut name, Some("Gone too".to_string())), Some("USB".to_string()));
+        assert_eq!(name, "Mic");
+
+        let mut name = String::new();
+        assert_eq!(pick(&devices, &mut name, None), None);
+        assert_eq!(name, "Mic");
+    }
+
+    #[test]
+    fn crash_while_running_stops_with_the_error() {
+        let (mut state, notices) = state(&["Mic"]);
+        running(&mut state);
+        state.recover(EngineError::ProcessingCrashed("boom".to_string()));
+        assert_eq!(state.snapshot.phase, Phase::Stopped);
+        assert!(matches!(state.snapshot.error.as_deref(), Some(EngineError::ProcessingCrashed(_))));
+        assert!(!state.snapshot.uptime.is_running());
+        assert!(matches!(notices.try_recv(), Ok(Notice::Stopped)));
+    }
+
+    #[test]
+    fn lost_device_waits_and_keeps_the_uptime() {
+        let (mut state, _notices) = state(&["Mic"]);
+        state.recover(EngineError::not_found("gone"));
+        assert_eq!(state.snapshot.phase, Phase::Stopped, "nothing to recover while stopped");
+
+        running(&mut state);
+        state.recover(EngineError::not_found("gone").with_device("Mic"));
+        assert_eq!(state.snapshot.phase, Phase::WaitingForDevices);
+        assert!(state.snapshot.uptime.is_running());
+        assert!(state.snapshot.error.is_none());
+    }
+
+    #[test]
+    fn second_stall_soon_after_reopening_restarts() {
+        let (mut state, _notices) = state(&["Mic"]);
+        running(&mut state);
+        state.output_reopened = Some(Instant::now());
+        state.recover(EngineError::OutputStalled { device: None });
+        assert_eq!(state.snapshot.phase, Phase::Starting);
+        let request = state.pending.as_ref().unwrap();
+        assert_eq!(request.restart, Some(RestartReason::DeviceDisconnect));
+        assert!(request.was_processing);
+    }
+
+    #[test]
+    fn missing_device_waits_when_the_start_allows_it() {
+        let (mut state, _notices) = state(&[MISSING]);
+        state.begin(StartRequest::start());
+        state.launch();
+        assert_eq!(state.snapshot.phase, Phase::WaitingForDevices);
+        assert!(state.starting.is_none());
+    }
+
+    #[test]
+    fn lock_pauses_and_unlock_resumes() {
+        let (mut state, _notices) = state(&["Mic"]);
+        running(&mut state);
+        state.session_event(SessionEvent::Locked);
+        assert_eq!(state.snapshot.phase, Phase::Running, "pausing is off");
+
+        state.policy.pause_when_locked = true;
+        state.session_event(SessionEvent::Suspending);
+        assert_eq!(state.snapshot.phase, Phase::Paused);
+        assert_eq!(state.snapshot.status, "Paused for sleep");
+        state.session_event(SessionEvent::Resumed);
+        assert_eq!(state.snapshot.phase, Phase::Paused, "still locked");
+        state.session_event(SessionEvent::Unlocked);
+        assert_eq!(state.snapshot.phase, Phase::Starting);
+        let message = state.pending.as_ref().and_then(|r| r.running_message.clone());
+        assert_eq!(message.as_deref(), Some("Processing audio (restarted after unlock)"));
+    }
+
+    #[test]
+    fn shell_and_session_end_go_to_the_ui() {
+        let (mut state, notices) = state(&["Mic"]);
+        state.session_event(SessionEvent::EndingSession);
+        assert!(matches!(notices.try_recv(), Ok(Notice::Session(SessionEvent::EndingSession))));
+        assert_eq!(state.snapshot.phase, Phase::Stopped);
+    }
+
+    #[test]
+    fn delayed_start_begins_when_due() {
+        let (mut state, _notices) = state(&["Mic"]);
+        state.set_phase(Phase::Delayed(Instant::now() + Duration::from_secs(60)), "");
+        state.check_delayed_start();
+        assert!(matches!(state.snapshot.phase, Phase::Delayed(_)));
+        state.set_phase(Phase::Delayed(Instant::now()), "");
+        state.check_delayed_start();
+        assert_eq!(state.snapshot.phase, Phase::Starting);
+    }
+
+    #[test]
+    fn schedule_acts_on_transitions_only() {
+        let (mut state, notices) = state(&["Mic"]);
+        // Start equal to end covers whole days
+        state.policy.schedule = ScheduleConfig { enabled: true, start: 0, end: 0, days: [true; 7] };
+        state.check_schedule();
+        assert_eq!(state.snapshot.phase, Phase::Starting);
+        assert!(matches!(notices.try_recv(), Ok(Notice::Schedule(true))));
+
+        // A manual stop holds until the next boundary
+        state.stop("Stopped");
+        state.schedule_checked = None;
+        state.check_schedule();
+        assert_eq!(state.snapshot.phase, Phase::Stopped);
+        assert!(notices.try_recv().is_err());
+    }
+
+    #[test]
+    fn saved_device_coming_back_is_switched_to_when_asked() {
+        let (mut state, notices) = state(&["Mic"]);
+        state.snapshot.fallback = Some(DevicePair { input: "USB".to_string(), output: String::new() });
+        assert!(!state.saved_devices_connected());
+
+        state.inputs.push("USB".to_string());
+        assert!(state.saved_devices_connected());
+        state.return_to_saved_devices();
+        assert_eq!(state.snapshot.devices.input, "USB");
+        assert!(state.snapshot.fallback.is_none());
+        assert_eq!(state.snapshot.phase, Phase::Stopped, "a stopped engine stays stopped");
+        assert!(matches!(notices.try_recv(), Ok(Notice::Devices { selected, .. }) if selected.input == "USB"));
+    }
+}
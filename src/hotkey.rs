@@ -0,0 +1,133 @@
+// System-wide shortcut that shows the window from the tray, or hides it when it's visible.
+// Registered with RegisterHotKey on a thread of its own that runs the message loop; presses
+// go into the tray listener's channel, so restoring follows exactly the same path as a tray
+// click. Only Windows has it; elsewhere start() says so.
+use crate::tray::WindowRequest;
+use std::sync::mpsc::Sender;
+
+pub const DEFAULT_HOTKEY: &str = "Ctrl+Alt+S";
+
+// Win32 MOD_* flags
+const MOD_ALT: u32 = 0x1;
+const MOD_CONTROL: u32 = 0x2;
+const MOD_SHIFT: u32 = 0x4;
+const MOD_WIN: u32 = 0x8;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct Hotkey {
+    modifiers: u32,
+    // Virtual-key code
+    key: u32,
+}
+
+impl Hotkey {
+    // "Ctrl+Alt+S", "Shift+Win+F9"; at least one modifier, then a letter, digit or F1-F24.
+    // An empty string means no hotkey.
+    pub fn parse(text: &str) -> Result<Option<Self>, String> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(None);
+        }
+        let mut modifiers = 0;
+        let mut key = None;
+        for part in text.split('+').map(str::trim) {
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => modifiers |= MOD_CONTROL,
+                "alt" => modifiers |= MOD_ALT,
+                "shift" => modifiers |= MOD_SHIFT,
+                "win" | "super" => modifiers |= MOD_WIN,
+                _ if key.is_some() => return Err(format!("More than one key in '{}'", text)),
+                name => key = Some(virtual_key(name).ok_or_else(|| format!("Unknown key '{}'", part))?),
+            }
+        }
+        match key {
+            None => Err("Add a key after the modifiers, e.g. Ctrl+Alt+S".to_string()),
+            Some(_) if modifiers == 0 => Err("Use at least one of Ctrl, Alt, Shift or Win".to_string()),
+            Some(key) => Ok(Some(Self { modifiers, key })),
+        }
+    }
+}
+
+// Letters and digits are their ASCII codes; F1 is 0x70
+fn virtual_key(name: &str) -> Option<u32> {
+    let bytes = name.as_bytes();
+    if bytes.len() == 1 && bytes[0].is_ascii_alphanumeric() {
+        return Some(bytes[0].to_ascii_uppercase() as u32);
+    }
+    match name.strip_prefix('f')?.parse::<u32>() {
+        Ok(n @ 1..=24) => Some(0x70 + n - 1),
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+pub struct HotkeyListener {
+    // Thread running the message loop; told to quit with WM_QUIT
+    #[cfg(windows)]
+    thread_id: Option<u32>,
+}
+
+#[cfg(windows)]
+const HOTKEY_ID: i32 = 1;
+
+#[cfg(windows)]
+impl HotkeyListener {
+    // Fails when another app already owns the combination
+    pub fn start(&mut self, hotkey: Hotkey, requests: Sender<WindowRequest>) -> Result<(), String> {
+        use windows_sys::Win32::System::Threading::GetCurrentThreadId;
+        use windows_sys::Win32::UI::Input::KeyboardAndMouse::{RegisterHotKey, UnregisterHotKey, MOD_NOREPEAT};
+        use windows_sys::Win32::UI::WindowsAndMessaging::{GetMessageW, MSG, WM_HOTKEY};
+
+        self.stop();
+        let (result_tx, result_rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || unsafe {
+            // The hotkey belongs to the thread that registers it, so this thread also gets the messages
+            if RegisterHotKey(0, HOTKEY_ID, hotkey.modifiers | MOD_NOREPEAT, hotkey.key) == 0 {
+                let _ = result_tx.send(Err(std::io::Error::last_os_error()));
+                return;
+            }
+            let _ = result_tx.send(Ok(GetCurrentThreadId()));
+            let mut msg: MSG = std::mem::zeroed();
+            while GetMessageW(&mut msg, 0, 0, 0) > 0 {
+                if msg.message == WM_HOTKEY && requests.send(WindowRequest::Toggle).is_err() {
+                    break;
+                }
+            }
+            UnregisterHotKey(0, HOTKEY_ID);
+        });
+        match result_rx.recv() {
+            Ok(Ok(thread_id)) => {
+                self.thread_id = Some(thread_id);
+                Ok(())
+            }
+            Ok(Err(e)) => Err(format!("Could not register the hotkey, another app may be using it ({})", e)),
+            Err(_) => Err("Hotkey thread stopped unexpectedly".to_string()),
+        }
+    }
+
+    pub fn stop(&mut self) {
+        use windows_sys::Win32::UI::WindowsAndMessaging::{PostThreadMessageW, WM_QUIT};
+
+        if let Some(thread_id) = self.thread_id.take() {
+            unsafe {
+                PostThreadMessageW(thread_id, WM_QUIT, 0, 0);
+            }
+        }
+    }
+}
+
+#[cfg(not(windows))]
+impl HotkeyListener {
+    pub fn start(&mut self, _hotkey: Hotkey, _requests: Sender<WindowRequest>) -> Result<(), String> {
+        Err("Global hotkeys are only available on Windows".to_string())
+    }
+
+    pub fn stop(&mut self) {}
+}
+
+impl Drop for HotkeyListener {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,85 @@
+// Microphone level while processing is stopped, so the mic can be checked before pressing
+// Start. A capture-only stream on a thread of its own, metered like the engine's output but
+// with no processing and no output. Shared-mode capture lets it sit next to the engine on the
+// same device, but it's closed whenever processing starts anyway.
+use cpal::traits::StreamTrait;
+use silentstream_core::audio_engine::{open_capture_stream, AtomicF32, CaptureSource, RNNOISE_FRAME_SIZE};
+use silentstream_core::dsp::{Frame, LevelMeter};
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+// How often the captured samples are metered
+const METER_INTERVAL: Duration = Duration::from_millis(20);
+
+pub struct InputMonitor {
+    // Device last opened, kept after a failed open so it isn't retried every frame
+    device: Option<String>,
+    // Dropping it ends the thread, which closes the stream
+    stop: Option<Sender<()>>,
+    pub rms: Arc<AtomicF32>,
+    pub peak: Arc<AtomicF32>,
+}
+
+impl Default for InputMonitor {
+    fn default() -> Self {
+        Self { device: None, stop: None, rms: Arc::new(AtomicF32::new(0.0)), peak: Arc::new(AtomicF32::new(0.0)) }
+    }
+}
+
+impl InputMonitor {
+    // Opens `device` unless it's already the one monitored (or failed to open)
+    pub fn monitor(&mut self, device: &str) {
+        if self.device.as_deref() == Some(device) {
+            return;
+        }
+        self.stop();
+        self.device = Some(device.to_string());
+        let (stop, stopped) = channel::<()>();
+        let (rms, peak) = (self.rms.clone(), self.peak.clone());
+        let source = CaptureSource { device: device.to_string(), loopback: false };
+        thread::spawn(move || {
+            let host = cpal::default_host();
+            let mut capture = match open_capture_stream(&host, &source, "Input monitor") {
+                Ok(capture) => capture,
+                Err(e) => {
+                    log::warn!("Can't show the level of '{}' while stopped: {}", source.device, e);
+                    return;
+                }
+            };
+            if let Err(e) = capture.stream.play() {
+                log::warn!("Can't show the level of '{}' while stopped: {}", source.device, e);
+                return;
+            }
+            let mut meter = LevelMeter::new();
+            let mut frame: Frame = [0.0; RNNOISE_FRAME_SIZE];
+            let mut filled = 0;
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(METER_INTERVAL) {
+                for sample in capture.samples.pop_iter() {
+                    frame[filled] = sample;
+                    filled += 1;
+                    if filled == frame.len() {
+                        meter.update(&frame);
+                        filled = 0;
+                    }
+                }
+                rms.store(meter.rms);
+                peak.store(meter.peak);
+            }
+            rms.store(0.0);
+            peak.store(0.0);
+        });
+        self.stop = Some(stop);
+    }
+
+    // Returns right away; the thread closes the stream on its own
+    pub fn stop(&mut self) {
+        if self.stop.take().is_some() {
+            log::debug!("Input monitor closed");
+        }
+        self.device = None;
+        self.rms.store(0.0);
+        self.peak.store(0.0);
+    }
+}
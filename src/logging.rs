@@ -0,0 +1,150 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+pub const LOG_FILE: &str = "silentstream.log";
+const MAX_FILE_BYTES: u64 = 2 * 1024 * 1024;
+// silentstream.log plus this many rotated files
+const ROTATED_FILES: usize = 3;
+// Entries kept in memory for the event log window
+const RECENT_CAPACITY: usize = 200;
+
+pub const LEVELS: [LevelFilter; 5] = [
+    LevelFilter::Error,
+    LevelFilter::Warn,
+    LevelFilter::Info,
+    LevelFilter::Debug,
+    LevelFilter::Trace,
+];
+
+pub fn log_dir(config_dir: &Path) -> PathBuf {
+    config_dir.join("logs")
+}
+
+#[derive(Clone)]
+pub struct LogEntry {
+    pub time: String,
+    pub level: Level,
+    pub message: String,
+}
+
+// Fixed-size ring of this app's own log records, oldest dropped first
+static RECENT: Mutex<VecDeque<LogEntry>> = Mutex::new(VecDeque::new());
+
+pub fn recent_entries() -> Vec<LogEntry> {
+    RECENT.lock().map(|r| r.iter().cloned().collect()).unwrap_or_default()
+}
+
+pub fn clear_recent() {
+    if let Ok(mut recent) = RECENT.lock() {
+        recent.clear();
+    }
+}
+
+fn remember(record: &Record) {
+    // Skip chatter from dependencies (cpal, winit, ...)
+    if !record.target().starts_with(env!("CARGO_CRATE_NAME")) {
+        return;
+    }
+    if let Ok(mut recent) = RECENT.lock() {
+        if recent.len() >= RECENT_CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(LogEntry {
+            time: chrono::Local::now().format("%m-%d %H:%M:%S").to_string(),
+            level: record.level(),
+            message: record.args().to_string(),
+        });
+    }
+}
+
+struct FileLogger {
+    // None when the config directory is unknown; entries then only go to memory
+    path: Option<PathBuf>,
+    file: Mutex<Option<File>>,
+}
+
+impl FileLogger {
+    fn rotate(&self, path: &Path, file: &mut Option<File>) {
+        *file = None;
+        let rotated = |n: usize| path.with_file_name(format!("silentstream.{}.log", n));
+        let _ = fs::remove_file(rotated(ROTATED_FILES));
+        for n in (1..ROTATED_FILES).rev() {
+            let _ = fs::rename(rotated(n), rotated(n + 1));
+        }
+        let _ = fs::rename(path, rotated(1));
+    }
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        remember(record);
+
+        let line = format!(
+            "{} [{}] {}: {}\n",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+
+        // Debug builds keep a console, so mirror everything there as well
+        if cfg!(debug_assertions) {
+            eprint!("{}", line);
+        }
+
+        let Some(path) = &self.path else { return };
+        let Ok(mut file) = self.file.lock() else { return };
+        let too_big = file
+            .as_ref()
+            .and_then(|f| f.metadata().ok())
+            .map(|m| m.len() >= MAX_FILE_BYTES)
+            .unwrap_or(false);
+        if too_big {
+            self.rotate(path, &mut file);
+        }
+        if file.is_none() {
+            *file = OpenOptions::new().create(true).append(true).open(path).ok();
+        }
+        if let Some(f) = file.as_mut() {
+            let _ = f.write_all(line.as_bytes());
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            if let Some(f) = file.as_mut() {
+                let _ = f.flush();
+            }
+        }
+    }
+}
+
+// Installs the global logger; without a usable config dir only the in-memory log is kept
+pub fn init(config_dir: Option<PathBuf>, level: LevelFilter) {
+    let path = config_dir.map(|d| log_dir(&d)).map(|dir| {
+        let _ = fs::create_dir_all(&dir);
+        dir.join(LOG_FILE)
+    });
+    let logger = FileLogger {
+        path,
+        file: Mutex::new(None),
+    };
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+pub fn set_level(level: LevelFilter) {
+    log::set_max_level(level);
+}
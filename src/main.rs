@@ -1,122 +1,339 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-mod audio_engine;
+mod automation;
+mod autostart;
+mod batch;
+mod core_audio;
+mod crash;
+mod default_device;
+mod device_filter;
+mod device_picker;
+mod diagnostics;
+mod earcon;
+mod engine_start;
+mod hotkey;
+mod engine_supervisor;
+mod input_monitor;
+mod logging;
+mod metrics;
+mod notifier;
+mod obs;
+mod osd;
+mod placement;
+mod platform;
+mod privacy;
+mod replays;
+mod restart_debounce;
+mod routing_check;
+mod session;
+mod settings;
+mod status_file;
+mod taskbar;
+mod theme;
+mod tray;
+mod updater;
+mod uptime;
 
 use eframe::egui;
-use crate::audio_engine::AudioEngine;
-use std::fs;
-use std::path::PathBuf;
+use silentstream_core::audio_engine::{
+    default_input_name, default_output_name, AudioEngine, CaptureSource, EngineError, EngineStatus, InputChannel, OverflowPolicy, SessionStats,
+    BLOCK_FRAMES, LATENCY_CAPS_MS, RING_BUFFER_SIZE,
+};
+use crate::autostart::{AutostartBackend, AUTOSTART_FLAG, AUTOSTART_LABEL};
+use crate::batch::{loudness_text, BatchDialog, BatchJob, FileState};
+use crate::replays::ReplayFile;
+use silentstream_core::offline_file::{OutputFormat, DEFAULT_CEILING_DB, TARGET_LUFS_RANGE};
+use silentstream_core::Controls;
+use crate::automation::{
+    format_time_of_day, parse_time_of_day, AppWatchConfig, AppWatcher, IdlePauseConfig, ListenerWatcher, ScheduleConfig,
+    TriggerAction, WEEKDAY_LABELS,
+};
+use crate::core_audio::MuteWatcher;
+use crate::default_device::{cable_capture_side, companion_capture_device, DefaultDeviceConfig, DefaultDeviceGuard, DefaultDeviceState};
+use crate::device_filter::VirtualInputFilter;
+use crate::device_picker::device_picker;
+use crate::earcon::Cue;
+use crate::engine_start::StartRequest;
+use crate::engine_supervisor::{EngineSnapshot, EngineSupervisor, Notice, Phase, Policy};
+use silentstream_core::dsp::{
+    BoostConfig, ClickConfig, DeEsserConfig, GateConfig, GateMode, MeterMode, MusicConfig, PlosiveConfig, SuppressionMode, VadPreset,
+    BOOST_RANGE, DE_ESSER_FREQUENCY_RANGE, DE_ESSER_THRESHOLD_RANGE, GATE_ATTACK_RANGE, GATE_ENERGY_FLOOR_RANGE, GATE_HOLD_RANGE, GATE_PRE_ROLL_RANGE, GATE_RELEASE_RANGE,
+    GATE_STEEPNESS_RANGE,
+};
+use crate::hotkey::HotkeyListener;
+use crate::input_monitor::InputMonitor;
+use crate::restart_debounce::{DeviceSelection, RestartDebouncer};
+use crate::metrics::MetricsLogger;
+use crate::status_file::StatusFile;
+use silentstream_core::noise_print::{self, NoisePrintConfig, NoiseProfile};
+use crate::obs::{ObsClient, ObsConfig};
+use crate::notifier::{Category, Notifier};
+use crate::osd::OsdCorner;
+use silentstream_core::pipeline::{ChainConfig, StageKind, STAGE_COUNT};
+use silentstream_core::plugin_host::{self, PluginConfig, PluginStatus};
+use silentstream_core::overload::OverloadPolicy;
+use silentstream_core::resample::ResamplerQuality;
+use crate::routing_check::{RoutingCheck, RoutingReport};
+use crate::session::SessionEvent;
+use crate::settings::{
+    get_config_dir, load_settings, save_settings, DevicePair, DeviceTuning, Settings, STARTUP_DELAY_MAX, VAD_THRESHOLD_MAX,
+};
+use crate::theme::{AnimationMode, Appearance};
+use crate::updater::{Release, UpdateChecker, UpdateEvent};
+use crate::uptime::RestartReason;
+use silentstream_core::vad_histogram::VAD_BUCKETS;
 use std::time::{Duration, Instant};
 use sysinfo::{System, Pid, ProcessRefreshKind};
-use tray_icon::{TrayIcon, TrayIconBuilder, TrayIconEvent, menu::{Menu, MenuItem, MenuEvent}};
-
-// Global tray icon storage to keep it alive
-static TRAY_ICON: std::sync::OnceLock<tray_icon::TrayIcon> = std::sync::OnceLock::new();
-
-// Get config path
-fn get_config_path() -> Option<PathBuf> {
-    if let Some(app_data) = std::env::var_os("APPDATA") {
-        let config_dir = PathBuf::from(app_data).join("SilentStream");
-        Some(config_dir.join("settings.txt"))
-    } else {
-        None
-    }
-}
-
-fn load_settings() -> (Option<String>, Option<String>, f32, bool, bool) {
-    if let Some(path) = get_config_path() {
-        if path.exists() {
-            if let Ok(content) = fs::read_to_string(&path) {
-                let lines: Vec<&str> = content.lines().collect();
-                if lines.len() >= 5 {
-                    let input = if lines[0].is_empty() { None } else { Some(lines[0].to_string()) };
-                    let output = if lines[1].is_empty() { None } else { Some(lines[1].to_string()) };
-                    let threshold = lines[2].parse().unwrap_or(0.1);
-                    let enabled = lines[3] == "true";
-                    let start_with_windows = lines[4] == "true";
-                    return (input, output, threshold, enabled, start_with_windows);
-                } else if lines.len() >= 4 {
-                    let input = if lines[0].is_empty() { None } else { Some(lines[0].to_string()) };
-                    let output = if lines[1].is_empty() { None } else { Some(lines[1].to_string()) };
-                    let threshold = lines[2].parse().unwrap_or(0.1);
-                    let enabled = lines[3] == "true";
-                    return (input, output, threshold, enabled, false);
-                }
-            }
-        }
-    }
-    (None, None, 0.1, true, false)
-}
-
-fn save_settings(input: &str, output: &str, threshold: f32, enabled: bool, start_with_windows: bool) {
-    if let Some(path) = get_config_path() {
-        if let Some(parent) = path.parent() {
-            let _ = fs::create_dir_all(parent);
-        }
-        let content = format!("{}\n{}\n{}\n{}\n{}", input, output, threshold, enabled, start_with_windows);
-        let _ = fs::write(&path, content);
-    }
-}
-
-fn set_autostart(enable: bool) {
-    use winreg::enums::*;
-    use winreg::RegKey;
-    
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    if let Ok(key) = hkcu.open_subkey_with_flags(r"Software\Microsoft\Windows\CurrentVersion\Run", KEY_SET_VALUE | KEY_QUERY_VALUE) {
-        if enable {
-            if let Ok(exe_path) = std::env::current_exe() {
-                let _ = key.set_value("SilentStream", &exe_path.to_string_lossy().to_string());
-            }
-        } else {
-            let _ = key.delete_value("SilentStream");
-        }
-    }
-}
-
-fn is_autostart_enabled() -> bool {
-    use winreg::enums::*;
-    use winreg::RegKey;
-    
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    if let Ok(key) = hkcu.open_subkey(r"Software\Microsoft\Windows\CurrentVersion\Run") {
-        key.get_value::<String, _>("SilentStream").is_ok()
-    } else {
-        false
-    }
-}
 
 struct SilentStreamApp {
-    audio_engine: AudioEngine,
+    // Shared with the supervisor, which starts, stops and recovers it
+    audio_engine: std::sync::Arc<std::sync::Mutex<AudioEngine>>,
     input_devices: Vec<String>,
+    // Chosen channel per multichannel input device
+    input_channels: std::collections::BTreeMap<String, InputChannel>,
+    // Channels of the selected input device, 0 if unknown
+    input_channel_count: usize,
+    // Tuning remembered per input device, and the global tuning used for all other devices.
+    // The live fields (vad_threshold, gate, ...) hold whichever applies to the current device.
+    device_profiles: std::collections::BTreeMap<String, DeviceTuning>,
+    global_tuning: DeviceTuning,
+    // Device whose profile is live; None while the global tuning is
+    tuning_device: Option<String>,
+    // "Using saved settings for ..." shown briefly after a profile is switched in
+    profile_notice: Option<(String, Instant)>,
+    favorites: [Option<DevicePair>; 2],
+    // Tray menu choices (swap, presets, exit), handled on the UI thread
+    tray_requests: std::sync::Arc<tray::TrayRequests>,
+    // Preset ticked in the tray menu; None until it has been synced
+    tray_preset: Option<Option<VadPreset>>,
+    confirm_exit: bool,
+    earcons_enabled: bool,
+    earcon_volume: f32,
+    // Whether the mic was live (processing, not muted or bypassed) last frame; None while stopped
+    earcon_state: Option<bool>,
+    // Capture endpoint the routing check listens on; empty = the cable end matching the output
+    routing_capture: String,
+    routing_check: Option<RoutingCheck>,
+    routing_report: Option<RoutingReport>,
+    // "Exit anyway?" dialog is up, and its "don't ask again" box
+    exit_prompt: bool,
+    exit_dont_ask: bool,
+    // The next close request goes through without asking
+    exit_confirmed: bool,
+    // Runs the engine's lifecycle on its own thread; the UI asks it to start and stop
+    supervisor: EngineSupervisor,
+    // Its snapshot as of this update(): phase, error, uptime, devices and fallback. The fallback
+    // names stay in the settings file until the user picks another device.
+    run: EngineSnapshot,
+    // Selection and policy last handed to the supervisor
+    sent_devices: DevicePair,
+    sent_policy: Policy,
+    // Input, channel and output picks wait here until they settle
+    device_restart: RestartDebouncer,
+    // Taken once per update(); everything that only displays engine state reads this
+    engine_status: EngineStatus,
+    show_error_details: bool,
+    // When the status was last copied with Ctrl+Shift+C or the status line button
+    status_copied: Option<Instant>,
+    return_to_saved_devices: bool,
+    permission_notice_seen: bool,
+    // Set when the input is exact silence and the privacy settings block the microphone
+    microphone_blocked: Option<&'static str>,
+    // Engine start (counters.starts) whose input has already been checked for that
+    privacy_checked_start: u64,
+    // OS default input and output, marked in the device lists
+    default_devices: (Option<String>, Option<String>),
+    default_devices_checked: Option<Instant>,
+    tray_swap_enabled: bool,
     output_devices: Vec<String>,
     selected_input_index: usize,
     selected_output_index: usize,
-    is_processing: bool,
     vad_threshold: f32,
-    noise_suppression_enabled: bool,
+    suppression_mode: SuppressionMode,
+    gate: GateConfig,
     status_message: String,
     first_frame: bool,
     show_settings: bool,
     start_with_windows: bool,
+    autostart_backend: AutostartBackend,
+    // Why the last autostart change failed
+    autostart_error: Option<String>,
+    // Shown once after the Run key entry was pointed at this executable
+    autostart_repaired: bool,
+    start_processing: bool,
+    restore_state: bool,
+    // Processing was stopped with the Stop button and not started since
+    stopped_by_user: bool,
+    // Bypass switched on by an OBS or app trigger; holds until processing is started by hand,
+    // by a trigger, or the suppression mode is picked again
+    trigger_bypass: bool,
+    startup_delay: u32,
     show_cpu_usage: bool,
     cpu_usage: f32,
     last_cpu_check: Instant,
     sysinfo: System,
     current_pid: Pid,
-    start_time: Instant,
+    // Phase of the breathing orb, which only advances while it breathes
+    breath_time: f32,
+    last_breath_frame: Instant,
+    // Last voice or pointer activity; the orb settles a few seconds after it
+    last_activity: Instant,
     smoothed_volume: f32,
+    meter_mode: MeterMode,
+    input_monitor_enabled: bool,
+    input_monitor: InputMonitor,
+    animations: AnimationMode,
+    appearance: Appearance,
+    osd_enabled: bool,
+    osd_corner: OsdCorner,
+    // Toasts and sound cues go through here, for quiet hours and the fullscreen check
+    notifier: Notifier,
+    quiet_start_text: String,
+    quiet_end_text: String,
+    // Mute and bypass state the OSD last saw, to spot changes
+    osd_state: Option<(bool, bool)>,
     is_minimized_to_tray: bool,
     last_restore_time: Option<Instant>,
+    // Where the window was before it was hidden to the tray
+    saved_placement: Option<placement::WindowPlacement>,
 
     tray_listener_started: bool,
     restore_requested: std::sync::Arc<std::sync::atomic::AtomicBool>,
     window_hwnd: std::sync::Arc<std::sync::Mutex<Option<isize>>>,
     // Shared flag so tray listener thread knows whether app is in tray mode
     in_tray_flag: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    // Set by the tray listener when the hotkey asks to hide a visible window
+    hide_requested: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    // Feeds the tray listener; the hotkey thread gets a clone
+    window_requests: Option<std::sync::mpsc::Sender<tray::WindowRequest>>,
+    window_hotkey: String,
+    hotkey_listener: HotkeyListener,
+    hotkey_error: Option<String>,
+    // None where there is no tray (other platforms, or creation failed); hiding then
+    // minimizes the window normally
+    tray: Option<tray::Tray>,
+    tray_tooltip: String,
+    tray_alert: tray::TrayAlert,
+    // Whether the tray currently shows the alert icon
+    tray_alert_shown: bool,
+    // Created on the first frame, once the window exists
+    taskbar_overlay: Option<taskbar::TaskbarOverlay>,
+
+    mute_watcher: Option<MuteWatcher>,
+
+    pause_when_locked: bool,
+    aec_enabled: bool,
+    // Output device used as the echo reference; empty = default output
+    aec_reference: String,
+    de_esser: DeEsserConfig,
+    dsp_chain: ChainConfig,
+    plugin_config: PluginConfig,
+    // Edited freely; becomes plugin_config.path on Load
+    plugin_path_text: String,
+    // Scanned the first time the plugin settings are shown
+    installed_plugins: Option<Vec<std::path::PathBuf>>,
+    monitor_enabled: bool,
+    // Empty = default output
+    monitor_device: String,
+    monitor_gain: f32,
+    mix_enabled: bool,
+    mix_source: CaptureSource,
+    mix_gain: f32,
+    plosive: PlosiveConfig,
+    click: ClickConfig,
+    boost: BoostConfig,
+    noise_print: NoisePrintConfig,
+    // Learned noise prints by input device, kept in their own file
+    noise_prints: std::collections::BTreeMap<String, NoiseProfile>,
+    music: MusicConfig,
+    resampler_quality: ResamplerQuality,
+    block_frames: usize,
+    overflow_policy: OverflowPolicy,
+    latency_cap_ms: u32,
+    overload_policy: OverloadPolicy,
+
+    obs_config: ObsConfig,
+    obs_client: ObsClient,
+    app_watch_config: AppWatchConfig,
+    app_watcher: AppWatcher,
+    // Text buffer for the watched app list (one executable per line)
+    app_watch_text: String,
+    virtual_input_filter: VirtualInputFilter,
+    // Text buffer for the virtual input patterns (one per line)
+    virtual_input_text: String,
+    schedule_config: ScheduleConfig,
+    idle_pause_config: IdlePauseConfig,
+    listener_watcher: ListenerWatcher,
+    // Processing is paused because no app records from the output
+    idle_paused: bool,
+    // Text buffers for the schedule times, parsed when editing ends
+    schedule_start_text: String,
+    schedule_end_text: String,
+
+    default_device_config: DefaultDeviceConfig,
+    default_device_guard: DefaultDeviceGuard,
+
+    // All-time totals up to `stats_flushed`, the session snapshot they were last merged from
+    lifetime_stats: SessionStats,
+    stats_flushed: SessionStats,
+    last_stats_save: Instant,
+
+    metrics_enabled: bool,
+    metrics_logger: MetricsLogger,
+    status_file_enabled: bool,
+    status_file_path: String,
+    status_file: StatusFile,
+    log_level: log::LevelFilter,
+
+    // Report text awaiting the user's review before it is written to disk
+    diagnostic_report: Option<String>,
+    show_event_log: bool,
+    show_batch: bool,
+    batch: BatchDialog,
+    // Replay recordings on disk, newest first; refreshed when one starts, stops or is deleted
+    replay_files: Vec<ReplayFile>,
+    // Underrun total at the last summary, logged at most once a minute
+    underruns_reported: u64,
+    last_underrun_check: Instant,
+    // Launched with --minimized (e.g. relaunch after a crash): go to the tray on the first frame
+    start_minimized: bool,
+
+    update_check_enabled: bool,
+    last_update_check: i64,
+    update_checker: UpdateChecker,
+    // Newer release found by the checker; cleared when the banner is dismissed
+    available_update: Option<Release>,
+
+    last_settings_check: Instant,
+    // Externally edited settings that need an audio restart, waiting for the user's go-ahead
+    pending_reload: Option<Settings>,
 }
 
 
+// Frame interval while something on screen is moving, and the slow tick otherwise
+const ANIMATION_REPAINT: Duration = Duration::from_millis(16);
+const IDLE_REPAINT: Duration = Duration::from_millis(250);
+// The breathing orb moves under a pixel per frame at 10 fps, and holds still this long after
+// the last voice or pointer activity so an idle window drops back to IDLE_REPAINT
+const BREATHING_REPAINT: Duration = Duration::from_millis(100);
+const BREATHING_AFTER_ACTIVITY: Duration = Duration::from_secs(5);
+// Refresh rate of the live buffer gauges while the Diagnostics section is open
+const DIAGNOSTICS_REPAINT: Duration = Duration::from_millis(100);
+// Below this the volume orb is invisible
+const VOLUME_EPSILON: f32 = 0.001;
+// Arrow-key step of the VAD threshold slider
+const VAD_THRESHOLD_STEP: f32 = 0.01;
+// How long the "using saved settings" notice stays up after a device switch
+const PROFILE_NOTICE_DURATION: Duration = Duration::from_secs(5);
+// How often the settings file is checked for external edits
+const SETTINGS_POLL: Duration = Duration::from_secs(3);
+// Exact-zero input for this long after a start triggers the microphone privacy check
+const SILENT_INPUT_SECS: u64 = 3;
+// How often the OS default devices marked in the device lists are looked up again
+const DEFAULT_DEVICES_REFRESH: Duration = Duration::from_secs(5);
+// How long "Copied" stays next to the status line
+const COPIED_FLASH: Duration = Duration::from_millis(1500);
+
 // Load Icon Helper
 fn load_app_icon() -> (Vec<u8>, u32, u32) {
     let image = image::load_from_memory(include_bytes!("../icon_256.png"))
@@ -127,23 +344,31 @@ fn load_app_icon() -> (Vec<u8>, u32, u32) {
     (rgba, width, height)
 }
 
-impl Default for SilentStreamApp {
-    fn default() -> Self {
+impl SilentStreamApp {
+    fn new(ctx: &egui::Context) -> Self {
         let engine = AudioEngine::new();
         let inputs = engine.get_input_devices();
         let outputs = engine.get_output_devices();
         
-        let (saved_input, saved_output, threshold, enabled, _start_win) = load_settings();
+        let settings = load_settings();
+        logging::set_level(settings.log_level);
+        engine.plugin.configure(&settings.plugin);
         
-        let selected_input_index = saved_input.as_ref()
-            .and_then(|name| inputs.iter().position(|d| d == name))
-            .unwrap_or(0);
-            
-        let selected_output_index = saved_output.as_ref()
-            .and_then(|name| outputs.iter().position(|d| d == name))
-            .unwrap_or(0);
+        let (selected_input_index, missing_input) =
+            resolve_device(&inputs, settings.input_device.as_deref(), default_input_name());
+        let (selected_output_index, missing_output) =
+            resolve_device(&outputs, settings.output_device.as_deref(), default_output_name());
+        let fallback = (missing_input || missing_output).then(|| DevicePair {
+            input: if missing_input { settings.input_device.clone().unwrap_or_default() } else { String::new() },
+            output: if missing_output { settings.output_device.clone().unwrap_or_default() } else { String::new() },
+        });
         
-        let start_with_windows = is_autostart_enabled();
+        let start_with_windows = autostart::is_enabled(settings.autostart_backend);
+        let autostart_repaired = start_with_windows
+            && autostart::repair(settings.autostart_backend).unwrap_or_else(|e| {
+                log::warn!("Could not update the autostart entry: {}", e);
+                false
+            });
         
         let mut sysinfo = System::new();
         sysinfo.refresh_cpu();
@@ -152,81 +377,206 @@ impl Default for SilentStreamApp {
         
         let restore_flag = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
         
-        // Setup Tray Icon
-        let tray_menu = Menu::new();
-        let tray_open = MenuItem::new("Open SilentStream", true, None);
-        let _ = tray_menu.append(&tray_open);
-        
-        // Load icon for tray
         let (icon_rgba, icon_width, icon_height) = load_app_icon();
-        
-        if let Ok(icon) = tray_icon::Icon::from_rgba(icon_rgba, icon_width, icon_height) {
-             let _ = TrayIconBuilder::new()
-                .with_menu(Box::new(tray_menu))
-                .with_tooltip("SilentStream")
-                .with_icon(icon)
-                .build()
-                .map(|t| { let _ = TRAY_ICON.set(t); });
-        }
+        let tray = tray::Tray::new(icon_rgba, icon_width, icon_height);
         
         // Tray Event Loop in a separate thread to ensure we catch events?
         // No, tray-icon uses a channel. We just need to make sure we poll it reliably.
         // We can however use the channel info to set the atomic flag which is checked every frame.
 
-        Self {
+        // With no devices yet, the saved names are what the supervisor waits for
+        let name = |list: &[String], i: usize, saved: &Option<String>| list.get(i).or(saved.as_ref()).cloned().unwrap_or_default();
+        let devices = DevicePair {
+            input: name(&inputs, selected_input_index, &settings.input_device),
+            output: name(&outputs, selected_output_index, &settings.output_device),
+        };
+        let engine = std::sync::Arc::new(std::sync::Mutex::new(engine));
+        let supervisor = EngineSupervisor::start(engine.clone(), (inputs.clone(), outputs.clone()), devices, fallback, ctx);
+
+        let mut app = Self {
             audio_engine: engine,
             input_devices: inputs,
+            input_channels: settings.input_channels.clone(),
+            input_channel_count: 0,
+            device_profiles: settings.device_profiles.clone(),
+            global_tuning: DeviceTuning::from_settings(&settings),
+            tuning_device: None,
+            profile_notice: None,
+            favorites: settings.favorites.clone(),
+            tray_requests: std::sync::Arc::new(tray::TrayRequests::default()),
+            tray_preset: None,
+            confirm_exit: settings.confirm_exit,
+            earcons_enabled: settings.earcons_enabled,
+            earcon_volume: settings.earcon_volume,
+            earcon_state: None,
+            routing_capture: String::new(),
+            routing_check: None,
+            routing_report: None,
+            exit_prompt: false,
+            exit_dont_ask: false,
+            exit_confirmed: false,
+            run: supervisor.snapshot(),
+            supervisor,
+            sent_devices: DevicePair::default(),
+            sent_policy: Policy::default(),
+            device_restart: RestartDebouncer::default(),
+            engine_status: EngineStatus::default(),
+            show_error_details: false,
+            status_copied: None,
+            return_to_saved_devices: settings.return_to_saved_devices,
+            permission_notice_seen: settings.permission_notice_seen,
+            microphone_blocked: None,
+            privacy_checked_start: 0,
+            default_devices: (None, None),
+            default_devices_checked: None,
+            tray_swap_enabled: false,
             output_devices: outputs,
             selected_input_index,
             selected_output_index,
-            is_processing: false,
-            vad_threshold: threshold,
-            noise_suppression_enabled: enabled,
+            vad_threshold: settings.vad_threshold,
+            suppression_mode: settings.suppression_mode,
+            gate: settings.gate,
             status_message: "Starting...".to_string(),
             first_frame: true,
             show_settings: false,
             start_with_windows,
+            autostart_backend: settings.autostart_backend,
+            autostart_error: None,
+            autostart_repaired,
+            start_processing: settings.start_processing,
+            restore_state: settings.restore_state,
+            stopped_by_user: settings.last_stopped,
+            trigger_bypass: settings.restore_state && settings.last_bypassed,
+            startup_delay: settings.startup_delay,
             show_cpu_usage: false,
             cpu_usage: 0.0,
             last_cpu_check: Instant::now(),
             sysinfo,
             current_pid,
-            start_time: Instant::now(),
+            breath_time: 0.0,
+            last_breath_frame: Instant::now(),
+            last_activity: Instant::now(),
             smoothed_volume: 0.0,
+            meter_mode: settings.meter_mode,
+            input_monitor_enabled: settings.input_monitor,
+            input_monitor: InputMonitor::default(),
+            animations: settings.animations,
+            appearance: settings.appearance,
+            osd_enabled: settings.osd_enabled,
+            osd_corner: settings.osd_corner,
+            notifier: Notifier::new(settings.notifications.clone()),
+            quiet_start_text: format_time_of_day(settings.notifications.quiet_start),
+            quiet_end_text: format_time_of_day(settings.notifications.quiet_end),
+            osd_state: None,
             is_minimized_to_tray: false,
             last_restore_time: None,
+            saved_placement: None,
             tray_listener_started: false,
             restore_requested: restore_flag,
             window_hwnd: std::sync::Arc::new(std::sync::Mutex::new(None)),
             in_tray_flag: std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false)),
-        }
+            hide_requested: std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            window_requests: None,
+            window_hotkey: settings.window_hotkey.clone(),
+            hotkey_listener: HotkeyListener::default(),
+            hotkey_error: None,
+            tray,
+            tray_tooltip: "SilentStream".to_string(),
+            tray_alert: tray::TrayAlert::default(),
+            tray_alert_shown: false,
+            taskbar_overlay: None,
+            mute_watcher: None,
+            pause_when_locked: settings.pause_when_locked,
+            aec_enabled: settings.aec_enabled,
+            aec_reference: settings.aec_reference.clone(),
+            de_esser: settings.de_esser,
+            dsp_chain: settings.dsp_chain,
+            plugin_config: settings.plugin.clone(),
+            plugin_path_text: settings.plugin.path.clone(),
+            installed_plugins: None,
+            monitor_enabled: settings.monitor_enabled,
+            monitor_device: settings.monitor_device.clone(),
+            monitor_gain: settings.monitor_gain,
+            mix_enabled: settings.mix_enabled,
+            mix_source: settings.mix_source.clone(),
+            mix_gain: settings.mix_gain,
+            plosive: settings.plosive,
+            click: settings.click,
+            boost: settings.boost,
+            noise_print: settings.noise_print,
+            noise_prints: get_config_dir().map(|dir| noise_print::load_prints(&dir)).unwrap_or_default(),
+            music: settings.music,
+            resampler_quality: settings.resampler_quality,
+            block_frames: settings.block_frames,
+            overflow_policy: settings.overflow_policy,
+            latency_cap_ms: settings.latency_cap_ms,
+            overload_policy: settings.overload_policy,
+            obs_config: settings.obs,
+            obs_client: ObsClient::new(),
+            app_watch_text: settings.app_watch.apps.join("\n"),
+            virtual_input_text: settings.virtual_input_filter.patterns.join("\n"),
+            virtual_input_filter: settings.virtual_input_filter,
+            app_watch_config: settings.app_watch,
+            app_watcher: AppWatcher::new(),
+            schedule_start_text: format_time_of_day(settings.schedule.start),
+            schedule_end_text: format_time_of_day(settings.schedule.end),
+            schedule_config: settings.schedule,
+            idle_pause_config: settings.idle_pause,
+            listener_watcher: ListenerWatcher::new(),
+            idle_paused: false,
+            default_device_config: settings.default_device,
+            default_device_guard: DefaultDeviceGuard::new(),
+            lifetime_stats: settings.lifetime_stats,
+            stats_flushed: SessionStats::default(),
+            last_stats_save: Instant::now(),
+            metrics_enabled: settings.metrics_enabled,
+            metrics_logger: MetricsLogger::new(),
+            status_file_enabled: settings.status_file_enabled,
+            status_file_path: settings.status_file_path.clone(),
+            status_file: StatusFile::default(),
+            log_level: settings.log_level,
+            diagnostic_report: None,
+            show_event_log: false,
+            show_batch: false,
+            batch: BatchDialog::new(settings.vad_threshold),
+            replay_files: get_config_dir().map(|dir| replays::list(&dir)).unwrap_or_default(),
+            underruns_reported: 0,
+            last_underrun_check: Instant::now(),
+            start_minimized: std::env::args().any(|a| a == "--minimized"),
+            update_check_enabled: settings.update_check_enabled,
+            last_update_check: settings.last_update_check,
+            update_checker: UpdateChecker::new(),
+            available_update: None,
+            last_settings_check: Instant::now(),
+            pending_reload: None,
+        };
+        // Starts the supervisor makes by itself find the engine set up already
+        app.configure_engine();
+        app.sync_supervisor();
+        app
     }
-}
 
-impl SilentStreamApp {
     fn draw_animated_background(&mut self, ui: &egui::Ui) {
         let rect = ui.max_rect();
         let painter = ui.painter();
-        let time = self.start_time.elapsed().as_secs_f32();
-        
-        // Get volume for reactivity
-        let current_vol = if let Ok(vol) = self.audio_engine.current_volume.lock() {
-            *vol * 5.0 // Gain up a bit for visualization
-        } else { 
-            0.0 
-        };
+        let now = Instant::now();
+        if self.is_breathing() {
+            // Capped so the first frame after a pause doesn't jump
+            self.breath_time += (now - self.last_breath_frame).as_secs_f32().min(BREATHING_REPAINT.as_secs_f32());
+        }
+        self.last_breath_frame = now;
         
-        // Smooth the volume (decay)
-        self.smoothed_volume = self.smoothed_volume * 0.9 + current_vol * 0.1;
+        // The engine already applies meter ballistics, so the level is used as is
+        self.smoothed_volume = self.output_level();
         
         // Pulse base
-        let pulse = (time * 0.5).sin() * 0.5 + 0.5; 
+        let pulse = (self.breath_time * 0.5).sin() * 0.5 + 0.5; 
         
         // Colors: Dark Purple / Blue theme
         // Center glow linked to volume
         
         // Orb 1: Breathing background orb - Purple Gradient (Circles)
-        {
+        if self.effective_animations() == AnimationMode::On {
             let center = egui::pos2(rect.right() - rect.width() * 0.2, rect.top() + rect.height() * 0.3);
             // Make base radius reactive to volume too, but subtler
             let reactive_scale = 1.0 + (self.smoothed_volume * 0.5); 
@@ -245,7 +595,7 @@ impl SilentStreamApp {
         }
         
         // Orb 2: Volume Reactive Orb - Bright Violet Gradient
-        if self.smoothed_volume > 0.001 {
+        if self.smoothed_volume > VOLUME_EPSILON {
             let center = egui::pos2(rect.center().x, rect.bottom() - 60.0);
             let radius = 80.0 + (self.smoothed_volume * 400.0).clamp(0.0, 300.0);
             let alpha_base = (self.smoothed_volume * 255.0).clamp(0.0, 255.0);
@@ -266,7 +616,67 @@ impl SilentStreamApp {
         }
     }
     
+    // Level for the orb, scaled up a bit for visualization: the output while processing, the
+    // microphone's input monitor while stopped
+    fn output_level(&self) -> f32 {
+        let (rms, peak) = if self.run.is_processing() {
+            (self.engine_status.rms, self.engine_status.peak)
+        } else {
+            (self.input_monitor.rms.load(), self.input_monitor.peak.load())
+        };
+        match self.meter_mode {
+            MeterMode::Rms => rms * 5.0,
+            MeterMode::Peak => peak * 1.5,
+        }
+    }
+
+    // Runs on every update(); the monitor only stays open while the window is on screen
+    fn sync_input_monitor(&mut self, ctx: &egui::Context) {
+        let minimized = ctx.input(|i| i.viewport().minimized).unwrap_or(false);
+        let wanted = self.input_monitor_enabled
+            && !self.is_minimized_to_tray
+            && !minimized
+            && !self.is_active()
+            && self.run.phase != Phase::WaitingForDevices;
+        match self.input_devices.get(self.selected_input_index) {
+            Some(device) if wanted => self.input_monitor.monitor(device),
+            _ => self.input_monitor.stop(),
+        }
+    }
+
+    // High contrast never paints behind the cards
+    fn effective_animations(&self) -> AnimationMode {
+        if self.appearance == Appearance::HighContrast { AnimationMode::Off } else { self.animations }
+    }
+
+    fn needs_animation(&self, ctx: &egui::Context) -> bool {
+        let interacting = ctx.input(|i| i.pointer.any_down()) || ctx.is_using_pointer();
+        if self.effective_animations() == AnimationMode::Off {
+            return interacting;
+        }
+        self.smoothed_volume > VOLUME_EPSILON || self.output_level() > VOLUME_EPSILON || interacting
+    }
+
+    fn is_breathing(&self) -> bool {
+        self.effective_animations() == AnimationMode::On && self.last_activity.elapsed() < BREATHING_AFTER_ACTIVITY
+    }
+
+    fn repaint_interval(&mut self, ctx: &egui::Context) -> Duration {
+        if self.needs_animation(ctx) {
+            self.last_activity = Instant::now();
+            ANIMATION_REPAINT
+        } else if self.is_breathing() {
+            BREATHING_REPAINT
+        } else {
+            IDLE_REPAINT
+        }
+    }
+
     fn apply_custom_theme(&self, ctx: &egui::Context) {
+        if self.appearance == Appearance::HighContrast {
+            self.apply_high_contrast_theme(ctx);
+            return;
+        }
         let mut visuals = egui::Visuals::dark();
         
         // Darker theme for background integration
@@ -285,56 +695,2786 @@ impl SilentStreamApp {
         visuals.selection.bg_fill = accent_purple;
         visuals.selection.stroke = egui::Stroke::new(1.0, accent_purple);
         
-        ctx.set_visuals(visuals);
+        let mut style = (*ctx.style()).clone();
+        style.visuals = visuals;
+        style.text_styles = egui::Style::default().text_styles;
+        ctx.set_style(style);
+    }
+
+    // White on black (21:1), opaque fills, 2 px outlines and text a fifth larger
+    fn apply_high_contrast_theme(&self, ctx: &egui::Context) {
+        let mut visuals = egui::Visuals::dark();
+        let accent = egui::Color32::from_rgb(76, 29, 149); // White text on it is above 7:1
+        let outline = egui::Stroke::new(2.0, egui::Color32::WHITE);
+
+        visuals.override_text_color = Some(egui::Color32::WHITE);
+        visuals.panel_fill = egui::Color32::BLACK;
+        visuals.window_fill = egui::Color32::BLACK;
+        visuals.window_stroke = outline;
+        visuals.extreme_bg_color = egui::Color32::BLACK;
+        visuals.faint_bg_color = egui::Color32::BLACK;
+
+        for widget in [
+            &mut visuals.widgets.noninteractive,
+            &mut visuals.widgets.inactive,
+            &mut visuals.widgets.hovered,
+            &mut visuals.widgets.active,
+            &mut visuals.widgets.open,
+        ] {
+            widget.bg_stroke = outline;
+            widget.fg_stroke = outline;
+        }
+        visuals.widgets.noninteractive.bg_fill = egui::Color32::BLACK;
+        visuals.widgets.inactive.bg_fill = egui::Color32::BLACK;
+        visuals.widgets.inactive.weak_bg_fill = egui::Color32::BLACK;
+        visuals.widgets.hovered.bg_fill = egui::Color32::from_gray(40);
+        visuals.widgets.hovered.weak_bg_fill = egui::Color32::from_gray(40);
+        visuals.widgets.active.bg_fill = accent;
+        visuals.widgets.active.weak_bg_fill = accent;
+        visuals.widgets.open.bg_fill = accent;
+        visuals.widgets.open.weak_bg_fill = accent;
+        visuals.selection.bg_fill = accent;
+        visuals.selection.stroke = egui::Stroke::new(2.0, egui::Color32::YELLOW);
+
+        let mut style = (*ctx.style()).clone();
+        style.visuals = visuals;
+        style.text_styles = egui::Style::default().text_styles;
+        for font in style.text_styles.values_mut() {
+            font.size *= 1.2;
+        }
+        ctx.set_style(style);
     }
     
+    fn current_settings(&self) -> Settings {
+        // Keep the saved devices while they're missing rather than forgetting them
+        let (input_device, output_device) = match self.run.phase {
+            Phase::WaitingForDevices => (
+                Some(self.run.devices.input.clone()).filter(|name| !name.is_empty()),
+                Some(self.run.devices.output.clone()).filter(|name| !name.is_empty()),
+            ),
+            _ => {
+                let fallback = self.run.fallback.clone().unwrap_or_default();
+                let saved_or = |saved: String, current: Option<&String>| {
+                    if saved.is_empty() { current.cloned() } else { Some(saved) }
+                };
+                (
+                    saved_or(fallback.input, self.input_devices.get(self.selected_input_index)),
+                    saved_or(fallback.output, self.output_devices.get(self.selected_output_index)),
+                )
+            }
+        };
+        let mut settings = Settings {
+            input_device,
+            output_device,
+            vad_threshold: self.vad_threshold,
+            suppression_mode: self.suppression_mode,
+            gate: self.gate,
+            start_with_windows: self.start_with_windows,
+            autostart_backend: self.autostart_backend,
+            start_processing: self.start_processing,
+            restore_state: self.restore_state,
+            last_stopped: self.stopped_by_user,
+            last_bypassed: self.trigger_bypass,
+            startup_delay: self.startup_delay,
+            window_hotkey: self.window_hotkey.clone(),
+            obs: self.obs_config.clone(),
+            app_watch: self.app_watch_config.clone(),
+            schedule: self.schedule_config.clone(),
+            idle_pause: self.idle_pause_config.clone(),
+            default_device: self.default_device_config.clone(),
+            lifetime_stats: self.total_lifetime_stats(),
+            metrics_enabled: self.metrics_enabled,
+            status_file_enabled: self.status_file_enabled,
+            status_file_path: self.status_file_path.clone(),
+            log_level: self.log_level,
+            update_check_enabled: self.update_check_enabled,
+            pause_when_locked: self.pause_when_locked,
+            animations: self.animations,
+            appearance: self.appearance,
+            osd_enabled: self.osd_enabled,
+            osd_corner: self.osd_corner,
+            notifications: self.notifier.policy.clone(),
+            meter_mode: self.meter_mode,
+            input_monitor: self.input_monitor_enabled,
+            last_update_check: self.last_update_check,
+            aec_enabled: self.aec_enabled,
+            aec_reference: self.aec_reference.clone(),
+            de_esser: self.de_esser,
+            plosive: self.plosive,
+            click: self.click,
+            boost: self.boost,
+            noise_print: self.noise_print,
+            music: self.music,
+            dsp_chain: self.dsp_chain,
+            plugin: self.plugin_config.clone(),
+            resampler_quality: self.resampler_quality,
+            block_frames: self.block_frames,
+            overflow_policy: self.overflow_policy,
+            latency_cap_ms: self.latency_cap_ms,
+            overload_policy: self.overload_policy,
+            input_channels: self.input_channels.clone(),
+            monitor_enabled: self.monitor_enabled,
+            monitor_device: self.monitor_device.clone(),
+            monitor_gain: self.monitor_gain,
+            mix_enabled: self.mix_enabled,
+            mix_source: self.mix_source.clone(),
+            mix_gain: self.mix_gain,
+            device_profiles: self.device_profiles.clone(),
+            favorites: self.favorites.clone(),
+            return_to_saved_devices: self.return_to_saved_devices,
+            permission_notice_seen: self.permission_notice_seen,
+            virtual_input_filter: self.virtual_input_filter.clone(),
+            confirm_exit: self.confirm_exit,
+            earcons_enabled: self.earcons_enabled,
+            earcon_volume: self.earcon_volume,
+        };
+        if let Some(device) = &self.tuning_device {
+            settings.device_profiles.insert(device.clone(), self.live_tuning());
+            self.global_tuning.apply_to(&mut settings);
+        }
+        settings
+    }
+
+    fn live_tuning(&self) -> DeviceTuning {
+        DeviceTuning {
+            vad_threshold: self.vad_threshold,
+            gate: self.gate,
+            de_esser: self.de_esser,
+            plosive: self.plosive,
+            click: self.click,
+            boost: self.boost,
+        }
+    }
+
+    fn set_live_tuning(&mut self, tuning: DeviceTuning) {
+        self.vad_threshold = tuning.vad_threshold;
+        self.engine().set_threshold(self.vad_threshold);
+        self.gate = tuning.gate;
+        self.apply_gate();
+        self.de_esser = tuning.de_esser;
+        self.apply_de_esser();
+        self.plosive = tuning.plosive;
+        self.apply_plosive();
+        self.click = tuning.click;
+        self.apply_click();
+        self.boost = tuning.boost;
+        self.apply_boost();
+    }
+
+    // Swaps in the selected input's remembered tuning, or the global one, after the input
+    // device may have changed
+    fn sync_device_profile(&mut self) {
+        let device = self.input_devices.get(self.selected_input_index).cloned();
+        let wanted = device.filter(|d| self.device_profiles.contains_key(d));
+        if wanted == self.tuning_device {
+            return;
+        }
+        let live = self.live_tuning();
+        match self.tuning_device.take() {
+            Some(previous) => {
+                self.device_profiles.insert(previous, live);
+            }
+            None => self.global_tuning = live,
+        }
+        let next = wanted.as_ref().and_then(|d| self.device_profiles.get(d)).copied().unwrap_or(self.global_tuning);
+        self.set_live_tuning(next);
+        if let Some(device) = &wanted {
+            log::info!("Using saved settings for '{}'", device);
+            self.profile_notice = Some((format!("Using saved settings for {}", device), Instant::now()));
+        }
+        self.tuning_device = wanted;
+    }
+
+    // Starts or stops remembering the current tuning for the selected input device
+    fn set_device_profile(&mut self, remember: bool) {
+        let Some(device) = self.input_devices.get(self.selected_input_index).cloned() else { return };
+        if remember {
+            self.device_profiles.insert(device.clone(), self.live_tuning());
+            self.global_tuning = self.live_tuning();
+            self.tuning_device = Some(device);
+        } else {
+            self.device_profiles.remove(&device);
+            self.tuning_device = None;
+            self.set_live_tuning(self.global_tuning);
+        }
+        self.save_current_settings();
+    }
+
     fn save_current_settings(&self) {
-        let input = self.input_devices.get(self.selected_input_index).map(|s| s.as_str()).unwrap_or("");
-        let output = self.output_devices.get(self.selected_output_index).map(|s| s.as_str()).unwrap_or("");
-        save_settings(input, output, self.vad_threshold, self.noise_suppression_enabled, self.start_with_windows);
+        save_settings(&self.current_settings());
+    }
+
+    fn session_stats(&self) -> SessionStats {
+        self.engine().stats.lock().map(|s| *s).unwrap_or_default()
+    }
+
+    fn total_lifetime_stats(&self) -> SessionStats {
+        self.lifetime_stats.add(&self.session_stats().since(&self.stats_flushed))
+    }
+
+    fn flush_lifetime_stats(&mut self) {
+        let session = self.session_stats();
+        self.lifetime_stats = self.lifetime_stats.add(&session.since(&self.stats_flushed));
+        self.stats_flushed = session;
+    }
+
+    fn save_stats_periodically(&mut self) {
+        if self.run.is_processing() && self.last_stats_save.elapsed() > Duration::from_secs(60) {
+            self.last_stats_save = Instant::now();
+            self.save_current_settings();
+        }
     }
     
     fn auto_start(&mut self) {
+        self.start_engine(StartRequest::start());
+    }
+
+    // The supervisor opens the devices; its snapshot says how it went
+    fn start_engine(&mut self, request: StartRequest) {
+        self.configure_engine();
+        self.input_monitor.stop();
+        self.supervisor.start_processing(self.start_devices(), request);
+        self.sync_run();
+    }
+
+    // Hands every setting to the engine, so any start finds it set up
+    fn configure_engine(&mut self) {
+        self.apply_suppression_mode();
+        self.apply_echo_reference();
+        self.apply_gate();
+        self.apply_de_esser();
+        self.apply_plosive();
+        self.apply_click();
+        self.apply_boost();
+        self.apply_noise_print();
+        self.apply_music();
+        self.apply_chain();
+        self.sync_device_profile();
+        self.apply_input_channel();
+        self.apply_monitor();
+        self.apply_mix();
+        self.apply_latency_cap();
+        self.apply_overload_policy();
+        let mut engine = self.engine();
+        engine.set_resampler_quality(self.resampler_quality);
+        engine.set_block_frames(self.block_frames);
+        engine.set_overflow_policy(self.overflow_policy);
+        engine.set_threshold(self.vad_threshold);
+    }
+
+    // What a start opens: the selection, or the names the supervisor already has while the
+    // device lists are empty
+    fn start_devices(&self) -> DevicePair {
         if self.input_devices.is_empty() || self.output_devices.is_empty() {
-            self.status_message = "No audio devices found".to_string();
+            self.run.devices.clone()
+        } else {
+            self.current_pair()
+        }
+    }
+
+    fn engine(&self) -> std::sync::MutexGuard<'_, AudioEngine> {
+        self.audio_engine.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    // Running, or on its way there
+    fn is_active(&self) -> bool {
+        self.run.is_active()
+    }
+
+    // The status line shows the category; the details and a suggestion expand on click
+    fn error_status(&self, error: &EngineError) -> String {
+        if let EngineError::DeviceInUse { device: Some(device), .. } = error {
+            let users = device_users(device);
+            if !users.is_empty() {
+                let what = if self.input_devices.get(self.selected_input_index) == Some(device) { "Microphone" } else { "Output device" };
+                return format!("{} is in use by {} — close it or pick another device", what, users.join(", "));
+            }
+        }
+        format!("Error: {}", error.summary())
+    }
+
+    // Takes the supervisor's latest snapshot; the status line follows it when it changed
+    fn sync_run(&mut self) {
+        let run = self.supervisor.snapshot();
+        if run.generation == self.run.generation {
             return;
         }
-        
-        if let Ok(mut bp) = self.audio_engine.bypass.lock() {
-            *bp = !self.noise_suppression_enabled;
+        let error_changed = match (&run.error, &self.run.error) {
+            (Some(new), Some(old)) => !std::sync::Arc::ptr_eq(new, old),
+            (new, old) => new.is_some() != old.is_some(),
+        };
+        if error_changed || run.phase != self.run.phase || run.status != self.run.status {
+            self.status_message = match &run.error {
+                Some(error) => self.error_status(error),
+                None => run.status.clone(),
+            };
+        }
+        self.run = run;
+    }
+
+    fn policy(&self) -> Policy {
+        Policy {
+            pause_when_locked: self.pause_when_locked,
+            return_to_saved_devices: self.return_to_saved_devices,
+            schedule: self.schedule_config.clone(),
+            input_channels: self.input_channels.clone(),
+        }
+    }
+
+    // Runs at the end of every update(): hands the supervisor the device picks and settings
+    // it acts on by itself
+    fn sync_supervisor(&mut self) {
+        let devices = self.current_pair();
+        if devices != self.sent_devices && !self.input_devices.is_empty() && !self.output_devices.is_empty() {
+            self.supervisor.select(devices.clone());
+            self.sent_devices = devices;
+        }
+        let policy = self.policy();
+        if policy != self.sent_policy {
+            self.supervisor.set_policy(policy.clone());
+            self.sent_policy = policy;
+        }
+    }
+
+    fn apply_echo_reference(&mut self) {
+        self.engine().set_echo_reference(self.aec_enabled.then(|| self.aec_reference.clone()));
+    }
+
+    fn selected_input_channel(&self) -> InputChannel {
+        self.input_devices
+            .get(self.selected_input_index)
+            .and_then(|name| self.input_channels.get(name))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn current_selection(&self) -> DeviceSelection {
+        DeviceSelection {
+            input: self.input_devices.get(self.selected_input_index).cloned().unwrap_or_default(),
+            output: self.output_devices.get(self.selected_output_index).cloned().unwrap_or_default(),
+            channel: self.selected_input_channel(),
+        }
+    }
+
+    fn handle_device_restart(&mut self, ctx: &egui::Context) {
+        let now = Instant::now();
+        let selection = self.current_selection();
+        if self.device_restart.take_due(now, &selection, self.run.is_processing()) {
+            log::info!("Applying device selection '{}' -> '{}'", selection.input, selection.output);
+            self.restart_audio(RestartReason::DeviceChange);
+        } else if let Some(remaining) = self.device_restart.remaining(now) {
+            ctx.request_repaint_after(remaining);
+        }
+    }
+
+    fn apply_input_channel(&mut self) {
+        self.apply_input_noise_print();
+        self.input_channel_count = self
+            .input_devices
+            .get(self.selected_input_index)
+            .map(|name| self.engine().input_channel_count(name))
+            .unwrap_or(0);
+        self.engine().set_input_channel(self.selected_input_channel());
+    }
+
+    fn apply_monitor(&mut self) {
+        self.engine().set_monitor_device(self.monitor_enabled.then(|| self.monitor_device.clone()));
+        self.engine().set_monitor_gain(self.monitor_gain);
+    }
+
+    fn apply_mix(&mut self) {
+        self.engine().set_mix_source(self.mix_enabled.then(|| self.mix_source.clone()));
+        self.engine().set_mix_gain(self.mix_gain);
+    }
+
+    fn apply_latency_cap(&self) {
+        self.engine().set_latency_cap_ms(self.latency_cap_ms);
+    }
+
+    fn apply_overload_policy(&self) {
+        self.engine().set_overload_policy(self.overload_policy);
+    }
+
+    fn apply_gate(&self) {
+        self.engine().set_gate(self.gate);
+    }
+
+    fn apply_de_esser(&self) {
+        self.engine().set_de_esser(self.de_esser);
+    }
+
+    fn apply_plosive(&self) {
+        self.engine().set_plosive(self.plosive);
+    }
+
+    fn apply_click(&self) {
+        self.engine().set_click(self.click);
+    }
+
+    fn apply_boost(&self) {
+        self.engine().set_boost(self.boost);
+    }
+
+    fn apply_noise_print(&self) {
+        self.engine().set_noise_print(self.noise_print);
+    }
+
+    // Hands the engine the print learned for the selected input device, if any
+    fn apply_input_noise_print(&self) {
+        let profile = self.input_devices.get(self.selected_input_index).and_then(|d| self.noise_prints.get(d).cloned());
+        self.engine().noise_print_slot.set_profile(profile);
+    }
+
+    fn apply_chain(&self) {
+        self.engine().set_chain(self.dsp_chain);
+    }
+
+    fn apply_music(&self) {
+        self.engine().set_music(self.music);
+    }
+
+    fn apply_suppression_mode(&self) {
+        self.engine().set_bypass(self.suppression_mode == SuppressionMode::Off || self.trigger_bypass);
+        self.engine().set_strong_suppression(self.suppression_mode == SuppressionMode::Strong);
+    }
+
+    fn restart_audio(&mut self, reason: RestartReason) {
+        self.sync_mute_watcher_device();
+        self.sync_device_profile();
+        self.apply_input_channel();
+
+        // Stops the running engine before the new session opens the devices
+        self.input_monitor.stop();
+        self.supervisor.restart_processing(self.start_devices(), reason);
+        self.sync_run();
+    }
+    
+    // Starting and stopping are silent; only changes while running get a cue, whatever caused them
+    fn handle_earcons(&mut self) {
+        let state = self.run.is_processing().then(|| {
+            !(self.engine_status.bypassed || self.is_system_muted())
+        });
+        let previous = std::mem::replace(&mut self.earcon_state, state);
+        if let (Some(before), Some(live)) = (previous, state) {
+            if before != live && self.earcons_enabled {
+                self.notify_earcon(Category::Status, if live { Cue::Live } else { Cue::Quiet });
+            }
+        }
+    }
+
+    fn handle_noise_print_learning(&mut self) {
+        let Some(profile) = self.engine().noise_print_slot.take_learned() else { return };
+        let Some(device) = self.input_devices.get(self.selected_input_index).cloned() else { return };
+        log::info!("Noise print learned for '{}'", device);
+        self.noise_prints.insert(device.clone(), profile);
+        self.save_noise_prints();
+        self.status_message = format!("Noise print learned for {}", device);
+    }
+
+    fn save_noise_prints(&self) {
+        if let Some(dir) = get_config_dir() {
+            noise_print::save_prints(&dir, &self.noise_prints);
+        }
+    }
+
+    // On the monitoring device when there is one, otherwise the default playback device
+    fn earcon_devices(&self) -> (&str, &str) {
+        let device = if self.monitor_enabled { self.monitor_device.as_str() } else { "" };
+        let processed_output = self.output_devices.get(self.selected_output_index).map(|s| s.as_str()).unwrap_or("");
+        (device, processed_output)
+    }
+
+    // The settings page's Test button; not subject to the notification policy
+    fn play_earcon(&self, cue: Cue) {
+        let (device, processed_output) = self.earcon_devices();
+        earcon::play(cue, self.earcon_volume, device, processed_output);
+    }
+
+    fn notify_earcon(&self, category: Category, cue: Cue) {
+        let (device, processed_output) = self.earcon_devices();
+        self.notifier.cue(category, cue, self.earcon_volume, device, processed_output);
+    }
+
+    // Status line text while processing can't keep up; nothing with Prefer quality, which
+    // carries on as before
+    fn overload_text(&self) -> Option<&'static str> {
+        if !self.run.is_processing() || !self.engine_status.overloaded {
+            return None;
+        }
+        match self.overload_policy {
+            OverloadPolicy::PreferQuality => None,
+            OverloadPolicy::AutoBypass => Some("CPU overloaded, passing audio through"),
+            OverloadPolicy::NotifyOnly => Some("CPU overloaded, audio may stutter"),
+        }
+    }
+
+    fn is_system_muted(&self) -> bool {
+        self.engine_status.system_muted
+    }
+
+    fn sync_mute_watcher_device(&self) {
+        if let Some(watcher) = &self.mute_watcher {
+            watcher.set_device(self.input_devices.get(self.selected_input_index).map(|s| s.as_str()));
+        }
+    }
+
+    fn update_tray_tooltip(&mut self) {
+        let tooltip = if let Some(error) = &self.run.error {
+            format!("SilentStream - Error: {}", error.summary())
+        } else if self.is_system_muted() {
+            "SilentStream - System-muted".to_string()
+        } else if !self.run.is_processing() && self.stopped_by_user {
+            "SilentStream - Stopped".to_string()
+        } else if self.run.is_processing() && self.trigger_bypass {
+            "SilentStream - Bypassed".to_string()
+        } else {
+            format!("SilentStream - {}", self.active_preset().map_or("Custom", |p| p.label()))
+        };
+        if tooltip != self.tray_tooltip {
+            if let Some(tray) = &self.tray {
+                tray.set_tooltip(&tooltip);
+            }
+            self.tray_tooltip = tooltip;
+        }
+    }
+
+    // Runs while hidden in the tray too; that's where the alert matters
+    fn update_tray_alert(&mut self, ctx: &egui::Context) {
+        let now = Instant::now();
+        let error = self.run.error.as_deref().map(EngineError::summary);
+        if self.tray_alert.update(error, !self.is_minimized_to_tray, now) {
+            log::info!("Tray alert raised: {}", error.unwrap_or_default());
+            // request_repaint_after is unreliable while hidden in the tray, so the blinks get their own wake-ups
+            let ctx = ctx.clone();
+            std::thread::spawn(move || {
+                for _ in 0..tray::ALERT_FLASHES * 2 {
+                    std::thread::sleep(tray::ALERT_FLASH_PERIOD);
+                    ctx.request_repaint();
+                }
+            });
+        }
+        let alert = self.tray_alert.shows_alert_icon(now);
+        if alert != self.tray_alert_shown {
+            if let Some(tray) = &self.tray {
+                tray.set_alert(alert);
+            }
+            self.tray_alert_shown = alert;
+        }
+    }
+
+    fn update_taskbar_overlay(&mut self) {
+        let Some(hwnd) = self.window_hwnd.lock().ok().and_then(|guard| *guard) else { return };
+        let alert = if self.is_system_muted() {
+            Some("Microphone muted")
+        } else if self.status_message.starts_with("Error") {
+            Some("Audio processing error")
+        } else {
+            None
+        };
+        self.taskbar_overlay.get_or_insert_with(taskbar::TaskbarOverlay::new).set(hwnd, alert);
+    }
+
+    // Changes made from the window itself are already visible, so only those arriving while
+    // it's in the background or in the tray get a toast
+    fn update_osd(&mut self, ctx: &egui::Context) {
+        let muted = self.is_system_muted();
+        let bypassed = self.engine_status.bypassed;
+        let state = Some((muted, bypassed));
+        if self.osd_state.is_some() && state != self.osd_state {
+            let focused = ctx.input(|i| i.viewport().focused).unwrap_or(false);
+            if self.osd_enabled && (self.is_minimized_to_tray || !focused) {
+                let text = if muted {
+                    "Muted"
+                } else if bypassed {
+                    "Suppression off"
+                } else {
+                    "Live"
+                };
+                self.notifier.toast(Category::Status, text);
+            }
+        }
+        self.osd_state = state;
+        self.notifier.draw(ctx, self.osd_corner);
+    }
+
+    fn current_pair(&self) -> DevicePair {
+        DevicePair {
+            input: self.input_devices.get(self.selected_input_index).cloned().unwrap_or_default(),
+            output: self.output_devices.get(self.selected_output_index).cloned().unwrap_or_default(),
+        }
+    }
+
+    // The pair a swap switches to (the other one if a favorite is active, else the first),
+    // or why there's nothing to swap to
+    fn swap_target(&self) -> Result<DevicePair, String> {
+        let [Some(first), Some(second)] = &self.favorites else {
+            return Err("Pin two device pairs to swap between them".to_string());
+        };
+        let target = if *first == self.current_pair() { second } else { first };
+        for device in [&target.input, &target.output] {
+            if !self.input_devices.contains(device) && !self.output_devices.contains(device) {
+                return Err(format!("'{}' is not connected", device));
+            }
+        }
+        Ok(target.clone())
+    }
+
+    fn swap_favorites(&mut self) {
+        let target = match self.swap_target() {
+            Ok(target) => target,
+            Err(reason) => {
+                log::warn!("Can't swap devices: {}", reason);
+                return;
+            }
+        };
+        let (Some(input), Some(output)) = (
+            self.input_devices.iter().position(|d| *d == target.input),
+            self.output_devices.iter().position(|d| *d == target.output),
+        ) else {
+            return;
+        };
+        log::info!("Swapping to '{}' -> '{}'", target.input, target.output);
+        self.selected_input_index = input;
+        self.selected_output_index = output;
+        self.restart_audio(RestartReason::DeviceChange);
+    }
+
+    fn handle_swap_request(&mut self) {
+        if self.tray_requests.swap.swap(false, std::sync::atomic::Ordering::SeqCst) {
+            self.swap_favorites();
+        }
+        let enabled = self.swap_target().is_ok();
+        if enabled != self.tray_swap_enabled {
+            if let Some(tray) = &self.tray {
+                tray.set_swap_enabled(enabled);
+            }
+            self.tray_swap_enabled = enabled;
+        }
+    }
+
+    fn active_preset(&self) -> Option<VadPreset> {
+        VadPreset::matching(self.vad_threshold, &self.gate)
+    }
+
+    fn apply_preset(&mut self, preset: VadPreset) {
+        log::info!("Sensitivity preset '{}' selected", preset.label());
+        preset.apply(&mut self.vad_threshold, &mut self.gate);
+        self.engine().set_threshold(self.vad_threshold);
+        self.apply_gate();
+        self.save_current_settings();
+    }
+
+    // Moves the threshold into the valley of the VAD histogram. Returns false while the
+    // histogram doesn't show separate noise and speech humps yet.
+    fn suggest_threshold(&mut self) -> bool {
+        let suggested = self.engine().vad_histogram.lock().ok().and_then(|h| h.suggest_threshold());
+        let Some(threshold) = suggested else { return false };
+        self.vad_threshold = threshold.clamp(0.0, VAD_THRESHOLD_MAX);
+        log::info!("VAD threshold set to suggested {:.2}", self.vad_threshold);
+        self.engine().set_threshold(self.vad_threshold);
+        self.save_current_settings();
+        true
+    }
+
+    // Bars for the buckets the threshold slider covers, on the slider's scale so the knob
+    // sits over the bucket it cuts at. Square-root heights keep the small hump visible.
+    fn draw_vad_histogram(&self, ui: &mut egui::Ui, width: f32) {
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(width, 28.0), egui::Sense::hover());
+        let counts = self.engine().vad_histogram.lock().map(|h| h.counts()).unwrap_or([0; VAD_BUCKETS]);
+        let visible = slider_buckets();
+        let peak = counts[..visible].iter().copied().max().unwrap_or(0);
+        if peak == 0 {
+            return;
+        }
+        let p = ui.painter();
+        let bar_w = rect.width() / visible as f32;
+        for (i, &count) in counts[..visible].iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            let h = rect.height() * (count as f32 / peak as f32).sqrt();
+            let left = rect.left() + i as f32 * bar_w;
+            let center = (i as f32 + 0.5) / VAD_BUCKETS as f32;
+            let color = if center >= self.vad_threshold {
+                egui::Color32::from_rgba_unmultiplied(139, 92, 246, 140)
+            } else {
+                egui::Color32::from_gray(90)
+            };
+            p.rect_filled(
+                egui::Rect::from_min_max(egui::pos2(left + 0.5, rect.bottom() - h), egui::pos2(left + bar_w - 0.5, rect.bottom())),
+                0.0,
+                color,
+            );
+        }
+    }
+
+    fn handle_preset_request(&mut self) {
+        let requested = self.tray_requests.preset.lock().ok().and_then(|mut p| p.take());
+        if let Some(preset) = requested {
+            self.apply_preset(preset);
+            // The clicked item ticked or unticked itself; put the marks right again
+            self.tray_preset = None;
+        }
+        let active = Some(self.active_preset());
+        if active != self.tray_preset {
+            if let Some(tray) = &self.tray {
+                tray.set_preset(active.flatten());
+            }
+            self.tray_preset = active;
+        }
+    }
+
+    // Runs on every update(), including while hidden in the tray; the supervisor wakes the UI
+    // whenever its snapshot changes or it has something for the UI to act on
+    fn handle_supervisor_events(&mut self, ctx: &egui::Context) {
+        // Taken first, so the snapshot is at least as new as the notices
+        let notices = self.supervisor.poll();
+        self.sync_run();
+        for notice in notices {
+            match notice {
+                Notice::Started { restart } => {
+                    self.device_restart.started(self.current_selection());
+                    if restart || self.stopped_by_user {
+                        self.stopped_by_user = false;
+                        self.save_current_settings();
+                    }
+                }
+                Notice::Stopped => self.notify_stopped(ctx),
+                Notice::Devices { inputs, outputs, selected } => self.adopt_devices(inputs, outputs, selected),
+                // Like a start trigger, scheduled hours end a trigger's bypass
+                Notice::Schedule(true) if self.trigger_bypass => {
+                    self.trigger_bypass = false;
+                    self.apply_suppression_mode();
+                    self.save_current_settings();
+                }
+                Notice::Schedule(_) => {}
+                // Never hold up a shutdown or logoff with the exit prompt
+                Notice::Session(SessionEvent::EndingSession) => {
+                    self.exit_prompt = false;
+                    self.exit_app(ctx);
+                    return;
+                }
+                Notice::Session(_) => self.retry_tray(),
+            }
+        }
+    }
+
+    // Processing stopped on its own; the same conditions as the mute and bypass toasts apply
+    fn notify_stopped(&mut self, ctx: &egui::Context) {
+        let focused = ctx.input(|i| i.viewport().focused).unwrap_or(false);
+        if self.is_minimized_to_tray || !focused {
+            if self.osd_enabled {
+                self.notifier.toast(Category::Critical, "Audio stopped");
+            }
+            if self.earcons_enabled {
+                self.notify_earcon(Category::Critical, Cue::Quiet);
+            }
+        }
+    }
+
+    fn minimize_to_tray(&mut self, ctx: &egui::Context) {
+        if self.tray.is_none() {
+            // Nothing to restore from, so keep it on the taskbar
+            log::info!("No tray icon, minimizing instead");
+            ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(true));
+            return;
+        }
+        self.is_minimized_to_tray = true;
+        log::info!("Minimized to tray");
+        self.in_tray_flag.store(true, std::sync::atomic::Ordering::SeqCst);
+        let hwnd = self.window_hwnd.lock().ok().and_then(|guard| *guard);
+        if let Some(placement) = hwnd.and_then(placement::WindowPlacement::capture) {
+            self.saved_placement = Some(placement);
+        }
+        // Hide window: use both egui commands and Win32
+        ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(true));
+        ctx.send_viewport_cmd(egui::ViewportCommand::Visible(false));
+        if let Some(hwnd) = hwnd {
+            platform::hide_window(hwnd);
+        }
+        platform::release_memory_while_hidden();
+    }
+
+    // Fresh device lists from the supervisor, with what it picked in them
+    fn adopt_devices(&mut self, inputs: Vec<String>, outputs: Vec<String>, selected: DevicePair) {
+        self.input_devices = inputs;
+        self.output_devices = outputs;
+        self.selected_input_index = self.input_devices.iter().position(|d| *d == selected.input).unwrap_or(0);
+        self.selected_output_index = self.output_devices.iter().position(|d| *d == selected.output).unwrap_or(0);
+        self.sent_devices = self.current_pair();
+        self.sync_mute_watcher_device();
+        self.sync_device_profile();
+        self.apply_input_channel();
+        self.save_current_settings();
+    }
+
+    // A device picked by hand replaces the missing one for that side
+    fn forget_fallback(&mut self, input: bool) {
+        self.supervisor.forget_fallback(input);
+        self.sync_run();
+    }
+
+    fn saved_devices_connected(&self) -> bool {
+        let Some(saved) = &self.run.fallback else { return false };
+        (saved.input.is_empty() || self.input_devices.contains(&saved.input))
+            && (saved.output.is_empty() || self.output_devices.contains(&saved.output))
+    }
+
+    // Restarts on the saved devices if running; the selection follows in a Devices notice
+    fn return_to_saved_devices(&mut self) {
+        self.supervisor.return_to_saved_devices();
+        self.sync_run();
+    }
+
+    fn refresh_default_devices(&mut self) {
+        if self.default_devices_checked.is_some_and(|t| t.elapsed() < DEFAULT_DEVICES_REFRESH) {
+            return;
+        }
+        self.default_devices = (default_input_name(), default_output_name());
+        self.default_devices_checked = Some(Instant::now());
+    }
+
+    fn draw_device_fallback_banner(&mut self, ui: &mut egui::Ui) {
+        let Some(saved) = self.run.fallback.clone() else { return };
+        let connected = self.saved_devices_connected();
+
+        egui::Frame::none()
+            .fill(egui::Color32::from_rgba_premultiplied(80, 60, 20, 240))
+            .rounding(12.0)
+            .inner_margin(10.0)
+            .show(ui, |ui| {
+                let sides = [
+                    ("input", &saved.input, self.input_devices.get(self.selected_input_index)),
+                    ("output", &saved.output, self.output_devices.get(self.selected_output_index)),
+                ];
+                for (kind, name, current) in sides {
+                    if name.is_empty() {
+                        continue;
+                    }
+                    let text = if connected {
+                        format!("Saved {} '{}' is connected again", kind, name)
+                    } else {
+                        format!("Saved {} '{}' not found — using '{}'", kind, name, current.map(|s| s.as_str()).unwrap_or("no device"))
+                    };
+                    ui.label(egui::RichText::new(text).size(12.0));
+                }
+                ui.horizontal(|ui| {
+                    if connected && ui.button("Switch back").clicked() {
+                        self.return_to_saved_devices();
+                    }
+                    if ui.button("Keep current").on_hover_text("Forget the saved device and keep using this one").clicked() {
+                        self.forget_fallback(true);
+                        self.forget_fallback(false);
+                        self.save_current_settings();
+                    }
+                });
+                if ui.checkbox(&mut self.return_to_saved_devices, "Switch back automatically").changed() {
+                    self.save_current_settings();
+                }
+            });
+        ui.add_space(10.0);
+    }
+
+    // Blocked capture delivers exact zeros, while even a quiet room leaves a noise floor.
+    // Zeros alone could still be a muted interface, so only the privacy state confirms it.
+    fn check_microphone_privacy(&mut self) {
+        if !self.run.is_processing() {
+            return;
+        }
+        let (counters, info) = {
+            let engine = self.engine();
+            (engine.counters.clone(), engine.stream_info.clone())
+        };
+        let start = counters.starts.load(std::sync::atomic::Ordering::Relaxed);
+        if start == self.privacy_checked_start {
+            return;
+        }
+        if counters.input_signal.load(std::sync::atomic::Ordering::Relaxed) {
+            self.privacy_checked_start = start;
+            self.microphone_blocked = None;
+            return;
+        }
+        let Some(info) = info else { return };
+        if counters.input_samples.load(std::sync::atomic::Ordering::Relaxed) < info.input_sample_rate as u64 * SILENT_INPUT_SECS {
+            return;
+        }
+        self.privacy_checked_start = start;
+        self.microphone_blocked = privacy::microphone_access_blocked();
+        match self.microphone_blocked {
+            Some(reason) => log::warn!("Input is silent: {}", reason),
+            None => log::info!("Input has been exact silence for {} s, but microphone access is allowed", SILENT_INPUT_SECS),
+        }
+    }
+
+    fn draw_microphone_blocked_banner(&mut self, ui: &mut egui::Ui) {
+        let Some(reason) = self.microphone_blocked else { return };
+        if !self.run.is_processing() {
+            return;
+        }
+        egui::Frame::none()
+            .fill(egui::Color32::from_rgba_premultiplied(80, 60, 20, 240))
+            .rounding(12.0)
+            .inner_margin(10.0)
+            .show(ui, |ui| {
+                ui.label(egui::RichText::new(format!("No sound from the microphone: {}.", reason)).size(12.0));
+                ui.label(egui::RichText::new("Turn it on in the privacy settings, then restart processing.").size(11.0));
+                ui.horizontal(|ui| {
+                    if ui.button("Open privacy settings").clicked() {
+                        platform::open_microphone_privacy_settings();
+                    }
+                    if ui.button("Restart").clicked() {
+                        self.restart_audio(RestartReason::Manual);
+                    }
+                });
+            });
+        ui.add_space(10.0);
+    }
+
+    // The tray can fail to appear when the shell isn't up yet or is broken (some remote
+    // sessions); try again once it's back. An existing icon is re-added by tray-icon itself.
+    fn retry_tray(&mut self) {
+        if self.tray.is_some() || !tray::SUPPORTED {
+            return;
+        }
+        let (icon_rgba, icon_width, icon_height) = load_app_icon();
+        self.tray = tray::Tray::new(icon_rgba, icon_width, icon_height);
+        if self.tray.is_some() {
+            log::info!("Tray icon created on retry");
+            // New icons start with the default tooltip, the swap entry disabled and no preset ticked
+            self.tray_tooltip = "SilentStream".to_string();
+            self.tray_alert_shown = false;
+            self.tray_swap_enabled = false;
+            self.tray_preset = None;
+        }
+    }
+
+    fn check_settings_file(&mut self, ctx: &egui::Context) {
+        if self.last_settings_check.elapsed() < SETTINGS_POLL {
+            return;
+        }
+        self.last_settings_check = Instant::now();
+        if !settings::changed_externally() {
+            return;
+        }
+        log::info!("Settings file changed on disk, reloading");
+        let settings = load_settings();
+        self.apply_live_settings(&settings, ctx);
+        self.pending_reload = None;
+        if self.engine_settings_differ(&settings) {
+            if self.is_active() {
+                self.pending_reload = Some(settings);
+            } else {
+                self.apply_engine_settings(&settings);
+            }
+        }
+    }
+
+    // Everything that can change without interrupting the audio. Lifetime stats stay
+    // with the running app, which is the only writer.
+    fn apply_live_settings(&mut self, settings: &Settings, ctx: &egui::Context) {
+        self.suppression_mode = settings.suppression_mode;
+        self.apply_suppression_mode();
+        self.global_tuning = DeviceTuning::from_settings(settings);
+        self.device_profiles = settings.device_profiles.clone();
+        self.tuning_device = None;
+        self.set_live_tuning(self.global_tuning);
+        self.sync_device_profile();
+        self.music = settings.music;
+        self.apply_music();
+        self.noise_print = settings.noise_print;
+        self.apply_noise_print();
+        self.latency_cap_ms = settings.latency_cap_ms;
+        self.apply_latency_cap();
+        self.overload_policy = settings.overload_policy;
+        self.apply_overload_policy();
+        self.dsp_chain = settings.dsp_chain;
+        if settings.plugin != self.plugin_config {
+            self.plugin_config = settings.plugin.clone();
+            self.plugin_path_text = self.plugin_config.path.clone();
+            self.engine().plugin.configure(&self.plugin_config);
+        }
+        self.apply_chain();
+        self.monitor_gain = settings.monitor_gain;
+        self.engine().set_monitor_gain(self.monitor_gain);
+        self.mix_gain = settings.mix_gain;
+        self.engine().set_mix_gain(self.mix_gain);
+        self.meter_mode = settings.meter_mode;
+        self.input_monitor_enabled = settings.input_monitor;
+        self.animations = settings.animations;
+        self.appearance = settings.appearance;
+        self.osd_enabled = settings.osd_enabled;
+        self.osd_corner = settings.osd_corner;
+        if settings.notifications != self.notifier.policy {
+            self.quiet_start_text = format_time_of_day(settings.notifications.quiet_start);
+            self.quiet_end_text = format_time_of_day(settings.notifications.quiet_end);
+            self.notifier.policy = settings.notifications.clone();
+        }
+        self.pause_when_locked = settings.pause_when_locked;
+        self.return_to_saved_devices = settings.return_to_saved_devices;
+        self.permission_notice_seen = settings.permission_notice_seen;
+        self.confirm_exit = settings.confirm_exit;
+        self.earcons_enabled = settings.earcons_enabled;
+        self.earcon_volume = settings.earcon_volume;
+        if settings.virtual_input_filter != self.virtual_input_filter {
+            self.virtual_input_text = settings.virtual_input_filter.patterns.join("\n");
+            self.virtual_input_filter = settings.virtual_input_filter.clone();
+        }
+        self.start_processing = settings.start_processing;
+        self.restore_state = settings.restore_state;
+        self.startup_delay = settings.startup_delay;
+        self.log_level = settings.log_level;
+        logging::set_level(self.log_level);
+        self.metrics_enabled = settings.metrics_enabled;
+        self.sync_metrics_logger();
+        self.status_file_enabled = settings.status_file_enabled;
+        self.status_file_path = settings.status_file_path.clone();
+        self.sync_status_file();
+        self.update_check_enabled = settings.update_check_enabled;
+        self.default_device_config = settings.default_device.clone();
+
+        if settings.window_hotkey != self.window_hotkey {
+            self.window_hotkey = settings.window_hotkey.clone();
+            self.sync_hotkey();
+        }
+        if settings.obs != self.obs_config {
+            self.obs_config = settings.obs.clone();
+            self.obs_client.start(&self.obs_config, ctx);
+        }
+        if settings.app_watch != self.app_watch_config {
+            self.app_watch_config = settings.app_watch.clone();
+            self.app_watch_text = self.app_watch_config.apps.join("\n");
+            self.app_watcher.start(&self.app_watch_config, ctx);
+        }
+        if settings.schedule != self.schedule_config {
+            self.schedule_config = settings.schedule.clone();
+            self.schedule_start_text = format_time_of_day(self.schedule_config.start);
+            self.schedule_end_text = format_time_of_day(self.schedule_config.end);
+        }
+        if settings.idle_pause != self.idle_pause_config {
+            self.idle_pause_config = settings.idle_pause.clone();
+            self.listener_watcher.start(&self.idle_pause_config, ctx);
+        }
+    }
+
+    fn engine_settings_differ(&self, settings: &Settings) -> bool {
+        settings.input_device.as_ref() != self.input_devices.get(self.selected_input_index)
+            || settings.output_device.as_ref() != self.output_devices.get(self.selected_output_index)
+            || settings.input_channels != self.input_channels
+            || settings.aec_enabled != self.aec_enabled
+            || settings.aec_reference != self.aec_reference
+            || settings.monitor_enabled != self.monitor_enabled
+            || settings.monitor_device != self.monitor_device
+            || settings.mix_enabled != self.mix_enabled
+            || settings.mix_source != self.mix_source
+            || settings.resampler_quality != self.resampler_quality
+            || settings.block_frames != self.block_frames
+            || settings.overflow_policy != self.overflow_policy
+    }
+
+    // Settings read by AudioEngine::start(); the caller restarts the engine if it's running
+    fn apply_engine_settings(&mut self, settings: &Settings) {
+        // Devices that aren't connected keep the current selection
+        if let Some(i) = settings.input_device.as_ref().and_then(|name| self.input_devices.iter().position(|d| d == name)) {
+            self.selected_input_index = i;
+        }
+        if let Some(i) = settings.output_device.as_ref().and_then(|name| self.output_devices.iter().position(|d| d == name)) {
+            self.selected_output_index = i;
+        }
+        self.input_channels = settings.input_channels.clone();
+        self.aec_enabled = settings.aec_enabled;
+        self.aec_reference = settings.aec_reference.clone();
+        self.monitor_enabled = settings.monitor_enabled;
+        self.monitor_device = settings.monitor_device.clone();
+        self.mix_enabled = settings.mix_enabled;
+        self.mix_source = settings.mix_source.clone();
+        self.resampler_quality = settings.resampler_quality;
+        self.block_frames = settings.block_frames;
+        self.overflow_policy = settings.overflow_policy;
+
+        self.apply_echo_reference();
+        self.apply_monitor();
+        self.apply_mix();
+        self.engine().set_resampler_quality(self.resampler_quality);
+        self.engine().set_block_frames(self.block_frames);
+        self.engine().set_overflow_policy(self.overflow_policy);
+        self.sync_mute_watcher_device();
+        self.sync_device_profile();
+        self.apply_input_channel();
+    }
+
+    fn draw_reload_banner(&mut self, ui: &mut egui::Ui) {
+        if self.pending_reload.is_none() {
+            return;
+        }
+        egui::Frame::none()
+            .fill(egui::Color32::from_rgba_premultiplied(80, 60, 20, 240))
+            .rounding(12.0)
+            .inner_margin(10.0)
+            .show(ui, |ui| {
+                ui.label(
+                    egui::RichText::new("The settings file was changed. Some changes need an audio restart.").size(11.0),
+                );
+                ui.horizontal(|ui| {
+                    if ui.button("Restart audio").clicked() {
+                        if let Some(settings) = self.pending_reload.take() {
+                            self.apply_engine_settings(&settings);
+                            self.restart_audio(RestartReason::SettingsChange);
+                        }
+                    }
+                    if ui.button("Keep current").clicked() {
+                        self.pending_reload = None;
+                        self.save_current_settings();
+                    }
+                });
+            });
+        ui.add_space(10.0);
+    }
+
+    fn draw_autostart_repaired_banner(&mut self, ui: &mut egui::Ui) {
+        if !self.autostart_repaired {
+            return;
+        }
+        egui::Frame::none()
+            .fill(egui::Color32::from_rgba_premultiplied(30, 50, 80, 240))
+            .rounding(12.0)
+            .inner_margin(10.0)
+            .show(ui, |ui| {
+                ui.label(
+                    egui::RichText::new(format!("SilentStream was moved; \"{}\" now points to its new location.", AUTOSTART_LABEL))
+                        .size(11.0),
+                );
+                if ui.button("OK").clicked() {
+                    self.autostart_repaired = false;
+                }
+            });
+        ui.add_space(10.0);
+    }
+
+    // macOS asks for microphone access the first time a stream opens, and a denied prompt
+    // only shows up as silence, so explain it up front until dismissed
+    fn draw_permission_notice(&mut self, ui: &mut egui::Ui) {
+        if !cfg!(target_os = "macos") || self.permission_notice_seen {
+            return;
+        }
+        egui::Frame::none()
+            .fill(egui::Color32::from_rgba_premultiplied(30, 50, 80, 240))
+            .rounding(12.0)
+            .inner_margin(10.0)
+            .show(ui, |ui| {
+                ui.label(
+                    egui::RichText::new(
+                        "macOS asks for microphone access when processing first starts. If the meter stays silent, allow SilentStream under Privacy & Security → Microphone.",
+                    )
+                    .size(11.0),
+                );
+                ui.horizontal(|ui| {
+                    if ui.button("Open Privacy settings").clicked() {
+                        platform::open_microphone_privacy_settings();
+                    }
+                    if ui.button("Got it").clicked() {
+                        self.permission_notice_seen = true;
+                        self.save_current_settings();
+                    }
+                });
+            });
+        ui.add_space(10.0);
+    }
+
+    fn delay_start(&mut self) {
+        self.supervisor.delay_start(self.start_devices(), Duration::from_secs(self.startup_delay as u64));
+        self.sync_run();
+    }
+
+    // The supervisor starts on time by itself; this only counts down on screen
+    fn show_delayed_start(&mut self, ctx: &egui::Context) {
+        let Phase::Delayed(due) = self.run.phase else { return };
+        let remaining = due.saturating_duration_since(Instant::now());
+        self.status_message = format!("Starting in {} s…", remaining.as_secs() + 1);
+        ctx.request_repaint_after(remaining.min(Duration::from_secs(1)));
+    }
+
+    // Moves the entry to `backend` (removing the old one) and turns it on or off there.
+    // The checkbox follows what's actually registered afterwards.
+    fn set_autostart(&mut self, backend: AutostartBackend, enable: bool) {
+        let mut result = Ok(());
+        if backend != self.autostart_backend {
+            result = autostart::set(self.autostart_backend, false);
+        }
+        result = result.and(autostart::set(backend, enable));
+        self.autostart_backend = backend;
+        self.start_with_windows = autostart::is_enabled(backend);
+        self.autostart_error = result.err();
+        self.save_current_settings();
+    }
+
+    fn toggle_processing(&mut self) {
+        // A manual choice overrides a pending resume after unlock, idle or delayed start
+        self.idle_paused = false;
+        self.trigger_bypass = false;
+        if matches!(self.run.phase, Phase::Delayed(_)) {
+            log::info!("Delayed start skipped by user");
+            self.auto_start();
+        } else if self.is_active() || self.run.phase == Phase::WaitingForDevices {
+            log::info!("Processing stopped by user");
+            self.stop_processing();
+            self.stopped_by_user = true;
+        } else {
+            log::info!("Processing started by user");
+            self.auto_start();
+        }
+        self.save_current_settings();
+    }
+
+    fn stop_processing(&mut self) {
+        self.supervisor.stop_processing();
+        self.sync_run();
+    }
+
+    fn apply_trigger_action(&mut self, action: TriggerAction) {
+        match action {
+            TriggerAction::Nothing => {}
+            TriggerAction::StartProcessing => {
+                if self.trigger_bypass {
+                    self.trigger_bypass = false;
+                    self.save_current_settings();
+                }
+                self.apply_suppression_mode();
+                if !self.is_active() {
+                    self.auto_start();
+                }
+            }
+            TriggerAction::StopProcessing => {
+                if self.is_active() {
+                    self.stop_processing();
+                }
+            }
+            TriggerAction::Bypass => {
+                if !self.trigger_bypass {
+                    self.trigger_bypass = true;
+                    self.apply_suppression_mode();
+                    self.save_current_settings();
+                }
+            }
+        }
+    }
+
+    fn handle_trigger_events(&mut self) {
+        if let Some(active) = self.obs_client.poll() {
+            let action = if active { self.obs_config.on_active } else { self.obs_config.on_inactive };
+            self.apply_trigger_action(action);
+        }
+        if let Some(running) = self.app_watcher.poll() {
+            let action = if running { TriggerAction::StartProcessing } else { self.app_watch_config.on_exit };
+            self.apply_trigger_action(action);
+        }
+    }
+
+    fn draw_obs_settings(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        ui.collapsing("OBS Integration", |ui| {
+            let mut reconnect = false;
+
+            if ui.checkbox(&mut self.obs_config.enabled, "Connect to obs-websocket").changed() {
+                reconnect = true;
+            }
+
+            ui.add_enabled_ui(self.obs_config.enabled, |ui| {
+                egui::Grid::new("obs_grid").num_columns(2).show(ui, |ui| {
+                    ui.label("Host:");
+                    reconnect |= ui.text_edit_singleline(&mut self.obs_config.host).lost_focus();
+                    ui.end_row();
+
+                    ui.label("Port:");
+                    let port = ui.add(egui::DragValue::new(&mut self.obs_config.port));
+                    reconnect |= port.drag_released() || port.lost_focus();
+                    ui.end_row();
+
+                    ui.label("Password:");
+                    reconnect |= ui.add(egui::TextEdit::singleline(&mut self.obs_config.password).password(true)).lost_focus();
+                    ui.end_row();
+
+                    ui.label("When live:");
+                    let mut action_changed = false;
+                    egui::ComboBox::from_id_source("obs_on_active").selected_text(self.obs_config.on_active.label()).show_ui(ui, |ui| {
+                        for action in TriggerAction::ALL {
+                            action_changed |= ui.selectable_value(&mut self.obs_config.on_active, action, action.label()).changed();
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("When idle:");
+                    egui::ComboBox::from_id_source("obs_on_inactive").selected_text(self.obs_config.on_inactive.label()).show_ui(ui, |ui| {
+                        for action in TriggerAction::ALL {
+                            action_changed |= ui.selectable_value(&mut self.obs_config.on_inactive, action, action.label()).changed();
+                        }
+                    });
+                    ui.end_row();
+
+                    if action_changed {
+                        self.save_current_settings();
+                    }
+                });
+            });
+
+            ui.label(egui::RichText::new(format!("Status: {}", self.obs_client.status())).size(11.0));
+
+            if reconnect {
+                self.obs_client.start(&self.obs_config, ctx);
+                self.save_current_settings();
+            }
+        });
+    }
+
+    fn draw_app_watch_settings(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        ui.collapsing("Auto-activate by application", |ui| {
+            if ui.checkbox(&mut self.app_watch_config.enabled, "Process only while these apps run").changed() {
+                self.app_watcher.start(&self.app_watch_config, ctx);
+                self.save_current_settings();
+            }
+
+            ui.add_enabled_ui(self.app_watch_config.enabled, |ui| {
+                ui.label(egui::RichText::new("Executable names, one per line").size(11.0));
+                let edit = ui.add(
+                    egui::TextEdit::multiline(&mut self.app_watch_text)
+                        .desired_rows(3)
+                        .desired_width(f32::INFINITY),
+                );
+                if edit.changed() {
+                    self.app_watch_config.apps = self.app_watch_text
+                        .lines()
+                        .map(|l| l.trim().to_string())
+                        .filter(|l| !l.is_empty())
+                        .collect();
+                    self.app_watcher.set_apps(&self.app_watch_config.apps);
+                }
+                if edit.lost_focus() {
+                    self.save_current_settings();
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("When all have exited:");
+                    egui::ComboBox::from_id_source("app_watch_on_exit").selected_text(self.app_watch_config.on_exit.label()).show_ui(ui, |ui| {
+                        for action in [TriggerAction::StopProcessing, TriggerAction::Bypass, TriggerAction::Nothing] {
+                            if ui.selectable_value(&mut self.app_watch_config.on_exit, action, action.label()).changed() {
+                                self.save_current_settings();
+                            }
+                        }
+                    });
+                });
+
+                let status = match self.app_watcher.running_app() {
+                    Some(name) => format!("Detected: {}", name),
+                    None => "No watched app running".to_string(),
+                };
+                ui.label(egui::RichText::new(status).size(11.0));
+            });
+        });
+    }
+
+    fn draw_notification_settings(&mut self, ui: &mut egui::Ui) {
+        ui.collapsing("Quiet hours", |ui| {
+            let policy = &mut self.notifier.policy;
+            let mut changed = ui.checkbox(&mut policy.quiet_hours, "No toasts or sound cues during these hours").changed();
+            ui.add_enabled_ui(policy.quiet_hours, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("From");
+                    let start = ui.add(egui::TextEdit::singleline(&mut self.quiet_start_text).desired_width(48.0));
+                    ui.label("to");
+                    let end = ui.add(egui::TextEdit::singleline(&mut self.quiet_end_text).desired_width(48.0));
+
+                    if start.lost_focus() {
+                        if let Some(m) = parse_time_of_day(&self.quiet_start_text) {
+                            changed |= m != policy.quiet_start;
+                            policy.quiet_start = m;
+                        }
+                        self.quiet_start_text = format_time_of_day(policy.quiet_start);
+                    }
+                    if end.lost_focus() {
+                        if let Some(m) = parse_time_of_day(&self.quiet_end_text) {
+                            changed |= m != policy.quiet_end;
+                            policy.quiet_end = m;
+                        }
+                        self.quiet_end_text = format_time_of_day(policy.quiet_end);
+                    }
+                });
+            });
+            changed |= ui.checkbox(&mut policy.quiet_when_fullscreen, "None while a fullscreen app is in front").changed();
+            changed |= ui
+                .checkbox(&mut policy.critical_always, "Always notify when processing stops on an error")
+                .changed();
+            if policy.in_quiet_hours() {
+                ui.label(egui::RichText::new("Inside quiet hours").size(11.0));
+            }
+
+            if changed {
+                self.save_current_settings();
+            }
+        });
+    }
+
+    fn draw_schedule_settings(&mut self, ui: &mut egui::Ui) {
+        ui.collapsing("Schedule", |ui| {
+            let mut changed = ui.checkbox(&mut self.schedule_config.enabled, "Only process during scheduled hours").changed();
+
+            ui.add_enabled_ui(self.schedule_config.enabled, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("From");
+                    let start = ui.add(egui::TextEdit::singleline(&mut self.schedule_start_text).desired_width(48.0));
+                    ui.label("to");
+                    let end = ui.add(egui::TextEdit::singleline(&mut self.schedule_end_text).desired_width(48.0));
+
+                    if start.lost_focus() {
+                        if let Some(m) = parse_time_of_day(&self.schedule_start_text) {
+                            changed |= m != self.schedule_config.start;
+                            self.schedule_config.start = m;
+                        }
+                        self.schedule_start_text = format_time_of_day(self.schedule_config.start);
+                    }
+                    if end.lost_focus() {
+                        if let Some(m) = parse_time_of_day(&self.schedule_end_text) {
+                            changed |= m != self.schedule_config.end;
+                            self.schedule_config.end = m;
+                        }
+                        self.schedule_end_text = format_time_of_day(self.schedule_config.end);
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    for (day, label) in self.schedule_config.days.iter_mut().zip(WEEKDAY_LABELS) {
+                        changed |= ui.toggle_value(day, label).changed();
+                    }
+                });
+
+                let status = if self.schedule_config.is_active_now() {
+                    "Inside scheduled hours"
+                } else {
+                    "Outside scheduled hours"
+                };
+                ui.label(egui::RichText::new(status).size(11.0));
+            });
+
+            if changed {
+                self.save_current_settings();
+            }
+        });
+    }
+
+    // Apps record from the capture end of a virtual cable; for other outputs, watch the output itself
+    fn listened_endpoint(&self) -> Option<String> {
+        let output = self.output_devices.get(self.selected_output_index)?;
+        Some(cable_capture_side(output, &self.input_devices).unwrap_or_else(|| output.clone()))
+    }
+
+    fn handle_idle_pause(&mut self) {
+        if !self.idle_pause_config.enabled {
+            if self.idle_paused {
+                self.resume_from_idle();
+            }
+            return;
+        }
+        self.listener_watcher.set_endpoint(self.listened_endpoint().as_deref());
+        // A restart (device change) clears the engine flag; stay paused until someone records
+        if self.idle_paused && self.run.is_processing() {
+            self.engine().idle.store(true, std::sync::atomic::Ordering::Relaxed);
+        }
+        let Some(listening) = self.listener_watcher.poll() else { return };
+        if !listening && self.run.is_processing() && !self.idle_paused {
+            log::info!("No app has recorded from the output for {} min, pausing", self.idle_pause_config.minutes);
+            self.idle_paused = true;
+            if self.idle_pause_config.release_devices {
+                self.stop_processing();
+            } else {
+                self.engine().idle.store(true, std::sync::atomic::Ordering::Relaxed);
+            }
+            self.status_message = "Idle — no app is using the microphone".to_string();
+        } else if listening && self.idle_paused {
+            log::info!("An app is recording from the output again, resuming");
+            self.resume_from_idle();
+        }
+    }
+
+    fn resume_from_idle(&mut self) {
+        self.idle_paused = false;
+        if self.run.is_processing() {
+            self.engine().idle.store(false, std::sync::atomic::Ordering::Relaxed);
+            self.status_message = "Processing audio".to_string();
+        } else if self.run.phase != Phase::Starting {
+            self.auto_start();
+        }
+    }
+
+    fn draw_idle_pause_settings(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        ui.collapsing("Pause when unused", |ui| {
+            let mut changed = ui
+                .checkbox(&mut self.idle_pause_config.enabled, "Pause while no app records from the output")
+                .changed();
+            ui.add_enabled_ui(self.idle_pause_config.enabled, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("After");
+                    let minutes = ui.add(egui::DragValue::new(&mut self.idle_pause_config.minutes).clamp_range(1..=120).suffix(" min"));
+                    changed |= minutes.drag_released() || minutes.lost_focus();
+                });
+                changed |= ui
+                    .checkbox(&mut self.idle_pause_config.release_devices, "Release the audio devices while paused")
+                    .on_hover_text("Off: the streams stay open and only the denoiser stops, so resuming is instant")
+                    .changed();
+                if let Some(endpoint) = self.listened_endpoint() {
+                    ui.label(egui::RichText::new(format!("Watching '{}'", endpoint)).size(11.0));
+                }
+            });
+            if changed {
+                self.listener_watcher.start(&self.idle_pause_config, ctx);
+                self.save_current_settings();
+            }
+        });
+    }
+
+    fn draw_virtual_input_settings(&mut self, ui: &mut egui::Ui) {
+        ui.collapsing("Virtual input devices", |ui| {
+            if ui.checkbox(&mut self.virtual_input_filter.enabled, "Hide virtual devices from the input list").changed() {
+                self.save_current_settings();
+            }
+            ui.add_enabled_ui(self.virtual_input_filter.enabled, |ui| {
+                ui.label(egui::RichText::new("Inputs whose name contains any of these, one per line").size(11.0));
+                let edit = ui.add(
+                    egui::TextEdit::multiline(&mut self.virtual_input_text)
+                        .desired_rows(4)
+                        .desired_width(f32::INFINITY),
+                );
+                if edit.changed() {
+                    self.virtual_input_filter.patterns = self.virtual_input_text
+                        .lines()
+                        .map(|l| l.trim().to_string())
+                        .filter(|l| !l.is_empty())
+                        .collect();
+                }
+                if edit.lost_focus() {
+                    self.save_current_settings();
+                }
+                if ui.small_button("Reset to defaults").clicked() {
+                    self.virtual_input_filter.patterns = VirtualInputFilter::default().patterns;
+                    self.virtual_input_text = self.virtual_input_filter.patterns.join("\n");
+                    self.save_current_settings();
+                }
+            });
+        });
+    }
+
+    fn draw_chain_settings(&mut self, ui: &mut egui::Ui) {
+        ui.collapsing("Processing order", |ui| {
+            ui.label(egui::RichText::new("Stages run top to bottom. The voice gate and click filter need noise suppression ahead of them.").size(11.0));
+            let mut changed = false;
+            for index in 0..STAGE_COUNT {
+                let entry = self.dsp_chain.entries[index];
+                ui.horizontal(|ui| {
+                    let can_up = self.dsp_chain.can_move(index, true);
+                    let can_down = self.dsp_chain.can_move(index, false);
+                    if ui.add_enabled(can_up, egui::Button::new("⏶").small()).clicked() {
+                        self.dsp_chain.move_stage(index, true);
+                        changed = true;
+                    }
+                    if ui.add_enabled(can_down, egui::Button::new("⏷").small()).clicked() {
+                        self.dsp_chain.move_stage(index, false);
+                        changed = true;
+                    }
+                    // The denoiser produces the VAD everything else relies on
+                    let mut enabled = entry.enabled;
+                    let toggle = ui.add_enabled(entry.kind != StageKind::Denoise, egui::Checkbox::new(&mut enabled, entry.kind.label()));
+                    if toggle.changed() {
+                        self.dsp_chain.entries[index].enabled = enabled;
+                        changed = true;
+                    }
+                });
+            }
+            if self.dsp_chain != ChainConfig::default() && ui.small_button("Restore default order").clicked() {
+                self.dsp_chain = ChainConfig::default();
+                changed = true;
+            }
+            if changed {
+                log::info!("Processing order: {}", self.dsp_chain.as_setting());
+                self.apply_chain();
+                self.save_current_settings();
+            }
+        });
+    }
+
+    fn draw_boost_settings(&mut self, ui: &mut egui::Ui) {
+        ui.collapsing("Mic boost", |ui| {
+            let slider = ui.add(egui::Slider::new(&mut self.boost.gain_db, BOOST_RANGE).text("Boost").suffix(" dB").step_by(1.0));
+            if slider.changed() {
+                self.apply_boost();
+            }
+            if slider.drag_released() || (slider.changed() && !slider.dragged()) {
+                self.save_current_settings();
+            }
+            ui.label(egui::RichText::new("For very quiet microphones; loud peaks saturate softly instead of clipping").size(11.0));
+
+            if self.boost.gain_db > 0.0 && self.run.is_processing() {
+                let saturation = self.engine_status.boost_saturation;
+                let (text, color) = if saturation < 0.01 {
+                    ("Saturation: none".to_string(), egui::Color32::from_rgb(67, 181, 129))
+                } else if saturation < 0.1 {
+                    (format!("Saturation: {:.0}% of the time", saturation * 100.0), egui::Color32::from_rgb(250, 166, 26))
+                } else {
+                    (format!("Saturation: {:.0}% of the time — lower the boost", saturation * 100.0), egui::Color32::from_rgb(240, 71, 71))
+                };
+                ui.label(egui::RichText::new(text).size(11.0).color(color));
+            }
+        });
+    }
+
+    fn draw_noise_print_settings(&mut self, ui: &mut egui::Ui) {
+        ui.collapsing("Noise print", |ui| {
+            ui.label(
+                egui::RichText::new("Removes steady tones and hum that noise suppression leaves behind, like monitor whine").size(11.0),
+            );
+            let device = self.input_devices.get(self.selected_input_index).cloned().unwrap_or_default();
+            let learned = self.noise_prints.contains_key(&device);
+            let stage_enabled = self.dsp_chain.entries.iter().any(|e| e.kind == StageKind::NoisePrint && e.enabled);
+            let slot = self.engine().noise_print_slot.clone();
+
+            match slot.learning_progress() {
+                Some(progress) => {
+                    ui.horizontal(|ui| {
+                        ui.add(egui::ProgressBar::new(progress).text("Listening… stay quiet").desired_width(ui.available_width() - 70.0));
+                        if ui.small_button("Cancel").clicked() {
+                            slot.cancel_learning();
+                        }
+                    });
+                    ui.ctx().request_repaint_after(Duration::from_millis(100));
+                }
+                None => {
+                    ui.horizontal(|ui| {
+                        let learn = ui
+                            .add_enabled(self.run.is_processing() && stage_enabled, egui::Button::new("Learn noise"))
+                            .on_hover_text("Listens for 2 seconds; don't speak until it's done");
+                        if learn.clicked() {
+                            log::info!("Learning noise print for '{}'", device);
+                            slot.start_learning();
+                        }
+                        if learned && ui.small_button("Clear").clicked() {
+                            log::info!("Noise print cleared for '{}'", device);
+                            self.noise_prints.remove(&device);
+                            self.save_noise_prints();
+                            slot.set_profile(None);
+                        }
+                    });
+                }
+            }
+
+            let status = if !stage_enabled {
+                "Turned off in Processing order".to_string()
+            } else if learned {
+                format!("Using the print learned for {}", device)
+            } else {
+                format!("No print learned for {}", device)
+            };
+            ui.label(egui::RichText::new(status).size(11.0));
+
+            ui.add_enabled_ui(learned, |ui| {
+                let mut percent = self.noise_print.amount * 100.0;
+                let slider = ui.add(egui::Slider::new(&mut percent, 0.0..=100.0).text("Amount").suffix("%"));
+                self.noise_print.amount = percent / 100.0;
+                if slider.changed() {
+                    self.apply_noise_print();
+                }
+                if slider.drag_released() || (slider.changed() && !slider.dragged()) {
+                    self.save_current_settings();
+                }
+            });
+        });
+    }
+
+    fn draw_plugin_settings(&mut self, ui: &mut egui::Ui) {
+        ui.collapsing("Effect plugin (CLAP)", |ui| {
+            let mut reload = ui.checkbox(&mut self.plugin_config.enabled, "Run a CLAP plugin in the processing chain").changed();
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.plugin_path_text)
+                        .hint_text("Path to a .clap file")
+                        .desired_width(ui.available_width() - 60.0),
+                );
+                if ui.button("Load").clicked() {
+                    reload = true;
+                }
+            });
+
+            let installed = self.installed_plugins.get_or_insert_with(plugin_host::installed_plugins);
+            let mut picked = None;
+            let mut rescan = false;
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_source("installed_plugins")
+                    .selected_text(format!("Installed plugins ({})", installed.len()))
+                    .width(ui.available_width() - 70.0)
+                    .show_ui(ui, |ui| {
+                        for path in installed.iter() {
+                            let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                            if ui.selectable_label(false, name).on_hover_text(path.display().to_string()).clicked() {
+                                picked = Some(path.display().to_string());
+                            }
+                        }
+                    });
+                rescan = ui.button("Rescan").clicked();
+            });
+            if rescan {
+                self.installed_plugins = None;
+            }
+            if let Some(path) = picked {
+                self.plugin_path_text = path;
+                self.plugin_config.enabled = true;
+                reload = true;
+            }
+
+            if reload {
+                let path = self.plugin_path_text.trim().to_string();
+                // Parameter ids only mean something to the plugin they came from
+                if path != self.plugin_config.path {
+                    self.plugin_config.params.clear();
+                }
+                self.plugin_config.path = path;
+                self.engine().plugin.configure(&self.plugin_config);
+                self.save_current_settings();
+            }
+
+            let status = self.engine().plugin.status();
+            match status {
+                PluginStatus::Off if self.plugin_config.enabled && !self.plugin_config.path.is_empty() => {
+                    ui.label(egui::RichText::new("Loads when processing starts").size(11.0));
+                }
+                PluginStatus::Off => {}
+                PluginStatus::Running(name) => {
+                    ui.label(egui::RichText::new(format!("Running: {}", name)).size(11.0));
+                }
+                PluginStatus::Failed(e) => {
+                    ui.colored_label(egui::Color32::from_rgb(240, 71, 71), format!("Plugin bypassed: {}", e));
+                }
+            }
+
+            let params = self.engine().plugin.params();
+            if params.is_empty() {
+                return;
+            }
+            let mut save = false;
+            egui::ScrollArea::vertical().id_source("plugin_params").max_height(200.0).show(ui, |ui| {
+                for param in &params {
+                    let mut value = param.value;
+                    let mut slider = egui::Slider::new(&mut value, param.min..=param.max).text(&param.name);
+                    if param.stepped {
+                        slider = slider.step_by(1.0);
+                    }
+                    let response = ui.add(slider);
+                    if response.changed() {
+                        self.engine().plugin.set_param(param.id, value);
+                        self.plugin_config.set_param(param.id, value);
+                    }
+                    save |= response.drag_released() || (response.changed() && !response.dragged());
+                }
+            });
+            if ui.small_button("Reset parameters").clicked() {
+                for param in &params {
+                    self.engine().plugin.set_param(param.id, param.default);
+                }
+                self.plugin_config.params.clear();
+                save = true;
+            }
+            if save {
+                self.save_current_settings();
+            }
+        });
+    }
+
+    fn draw_de_esser_settings(&mut self, ui: &mut egui::Ui) {
+        ui.collapsing("De-esser", |ui| {
+            let mut changed = ui.checkbox(&mut self.de_esser.enabled, "Tame harsh \"s\" sounds").changed();
+            // Sliders save once dragging ends rather than on every step
+            let mut save = changed;
+
+            ui.add_enabled_ui(self.de_esser.enabled, |ui| {
+                let frequency = ui.add(
+                    egui::Slider::new(&mut self.de_esser.frequency, DE_ESSER_FREQUENCY_RANGE)
+                        .text("Center")
+                        .suffix(" Hz")
+                        .step_by(100.0),
+                );
+                let threshold = ui.add(
+                    egui::Slider::new(&mut self.de_esser.threshold_db, DE_ESSER_THRESHOLD_RANGE).text("Threshold").suffix(" dB"),
+                );
+                let mut percent = self.de_esser.amount * 100.0;
+                let amount = ui.add(egui::Slider::new(&mut percent, 0.0..=100.0).text("Amount").suffix("%"));
+                self.de_esser.amount = percent / 100.0;
+
+                for response in [&frequency, &threshold, &amount] {
+                    changed |= response.changed();
+                    save |= response.drag_released() || (response.changed() && !response.dragged());
+                }
+            });
+
+            if changed {
+                self.apply_de_esser();
+            }
+            if save {
+                self.save_current_settings();
+            }
+        });
+    }
+
+    fn draw_plosive_settings(&mut self, ui: &mut egui::Ui) {
+        ui.collapsing("Plosive reduction", |ui| {
+            let mut changed = ui.checkbox(&mut self.plosive.enabled, "Soften \"p\" and \"b\" pops").changed();
+            let mut save = changed;
+
+            ui.add_enabled_ui(self.plosive.enabled, |ui| {
+                let mut percent = self.plosive.strength * 100.0;
+                let strength = ui.add(egui::Slider::new(&mut percent, 0.0..=100.0).text("Strength").suffix("%"));
+                self.plosive.strength = percent / 100.0;
+                changed |= strength.changed();
+                save |= strength.drag_released() || (strength.changed() && !strength.dragged());
+            });
+
+            if changed {
+                self.apply_plosive();
+            }
+            if save {
+                self.save_current_settings();
+            }
+        });
+    }
+
+    fn draw_click_settings(&mut self, ui: &mut egui::Ui) {
+        ui.collapsing("Keyboard clicks", |ui| {
+            let mut changed = ui.checkbox(&mut self.click.enabled, "Turn down key clicks between words").changed();
+            let mut save = changed;
+
+            ui.add_enabled_ui(self.click.enabled, |ui| {
+                let mut percent = self.click.sensitivity * 100.0;
+                let sensitivity = ui.add(egui::Slider::new(&mut percent, 0.0..=100.0).text("Sensitivity").suffix("%"));
+                self.click.sensitivity = percent / 100.0;
+                changed |= sensitivity.changed();
+                save |= sensitivity.drag_released() || (sensitivity.changed() && !sensitivity.dragged());
+                ui.label(egui::RichText::new("Adds 10 ms of delay while enabled").size(11.0));
+            });
+
+            if changed {
+                self.apply_click();
+            }
+            if save {
+                self.save_current_settings();
+            }
+        });
+    }
+
+    fn draw_music_settings(&mut self, ui: &mut egui::Ui) {
+        ui.collapsing("Music detection", |ui| {
+            let mut changed = ui.checkbox(&mut self.music.enabled, "Pass music through unprocessed").changed();
+            let mut save = changed;
+
+            ui.add_enabled_ui(self.music.enabled, |ui| {
+                let mut percent = self.music.sensitivity * 100.0;
+                let sensitivity = ui.add(egui::Slider::new(&mut percent, 0.0..=100.0).text("Sensitivity").suffix("%"));
+                self.music.sensitivity = percent / 100.0;
+                changed |= sensitivity.changed();
+                save |= sensitivity.drag_released() || (sensitivity.changed() && !sensitivity.dragged());
+                ui.label(
+                    egui::RichText::new("Switches to bypass while an instrument is playing, back after 3 s without").size(11.0),
+                );
+            });
+
+            if changed {
+                self.apply_music();
+            }
+            if save {
+                self.save_current_settings();
+            }
+        });
+    }
+
+    fn draw_monitor_settings(&mut self, ui: &mut egui::Ui) {
+        ui.collapsing("Monitor output", |ui| {
+            let mut restart = ui.checkbox(&mut self.monitor_enabled, "Also play the processed audio on").changed();
+
+            ui.add_enabled_ui(self.monitor_enabled, |ui| {
+                let selected = if self.monitor_device.is_empty() { "Default output" } else { self.monitor_device.as_str() };
+                egui::ComboBox::from_id_source("monitor_device")
+                    .selected_text(selected)
+                    .width(ui.available_width() - 8.0)
+                    .show_ui(ui, |ui| {
+                        restart |= ui.selectable_value(&mut self.monitor_device, String::new(), "Default output").changed();
+                        for name in &self.output_devices {
+                            restart |= ui.selectable_value(&mut self.monitor_device, name.clone(), name).changed();
+                        }
+                    });
+
+                let mut percent = self.monitor_gain * 100.0;
+                let level = ui.add(egui::Slider::new(&mut percent, 0.0..=100.0).text("Level").suffix("%"));
+                self.monitor_gain = percent / 100.0;
+                if level.changed() {
+                    self.engine().set_monitor_gain(self.monitor_gain);
+                }
+                if level.drag_released() || (level.changed() && !level.dragged()) {
+                    self.save_current_settings();
+                }
+            });
+
+            if restart {
+                log::info!("Monitor output {}", if self.monitor_enabled { "enabled" } else { "disabled" });
+                self.apply_monitor();
+                if self.is_active() {
+                    self.restart_audio(RestartReason::SettingsChange);
+                } else {
+                    self.save_current_settings();
+                }
+            }
+        });
+    }
+
+    fn draw_earcon_settings(&mut self, ui: &mut egui::Ui) {
+        ui.collapsing("Sound cues", |ui| {
+            let mut save = ui.checkbox(&mut self.earcons_enabled, "Play a tone when the mic is muted, bypassed or live again").changed();
+            ui.add_enabled_ui(self.earcons_enabled, |ui| {
+                ui.horizontal(|ui| {
+                    let mut percent = self.earcon_volume * 100.0;
+                    let volume = ui.add(egui::Slider::new(&mut percent, 0.0..=100.0).text("Volume").suffix("%"));
+                    self.earcon_volume = percent / 100.0;
+                    save |= volume.drag_released() || (volume.changed() && !volume.dragged());
+                    if ui.small_button("Test").clicked() {
+                        self.play_earcon(Cue::Live);
+                    }
+                });
+            });
+            ui.label(
+                egui::RichText::new("Played on the monitor output, or the default playback device; never on the processed output")
+                    .size(11.0),
+            );
+            if save {
+                self.save_current_settings();
+            }
+        });
+    }
+
+    fn draw_routing_check(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        if let Some(report) = self.routing_check.as_ref().and_then(|c| c.poll()) {
+            self.routing_check = None;
+            self.routing_report = Some(report);
+        }
+        ui.collapsing("Verify routing", |ui| {
+            let output = self.output_devices.get(self.selected_output_index).cloned().unwrap_or_default();
+            let automatic = companion_capture_device(&output, &self.input_devices);
+            let shown = if self.routing_capture.is_empty() {
+                format!("Automatic ({})", automatic.as_deref().unwrap_or("none found"))
+            } else {
+                self.routing_capture.clone()
+            };
+            ui.label(egui::RichText::new(format!("Plays a short tone on '{}' and listens for it on:", output)).size(11.0));
+            egui::ComboBox::from_id_source("routing_capture").selected_text(shown).width(ui.available_width() - 8.0).show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.routing_capture, String::new(), "Automatic");
+                for name in self.input_devices.iter() {
+                    ui.selectable_value(&mut self.routing_capture, name.clone(), name);
+                }
+            });
+            let capture = if self.routing_capture.is_empty() { automatic } else { Some(self.routing_capture.clone()) };
+
+            ui.horizontal(|ui| {
+                let ready = self.routing_check.is_none() && capture.is_some() && !output.is_empty();
+                if ui.add_enabled(ready, egui::Button::new("Verify routing")).clicked() {
+                    if let Some(capture) = &capture {
+                        self.routing_report = None;
+                        self.routing_check = Some(RoutingCheck::start(&output, capture, ctx));
+                    }
+                }
+                if self.routing_check.is_some() {
+                    ui.spinner();
+                }
+            });
+            ui.label(egui::RichText::new("Anyone listening on the cable will hear the tone").size(11.0));
+
+            if let Some(report) = &self.routing_report {
+                let color = if report.is_ok() { egui::Color32::from_rgb(67, 181, 129) } else { egui::Color32::from_rgb(250, 166, 26) };
+                ui.label(egui::RichText::new(report.message()).size(11.0).color(color));
+            }
+        });
+    }
+
+    fn draw_mix_settings(&mut self, ui: &mut egui::Ui) {
+        ui.collapsing("Mix input", |ui| {
+            let mut restart = ui.checkbox(&mut self.mix_enabled, "Mix another source under my voice").changed();
+
+            ui.add_enabled_ui(self.mix_enabled, |ui| {
+                let selected = match (self.mix_source.device.is_empty(), self.mix_source.loopback) {
+                    (true, false) => "Default input".to_string(),
+                    (true, true) => "Default output (what you hear)".to_string(),
+                    (false, false) => self.mix_source.device.clone(),
+                    (false, true) => format!("{} (what you hear)", self.mix_source.device),
+                };
+                egui::ComboBox::from_id_source("mix_source")
+                    .selected_text(selected)
+                    .width(ui.available_width() - 8.0)
+                    .show_ui(ui, |ui| {
+                        let mut options = vec![
+                            (CaptureSource::default(), "Default input".to_string()),
+                            (CaptureSource { device: String::new(), loopback: true }, "Default output (what you hear)".to_string()),
+                        ];
+                        options.extend(
+                            self.input_devices.iter().map(|d| (CaptureSource { device: d.clone(), loopback: false }, d.clone())),
+                        );
+                        options.extend(self.output_devices.iter().map(|d| {
+                            (CaptureSource { device: d.clone(), loopback: true }, format!("{} (what you hear)", d))
+                        }));
+                        for (source, label) in options {
+                            restart |= ui.selectable_value(&mut self.mix_source, source, label).changed();
+                        }
+                    });
+
+                let mut percent = self.mix_gain * 100.0;
+                let volume = ui.add(egui::Slider::new(&mut percent, 0.0..=100.0).text("Volume").suffix("%"));
+                self.mix_gain = percent / 100.0;
+                if volume.changed() {
+                    self.engine().set_mix_gain(self.mix_gain);
+                }
+                if volume.drag_released() || (volume.changed() && !volume.dragged()) {
+                    self.save_current_settings();
+                }
+            });
+
+            if restart {
+                log::info!("Mix input {}", if self.mix_enabled { "enabled" } else { "disabled" });
+                self.apply_mix();
+                if self.is_active() {
+                    self.restart_audio(RestartReason::SettingsChange);
+                } else {
+                    self.save_current_settings();
+                }
+            }
+        });
+    }
+
+    fn draw_echo_cancellation_settings(&mut self, ui: &mut egui::Ui) {
+        ui.collapsing("Echo cancellation", |ui| {
+            let mut changed = ui.checkbox(&mut self.aec_enabled, "Remove speaker playback from the mic").changed();
+
+            ui.add_enabled_ui(self.aec_enabled, |ui| {
+                ui.label("Reference device:");
+                let selected = if self.aec_reference.is_empty() { "Default output" } else { self.aec_reference.as_str() };
+                egui::ComboBox::from_id_source("aec_reference")
+                    .selected_text(selected)
+                    .width(ui.available_width() - 8.0)
+                    .show_ui(ui, |ui| {
+                        changed |= ui.selectable_value(&mut self.aec_reference, String::new(), "Default output").changed();
+                        for name in &self.output_devices {
+                            changed |= ui.selectable_value(&mut self.aec_reference, name.clone(), name).changed();
+                        }
+                    });
+
+                let delay = self.engine_status.echo_delay_ms;
+                if self.run.is_processing() && delay > 0 {
+                    ui.label(egui::RichText::new(format!("Estimated echo delay: {} ms", delay)).size(11.0));
+                }
+            });
+
+            if changed {
+                log::info!("Echo cancellation {}", if self.aec_enabled { "enabled" } else { "disabled" });
+                self.apply_echo_reference();
+                if self.is_active() {
+                    self.restart_audio(RestartReason::SettingsChange);
+                } else {
+                    self.save_current_settings();
+                }
+            }
+        });
+    }
+
+    // The capture device that should stay Windows' default communication device
+    fn expected_default_device(&self) -> Option<String> {
+        if !self.default_device_config.device.is_empty() {
+            return Some(self.default_device_config.device.clone());
+        }
+        let output = self.output_devices.get(self.selected_output_index)?;
+        companion_capture_device(output, &self.input_devices)
+    }
+
+    fn check_default_device(&mut self) {
+        if !cfg!(windows) || !self.default_device_config.enabled {
+            return;
+        }
+        let Some(expected) = self.expected_default_device() else { return };
+
+        self.default_device_guard.check(&expected);
+        let drifted = matches!(self.default_device_guard.state(), DefaultDeviceState::Drifted { .. });
+        if drifted && self.default_device_config.auto_fix && self.default_device_guard.can_fix() {
+            self.fix_default_device(&expected);
+        }
+    }
+
+    fn fix_default_device(&mut self, expected: &str) {
+        match self.default_device_guard.fix(expected) {
+            Ok(()) => self.status_message = format!("Default communication device set to {}", expected),
+            Err(e) => {
+                log::warn!("Could not set default communication device to '{}': {}", expected, e);
+                self.status_message = format!("Error: could not set default device: {}", e);
+            }
+        }
+    }
+
+    fn draw_default_device_banner(&mut self, ui: &mut egui::Ui) {
+        if !self.default_device_config.enabled {
+            return;
+        }
+        let DefaultDeviceState::Drifted { current } = self.default_device_guard.state().clone() else { return };
+        let Some(expected) = self.expected_default_device() else { return };
+
+        egui::Frame::none()
+            .fill(egui::Color32::from_rgba_premultiplied(80, 60, 20, 240))
+            .rounding(12.0)
+            .inner_margin(10.0)
+            .show(ui, |ui| {
+                ui.label(
+                    egui::RichText::new(format!(
+                        "Windows is using '{}' as the communication microphone instead of '{}'",
+                        current.as_deref().unwrap_or("no device"),
+                        expected
+                    ))
+                    .size(11.0),
+                );
+                ui.horizontal(|ui| {
+                    if self.default_device_guard.can_fix() {
+                        if ui.button("Fix").clicked() {
+                            self.fix_default_device(&expected);
+                        }
+                    } else if ui.button("Open Sound settings").clicked() {
+                        platform::open_sound_settings();
+                    }
+                });
+            });
+        ui.add_space(10.0);
+    }
+
+    fn draw_default_device_settings(&mut self, ui: &mut egui::Ui) {
+        ui.collapsing("Default communication device", |ui| {
+            let mut changed = false;
+            let automatic = match self.output_devices.get(self.selected_output_index) {
+                Some(output) => companion_capture_device(output, &self.input_devices),
+                None => None,
+            };
+            let shown = if self.default_device_config.device.is_empty() {
+                format!("Automatic ({})", automatic.as_deref().unwrap_or("none found"))
+            } else {
+                self.default_device_config.device.clone()
+            };
+
+            changed |= ui.checkbox(&mut self.default_device_config.enabled, format!("Keep {} as default", shown)).changed();
+
+            ui.add_enabled_ui(self.default_device_config.enabled, |ui| {
+                egui::ComboBox::from_id_source("default_comm_device").selected_text(shown.clone()).width(ui.available_width() - 8.0).show_ui(ui, |ui| {
+                    changed |= ui.selectable_value(&mut self.default_device_config.device, String::new(), "Automatic").changed();
+                    for name in self.input_devices.iter() {
+                        changed |= ui.selectable_value(&mut self.default_device_config.device, name.clone(), name).changed();
+                    }
+                });
+                changed |= ui.checkbox(&mut self.default_device_config.auto_fix, "Fix automatically when it changes").changed();
+
+                let status = match self.default_device_guard.state() {
+                    DefaultDeviceState::Unknown => "Not checked yet".to_string(),
+                    DefaultDeviceState::Ok => "Default communication device is correct".to_string(),
+                    DefaultDeviceState::Drifted { current } => format!("Currently: {}", current.as_deref().unwrap_or("no device")),
+                };
+                ui.label(egui::RichText::new(status).size(11.0));
+                if !self.default_device_guard.can_fix() {
+                    ui.label(egui::RichText::new("Automatic fixing isn't available on this system").size(11.0));
+                }
+            });
+
+            if changed {
+                self.default_device_guard.reset();
+                self.save_current_settings();
+            }
+        });
+    }
+
+    fn draw_statistics(&mut self, ui: &mut egui::Ui) {
+        fn summary(stats: &SessionStats) -> String {
+            format!(
+                "You spoke for {}, gate was closed {:.0}% of the time, {} clipping events.",
+                format_duration(stats.talk_seconds()),
+                stats.gated_percent(),
+                stats.clip_events
+            )
+        }
+        fn details(ui: &mut egui::Ui, stats: &SessionStats) {
+            ui.label(egui::RichText::new(summary(stats)).size(11.0));
+            ui.label(
+                egui::RichText::new(format!(
+                    "System-muted: {}  ·  Average VAD: {:.2}",
+                    format_duration(stats.muted_seconds()),
+                    stats.average_vad()
+                ))
+                .size(11.0)
+                .color(egui::Color32::from_rgb(142, 146, 151)),
+            );
+        }
+
+        ui.collapsing("Statistics", |ui| {
+            ui.label("This session");
+            details(ui, &self.session_stats());
+            ui.add_space(4.0);
+            ui.label("All time");
+            details(ui, &self.total_lifetime_stats());
+            ui.add_space(4.0);
+
+            ui.horizontal(|ui| {
+                if ui.button("Reset session").clicked() {
+                    self.flush_lifetime_stats();
+                    if let Ok(mut st) = self.engine().stats.lock() {
+                        *st = SessionStats::default();
+                    }
+                    self.stats_flushed = SessionStats::default();
+                    self.save_current_settings();
+                }
+                if ui.button("Reset all time").clicked() {
+                    self.flush_lifetime_stats();
+                    self.lifetime_stats = SessionStats::default();
+                    self.save_current_settings();
+                }
+            });
+        });
+    }
+
+    // Only drawn while expanded, so the gauges cost nothing otherwise
+    fn draw_buffer_diagnostics(&mut self, ui: &mut egui::Ui) {
+        ui.collapsing("Diagnostics", |ui| {
+            let (info, counters) = {
+                let engine = self.engine();
+                (engine.stream_info.clone(), engine.counters.clone())
+            };
+            let Some(info) = info else {
+                ui.label(egui::RichText::new("Start processing to see live buffer levels.").size(11.0));
+                return;
+            };
+            ui.ctx().request_repaint_after(DIAGNOSTICS_REPAINT);
+
+            let status = &self.engine_status;
+            let buffer_gauge = |ui: &mut egui::Ui, name: &str, fill: usize| {
+                let fraction = fill as f32 / RING_BUFFER_SIZE as f32;
+                ui.label(egui::RichText::new(format!("{}: {:.0}%", name, fraction * 100.0)).size(11.0));
+                ui.add(egui::ProgressBar::new(fraction).desired_height(6.0));
+            };
+            buffer_gauge(ui, "Input buffer", status.input_fill);
+            buffer_gauge(ui, "Output buffer", status.output_fill);
+            ui.add_space(4.0);
+
+            let resampler = match info.resampler {
+                Some(name) => format!("{} ({:.1} ms)", name, info.resampler_delay_ms),
+                None => "not needed".to_string(),
+            };
+            let muted = egui::Color32::from_rgb(142, 146, 151);
+            for line in [
+                format!("Input: {} Hz  ·  Output: {} Hz", status.input_sample_rate, status.output_sample_rate),
+                format!("Resampler: {}", resampler),
+                format!("Latency estimate: {:.0} ms", status.latency_ms),
+                format!("Processing load: {:.0}%{}", status.processing_load * 100.0, if status.overloaded { " (overloaded)" } else { "" }),
+            ] {
+                ui.label(egui::RichText::new(line).size(11.0).color(muted));
+            }
+            let backlog_ms = status.backlog_ms;
+            let backlog = match (self.latency_cap_ms, status.latency_skips) {
+                (0, _) => format!("Backlog: {} ms (no cap)", backlog_ms),
+                (cap, 0) => format!("Backlog: {} ms (cap {} ms)", backlog_ms, cap),
+                (cap, skips) => format!("Backlog: {} ms (cap {} ms, {} ms skipped)", backlog_ms, cap, skips * 10),
+            };
+            ui.label(egui::RichText::new(backlog).size(11.0).color(muted));
+            if status.input_errors > 0 {
+                ui.label(egui::RichText::new(format!("Input callback errors: {}", status.input_errors)).size(11.0).color(muted));
+            }
+            ui.add_space(4.0);
+
+            ui.collapsing("Stream parameters", |ui| {
+                egui::Grid::new("stream_parameters").num_columns(2).spacing([12.0, 2.0]).show(ui, |ui| {
+                    for (name, value) in diagnostics::stream_parameters(&info, &counters) {
+                        ui.label(egui::RichText::new(name).size(11.0).color(muted));
+                        ui.label(egui::RichText::new(value).size(11.0));
+                        ui.end_row();
+                    }
+                });
+                if ui.small_button("Copy to clipboard").clicked() {
+                    let text = diagnostics::stream_parameters_markdown(&info, &counters);
+                    ui.output_mut(|o| o.copied_text = text);
+                }
+            });
+        });
+    }
+
+    fn draw_replay_settings(&mut self, ui: &mut egui::Ui) {
+        ui.collapsing("Replay recording", |ui| {
+            let muted = egui::Color32::from_rgb(142, 146, 151);
+            ui.label(
+                egui::RichText::new(
+                    "Records the raw microphone audio and every control change so a problem can be reproduced exactly. \
+                     Recordings contain everything you say; delete them once you're done.",
+                )
+                .size(11.0)
+                .color(muted),
+            );
+            if self.engine_status.replay_recording {
+                if ui.button("⏹ Stop recording").clicked() {
+                    self.engine().stop_replay_recording();
+                    self.refresh_replay_files();
+                }
+            } else {
+                let start = ui
+                    .add_enabled(self.run.is_processing(), egui::Button::new("⏺ Start recording"))
+                    .on_disabled_hover_text("Start processing first");
+                if start.clicked() {
+                    self.start_replay_recording();
+                }
+            }
+
+            let mut delete = None;
+            for file in &self.replay_files {
+                ui.horizontal(|ui| {
+                    let name = file.path.file_stem().map_or_else(String::new, |n| n.to_string_lossy().into_owned());
+                    ui.label(egui::RichText::new(format!("{}  ({:.1} MB)", name, file.bytes as f64 / 1_000_000.0)).size(11.0));
+                    // Deleting the file being written would only lose the rest of it
+                    let writing = self.engine_status.replay_recording && Some(&file.path) == self.replay_files.first().map(|f| &f.path);
+                    if ui.add_enabled(!writing, egui::Button::new("Delete").small()).clicked() {
+                        delete = Some(file.path.clone());
+                    }
+                });
+            }
+            if let Some(path) = delete {
+                replays::delete(&path);
+                self.refresh_replay_files();
+            }
+            ui.horizontal(|ui| {
+                if !self.replay_files.is_empty() && ui.add_enabled(!self.engine_status.replay_recording, egui::Button::new("Delete all").small()).clicked() {
+                    for file in &self.replay_files {
+                        replays::delete(&file.path);
+                    }
+                    self.refresh_replay_files();
+                }
+                if ui.small_button("Open folder").on_hover_text(format!("Replay one with {} <file>", replays::REPLAY_FLAG)).clicked() {
+                    if let Some(dir) = get_config_dir() {
+                        open_folder(&replays::replay_dir(&dir));
+                    }
+                }
+            });
+        });
+    }
+
+    fn start_replay_recording(&mut self) {
+        let Some(dir) = get_config_dir() else { return };
+        let started = replays::new_path(&dir).and_then(|path| self.engine().start_replay_recording(&path));
+        if let Err(e) = started {
+            log::warn!("Could not start a replay recording: {}", e);
+            self.status_message = format!("Error: could not start recording ({})", e);
+        }
+        let recording = self.engine().replay_recording.load(std::sync::atomic::Ordering::Relaxed);
+        self.engine_status.replay_recording = recording;
+        self.refresh_replay_files();
+    }
+
+    fn refresh_replay_files(&mut self) {
+        self.replay_files = get_config_dir().map(|dir| replays::list(&dir)).unwrap_or_default();
+    }
+
+    fn sync_metrics_logger(&mut self) {
+        if self.metrics_enabled && !self.metrics_logger.is_running() {
+            if let Some(dir) = get_config_dir() {
+                let (counters, stats) = {
+                    let engine = self.engine();
+                    (engine.counters.clone(), engine.stats.clone())
+                };
+                self.metrics_logger.start(metrics::metrics_dir(&dir), counters, stats);
+            }
+        } else if !self.metrics_enabled {
+            self.metrics_logger.stop();
+        }
+    }
+
+    // Restarts the writer when the path changes
+    fn sync_status_file(&mut self) {
+        let path = match self.status_file_path.trim() {
+            "" => get_config_dir().map(|dir| status_file::default_path(&dir)),
+            custom => Some(std::path::PathBuf::from(custom)),
+        };
+        match path {
+            Some(path) if self.status_file_enabled => {
+                if self.status_file.path() != Some(path.as_path()) {
+                    self.status_file.start(path, &self.audio_engine.lock().unwrap_or_else(std::sync::PoisonError::into_inner));
+                }
+            }
+            _ => self.status_file.stop(),
+        }
+    }
+
+    fn copy_status(&mut self, ctx: &egui::Context) {
+        let settings = self.current_settings();
+        let engine = self.engine();
+        let text = diagnostics::status_text(&self.engine_status, engine.stream_info.as_ref(), &engine.counters, &settings, &self.run.uptime);
+        drop(engine);
+        ctx.output_mut(|o| o.copied_text = text);
+        self.status_copied = Some(Instant::now());
+    }
+
+    fn create_diagnostic_report(&mut self) {
+        let config_dir = get_config_dir();
+        let (settings, stats) = (self.current_settings(), self.session_stats());
+        let engine = self.engine();
+        let report = diagnostics::build_report(&settings, engine.stream_info.as_ref(), &engine.counters, &stats, &self.run.uptime, config_dir.as_deref());
+        drop(engine);
+        self.diagnostic_report = Some(report);
+    }
+
+    fn draw_diagnostic_report(&mut self, ctx: &egui::Context) {
+        let Some(report) = &self.diagnostic_report else { return };
+        let mut open = true;
+        let mut save = false;
+        let mut close = false;
+
+        egui::Window::new("Diagnostic report")
+            .open(&mut open)
+            .collapsible(false)
+            .default_size([330.0, 360.0])
+            .show(ctx, |ui| {
+                ui.label(egui::RichText::new("This is everything the report contains. Review it before saving.").size(11.0));
+                ui.add_space(4.0);
+                egui::ScrollArea::both().max_height(280.0).show(ui, |ui| {
+                    ui.add(egui::Label::new(egui::RichText::new(report.as_str()).monospace().size(10.0)).wrap(false));
+                });
+                ui.add_space(4.0);
+                ui.horizontal(|ui| {
+                    save = ui.button("Save and open folder").clicked();
+                    close = ui.button("Cancel").clicked();
+                });
+            });
+
+        if save {
+            match get_config_dir().ok_or_else(|| "No config directory (APPDATA is not set)".to_string()).and_then(|dir| {
+                diagnostics::save_report(&dir, report).map_err(|e| e.to_string())
+            }) {
+                Ok(path) => {
+                    log::info!("Diagnostic report written to {}", path.display());
+                    if let Some(dir) = path.parent() {
+                        open_folder(dir);
+                    }
+                    self.status_message = "Diagnostic report saved".to_string();
+                }
+                Err(e) => {
+                    log::error!("Failed to write diagnostic report: {}", e);
+                    self.status_message = format!("Error: could not save report: {}", e);
+                }
+            }
+        }
+        if save || close || !open {
+            self.diagnostic_report = None;
+        }
+    }
+
+    // Closing the window (Alt+F4, the title bar) or the tray's Exit. While processing, the
+    // user is asked first, since exiting leaves apps with the unfiltered microphone.
+    fn handle_exit_request(&mut self, ctx: &egui::Context) {
+        let from_tray = self.tray_requests.exit.swap(false, std::sync::atomic::Ordering::SeqCst);
+        let from_window = ctx.input(|i| i.viewport().close_requested());
+        if !(from_tray || from_window) || self.exit_confirmed {
+            return;
+        }
+        if !(self.run.is_processing() && self.confirm_exit) {
+            self.exit_app(ctx);
+            return;
+        }
+        if from_window {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+        }
+        if self.is_minimized_to_tray {
+            self.in_tray_flag.store(false, std::sync::atomic::Ordering::SeqCst);
+            self.restore_requested.store(true, std::sync::atomic::Ordering::SeqCst);
+        }
+        self.exit_prompt = true;
+        self.exit_dont_ask = false;
+        log::info!("Exit requested while processing, asking first");
+    }
+
+    fn exit_app(&mut self, ctx: &egui::Context) {
+        log::info!("Exiting");
+        self.exit_confirmed = true;
+        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+    }
+
+    fn draw_exit_prompt(&mut self, ctx: &egui::Context) {
+        if !self.exit_prompt {
+            return;
+        }
+        let mut exit = false;
+        let mut cancel = false;
+        egui::Window::new("Exit SilentStream?")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label("Noise suppression is active — exit anyway?");
+                ui.add_space(4.0);
+                ui.checkbox(&mut self.exit_dont_ask, "Don't ask again");
+                ui.add_space(4.0);
+                ui.horizontal(|ui| {
+                    exit = ui.button("Exit").clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+        if exit {
+            if self.exit_dont_ask {
+                self.confirm_exit = false;
+                self.save_current_settings();
+            }
+            self.exit_prompt = false;
+            self.exit_app(ctx);
+        } else if cancel || ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.exit_prompt = false;
+        }
+    }
+
+    fn report_underruns(&mut self) {
+        if self.last_underrun_check.elapsed() < Duration::from_secs(60) {
+            return;
+        }
+        self.last_underrun_check = Instant::now();
+        let total = self.engine_status.underruns;
+        if total > self.underruns_reported && self.run.is_processing() {
+            log::warn!("{} output samples played as silence in the last minute (buffer underrun)", total - self.underruns_reported);
+        }
+        self.underruns_reported = total;
+    }
+
+    fn draw_event_log(&mut self, ctx: &egui::Context) {
+        if !self.show_event_log {
+            return;
+        }
+        let entries = logging::recent_entries();
+        let mut open = true;
+
+        egui::Window::new("Event log")
+            .open(&mut open)
+            .collapsible(false)
+            .default_size([330.0, 360.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Copy all").clicked() {
+                        let text: Vec<String> = entries
+                            .iter()
+                            .map(|e| format!("{} [{}] {}", e.time, e.level, e.message))
+                            .collect();
+                        ui.output_mut(|o| o.copied_text = text.join("\n"));
+                    }
+                    if ui.button("Clear").clicked() {
+                        logging::clear_recent();
+                    }
+                });
+                ui.add_space(4.0);
+                egui::ScrollArea::vertical().stick_to_bottom(true).show(ui, |ui| {
+                    if entries.is_empty() {
+                        ui.label(egui::RichText::new("No events yet").size(11.0));
+                    }
+                    for entry in entries.iter() {
+                        let color = match entry.level {
+                            log::Level::Error => egui::Color32::from_rgb(240, 71, 71),
+                            log::Level::Warn => egui::Color32::from_rgb(250, 166, 26),
+                            log::Level::Info => egui::Color32::from_rgb(220, 221, 222),
+                            _ => egui::Color32::from_rgb(142, 146, 151),
+                        };
+                        ui.label(
+                            egui::RichText::new(format!("{}  {}", entry.time, entry.message))
+                                .size(11.0)
+                                .color(color),
+                        );
+                    }
+                });
+            });
+
+        if !open {
+            self.show_event_log = false;
+        }
+    }
+
+    // Keeps running with the window closed; the list shows where it got to when reopened
+    fn draw_batch_window(&mut self, ctx: &egui::Context) {
+        if let Some(job) = self.batch.job.as_mut() {
+            job.poll();
+        }
+        if !self.show_batch {
+            return;
+        }
+        let running = self.batch.is_running();
+        let dropped: Vec<std::path::PathBuf> = ctx.input(|i| i.raw.dropped_files.iter().filter_map(|f| f.path.clone()).collect());
+        if !dropped.is_empty() && !running {
+            self.batch.add(&dropped);
+        }
+        let mut open = true;
+        let mut start = false;
+
+        egui::Window::new("Batch process")
+            .open(&mut open)
+            .collapsible(false)
+            .default_size([360.0, 400.0])
+            .show(ctx, |ui| {
+                ui.add_enabled_ui(!running, |ui| {
+                    ui.label(egui::RichText::new("Drop recordings (WAV, FLAC, MP3, Ogg) or folders here, or add one by path").size(11.0));
+                    ui.horizontal(|ui| {
+                        let field = ui.add(egui::TextEdit::singleline(&mut self.batch.path).hint_text("Folder or file").desired_width(220.0));
+                        let entered = field.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                        if field.changed() {
+                            self.batch.path_missing = false;
+                        }
+                        if (ui.button("Add").clicked() || entered) && !self.batch.path.trim().is_empty() {
+                            let path = std::path::PathBuf::from(self.batch.path.trim());
+                            self.batch.path_missing = !path.exists();
+                            if !self.batch.path_missing {
+                                self.batch.add(&[path]);
+                                self.batch.path.clear();
+                            }
+                        }
+                        if ui.button("Clear").clicked() {
+                            self.batch.inputs.clear();
+                            self.batch.job = None;
+                        }
+                    });
+                    if self.batch.path_missing {
+                        ui.label(egui::RichText::new("Not found").size(11.0).color(egui::Color32::from_rgb(240, 71, 71)));
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Threshold:");
+                        ui.add(egui::Slider::new(&mut self.batch.threshold, 0.0..=VAD_THRESHOLD_MAX));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Strength:");
+                        for mode in [SuppressionMode::Normal, SuppressionMode::Strong] {
+                            ui.selectable_value(&mut self.batch.mode, mode, mode.label());
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Output folder suffix:");
+                        ui.add(egui::TextEdit::singleline(&mut self.batch.suffix).desired_width(100.0));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Output:");
+                        for format in OutputFormat::ALL {
+                            ui.selectable_value(&mut self.batch.format, format, format.label());
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.batch.normalize, "Normalize loudness to")
+                            .on_hover_text(format!("After denoising, with the true peak kept under {} dBTP", DEFAULT_CEILING_DB));
+                        ui.add_enabled(self.batch.normalize, egui::Slider::new(&mut self.batch.target_lufs, TARGET_LUFS_RANGE).suffix(" LUFS"));
+                    });
+                });
+                ui.horizontal(|ui| {
+                    if running {
+                        if ui.button("Cancel").clicked() {
+                            if let Some(job) = &self.batch.job {
+                                job.cancel();
+                            }
+                        }
+                    } else {
+                        let ready = !self.batch.inputs.is_empty() && !self.batch.suffix.trim().is_empty();
+                        start = ui.add_enabled(ready, egui::Button::new("Process")).clicked();
+                    }
+                    if let Some(job) = &self.batch.job {
+                        let (done, total) = job.counts();
+                        ui.label(format!("{} of {} done", done, total));
+                    }
+                });
+                ui.add_space(4.0);
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    if self.batch.inputs.is_empty() {
+                        ui.label(egui::RichText::new("No files yet").size(11.0));
+                    }
+                    let files = self.batch.job.as_ref().map(|job| &job.files);
+                    for (i, input) in self.batch.inputs.iter().enumerate() {
+                        let name = input.file_name().map_or_else(|| input.display().to_string(), |n| n.to_string_lossy().into_owned());
+                        let state = files.and_then(|files| files.get(i)).map_or(&FileState::Queued, |f| &f.state);
+                        ui.horizontal(|ui| {
+                            ui.label(egui::RichText::new(name).size(11.0)).on_hover_text(input.display().to_string());
+                            let (text, color) = match state {
+                                FileState::Running(done) => {
+                                    ui.add(egui::ProgressBar::new(*done).desired_width(120.0).show_percentage());
+                                    return;
+                                }
+                                FileState::Queued => ("Waiting".to_string(), egui::Color32::from_rgb(142, 146, 151)),
+                                FileState::Done(report) => (format!("Done, {}", loudness_text(report)), egui::Color32::from_rgb(67, 181, 129)),
+                                FileState::Skipped(reason) => (format!("Skipped: {}", reason), egui::Color32::from_rgb(250, 166, 26)),
+                                FileState::Failed(reason) => (format!("Failed: {}", reason), egui::Color32::from_rgb(240, 71, 71)),
+                                FileState::Cancelled => ("Cancelled".to_string(), egui::Color32::from_rgb(142, 146, 151)),
+                            };
+                            ui.label(egui::RichText::new(text).size(11.0).color(color));
+                        });
+                    }
+                });
+            });
+
+        if start {
+            self.start_batch(ctx);
         }
-        
-        if let Ok(mut th) = self.audio_engine.vad_threshold.lock() {
-            *th = self.vad_threshold;
+        if !open {
+            self.show_batch = false;
         }
-        
-        match self.audio_engine.start(self.selected_input_index, self.selected_output_index) {
-            Ok(_) => {
-                self.is_processing = true;
-                self.status_message = "Processing audio".to_string();
-            },
-            Err(e) => {
-                self.status_message = format!("Error: {}", e);
-            }
+    }
+
+    // Same filters as live processing, with the window's threshold and strength
+    fn start_batch(&mut self, ctx: &egui::Context) {
+        let controls = Controls {
+            threshold: self.batch.threshold,
+            gate: self.gate,
+            two_pass: self.batch.mode == SuppressionMode::Strong,
+            de_esser: self.de_esser,
+            plosive: self.plosive,
+            click: self.click,
+            music: self.music,
+            chain: self.dsp_chain,
+            boost: self.boost,
+            ..Controls::default()
+        };
+        let suffix = self.batch.suffix.trim().to_string();
+        self.batch.job = Some(BatchJob::start(self.batch.inputs.clone(), &suffix, self.batch.options(), controls, ctx));
+    }
+
+    fn sync_update_checker(&mut self, ctx: &egui::Context) {
+        if self.update_check_enabled {
+            self.update_checker.start(self.last_update_check, ctx);
+        } else {
+            self.update_checker.stop();
+            self.available_update = None;
         }
     }
-    
-    fn restart_audio(&mut self) {
-        self.audio_engine.stop();
-        self.is_processing = false;
-        
-        match self.audio_engine.start(self.selected_input_index, self.selected_output_index) {
-            Ok(_) => {
-                self.is_processing = true;
-                self.status_message = "Processing audio".to_string();
-                self.save_current_settings();
-            },
-            Err(e) => {
-                self.status_message = format!("Error: {}", e);
+
+    fn handle_update_events(&mut self) {
+        for event in self.update_checker.poll() {
+            match event {
+                UpdateEvent::Checked(at) => {
+                    self.last_update_check = at;
+                    self.save_current_settings();
+                }
+                UpdateEvent::Available(release) => self.available_update = Some(release),
             }
         }
     }
-    
+
+    fn draw_update_banner(&mut self, ui: &mut egui::Ui) {
+        let Some(release) = self.available_update.clone() else { return };
+
+        egui::Frame::none()
+            .fill(egui::Color32::from_rgba_premultiplied(45, 35, 80, 240))
+            .rounding(12.0)
+            .inner_margin(10.0)
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new(format!("{} available", release.version)).size(11.0));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.small_button("Dismiss").clicked() {
+                            self.available_update = None;
+                        }
+                        if ui.small_button("Release page").clicked() {
+                            platform::open(&release.url);
+                        }
+                    });
+                });
+            });
+        ui.add_space(10.0);
+    }
+
     fn update_cpu_usage(&mut self) {
         if self.show_cpu_usage && self.last_cpu_check.elapsed() > Duration::from_millis(1000) {
             self.sysinfo.refresh_process_specifics(
@@ -360,73 +3500,89 @@ impl SilentStreamApp {
             self.tray_listener_started = true;
             let ctx_clone = ctx.clone();
             let restore_flag = self.restore_requested.clone();
+            let hide_flag = self.hide_requested.clone();
             let hwnd_store = self.window_hwnd.clone();
             let in_tray = self.in_tray_flag.clone();
 
-            std::thread::spawn(move || {
-                loop {
-                    let mut got_click = false;
-
-                    // Drain all events (must always drain to avoid channel backup)
-                    while let Ok(_) = MenuEvent::receiver().try_recv() {
-                        got_click = true;
-                    }
-                    while let Ok(event) = TrayIconEvent::receiver().try_recv() {
-                        if let TrayIconEvent::Click { .. } = &event {
-                            got_click = true;
-                        }
-                    }
+            // Route tray and menu clicks and the hotkey into one channel so the listener can
+            // block on it instead of polling while the app sits in the tray. Only this thread
+            // restores, so a click and a hotkey press can't both act on the same hide.
+            let (click_tx, click_rx) = std::sync::mpsc::channel::<tray::WindowRequest>();
+            tray::listen(click_tx.clone(), self.tray_requests.clone(), ctx);
+            self.window_requests = Some(click_tx);
 
+            std::thread::spawn(move || {
+                while let Ok(request) = click_rx.recv() {
                     // Only restore if we're actually in tray mode
-                    if got_click && in_tray.load(std::sync::atomic::Ordering::SeqCst) {
+                    if in_tray.load(std::sync::atomic::Ordering::SeqCst) {
                          in_tray.store(false, std::sync::atomic::Ordering::SeqCst);
                          restore_flag.store(true, std::sync::atomic::Ordering::SeqCst);
 
                          // Restore window from background thread
-                         if let Ok(guard) = hwnd_store.lock() {
-                             if let Some(hwnd) = *guard {
-                                 let hwnd_copy = hwnd;
-                                 std::thread::spawn(move || {
-                                     unsafe {
-                                         use windows_sys::Win32::UI::WindowsAndMessaging::*;
-                                         ShowWindow(hwnd_copy as isize, SW_SHOW as i32);
-                                         ShowWindow(hwnd_copy as isize, SW_RESTORE as i32);
-                                         SetForegroundWindow(hwnd_copy as isize);
-                                     }
-                                 });
-                             }
+                         if let Some(hwnd) = hwnd_store.lock().ok().and_then(|guard| *guard) {
+                             std::thread::spawn(move || platform::show_window(hwnd));
                          }
 
+                         ctx_clone.request_repaint();
+                    } else if request == tray::WindowRequest::Toggle {
+                         // Hiding needs the UI thread
+                         hide_flag.store(true, std::sync::atomic::Ordering::SeqCst);
                          ctx_clone.request_repaint();
                     }
-
-                    std::thread::sleep(Duration::from_millis(100));
                 }
             });
         }
     }
     
+    // Re-registers after the shortcut changed; an empty one just unregisters
+    fn sync_hotkey(&mut self) {
+        self.hotkey_listener.stop();
+        self.hotkey_error = None;
+        let Some(requests) = self.window_requests.clone() else { return };
+        match hotkey::Hotkey::parse(&self.window_hotkey) {
+            Ok(Some(hotkey)) => {
+                if let Err(e) = self.hotkey_listener.start(hotkey, requests) {
+                    log::warn!("Hotkey {}: {}", self.window_hotkey, e);
+                    self.hotkey_error = Some(e);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => self.hotkey_error = Some(e),
+        }
+    }
+
+    // The hotkey pressed while the window is showing
+    fn check_hide_request(&mut self, ctx: &egui::Context) {
+        if !self.hide_requested.swap(false, std::sync::atomic::Ordering::SeqCst) || self.is_minimized_to_tray {
+            return;
+        }
+        // Without a tray the window was only minimized, so the hotkey brings it back
+        if ctx.input(|i| i.viewport().minimized).unwrap_or(false) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+            ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+        } else {
+            self.minimize_to_tray(ctx);
+        }
+    }
+
     fn check_restore_request(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         if self.restore_requested.load(std::sync::atomic::Ordering::Relaxed) {
              self.restore_requested.store(false, std::sync::atomic::Ordering::Relaxed);
              self.is_minimized_to_tray = false;
              self.last_restore_time = Some(Instant::now());
+             log::info!("Restored from tray");
 
              // Egui restore commands
              ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
              ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
              ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
 
-             // Force Win32 Restore
-             if let Ok(guard) = self.window_hwnd.lock() {
-                 if let Some(hwnd) = *guard {
-                     unsafe {
-                         use windows_sys::Win32::UI::WindowsAndMessaging::*;
-                         ShowWindow(hwnd as _, SW_RESTORE as i32);
-                         ShowWindow(hwnd as _, SW_SHOW as i32);
-                         SetForegroundWindow(hwnd as _);
-                         BringWindowToTop(hwnd as _);
-                     }
+             // Force a native restore where there is a window handle
+             if let Some(hwnd) = self.window_hwnd.lock().ok().and_then(|guard| *guard) {
+                 platform::show_window(hwnd);
+                 // SW_RESTORE alone can bring it back on the primary display at its old size
+                 if let Some(placement) = &self.saved_placement {
+                     placement.restore(hwnd);
                  }
              }
 
@@ -437,20 +3593,42 @@ impl SilentStreamApp {
 
 impl eframe::App for SilentStreamApp {
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
-        // CAPTURE HWND ONCE
+        // CAPTURE HWND ONCE (stays None off Windows)
         if self.window_hwnd.lock().unwrap().is_none() {
-             use raw_window_handle::{HasWindowHandle, RawWindowHandle};
-             if let Ok(handle) = frame.window_handle() {
-                 if let RawWindowHandle::Win32(handle) = handle.as_raw() {
-                     let hwnd = handle.hwnd.get();
-                     *self.window_hwnd.lock().unwrap() = Some(hwnd);
-                 }
+             if let Some(hwnd) = platform::window_handle(frame) {
+                 *self.window_hwnd.lock().unwrap() = Some(hwnd);
              }
         }
 
         // Tray listener must always run to handle restore clicks
         self.ensure_tray_listener(ctx);
+        self.handle_exit_request(ctx);
         self.check_restore_request(ctx, frame);
+        self.check_hide_request(ctx);
+        self.handle_supervisor_events(ctx);
+        self.handle_trigger_events();
+        self.handle_idle_pause();
+        let status = self.engine().status();
+        self.engine_status = status;
+        if self.status_file.is_running() {
+            self.status_file.set_devices(self.engine_status.input_device.clone().zip(self.engine_status.output_device.clone()));
+        }
+        self.handle_earcons();
+        self.handle_noise_print_learning();
+        self.handle_update_events();
+        self.update_tray_tooltip();
+        self.update_tray_alert(ctx);
+        self.update_taskbar_overlay();
+        self.handle_swap_request();
+        self.handle_preset_request();
+        self.handle_device_restart(ctx);
+        self.sync_input_monitor(ctx);
+        self.check_microphone_privacy();
+        self.check_default_device();
+        self.save_stats_periodically();
+        self.report_underruns();
+        self.update_osd(ctx);
+        self.sync_supervisor();
 
         // When minimized to tray: skip ALL rendering and UI work.
         // eframe 0.26 has a bug where request_repaint_after is ignored on Windows,
@@ -463,20 +3641,54 @@ impl eframe::App for SilentStreamApp {
 
         self.apply_custom_theme(ctx);
 
+        let copy_shortcut = egui::KeyboardShortcut::new(egui::Modifiers::CTRL | egui::Modifiers::SHIFT, egui::Key::C);
+        if ctx.input_mut(|i| i.consume_shortcut(&copy_shortcut)) {
+            self.copy_status(ctx);
+        }
+
         if self.first_frame {
             self.first_frame = false;
-            self.auto_start();
+            let system_muted = self.engine().system_muted.clone();
+            self.mute_watcher = Some(MuteWatcher::start(system_muted, ctx));
+            self.sync_mute_watcher_device();
+            let at_login = std::env::args().any(|a| a == AUTOSTART_FLAG);
+            if self.restore_state && self.stopped_by_user {
+                log::info!("Staying stopped as in the last session");
+                self.status_message = "Stopped (as left last session)".to_string();
+            } else if self.start_processing && at_login && self.startup_delay > 0 {
+                self.delay_start();
+            } else if self.start_processing {
+                self.auto_start();
+            } else {
+                self.status_message = "Stopped".to_string();
+            }
+            self.obs_client.start(&self.obs_config, ctx);
+            self.app_watcher.start(&self.app_watch_config, ctx);
+            self.listener_watcher.start(&self.idle_pause_config, ctx);
+            self.sync_metrics_logger();
+            self.sync_status_file();
+            self.sync_hotkey();
+            self.sync_update_checker(ctx);
+            if self.start_minimized {
+                self.minimize_to_tray(ctx);
+                return;
+            }
         }
 
         self.update_cpu_usage();
+        self.check_settings_file(ctx);
+        self.show_delayed_start(ctx);
 
-        // Repaint at ~60fps for smooth animation
-        ctx.request_repaint_after(Duration::from_millis(16));
+        // Only animate at full rate while the orb reacts to the voice or the user is
+        // interacting; state changes from other threads call request_repaint() themselves
+        ctx.request_repaint_after(self.repaint_interval(ctx));
 
         egui::CentralPanel::default()
             .frame(egui::Frame::none().inner_margin(16.0))
             .show(ctx, |ui| {
-                self.draw_animated_background(ui);
+                if self.effective_animations() != AnimationMode::Off {
+                    self.draw_animated_background(ui);
+                }
                 
                 // Push cursor down past the manual header
                 ui.add_space(20.0);
@@ -515,6 +3727,8 @@ impl eframe::App for SilentStreamApp {
                                 self.show_settings = !self.show_settings;
                              }
                              let s_res = s_res.on_hover_text("Settings"); // Chain tooltip logic
+                             let settings_open = self.show_settings;
+                             s_res.widget_info(|| egui::WidgetInfo::selected(egui::WidgetType::Button, settings_open, "Settings"));
 
                              let s_visuals = ui.style().interact(&s_res);
                              let s_bg = if s_res.hovered() { 
@@ -522,7 +3736,7 @@ impl eframe::App for SilentStreamApp {
                              } else { 
                                  egui::Color32::from_rgba_premultiplied(45, 45, 50, 255) 
                              };
-                             ui.painter().rect(s_rect, egui::Rounding::same(8.0), s_bg, egui::Stroke::NONE);
+                             ui.painter().rect(s_rect, egui::Rounding::same(8.0), s_bg, focus_stroke(ui, &s_res));
                              
                              // Paint Gear Icon Centered
                              ui.painter().text(
@@ -540,20 +3754,12 @@ impl eframe::App for SilentStreamApp {
                              
                              // Handle interaction - Minimize to tray
                              if response.clicked() {
-                                self.is_minimized_to_tray = true;
-                                self.in_tray_flag.store(true, std::sync::atomic::Ordering::SeqCst);
-                                // Hide window: use both egui commands and Win32
-                                ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(true));
-                                ctx.send_viewport_cmd(egui::ViewportCommand::Visible(false));
-                                if let Ok(guard) = self.window_hwnd.lock() {
-                                    if let Some(hwnd) = *guard {
-                                        unsafe {
-                                            use windows_sys::Win32::UI::WindowsAndMessaging::*;
-                                            ShowWindow(hwnd as isize, SW_HIDE as i32);
-                                        }
-                                    }
-                                }
+                                self.minimize_to_tray(ctx);
                              }
+                             // Without a tray icon there'd be no way back, so it's a plain minimize
+                             let hide_label = if self.tray.is_some() { "Hide to tray" } else { "Minimize" };
+                             let response = response.on_hover_text(hide_label);
+                             response.widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Button, hide_label));
                              
                              // Paint button background
                              let visuals = ui.style().interact(&response);
@@ -563,7 +3769,7 @@ impl eframe::App for SilentStreamApp {
                                  egui::Color32::from_rgba_premultiplied(45, 45, 50, 255) 
                              };
                              
-                             ui.painter().rect(rect, egui::Rounding::same(8.0), bg_color, egui::Stroke::NONE);
+                             ui.painter().rect(rect, egui::Rounding::same(8.0), bg_color, focus_stroke(ui, &response));
                              
                              // Paint Arrow Icon (Diagonal Down-Right)
                              let center = rect.center();
@@ -585,6 +3791,37 @@ impl eframe::App for SilentStreamApp {
                                  [egui::pos2(center.x + size, center.y + size), egui::pos2(center.x, center.y + size)], 
                                  egui::Stroke::new(1.5, arrow_color)
                              );
+
+                             // Swap between the favorite device pairs, once two are pinned
+                             if self.favorites.iter().all(|f| f.is_some()) {
+                                 ui.add_space(8.0);
+                                 let target = self.swap_target();
+                                 let button = egui::Button::new("⇄").min_size(egui::vec2(28.0, 28.0));
+                                 let response = ui.add_enabled(target.is_ok(), button);
+                                 let response = match &target {
+                                     Ok(pair) => response.on_hover_text(format!("Swap to {} → {}", pair.input, pair.output)),
+                                     Err(reason) => response.on_disabled_hover_text(reason),
+                                 };
+                                 if response.clicked() {
+                                     self.swap_favorites();
+                                 }
+                             }
+                        });
+
+                        // Start/Stop at the left end of the bar
+                        ui.with_layout(egui::Layout::left_to_right(egui::Align::Min), |ui| {
+                            let (label, hover) = if self.run.is_processing() || self.run.phase == Phase::WaitingForDevices {
+                                ("■ Stop", "Stop processing and release the audio devices")
+                            } else {
+                                ("▶ Start", "Start processing")
+                            };
+                            let mut button = egui::Button::new(egui::RichText::new(label).strong()).min_size(egui::vec2(72.0, 28.0));
+                            if !self.run.is_processing() && self.run.phase != Phase::WaitingForDevices {
+                                button = button.fill(egui::Color32::from_rgb(139, 92, 246));
+                            }
+                            if ui.add_enabled(self.run.phase != Phase::Starting, button).on_hover_text(hover).clicked() {
+                                self.toggle_processing();
+                            }
                         });
                      });
                 });
@@ -600,13 +3837,77 @@ impl eframe::App for SilentStreamApp {
                         .show(ui, |ui| {
                             ui.label("⚙ Advanced Settings");
                             ui.add_space(8.0);
+
+                            if tray::SUPPORTED && self.tray.is_none() {
+                                ui.horizontal(|ui| {
+                                    ui.label(
+                                        egui::RichText::new("Tray icon unavailable; the hide button minimizes instead.")
+                                            .size(11.0)
+                                            .color(egui::Color32::from_rgb(250, 166, 26)),
+                                    );
+                                    if ui.small_button("Retry").clicked() {
+                                        self.retry_tray();
+                                    }
+                                });
+                            }
                             
                             let mut start_win = self.start_with_windows;
-                            if ui.checkbox(&mut start_win, "Start with Windows").changed() {
-                                self.start_with_windows = start_win;
-                                set_autostart(self.start_with_windows);
+                            if ui.checkbox(&mut start_win, AUTOSTART_LABEL).changed() {
+                                self.set_autostart(self.autostart_backend, start_win);
+                            }
+                            // Only Windows has more than one way to start at login
+                            if cfg!(windows) {
+                                ui.horizontal(|ui| {
+                                    ui.label("Start using:");
+                                    let mut backend = self.autostart_backend;
+                                    egui::ComboBox::from_id_source("autostart_backend")
+                                        .selected_text(backend.label())
+                                        .show_ui(ui, |ui| {
+                                            for option in AutostartBackend::ALL {
+                                                ui.selectable_value(&mut backend, option, option.label());
+                                            }
+                                        });
+                                    if backend != self.autostart_backend {
+                                        self.set_autostart(backend, self.start_with_windows);
+                                    }
+                                });
+                            }
+                            if let Some(error) = &self.autostart_error {
+                                ui.label(egui::RichText::new(error).size(11.0).color(egui::Color32::from_rgb(240, 71, 71)));
+                            }
+                            if ui.checkbox(&mut self.start_processing, "Start processing automatically on launch").changed() {
+                                self.save_current_settings();
+                            }
+                            ui.horizontal(|ui| {
+                                ui.label("Show/hide hotkey:");
+                                let field = ui.add(
+                                    egui::TextEdit::singleline(&mut self.window_hotkey)
+                                        .hint_text("none")
+                                        .desired_width(120.0),
+                                ).on_hover_text("Works anywhere, e.g. Ctrl+Alt+S; leave empty for none");
+                                if field.lost_focus() {
+                                    self.sync_hotkey();
+                                    self.save_current_settings();
+                                }
+                            });
+                            if let Some(error) = &self.hotkey_error {
+                                ui.label(egui::RichText::new(error).size(11.0).color(egui::Color32::from_rgb(240, 71, 71)));
+                            }
+                            if ui
+                                .checkbox(&mut self.restore_state, "Restore previous state on launch")
+                                .on_hover_text("Stays stopped or bypassed if that's how SilentStream was left, e.g. after a reboot for updates")
+                                .changed()
+                            {
                                 self.save_current_settings();
                             }
+                            ui.add_enabled_ui(self.start_with_windows && self.start_processing, |ui| {
+                                let delay = ui
+                                    .add(egui::Slider::new(&mut self.startup_delay, 0..=STARTUP_DELAY_MAX).text("Delay after login").suffix(" s"))
+                                    .on_hover_text("Gives USB audio drivers time to load before processing starts");
+                                if delay.drag_released() || (delay.changed() && !delay.dragged()) {
+                                    self.save_current_settings();
+                                }
+                            });
                             
                             ui.add_space(4.0);
                             
@@ -617,42 +3918,441 @@ impl eframe::App for SilentStreamApp {
                             if self.show_cpu_usage {
                                 ui.label(format!("SilentStream CPU: {:.1}%", self.cpu_usage));
                             }
+
+                            let uptime = ui.label(egui::RichText::new(self.run.uptime.summary()).size(11.0));
+                            if self.run.uptime.total_restarts() > 0 {
+                                uptime.on_hover_ui(|ui| {
+                                    for restart in self.run.uptime.recent().rev() {
+                                        ui.label(format!("{}  {}", restart.at.format("%H:%M:%S"), restart.reason.label()));
+                                    }
+                                });
+                            }
+
+                            ui.add_space(4.0);
+
+                            ui.horizontal(|ui| {
+                                if ui.checkbox(&mut self.metrics_enabled, "Log metrics to CSV").changed() {
+                                    self.sync_metrics_logger();
+                                    self.save_current_settings();
+                                }
+                                if ui.small_button("Open metrics folder").clicked() {
+                                    if let Some(dir) = get_config_dir() {
+                                        open_folder(&metrics::metrics_dir(&dir));
+                                    }
+                                }
+                            });
+
+                            if ui
+                                .checkbox(&mut self.status_file_enabled, "Write status file for overlays")
+                                .on_hover_text("JSON with live/gated/muted, level and devices, updated up to 4 times a second for OBS browser sources or scripts")
+                                .changed()
+                            {
+                                self.sync_status_file();
+                                self.save_current_settings();
+                            }
+                            if self.status_file_enabled {
+                                let path = ui.add(
+                                    egui::TextEdit::singleline(&mut self.status_file_path)
+                                        .hint_text("status.json in the settings folder")
+                                        .desired_width(260.0),
+                                );
+                                if path.lost_focus() {
+                                    self.sync_status_file();
+                                    self.save_current_settings();
+                                }
+                            }
+
+                            ui.horizontal(|ui| {
+                                ui.label("Log level:");
+                                egui::ComboBox::from_id_source("log_level").selected_text(self.log_level.to_string()).show_ui(ui, |ui| {
+                                    for level in logging::LEVELS {
+                                        if ui.selectable_value(&mut self.log_level, level, level.to_string()).changed() {
+                                            logging::set_level(self.log_level);
+                                            self.save_current_settings();
+                                        }
+                                    }
+                                });
+                                if ui.small_button("Events").clicked() {
+                                    self.show_event_log = !self.show_event_log;
+                                }
+                                if ui.small_button("View logs").clicked() {
+                                    if let Some(dir) = get_config_dir() {
+                                        open_folder(&logging::log_dir(&dir));
+                                    }
+                                }
+                            });
+
+                            if ui.button("Batch process…").on_hover_text("Denoise recordings with the current filters").clicked() {
+                                self.show_batch = !self.show_batch;
+                            }
+
+                            ui.horizontal(|ui| {
+                                ui.label("Appearance:");
+                                egui::ComboBox::from_id_source("appearance").selected_text(self.appearance.label()).show_ui(ui, |ui| {
+                                    for appearance in Appearance::ALL {
+                                        if ui.selectable_value(&mut self.appearance, appearance, appearance.label()).changed() {
+                                            self.save_current_settings();
+                                        }
+                                    }
+                                });
+                            });
+
+                            ui.horizontal(|ui| {
+                                ui.label("Animations:");
+                                egui::ComboBox::from_id_source("animations").selected_text(self.animations.label()).show_ui(ui, |ui| {
+                                    for mode in AnimationMode::ALL {
+                                        if ui.selectable_value(&mut self.animations, mode, mode.label()).changed() {
+                                            self.smoothed_volume = 0.0;
+                                            self.save_current_settings();
+                                        }
+                                    }
+                                });
+                            });
+
+                            ui.horizontal(|ui| {
+                                ui.label("Resampler quality:");
+                                let before = self.resampler_quality;
+                                egui::ComboBox::from_id_source("resampler_quality")
+                                    .selected_text(self.resampler_quality.label())
+                                    .show_ui(ui, |ui| {
+                                        for quality in ResamplerQuality::ALL {
+                                            ui.selectable_value(&mut self.resampler_quality, quality, quality.label())
+                                                .on_hover_text(quality.implementation());
+                                        }
+                                    });
+                                if self.resampler_quality != before {
+                                    log::info!("Resampler quality changed to {}", self.resampler_quality.label());
+                                    self.engine().set_resampler_quality(self.resampler_quality);
+                                    if self.is_active() {
+                                        self.restart_audio(RestartReason::SettingsChange);
+                                    } else {
+                                        self.save_current_settings();
+                                    }
+                                }
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Processing block:");
+                                let before = self.block_frames;
+                                egui::ComboBox::from_id_source("block_frames")
+                                    .selected_text(block_frames_label(self.block_frames))
+                                    .show_ui(ui, |ui| {
+                                        for n in BLOCK_FRAMES {
+                                            ui.selectable_value(&mut self.block_frames, n, block_frames_label(n));
+                                        }
+                                    });
+                                if self.block_frames != before {
+                                    log::info!("Processing block changed to {} frame(s)", self.block_frames);
+                                    self.engine().set_block_frames(self.block_frames);
+                                    if self.is_active() {
+                                        self.restart_audio(RestartReason::SettingsChange);
+                                    } else {
+                                        self.save_current_settings();
+                                    }
+                                }
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("When input backs up:");
+                                let before = self.overflow_policy;
+                                egui::ComboBox::from_id_source("overflow_policy")
+                                    .selected_text(self.overflow_policy.label())
+                                    .show_ui(ui, |ui| {
+                                        for policy in OverflowPolicy::ALL {
+                                            ui.selectable_value(&mut self.overflow_policy, policy, policy.label());
+                                        }
+                                    });
+                                if self.overflow_policy != before {
+                                    log::info!("Overflow policy changed to {}", self.overflow_policy.label());
+                                    self.engine().set_overflow_policy(self.overflow_policy);
+                                    if self.is_active() {
+                                        self.restart_audio(RestartReason::SettingsChange);
+                                    } else {
+                                        self.save_current_settings();
+                                    }
+                                }
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Latency cap:");
+                                let before = self.latency_cap_ms;
+                                egui::ComboBox::from_id_source("latency_cap")
+                                    .selected_text(latency_cap_label(self.latency_cap_ms))
+                                    .show_ui(ui, |ui| {
+                                        for ms in LATENCY_CAPS_MS {
+                                            ui.selectable_value(&mut self.latency_cap_ms, ms, latency_cap_label(ms));
+                                        }
+                                    });
+                                if self.latency_cap_ms != before {
+                                    log::info!("Latency cap changed to {}", latency_cap_label(self.latency_cap_ms));
+                                    self.apply_latency_cap();
+                                    self.save_current_settings();
+                                }
+                            })
+                            .response
+                            .on_hover_text("When audio backs up past this, frames are dropped (silent ones first) until it catches up");
+                            ui.horizontal(|ui| {
+                                ui.label("When the CPU can't keep up:");
+                                let before = self.overload_policy;
+                                egui::ComboBox::from_id_source("overload_policy")
+                                    .selected_text(self.overload_policy.label())
+                                    .show_ui(ui, |ui| {
+                                        for policy in OverloadPolicy::ALL {
+                                            ui.selectable_value(&mut self.overload_policy, policy, policy.label());
+                                        }
+                                    });
+                                if self.overload_policy != before {
+                                    log::info!("Overload policy changed to {}", self.overload_policy.label());
+                                    self.apply_overload_policy();
+                                    self.save_current_settings();
+                                }
+                            })
+                            .response
+                            .on_hover_text("Auto-bypass passes the mic through unprocessed until the load drops; Notify only keeps processing but says so");
+
+                            let stream_info = self.engine().stream_info.clone();
+                            if let Some(info) = &stream_info {
+                                let in_use = match info.resampler {
+                                    Some(name) => format!("In use: {}, {:.1} ms delay", name, info.resampler_delay_ms),
+                                    None => "In use: none (input already at 48 kHz)".to_string(),
+                                };
+                                ui.label(egui::RichText::new(in_use).size(11.0));
+                                // The click assist holds one frame back to look ahead
+                                let lookahead = if self.click.enabled { 10.0 } else { 0.0 };
+                                let delay = info.processing_delay_ms() + lookahead;
+                                ui.label(egui::RichText::new(format!("Estimated processing delay: {:.0} ms", delay)).size(11.0));
+                            }
+
+                            ui.horizontal(|ui| {
+                                ui.label("Level meter:");
+                                for mode in MeterMode::ALL {
+                                    if ui.selectable_value(&mut self.meter_mode, mode, mode.label()).changed() {
+                                        self.save_current_settings();
+                                    }
+                                }
+                            });
+                            if ui
+                                .checkbox(&mut self.input_monitor_enabled, "Show the microphone level while stopped")
+                                .on_hover_text("Keeps the microphone open while the window is visible and processing is stopped")
+                                .changed()
+                            {
+                                self.save_current_settings();
+                            }
+
+                            if ui.checkbox(&mut self.pause_when_locked, "Pause while locked or asleep").changed() {
+                                self.save_current_settings();
+                            }
+                            if ui.checkbox(&mut self.confirm_exit, "Ask before exiting while processing").changed() {
+                                self.save_current_settings();
+                            }
+
+                            ui.horizontal(|ui| {
+                                if ui.checkbox(&mut self.osd_enabled, "Show a toast on mute or bypass changes").changed() {
+                                    self.save_current_settings();
+                                }
+                                ui.add_enabled_ui(self.osd_enabled, |ui| {
+                                    let before = self.osd_corner;
+                                    egui::ComboBox::from_id_source("osd_corner")
+                                        .selected_text(self.osd_corner.label())
+                                        .show_ui(ui, |ui| {
+                                            for corner in OsdCorner::ALL {
+                                                ui.selectable_value(&mut self.osd_corner, corner, corner.label());
+                                            }
+                                        });
+                                    if self.osd_corner != before {
+                                        self.notifier.preview_toast("Live");
+                                        self.save_current_settings();
+                                    }
+                                });
+                            });
+                            self.draw_notification_settings(ui);
+
+                            if ui.checkbox(&mut self.update_check_enabled, "Check for updates once a day").changed() {
+                                self.sync_update_checker(ctx);
+                                self.save_current_settings();
+                            }
+
+                            if ui.button("Create diagnostic report").clicked() {
+                                self.create_diagnostic_report();
+                            }
+
+                            ui.add_space(4.0);
+                            self.draw_obs_settings(ui, ctx);
+                            self.draw_app_watch_settings(ui, ctx);
+                            self.draw_schedule_settings(ui);
+                            if cfg!(windows) {
+                                self.draw_idle_pause_settings(ui, ctx);
+                            }
+                            self.draw_echo_cancellation_settings(ui);
+                            self.draw_monitor_settings(ui);
+                            self.draw_earcon_settings(ui);
+                            self.draw_mix_settings(ui);
+                            self.draw_virtual_input_settings(ui);
+                            self.draw_chain_settings(ui);
+                            self.draw_boost_settings(ui);
+                            self.draw_noise_print_settings(ui);
+                            self.draw_plugin_settings(ui);
+                            self.draw_de_esser_settings(ui);
+                            self.draw_plosive_settings(ui);
+                            self.draw_click_settings(ui);
+                            self.draw_music_settings(ui);
+                            if cfg!(windows) {
+                                self.draw_default_device_settings(ui);
+                            }
+                            self.draw_routing_check(ui, ctx);
+                            self.draw_statistics(ui);
+                            self.draw_buffer_diagnostics(ui);
+                            self.draw_replay_settings(ui);
                         });
                     ui.add_space(10.0);
                 }
 
-                // Cards with slight transparency
-                let card_fill = egui::Color32::from_rgba_premultiplied(43, 45, 49, 240);
+                self.draw_update_banner(ui);
+                self.draw_reload_banner(ui);
+                self.draw_autostart_repaired_banner(ui);
+                self.draw_permission_notice(ui);
+                self.draw_default_device_banner(ui);
+                self.draw_device_fallback_banner(ui);
+                self.draw_microphone_blocked_banner(ui);
+
+                // Cards with slight transparency, opaque and outlined in high contrast
+                let (card_fill, card_stroke) = if self.appearance == Appearance::HighContrast {
+                    (egui::Color32::BLACK, egui::Stroke::new(2.0, egui::Color32::WHITE))
+                } else {
+                    (egui::Color32::from_rgba_premultiplied(43, 45, 49, 240), egui::Stroke::NONE)
+                };
                 
                 // Audio Devices
                 egui::Frame::none()
                     .fill(card_fill)
+                    .stroke(card_stroke)
                     .rounding(12.0)
                     .inner_margin(12.0)
                     .show(ui, |ui| {
                         ui.label(egui::RichText::new("Audio Devices").strong());
                         ui.add_space(8.0);
 
-                        ui.label("Input:");
-                        let selected_input = self.input_devices.get(self.selected_input_index).map(|s| s.as_str()).unwrap_or("No device");
-                        let old_in = self.selected_input_index;
-                        egui::ComboBox::from_id_source("input").selected_text(selected_input).width(ui.available_width()-8.0).show_ui(ui, |ui| {
-                            for (i, name) in self.input_devices.iter().enumerate() {
-                                ui.selectable_value(&mut self.selected_input_index, i, name);
+                        let hidden = self.input_devices.iter().filter(|d| !self.virtual_input_filter.shows(d)).count();
+                        ui.horizontal(|ui| {
+                            ui.label("Input:");
+                            if hidden > 0 || !self.virtual_input_filter.enabled {
+                                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                    let mut show_all = !self.virtual_input_filter.enabled;
+                                    let label = if hidden > 0 { format!("Show all devices ({} hidden)", hidden) } else { "Show all devices".to_string() };
+                                    if ui.checkbox(&mut show_all, egui::RichText::new(label).size(11.0))
+                                        .on_hover_text("Virtual cables, mixer outputs and stereo mix are hidden because using one as the microphone can feed the output back into itself")
+                                        .changed()
+                                    {
+                                        self.virtual_input_filter.enabled = !show_all;
+                                        self.save_current_settings();
+                                    }
+                                });
                             }
                         });
-                        if old_in != self.selected_input_index { self.restart_audio(); }
+                        self.refresh_default_devices();
+                        // Picks wait until the restart under way has finished
+                        let pickers_enabled = self.run.phase != Phase::Starting;
+                        let filter = &self.virtual_input_filter;
+                        let picked = ui
+                            .add_enabled_ui(pickers_enabled, |ui| {
+                                device_picker(
+                                    ui,
+                                    "input",
+                                    &self.input_devices,
+                                    &mut self.selected_input_index,
+                                    self.default_devices.0.as_deref(),
+                                    |name| filter.shows(name),
+                                )
+                            })
+                            .inner;
+                        if picked {
+                            log::info!("Input device changed to '{}'", self.input_devices[self.selected_input_index]);
+                            self.forget_fallback(true);
+                            self.device_restart.changed(Instant::now());
+                        }
+
+                        if self.input_channel_count > 1 {
+                            let current = self.selected_input_channel();
+                            let mut selected = current;
+                            ui.horizontal(|ui| {
+                                ui.set_enabled(pickers_enabled);
+                                ui.label("Channel:");
+                                egui::ComboBox::from_id_source("input_channel").selected_text(current.label()).show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut selected, InputChannel::Mix, InputChannel::Mix.label());
+                                    for c in 0..self.input_channel_count {
+                                        let channel = InputChannel::Channel(c);
+                                        ui.selectable_value(&mut selected, channel, channel.label());
+                                    }
+                                });
+                            });
+                            if selected != current {
+                                if let Some(name) = self.input_devices.get(self.selected_input_index).cloned() {
+                                    log::info!("Input channel for '{}' changed to {}", name, selected.label());
+                                    self.input_channels.insert(name, selected);
+                                    self.device_restart.changed(Instant::now());
+                                }
+                            }
+                        }
+
+                        let mut remember = self.tuning_device.is_some();
+                        if ui
+                            .checkbox(&mut remember, "Remember settings for this device")
+                            .on_hover_text("Threshold, gate and filters are kept separately for this microphone")
+                            .changed()
+                        {
+                            self.set_device_profile(remember);
+                        }
+                        if let Some((notice, shown)) = &self.profile_notice {
+                            if shown.elapsed() < PROFILE_NOTICE_DURATION {
+                                ui.label(
+                                    egui::RichText::new(notice).size(11.0).color(egui::Color32::from_rgb(142, 146, 151)),
+                                );
+                            } else {
+                                self.profile_notice = None;
+                            }
+                        }
 
                         ui.add_space(8.0);
                         ui.label("Output:");
-                        let selected_output = self.output_devices.get(self.selected_output_index).map(|s| s.as_str()).unwrap_or("No device");
-                        let old_out = self.selected_output_index;
-                        egui::ComboBox::from_id_source("output").selected_text(selected_output).width(ui.available_width()-8.0).show_ui(ui, |ui| {
-                            for (i, name) in self.output_devices.iter().enumerate() {
-                                ui.selectable_value(&mut self.selected_output_index, i, name);
+                        let picked = ui
+                            .add_enabled_ui(pickers_enabled, |ui| {
+                                device_picker(
+                                    ui,
+                                    "output",
+                                    &self.output_devices,
+                                    &mut self.selected_output_index,
+                                    self.default_devices.1.as_deref(),
+                                    |_| true,
+                                )
+                            })
+                            .inner;
+                        if picked {
+                            log::info!("Output device changed to '{}'", self.output_devices[self.selected_output_index]);
+                            self.forget_fallback(false);
+                            self.device_restart.changed(Instant::now());
+                        }
+
+                        ui.add_space(4.0);
+                        ui.horizontal(|ui| {
+                            ui.label("Favorites:");
+                            let current = self.current_pair();
+                            for i in 0..self.favorites.len() {
+                                let (text, hover) = match &self.favorites[i] {
+                                    Some(pair) => (
+                                        format!("★ {}", i + 1),
+                                        format!("{} → {}\nClick to pin the current pair, right-click to unpin", pair.input, pair.output),
+                                    ),
+                                    None => (format!("☆ {}", i + 1), "Pin the current input and output".to_string()),
+                                };
+                                let response = ui
+                                    .selectable_label(self.favorites[i].as_ref() == Some(&current), text)
+                                    .on_hover_text(hover);
+                                if response.clicked() {
+                                    self.favorites[i] = Some(current.clone());
+                                    self.save_current_settings();
+                                } else if response.secondary_clicked() {
+                                    self.favorites[i] = None;
+                                    self.save_current_settings();
+                                }
                             }
                         });
-                        if old_out != self.selected_output_index { self.restart_audio(); }
                     });
 
                 ui.add_space(10.0);
@@ -660,35 +4360,94 @@ impl eframe::App for SilentStreamApp {
                 // Audio Settings
                 egui::Frame::none()
                     .fill(card_fill)
+                    .stroke(card_stroke)
                     .rounding(12.0)
                     .inner_margin(12.0)
                     .show(ui, |ui| {
                         ui.label(egui::RichText::new("Audio Settings").strong());
                         ui.add_space(8.0);
                         
-                        if ui.checkbox(&mut self.noise_suppression_enabled, "Enable Noise Suppression").changed() {
-                            if let Ok(mut bp) = self.audio_engine.bypass.lock() {
-                                *bp = !self.noise_suppression_enabled;
+                        ui.horizontal(|ui| {
+                            ui.label("Noise Suppression:");
+                            for mode in SuppressionMode::ALL {
+                                // Also on picking the current mode, which ends a trigger bypass
+                                if ui.selectable_value(&mut self.suppression_mode, mode, mode.label()).clicked() {
+                                    self.trigger_bypass = false;
+                                    self.apply_suppression_mode();
+                                    self.save_current_settings();
+                                }
+                            }
+                        });
+
+                        ui.horizontal(|ui| {
+                            ui.label("Gate:");
+                            for mode in GateMode::ALL {
+                                if ui.selectable_value(&mut self.gate.mode, mode, mode.label()).changed() {
+                                    self.apply_gate();
+                                    self.save_current_settings();
+                                }
+                            }
+                        });
+                        if self.gate.mode == GateMode::Soft {
+                            let steepness = ui.add(
+                                egui::Slider::new(&mut self.gate.steepness, GATE_STEEPNESS_RANGE).text("Steepness"),
+                            );
+                            if steepness.changed() {
+                                self.apply_gate();
+                            }
+                            if steepness.drag_released() || (steepness.changed() && !steepness.dragged()) {
+                                self.save_current_settings();
+                            }
+                        }
+
+                        let muted = self.is_system_muted();
+                        let mute_label = if muted { "🔇 Unmute microphone" } else { "Mute microphone (Windows)" };
+                        if ui.button(mute_label).clicked() {
+                            if let Some(name) = self.input_devices.get(self.selected_input_index) {
+                                if !core_audio::set_capture_mute(name, !muted) {
+                                    log::warn!("Could not change the mute state of '{}'", name);
+                                    self.status_message = "Error: could not change the microphone mute state".to_string();
+                                }
                             }
-                            self.save_current_settings();
                         }
                         
                         ui.add_space(10.0);
                         ui.label(format!("VAD Threshold: {:.2}", self.vad_threshold));
                         ui.add_space(4.0);
                         
-                        // Slider
+                        // Slider, with the recent VAD distribution drawn above it
                         let slider_width = ui.available_width() - 8.0;
+                        self.draw_vad_histogram(ui, slider_width);
                         let (rect, response) = ui.allocate_exact_size(egui::vec2(slider_width, 18.0), egui::Sense::click_and_drag());
                         
                         if response.dragged() || response.clicked() {
                             if let Some(pos) = response.interact_pointer_pos() {
                                 let t = ((pos.x - rect.left()) / rect.width()).clamp(0.0, 1.0);
-                                self.vad_threshold = t * 0.5;
-                                if let Ok(mut th) = self.audio_engine.vad_threshold.lock() { *th = self.vad_threshold; }
+                                self.vad_threshold = t * VAD_THRESHOLD_MAX;
+                                self.engine().set_threshold(self.vad_threshold);
                             }
                         }
                         if response.drag_released() { self.save_current_settings(); }
+
+                        // Arrow keys while focused, like egui's own slider
+                        if response.has_focus() {
+                            ui.memory_mut(|m| {
+                                m.set_focus_lock_filter(
+                                    response.id,
+                                    egui::EventFilter { horizontal_arrows: true, ..Default::default() },
+                                )
+                            });
+                            let steps = ui.input(|i| {
+                                i.num_presses(egui::Key::ArrowRight) as f32 - i.num_presses(egui::Key::ArrowLeft) as f32
+                            });
+                            if steps != 0.0 {
+                                self.vad_threshold = (self.vad_threshold + steps * VAD_THRESHOLD_STEP).clamp(0.0, VAD_THRESHOLD_MAX);
+                                self.engine().set_threshold(self.vad_threshold);
+                                self.save_current_settings();
+                            }
+                        }
+                        let threshold = self.vad_threshold;
+                        response.widget_info(|| egui::WidgetInfo::slider(threshold as f64, "VAD threshold"));
                         
                         // Draw slider
                         let p = ui.painter();
@@ -696,20 +4455,112 @@ impl eframe::App for SilentStreamApp {
                             egui::Rect::from_min_size(egui::pos2(rect.left(), rect.center().y - 3.0), egui::vec2(rect.width(), 6.0)),
                             3.0, egui::Color32::from_rgb(54, 57, 63)
                         );
-                        let fill_w = rect.width() * (self.vad_threshold / 0.5).clamp(0.0, 1.0);
+                        let fill_w = rect.width() * (self.vad_threshold / VAD_THRESHOLD_MAX).clamp(0.0, 1.0);
                         p.rect_filled(
                             egui::Rect::from_min_size(egui::pos2(rect.left(), rect.center().y - 3.0), egui::vec2(fill_w, 6.0)),
                             3.0, egui::Color32::from_rgb(139, 92, 246) // Purple
                         );
                         let kx = rect.left() + fill_w;
-                        p.circle_filled(egui::pos2(kx.clamp(rect.left()+7.0, rect.right()-7.0), rect.center().y), 7.0, egui::Color32::WHITE);
+                        let knob = egui::pos2(kx.clamp(rect.left()+7.0, rect.right()-7.0), rect.center().y);
+                        p.circle(knob, 7.0, egui::Color32::WHITE, focus_stroke(ui, &response));
+
+                        ui.add_space(6.0);
+                        let active = self.active_preset();
+                        ui.horizontal(|ui| {
+                            for preset in VadPreset::ALL {
+                                if ui.selectable_label(active == Some(preset), preset.label()).clicked() && active != Some(preset) {
+                                    self.apply_preset(preset);
+                                }
+                            }
+                            if active.is_none() {
+                                ui.add_enabled(false, egui::SelectableLabel::new(true, "Custom"));
+                            }
+                        });
+                        let (histogram_frames, above_range) = self
+                            .engine()
+                            .vad_histogram
+                            .lock()
+                            .map(|h| {
+                                let visible = slider_buckets();
+                                (h.total(), h.counts()[visible..].iter().sum::<u32>())
+                            })
+                            .unwrap_or((0, 0));
+                        ui.horizontal(|ui| {
+                            let suggest = ui
+                                .small_button("Suggest threshold")
+                                .on_hover_text("Place the threshold between the noise and speech humps of the last minute");
+                            if suggest.clicked() && !self.suggest_threshold() {
+                                self.status_message =
+                                    "Not enough data to suggest a threshold yet — talk for a while, then try again".to_string();
+                            }
+                            if ui.small_button("Reset").on_hover_text("Clear the VAD histogram").clicked() {
+                                if let Ok(mut h) = self.engine().vad_histogram.lock() {
+                                    h.reset();
+                                }
+                            }
+                            if histogram_frames > 0 {
+                                let share = above_range as f32 / histogram_frames as f32 * 100.0;
+                                ui.label(
+                                    egui::RichText::new(format!("{:.0}% above {:.2}", share, VAD_THRESHOLD_MAX)).size(11.0).weak(),
+                                );
+                            }
+                        });
+                        ui.collapsing("Gate timing", |ui| {
+                            let mut changed = false;
+                            let mut released = false;
+                            for (value, range, name) in [
+                                (&mut self.gate.hold_ms, GATE_HOLD_RANGE, "Hold"),
+                                (&mut self.gate.attack_ms, GATE_ATTACK_RANGE, "Attack"),
+                                (&mut self.gate.release_ms, GATE_RELEASE_RANGE, "Release"),
+                            ] {
+                                let slider = ui.add(egui::Slider::new(value, range).text(name).suffix(" ms").fixed_decimals(0));
+                                changed |= slider.changed();
+                                released |= slider.drag_released() || (slider.changed() && !slider.dragged());
+                            }
+                            let pre_roll = ui
+                                .add(
+                                    egui::Slider::new(&mut self.gate.pre_roll_ms, GATE_PRE_ROLL_RANGE)
+                                        .text("Pre-roll")
+                                        .suffix(" ms")
+                                        .step_by(10.0),
+                                )
+                                .on_hover_text("Keeps the start of words that open the gate, at the cost of this much extra delay");
+                            changed |= pre_roll.changed();
+                            released |= pre_roll.drag_released() || (pre_roll.changed() && !pre_roll.dragged());
+                            let floor = ui
+                                .add(
+                                    egui::Slider::new(&mut self.gate.energy_floor_db, GATE_ENERGY_FLOOR_RANGE)
+                                        .text("Energy floor")
+                                        .custom_formatter(|db, _| if db >= 0.0 { "Off".to_string() } else { format!("{:.0} dB", db) })
+                                        .custom_parser(|text| match text.trim() {
+                                            "Off" | "off" => Some(0.0),
+                                            text => text.trim_end_matches("dB").trim().parse().ok(),
+                                        }),
+                                )
+                                .on_hover_text("Keeps an open gate open while the cleaned-up voice is louder than this, so whispers aren't chopped when the VAD dips");
+                            changed |= floor.changed();
+                            released |= floor.drag_released() || (floor.changed() && !floor.dragged());
+                            if changed {
+                                self.apply_gate();
+                            }
+                            if released {
+                                self.save_current_settings();
+                            }
+                        });
                     });
                 
                 ui.add_space(12.0);
                 
                 // Bottom Status
                 ui.vertical_centered(|ui| {
-                    let color = if self.is_processing {
+                    let system_muted = self.is_system_muted();
+                    let music = self.run.is_processing() && self.engine_status.music_passthrough;
+                    let overloaded = self.overload_text();
+                    let color = if system_muted || overloaded.is_some() {
+                        egui::Color32::from_rgb(250, 166, 26)
+                    } else if music {
+                        egui::Color32::from_rgb(88, 166, 255)
+                    } else if self.run.is_processing() {
                         egui::Color32::from_rgb(67, 181, 129)
                     } else if self.status_message.contains("Error") {
                         egui::Color32::from_rgb(240, 71, 71)
@@ -719,18 +4570,186 @@ impl eframe::App for SilentStreamApp {
                     
                     ui.horizontal(|ui| {
                         ui.with_layout(egui::Layout::left_to_right(egui::Align::Center).with_main_align(egui::Align::Center), |ui| {
-                             let (rect, _) = ui.allocate_exact_size(egui::vec2(8.0, 8.0), egui::Sense::hover());
-                             ui.painter().circle_filled(rect.center(), 3.0, color);
+                             if self.run.phase == Phase::Starting {
+                                 ui.add(egui::Spinner::new().size(11.0));
+                             } else {
+                                 let (rect, _) = ui.allocate_exact_size(egui::vec2(8.0, 8.0), egui::Sense::hover());
+                                 ui.painter().circle_filled(rect.center(), 3.0, color);
+                             }
                              
-                             ui.label(egui::RichText::new(&self.status_message).size(11.0).color(color));
+                             let text = if system_muted {
+                                 "System-muted"
+                             } else if let Some(text) = overloaded {
+                                 text
+                             } else if music {
+                                 "Music detected, passing through"
+                             } else if self.run.is_processing() && self.trigger_bypass {
+                                 "Bypassed until processing is started or a mode is picked"
+                             } else {
+                                 self.status_message.as_str()
+                             };
+                             if matches!(self.run.phase, Phase::Delayed(_)) && !system_muted {
+                                 let label = egui::Label::new(egui::RichText::new(text).size(11.0).color(color).underline())
+                                     .sense(egui::Sense::click());
+                                 if ui.add(label).on_hover_text("Start now").clicked() {
+                                     self.toggle_processing();
+                                 }
+                             } else if let (Some(error), false) = (&self.run.error, system_muted) {
+                                 let busy = matches!(**error, EngineError::DeviceInUse { .. });
+                                 let arrow = if self.show_error_details { "▾" } else { "▸" };
+                                 let label = egui::Label::new(egui::RichText::new(format!("{} {}", text, arrow)).size(11.0).color(color))
+                                     .sense(egui::Sense::click());
+                                 if ui.add(label).on_hover_text("Show details").clicked() {
+                                     self.show_error_details = !self.show_error_details;
+                                 }
+                                 // The other app may let go at any moment; no need to restart SilentStream
+                                 if busy && ui.small_button("Retry").clicked() {
+                                     log::info!("Retrying start after device-in-use error");
+                                     self.auto_start();
+                                 }
+                             } else {
+                                 ui.label(egui::RichText::new(text).size(11.0).color(color));
+                             }
+                             match self.status_copied {
+                                 Some(at) if at.elapsed() < COPIED_FLASH => {
+                                     ui.label(egui::RichText::new("Copied").size(11.0).color(egui::Color32::from_rgb(67, 181, 129)));
+                                     ctx.request_repaint_after(COPIED_FLASH - at.elapsed());
+                                 }
+                                 _ => {
+                                     if ui.small_button("📋").on_hover_text("Copy status (Ctrl+Shift+C)").clicked() {
+                                         self.copy_status(ctx);
+                                     }
+                                 }
+                             }
                         });
                     });
+
+                    // Always visible while recording: the file holds everything said into the mic
+                    if self.engine_status.replay_recording {
+                        let label = egui::Label::new(
+                            egui::RichText::new("⏺ Recording for replay").size(11.0).color(egui::Color32::from_rgb(240, 71, 71)),
+                        )
+                        .sense(egui::Sense::click());
+                        if ui.add(label).on_hover_text("Stop recording").clicked() {
+                            self.engine().stop_replay_recording();
+                            self.refresh_replay_files();
+                        }
+                    }
+
+                    if let (Some(error), true) = (&self.run.error, self.show_error_details) {
+                        ui.add_space(4.0);
+                        egui::Frame::none()
+                            .fill(egui::Color32::from_rgba_premultiplied(60, 30, 30, 240))
+                            .rounding(8.0)
+                            .inner_margin(8.0)
+                            .show(ui, |ui| {
+                                ui.label(egui::RichText::new(error.suggestion()).size(12.0));
+                                ui.add_space(4.0);
+                                ui.add(egui::Label::new(egui::RichText::new(error.details()).size(11.0).monospace()).wrap(true));
+                                if ui.small_button("Copy details").clicked() {
+                                    ui.output_mut(|o| o.copied_text = error.to_string());
+                                }
+                            });
+                    }
                 });
             });
+
+        self.draw_diagnostic_report(ctx);
+        self.draw_event_log(ctx);
+        self.draw_batch_window(ctx);
+        self.draw_exit_prompt(ctx);
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.save_current_settings();
+        self.input_monitor.stop();
+        self.supervisor.shutdown();
+        self.engine().stop_and_wait();
+        self.status_file.stop();
+        let hwnd = self.window_hwnd.lock().ok().and_then(|guard| *guard);
+        if let (Some(overlay), Some(hwnd)) = (self.taskbar_overlay.as_mut(), hwnd) {
+            overlay.set(hwnd, None);
+        }
+    }
+}
+
+// Names of the other processes streaming on an audio device, for the "in use by" message
+fn device_users(device: &str) -> Vec<String> {
+    let mut system = System::new();
+    let mut names: Vec<String> = core_audio::session_processes(device)
+        .into_iter()
+        .filter_map(|pid| {
+            let pid = Pid::from_u32(pid);
+            system.refresh_process(pid);
+            system.process(pid).map(|process| process.name().to_string())
+        })
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+fn open_folder(path: &std::path::Path) {
+    let _ = std::fs::create_dir_all(path);
+    platform::open(path);
+}
+
+fn latency_cap_label(ms: u32) -> String {
+    if ms == 0 { "Off".to_string() } else { format!("{} ms", ms) }
+}
+
+fn block_frames_label(frames: usize) -> String {
+    if frames == 1 { "1 frame (10 ms)".to_string() } else { format!("{} frames ({} ms)", frames, frames * 10) }
+}
+
+// Index of `saved` in `devices`, else of the system default, else the first device.
+// The flag is set when a saved name was given but isn't connected.
+fn resolve_device(devices: &[String], saved: Option<&str>, default: Option<String>) -> (usize, bool) {
+    if let Some(i) = saved.and_then(|name| devices.iter().position(|d| d == name)) {
+        return (i, false);
+    }
+    let fallback = default.and_then(|name| devices.iter().position(|d| *d == name)).unwrap_or(0);
+    (fallback, saved.is_some() && !devices.is_empty())
+}
+
+// Histogram buckets the threshold slider's range covers
+fn slider_buckets() -> usize {
+    ((VAD_BUCKETS as f32 * VAD_THRESHOLD_MAX).ceil() as usize).min(VAD_BUCKETS)
+}
+
+// Keyboard focus ring for the custom-painted controls
+fn focus_stroke(ui: &egui::Ui, response: &egui::Response) -> egui::Stroke {
+    if response.has_focus() { ui.visuals().selection.stroke } else { egui::Stroke::NONE }
+}
+
+fn format_duration(seconds: f64) -> String {
+    let total = seconds as u64;
+    let (h, m, s) = (total / 3600, (total / 60) % 60, total % 60);
+    if h > 0 {
+        format!("{} h {} min", h, m)
+    } else if m > 0 {
+        format!("{} min", m)
+    } else {
+        format!("{} s", s)
     }
 }
 
 fn main() -> eframe::Result<()> {
+    // An explicit config directory that can't be used is a deployment mistake; say so instead
+    // of silently running without saved settings
+    if let Some(dir) = settings::config_dir_override() {
+        if let Err(e) = settings::prepare_config_dir(&dir) {
+            crash::show_error("SilentStream configuration", &format!("The configuration directory can't be used.\n\n{}", e));
+            std::process::exit(1);
+        }
+    }
+    logging::init(get_config_dir(), log::LevelFilter::Info);
+    if let Some(recording) = replays::replay_arg() {
+        std::process::exit(replays::run(&recording));
+    }
+    crash::install(get_config_dir());
+    log::info!("SilentStream {} starting", env!("CARGO_PKG_VERSION"));
+
     let (icon_rgba, icon_width, icon_height) = load_app_icon();
     let icon_data = egui::IconData {
         rgba: icon_rgba,
@@ -751,6 +4770,6 @@ fn main() -> eframe::Result<()> {
     eframe::run_native(
         "SilentStream",
         options,
-        Box::new(|_cc| Box::new(SilentStreamApp::default())),
+        Box::new(|cc| Box::new(SilentStreamApp::new(&cc.egui_ctx))),
     )
 }
@@ -0,0 +1,150 @@
+use silentstream_core::audio_engine::{EngineCounters, SessionStats, RING_BUFFER_SIZE};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, ProcessRefreshKind, System};
+
+const METRICS_INTERVAL: Duration = Duration::from_secs(5);
+const MAX_FILE_BYTES: u64 = 5 * 1024 * 1024;
+// metrics.csv plus this many rotated files
+const ROTATED_FILES: usize = 2;
+// Rows kept while the file can't be opened (e.g. Excel holds it); ~1 hour at 5 s
+const MAX_PENDING_ROWS: usize = 720;
+
+const HEADER: &str = "timestamp,input_fill_pct,output_fill_pct,underruns,overruns,cpu_pct,vad_mean,gate_pct,restarts";
+
+pub fn metrics_dir(config_dir: &Path) -> PathBuf {
+    config_dir.join("metrics")
+}
+
+// Appends a CSV row every few seconds from its own thread, never from the audio path
+pub struct MetricsLogger {
+    stop_flag: Option<Arc<AtomicBool>>,
+}
+
+impl MetricsLogger {
+    pub fn new() -> Self {
+        Self { stop_flag: None }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.stop_flag.is_some()
+    }
+
+    pub fn start(&mut self, dir: PathBuf, counters: Arc<EngineCounters>, stats: Arc<Mutex<SessionStats>>) {
+        self.stop();
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let stop = stop_flag.clone();
+
+        thread::spawn(move || {
+            let _ = fs::create_dir_all(&dir);
+            let path = dir.join("metrics.csv");
+            let pid = Pid::from(std::process::id() as usize);
+            let mut system = System::new();
+            system.refresh_cpu();
+
+            let mut pending: Vec<String> = Vec::new();
+            let mut last_underruns = counters.underruns.load(Ordering::Relaxed);
+            let mut last_overruns = counters.overruns.load(Ordering::Relaxed);
+            let mut last_stats = stats.lock().map(|s| *s).unwrap_or_default();
+
+            loop {
+                let wake = Instant::now() + METRICS_INTERVAL;
+                while Instant::now() < wake {
+                    if stop.load(Ordering::SeqCst) {
+                        return;
+                    }
+                    thread::sleep(Duration::from_millis(200));
+                }
+
+                system.refresh_process_specifics(pid, ProcessRefreshKind::new().with_cpu());
+                let cpus = system.cpus().len().max(1) as f32;
+                let cpu = system.process(pid).map(|p| p.cpu_usage() / cpus).unwrap_or(0.0);
+
+                let underruns = counters.underruns.load(Ordering::Relaxed);
+                let overruns = counters.overruns.load(Ordering::Relaxed);
+                let current = stats.lock().map(|s| *s).unwrap_or_default();
+                let interval = current.since(&last_stats);
+
+                pending.push(format!(
+                    "{},{:.1},{:.1},{},{},{:.1},{:.3},{:.1},{}",
+                    chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+                    fill_percent(counters.input_fill.load(Ordering::Relaxed)),
+                    fill_percent(counters.output_fill.load(Ordering::Relaxed)),
+                    underruns.saturating_sub(last_underruns),
+                    overruns.saturating_sub(last_overruns),
+                    cpu,
+                    interval.average_vad(),
+                    interval.gated_percent(),
+                    counters.starts.load(Ordering::Relaxed).saturating_sub(1),
+                ));
+                last_underruns = underruns;
+                last_overruns = overruns;
+                last_stats = current;
+
+                rotate_if_needed(&path);
+                if let Err(e) = write_rows(&path, &pending) {
+                    log::debug!("Metrics file busy, keeping {} rows for later: {}", pending.len(), e);
+                } else {
+                    pending.clear();
+                }
+                if pending.len() > MAX_PENDING_ROWS {
+                    // Still locked after a long time; drop the oldest rows
+                    let excess = pending.len() - MAX_PENDING_ROWS;
+                    pending.drain(..excess);
+                }
+            }
+        });
+
+        self.stop_flag = Some(stop_flag);
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(flag) = self.stop_flag.take() {
+            flag.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+impl Drop for MetricsLogger {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn fill_percent(samples: usize) -> f32 {
+    samples as f32 * 100.0 / RING_BUFFER_SIZE as f32
+}
+
+fn write_rows(path: &Path, rows: &[String]) -> std::io::Result<()> {
+    let is_new = !path.exists();
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut content = String::new();
+    if is_new {
+        content.push_str(HEADER);
+        content.push('\n');
+    }
+    for row in rows {
+        content.push_str(row);
+        content.push('\n');
+    }
+    file.write_all(content.as_bytes())
+}
+
+// metrics.csv -> metrics.1.csv -> metrics.2.csv; failures (file in use) are retried next time
+fn rotate_if_needed(path: &Path) {
+    let too_big = fs::metadata(path).map(|m| m.len() >= MAX_FILE_BYTES).unwrap_or(false);
+    if !too_big {
+        return;
+    }
+    let rotated = |n: usize| path.with_file_name(format!("metrics.{}.csv", n));
+    let _ = fs::remove_file(rotated(ROTATED_FILES));
+    for n in (1..ROTATED_FILES).rev() {
+        let _ = fs::rename(rotated(n), rotated(n + 1));
+    }
+    let _ = fs::rename(path, rotated(1));
+}
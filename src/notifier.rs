@@ -0,0 +1,110 @@
+// The one place that decides whether a toast or a sound cue may go out. Quiet hours and "not
+// over a fullscreen app" apply to every toast and cue alike; critical errors can be let through
+// anyway. Previews from the settings page skip the policy, since the user asked for them.
+use crate::earcon::{self, Cue};
+use crate::osd::{Osd, OsdCorner};
+use crate::platform;
+use chrono::Timelike;
+use eframe::egui;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Category {
+    // Mute, bypass and live changes
+    Status,
+    // Processing stopped by an error the app couldn't recover from
+    Critical,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct NotificationPolicy {
+    pub quiet_hours: bool,
+    // Minutes since midnight; an end before the start runs past midnight
+    pub quiet_start: u32,
+    pub quiet_end: u32,
+    // Nothing while the foreground window covers its whole monitor (games, videos)
+    pub quiet_when_fullscreen: bool,
+    // Critical errors ignore both of the above
+    pub critical_always: bool,
+}
+
+impl Default for NotificationPolicy {
+    fn default() -> Self {
+        Self { quiet_hours: false, quiet_start: 22 * 60, quiet_end: 7 * 60, quiet_when_fullscreen: false, critical_always: true }
+    }
+}
+
+impl NotificationPolicy {
+    pub fn in_quiet_hours_at(&self, minute: u32) -> bool {
+        if !self.quiet_hours || self.quiet_start == self.quiet_end {
+            return false;
+        }
+        if self.quiet_start < self.quiet_end {
+            minute >= self.quiet_start && minute < self.quiet_end
+        } else {
+            minute >= self.quiet_start || minute < self.quiet_end
+        }
+    }
+
+    pub fn in_quiet_hours(&self) -> bool {
+        let now = chrono::Local::now();
+        self.in_quiet_hours_at(now.hour() * 60 + now.minute())
+    }
+
+    // Why `category` has to stay quiet right now, if it does
+    pub fn blocked(&self, category: Category) -> Option<&'static str> {
+        if category == Category::Critical && self.critical_always {
+            return None;
+        }
+        if self.in_quiet_hours() {
+            return Some("quiet hours");
+        }
+        if self.quiet_when_fullscreen && platform::foreground_is_fullscreen() {
+            return Some("fullscreen app in front");
+        }
+        None
+    }
+}
+
+#[derive(Default)]
+pub struct Notifier {
+    pub policy: NotificationPolicy,
+    osd: Osd,
+}
+
+impl Notifier {
+    pub fn new(policy: NotificationPolicy) -> Self {
+        Self { policy, osd: Osd::default() }
+    }
+
+    pub fn allows(&self, category: Category) -> bool {
+        match self.policy.blocked(category) {
+            Some(reason) => {
+                log::debug!("{:?} notification held back: {}", category, reason);
+                false
+            }
+            None => true,
+        }
+    }
+
+    pub fn toast(&mut self, category: Category, text: &str) {
+        if self.allows(category) {
+            self.osd.show(text);
+        }
+    }
+
+    // `device` and `processed_output` as for earcon::play
+    pub fn cue(&self, category: Category, cue: Cue, volume: f32, device: &str, processed_output: &str) {
+        if self.allows(category) {
+            earcon::play(cue, volume, device, processed_output);
+        }
+    }
+
+    pub fn preview_toast(&mut self, text: &str) {
+        self.osd.show(text);
+    }
+
+    // Call every frame, including while the main window is hidden in the tray
+    pub fn draw(&mut self, ctx: &egui::Context, corner: OsdCorner) {
+        self.osd.draw(ctx, corner);
+    }
+}
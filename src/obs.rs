@@ -0,0 +1,384 @@
+use crate::automation::TriggerAction;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use eframe::egui;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tungstenite::{Message, WebSocket};
+
+// obs-websocket v5 opcodes
+const OP_HELLO: u64 = 0;
+const OP_IDENTIFY: u64 = 1;
+const OP_IDENTIFIED: u64 = 2;
+const OP_EVENT: u64 = 5;
+const OP_REQUEST: u64 = 6;
+const OP_REQUEST_RESPONSE: u64 = 7;
+
+// EventSubscription::Outputs - stream/record state changes
+const EVENT_SUB_OUTPUTS: u64 = 1 << 6;
+
+// Close code OBS sends when the password is wrong
+const CLOSE_AUTH_FAILED: u16 = 4009;
+
+const MAX_BACKOFF_SECS: u64 = 30;
+
+#[derive(Clone, PartialEq)]
+pub struct ObsConfig {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub password: String,
+    // Applied when OBS starts streaming or recording
+    pub on_active: TriggerAction,
+    // Applied once neither streaming nor recording is running
+    pub on_inactive: TriggerAction,
+}
+
+impl Default for ObsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: "localhost".to_string(),
+            port: 4455,
+            password: String::new(),
+            on_active: TriggerAction::StartProcessing,
+            on_inactive: TriggerAction::StopProcessing,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum ObsStatus {
+    Disabled,
+    Connecting,
+    Connected { streaming: bool, recording: bool },
+    Retrying { error: String, retry_in: u64 },
+}
+
+impl std::fmt::Display for ObsStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ObsStatus::Disabled => write!(f, "Disabled"),
+            ObsStatus::Connecting => write!(f, "Connecting..."),
+            ObsStatus::Connected { streaming, recording } => match (streaming, recording) {
+                (true, true) => write!(f, "Connected - streaming and recording"),
+                (true, false) => write!(f, "Connected - streaming"),
+                (false, true) => write!(f, "Connected - recording"),
+                (false, false) => write!(f, "Connected - idle"),
+            },
+            ObsStatus::Retrying { error, retry_in } => write!(f, "{} (retrying in {}s)", error, retry_in),
+        }
+    }
+}
+
+pub struct ObsClient {
+    status: Arc<Mutex<ObsStatus>>,
+    stop_flag: Option<Arc<AtomicBool>>,
+    // Carries "is OBS streaming or recording" whenever it changes
+    events: Option<Receiver<bool>>,
+}
+
+impl ObsClient {
+    pub fn new() -> Self {
+        Self {
+            status: Arc::new(Mutex::new(ObsStatus::Disabled)),
+            stop_flag: None,
+            events: None,
+        }
+    }
+
+    pub fn start(&mut self, config: &ObsConfig, ctx: &egui::Context) {
+        self.stop();
+        if !config.enabled {
+            return;
+        }
+
+        // Fresh status slot so a thread that is still winding down can't overwrite it
+        self.status = Arc::new(Mutex::new(ObsStatus::Connecting));
+
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = channel();
+        let status = self.status.clone();
+        let config = config.clone();
+        let ctx = ctx.clone();
+        let stop = stop_flag.clone();
+
+        thread::spawn(move || connection_loop(config, stop, status, tx, ctx));
+
+        self.stop_flag = Some(stop_flag);
+        self.events = Some(rx);
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(flag) = self.stop_flag.take() {
+            flag.store(true, Ordering::SeqCst);
+        }
+        self.events = None;
+        if let Ok(mut s) = self.status.lock() {
+            *s = ObsStatus::Disabled;
+        }
+    }
+
+    pub fn status(&self) -> ObsStatus {
+        self.status.lock().map(|s| s.clone()).unwrap_or(ObsStatus::Disabled)
+    }
+
+    // Latest output state reported since the last poll, if any
+    pub fn poll(&self) -> Option<bool> {
+        self.events.as_ref().and_then(|rx| rx.try_iter().last())
+    }
+}
+
+impl Drop for ObsClient {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn set_status(status: &Arc<Mutex<ObsStatus>>, value: ObsStatus, ctx: &egui::Context) {
+    if let Ok(mut s) = status.lock() {
+        *s = value;
+    }
+    ctx.request_repaint();
+}
+
+fn connection_loop(
+    config: ObsConfig,
+    stop: Arc<AtomicBool>,
+    status: Arc<Mutex<ObsStatus>>,
+    tx: Sender<bool>,
+    ctx: egui::Context,
+) {
+    let mut backoff = 1;
+
+    while !stop.load(Ordering::SeqCst) {
+        set_status(&status, ObsStatus::Connecting, &ctx);
+
+        let mut session = Session {
+            stop: &stop,
+            status: &status,
+            tx: &tx,
+            ctx: &ctx,
+            streaming: false,
+            recording: false,
+            identified: false,
+            initial_pending: 0,
+            last_sent: None,
+        };
+        let error = match session.run(&config) {
+            Ok(()) => "Connection closed".to_string(),
+            Err(e) => e,
+        };
+        if !stop.load(Ordering::SeqCst) {
+            log::warn!("OBS websocket {}:{}: {}", config.host, config.port, error);
+        }
+        // A session that got as far as Identified was healthy; start over with a short delay
+        if session.identified {
+            backoff = 1;
+        }
+
+        if stop.load(Ordering::SeqCst) {
+            break;
+        }
+
+        // Wait out the backoff in small steps so stop() takes effect quickly
+        for remaining in (1..=backoff).rev() {
+            set_status(&status, ObsStatus::Retrying { error: error.clone(), retry_in: remaining }, &ctx);
+            for _ in 0..10 {
+                if stop.load(Ordering::SeqCst) {
+                    return;
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+        }
+        backoff = (backoff * 2).min(MAX_BACKOFF_SECS);
+    }
+}
+
+struct Session<'a> {
+    stop: &'a Arc<AtomicBool>,
+    status: &'a Arc<Mutex<ObsStatus>>,
+    tx: &'a Sender<bool>,
+    ctx: &'a egui::Context,
+    streaming: bool,
+    recording: bool,
+    identified: bool,
+    // Initial status requests still outstanding; nothing is published until both answer
+    initial_pending: usize,
+    last_sent: Option<bool>,
+}
+
+impl Session<'_> {
+    fn run(&mut self, config: &ObsConfig) -> Result<(), String> {
+        let addr = (config.host.as_str(), config.port)
+            .to_socket_addrs()
+            .map_err(|e| format!("Cannot resolve {}: {}", config.host, e))?
+            .next()
+            .ok_or_else(|| format!("Cannot resolve {}", config.host))?;
+
+        let stream = TcpStream::connect_timeout(&addr, Duration::from_secs(3))
+            .map_err(|e| format!("Cannot reach OBS: {}", e))?;
+        let _ = stream.set_read_timeout(Some(Duration::from_secs(3)));
+
+        let url = format!("ws://{}:{}", config.host, config.port);
+        let (mut socket, _) = tungstenite::client(url, stream)
+            .map_err(|e| format!("Handshake failed: {}", e))?;
+
+        // Short read timeout from here on so the stop flag is checked regularly
+        let _ = socket.get_ref().set_read_timeout(Some(Duration::from_millis(200)));
+
+        while !self.stop.load(Ordering::SeqCst) {
+            let message = match socket.read() {
+                Ok(m) => m,
+                Err(tungstenite::Error::Io(e))
+                    if e.kind() == std::io::ErrorKind::WouldBlock || e.kind() == std::io::ErrorKind::TimedOut =>
+                {
+                    let _ = socket.flush();
+                    continue;
+                }
+                Err(e) => return Err(format!("Connection lost: {}", e)),
+            };
+
+            match message {
+                Message::Text(text) => {
+                    let value: Value = match serde_json::from_str(&text) {
+                        Ok(v) => v,
+                        Err(_) => continue,
+                    };
+                    self.handle_message(&mut socket, config, &value)?;
+                }
+                Message::Close(frame) => {
+                    if let Some(frame) = frame {
+                        if u16::from(frame.code) == CLOSE_AUTH_FAILED {
+                            return Err("Authentication failed - check the password".to_string());
+                        }
+                        return Err(format!("OBS closed the connection: {}", frame.reason));
+                    }
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+
+        let _ = socket.close(None);
+        Ok(())
+    }
+
+    fn handle_message(&mut self, socket: &mut WebSocket<TcpStream>, config: &ObsConfig, value: &Value) -> Result<(), String> {
+        let data = &value["d"];
+        match value["op"].as_u64() {
+            Some(OP_HELLO) => {
+                let mut identify = json!({
+                    "rpcVersion": 1,
+                    "eventSubscriptions": EVENT_SUB_OUTPUTS,
+                });
+                if let Some(auth) = data.get("authentication") {
+                    if config.password.is_empty() {
+                        return Err("OBS requires a password".to_string());
+                    }
+                    let challenge = auth["challenge"].as_str().unwrap_or("");
+                    let salt = auth["salt"].as_str().unwrap_or("");
+                    identify["authentication"] = json!(auth_response(&config.password, salt, challenge));
+                }
+                send(socket, json!({ "op": OP_IDENTIFY, "d": identify }))?;
+            }
+            Some(OP_IDENTIFIED) => {
+                log::info!("Connected to OBS at {}:{}", config.host, config.port);
+                self.identified = true;
+                self.initial_pending = 2;
+                self.update_status();
+                // Pick up outputs that were already running before we connected
+                for request in ["GetStreamStatus", "GetRecordStatus"] {
+                    send(socket, json!({
+                        "op": OP_REQUEST,
+                        "d": { "requestType": request, "requestId": request },
+                    }))?;
+                }
+            }
+            Some(OP_EVENT) => {
+                let active = data["eventData"]["outputActive"].as_bool();
+                match (data["eventType"].as_str(), active) {
+                    (Some("StreamStateChanged"), Some(active)) => {
+                        self.streaming = active;
+                        self.publish();
+                    }
+                    (Some("RecordStateChanged"), Some(active)) => {
+                        self.recording = active;
+                        self.publish();
+                    }
+                    _ => {}
+                }
+            }
+            Some(OP_REQUEST_RESPONSE) => {
+                let active = data["responseData"]["outputActive"].as_bool().unwrap_or(false);
+                match data["requestType"].as_str() {
+                    Some("GetStreamStatus") => self.streaming = active,
+                    Some("GetRecordStatus") => self.recording = active,
+                    _ => return Ok(()),
+                }
+                self.initial_pending = self.initial_pending.saturating_sub(1);
+                self.publish();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn update_status(&self) {
+        set_status(self.status, ObsStatus::Connected { streaming: self.streaming, recording: self.recording }, self.ctx);
+    }
+
+    fn publish(&mut self) {
+        self.update_status();
+        if self.initial_pending > 0 {
+            return;
+        }
+        let active = self.streaming || self.recording;
+        if self.last_sent != Some(active) {
+            self.last_sent = Some(active);
+            let _ = self.tx.send(active);
+            self.ctx.request_repaint();
+        }
+    }
+}
+
+fn send(socket: &mut WebSocket<TcpStream>, value: Value) -> Result<(), String> {
+    socket
+        .send(Message::Text(value.to_string()))
+        .map_err(|e| format!("Connection lost: {}", e))
+}
+
+// base64(sha256(base64(sha256(password + salt)) + challenge)), as specified by obs-websocket v5
+fn auth_response(password: &str, salt: &str, challenge: &str) -> String {
+    let secret = STANDARD.encode(Sha256::digest(format!("{}{}", password, salt).as_bytes()));
+    STANDARD.encode(Sha256::digest(format!("{}{}", secret, challenge).as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The Hello/Identify example from the obs-websocket v5 protocol docs
+    #[test]
+    fn auth_response_matches_the_protocol_example() {
+        let response = auth_response(
+            "supersecretpassword",
+            "lM1GncleQOaCu9lT1yeUZhFYnqhsLLP1G5lAGo3ixaI=",
+            "+IxH4CnCiqpX1rM9scsNynZzbOe4KhDeYcTNS3PDaeY=",
+        );
+        assert_eq!(response, "1Ct943GAT+6YQUUX47Ia/ncufilbe6+oD6lY+5kaCu4=");
+    }
+
+    #[test]
+    fn auth_response_depends_on_every_input() {
+        let base = auth_response("password", "salt", "challenge");
+        assert_ne!(base, auth_response("Password", "salt", "challenge"));
+        assert_ne!(base, auth_response("password", "Salt", "challenge"));
+        assert_ne!(base, auth_response("password", "salt", "Challenge"));
+    }
+}
@@ -0,0 +1,134 @@
+// Brief on-screen toast confirming a mute or bypass change made while the window isn't in
+// front (keyboard mic-mute key, automation). Drawn as a borderless, click-through viewport
+// that never takes focus.
+use eframe::egui;
+use std::time::{Duration, Instant};
+
+const SHOW_DURATION: Duration = Duration::from_millis(1500);
+const FADE_DURATION: Duration = Duration::from_millis(200);
+const TOAST_SIZE: egui::Vec2 = egui::vec2(180.0, 44.0);
+// Distance from the edges of the work area
+const TOAST_MARGIN: f32 = 24.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OsdCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl OsdCorner {
+    pub const ALL: [OsdCorner; 4] = [OsdCorner::TopLeft, OsdCorner::TopRight, OsdCorner::BottomLeft, OsdCorner::BottomRight];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OsdCorner::TopLeft => "top_left",
+            OsdCorner::TopRight => "top_right",
+            OsdCorner::BottomLeft => "bottom_left",
+            OsdCorner::BottomRight => "bottom_right",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|c| c.as_str() == s)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            OsdCorner::TopLeft => "Top left",
+            OsdCorner::TopRight => "Top right",
+            OsdCorner::BottomLeft => "Bottom left",
+            OsdCorner::BottomRight => "Bottom right",
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Osd {
+    message: Option<(String, Instant)>,
+}
+
+impl Osd {
+    // Replaces whatever is showing and restarts the timer
+    pub fn show(&mut self, text: &str) {
+        self.message = Some((text.to_string(), Instant::now()));
+    }
+
+    // Call every frame, including while the main window is hidden in the tray
+    pub fn draw(&mut self, ctx: &egui::Context, corner: OsdCorner) {
+        let Some((text, shown_at)) = &self.message else { return };
+        let elapsed = shown_at.elapsed();
+        if elapsed >= SHOW_DURATION {
+            self.message = None;
+            return;
+        }
+        let fade = FADE_DURATION.as_secs_f32();
+        let alpha = (elapsed.as_secs_f32() / fade).min((SHOW_DURATION - elapsed).as_secs_f32() / fade).min(1.0);
+        let text = text.clone();
+
+        let builder = egui::ViewportBuilder::default()
+            .with_title("SilentStream")
+            .with_decorations(false)
+            .with_resizable(false)
+            .with_always_on_top()
+            .with_mouse_passthrough(true)
+            .with_active(false)
+            .with_inner_size(TOAST_SIZE)
+            .with_position(position(ctx, corner));
+        ctx.show_viewport_immediate(egui::ViewportId::from_hash_of("osd"), builder, |ctx, _| {
+            egui::CentralPanel::default().frame(egui::Frame::none()).show(ctx, |ui| {
+                let rect = ui.max_rect();
+                let fill = egui::Color32::from_rgb(43, 45, 49).linear_multiply(alpha);
+                ui.painter().rect_filled(rect, 10.0, fill);
+                ui.painter().text(
+                    rect.center(),
+                    egui::Align2::CENTER_CENTER,
+                    text,
+                    egui::FontId::proportional(18.0),
+                    egui::Color32::WHITE.linear_multiply(alpha),
+                );
+            });
+        });
+        // Keep the fade moving even when nothing else repaints
+        ctx.request_repaint_after(Duration::from_millis(16));
+    }
+}
+
+// Top-left of the toast in points, inside the primary monitor's work area
+fn position(ctx: &egui::Context, corner: OsdCorner) -> egui::Pos2 {
+    let work = work_area(ctx);
+    let (left, top, right, bottom) = (work.left(), work.top(), work.right(), work.bottom());
+    let x = match corner {
+        OsdCorner::TopLeft | OsdCorner::BottomLeft => left + TOAST_MARGIN,
+        OsdCorner::TopRight | OsdCorner::BottomRight => right - TOAST_SIZE.x - TOAST_MARGIN,
+    };
+    let y = match corner {
+        OsdCorner::TopLeft | OsdCorner::TopRight => top + TOAST_MARGIN,
+        OsdCorner::BottomLeft | OsdCorner::BottomRight => bottom - TOAST_SIZE.y - TOAST_MARGIN,
+    };
+    egui::pos2(x, y)
+}
+
+#[cfg(windows)]
+fn work_area(ctx: &egui::Context) -> egui::Rect {
+    use windows_sys::Win32::Foundation::RECT;
+    use windows_sys::Win32::UI::WindowsAndMessaging::{SystemParametersInfoW, SPI_GETWORKAREA};
+
+    let mut work = RECT { left: 0, top: 0, right: 1920, bottom: 1080 };
+    unsafe {
+        SystemParametersInfoW(SPI_GETWORKAREA, 0, &mut work as *mut RECT as *mut _, 0);
+    }
+    let scale = ctx.input(|i| i.viewport().native_pixels_per_point).unwrap_or(1.0);
+    egui::Rect::from_min_max(
+        egui::pos2(work.left as f32 / scale, work.top as f32 / scale),
+        egui::pos2(work.right as f32 / scale, work.bottom as f32 / scale),
+    )
+}
+
+// No portable work area query; the whole monitor is close enough with the margin
+#[cfg(not(windows))]
+fn work_area(ctx: &egui::Context) -> egui::Rect {
+    let size = ctx.input(|i| i.viewport().monitor_size).unwrap_or(egui::vec2(1920.0, 1080.0));
+    egui::Rect::from_min_size(egui::Pos2::ZERO, size)
+}
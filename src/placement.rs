@@ -0,0 +1,92 @@
+// Window position saved when hiding to the tray, so a restore puts the window back on the
+// monitor it was on instead of wherever Windows decides. Elsewhere nothing is captured and
+// the window manager places the window.
+#[cfg(windows)]
+use windows_sys::Win32::{
+    Foundation::RECT,
+    Graphics::Gdi::{
+        GetMonitorInfoW, IntersectRect, MonitorFromRect, MonitorFromWindow, HMONITOR, MONITORINFO, MONITOR_DEFAULTTONEAREST,
+    },
+    UI::HiDpi::{GetDpiForMonitor, MDT_EFFECTIVE_DPI},
+    UI::WindowsAndMessaging::{GetWindowRect, IsIconic, SetWindowPos, SWP_NOACTIVATE, SWP_NOZORDER},
+};
+
+// Used when the monitor's DPI can't be queried
+#[cfg(windows)]
+const DEFAULT_DPI: u32 = 96;
+
+#[cfg(windows)]
+#[derive(Clone, Copy)]
+pub struct WindowPlacement {
+    rect: RECT,
+    // DPI of the monitor the window was on
+    dpi: u32,
+}
+
+#[cfg(windows)]
+impl WindowPlacement {
+    // None while minimized: the rect is then parked off-screen and not worth keeping
+    pub fn capture(hwnd: isize) -> Option<Self> {
+        unsafe {
+            if IsIconic(hwnd) != 0 {
+                return None;
+            }
+            let mut rect = RECT { left: 0, top: 0, right: 0, bottom: 0 };
+            if GetWindowRect(hwnd, &mut rect) == 0 {
+                return None;
+            }
+            let dpi = monitor_dpi(MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST));
+            Some(Self { rect, dpi })
+        }
+    }
+
+    // Puts the window back where it was. If that monitor is gone, the window moves to the
+    // nearest one; either way it's scaled to the target DPI and kept inside the work area.
+    pub fn restore(&self, hwnd: isize) {
+        unsafe {
+            let monitor = MonitorFromRect(&self.rect, MONITOR_DEFAULTTONEAREST);
+            let mut info: MONITORINFO = std::mem::zeroed();
+            info.cbSize = std::mem::size_of::<MONITORINFO>() as u32;
+            if GetMonitorInfoW(monitor, &mut info) == 0 {
+                return;
+            }
+            let work = info.rcWork;
+
+            let scale = monitor_dpi(monitor) as f32 / self.dpi as f32;
+            let width = (((self.rect.right - self.rect.left) as f32 * scale).round() as i32).min(work.right - work.left);
+            let height = (((self.rect.bottom - self.rect.top) as f32 * scale).round() as i32).min(work.bottom - work.top);
+
+            let mut overlap = RECT { left: 0, top: 0, right: 0, bottom: 0 };
+            let (x, y) = if IntersectRect(&mut overlap, &self.rect, &info.rcMonitor) != 0 {
+                (self.rect.left, self.rect.top)
+            } else {
+                log::info!("Saved window position is off-screen, centering on the nearest monitor");
+                (work.left + (work.right - work.left - width) / 2, work.top + (work.bottom - work.top - height) / 2)
+            };
+            let x = x.clamp(work.left, work.right - width);
+            let y = y.clamp(work.top, work.bottom - height);
+
+            SetWindowPos(hwnd, 0, x, y, width, height, SWP_NOZORDER | SWP_NOACTIVATE);
+        }
+    }
+}
+
+#[cfg(windows)]
+fn monitor_dpi(monitor: HMONITOR) -> u32 {
+    let (mut dpi_x, mut dpi_y) = (0u32, 0u32);
+    let ok = unsafe { GetDpiForMonitor(monitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y) } == 0;
+    if ok && dpi_x > 0 { dpi_x } else { DEFAULT_DPI }
+}
+
+#[cfg(not(windows))]
+#[derive(Clone, Copy)]
+pub struct WindowPlacement;
+
+#[cfg(not(windows))]
+impl WindowPlacement {
+    pub fn capture(_hwnd: isize) -> Option<Self> {
+        None
+    }
+
+    pub fn restore(&self, _hwnd: isize) {}
+}
@@ -0,0 +1,138 @@
+// The few OS calls the UI makes directly. On Windows the window is hidden and restored
+// through Win32 as well, since eframe's commands alone don't bring it back reliably;
+// elsewhere (macOS, Linux) there is no window handle and the ViewportCommands do the job.
+use std::ffi::OsStr;
+use std::process::Command;
+
+#[cfg(windows)]
+pub fn window_handle(frame: &eframe::Frame) -> Option<isize> {
+    use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+    match frame.window_handle().ok()?.as_raw() {
+        RawWindowHandle::Win32(handle) => Some(handle.hwnd.get()),
+        _ => None,
+    }
+}
+
+#[cfg(not(windows))]
+pub fn window_handle(_frame: &eframe::Frame) -> Option<isize> {
+    None
+}
+
+#[cfg(windows)]
+pub fn hide_window(hwnd: isize) {
+    use windows_sys::Win32::UI::WindowsAndMessaging::{ShowWindow, SW_HIDE};
+    unsafe {
+        ShowWindow(hwnd, SW_HIDE);
+    }
+}
+
+#[cfg(not(windows))]
+pub fn hide_window(_hwnd: isize) {}
+
+#[cfg(windows)]
+pub fn show_window(hwnd: isize) {
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        BringWindowToTop, SetForegroundWindow, ShowWindow, SW_RESTORE, SW_SHOW,
+    };
+    unsafe {
+        ShowWindow(hwnd, SW_RESTORE);
+        ShowWindow(hwnd, SW_SHOW);
+        SetForegroundWindow(hwnd);
+        BringWindowToTop(hwnd);
+    }
+}
+
+#[cfg(not(windows))]
+pub fn show_window(_hwnd: isize) {}
+
+// Nothing is drawn while in the tray, so hand the UI's pages back to Windows; they
+// fault back in on restore, which costs a few milliseconds at most
+#[cfg(windows)]
+pub fn release_memory_while_hidden() {
+    use windows_sys::Win32::System::Threading::{GetCurrentProcess, SetProcessWorkingSetSize};
+    unsafe {
+        SetProcessWorkingSetSize(GetCurrentProcess(), usize::MAX, usize::MAX);
+    }
+}
+
+#[cfg(not(windows))]
+pub fn release_memory_while_hidden() {}
+
+// True while the foreground window covers its whole monitor: a game, a fullscreen video or
+// presentation. The desktop also covers the monitor, so it doesn't count.
+#[cfg(windows)]
+pub fn foreground_is_fullscreen() -> bool {
+    use windows_sys::Win32::Foundation::RECT;
+    use windows_sys::Win32::Graphics::Gdi::{GetMonitorInfoW, MonitorFromWindow, MONITORINFO, MONITOR_DEFAULTTONULL};
+    use windows_sys::Win32::UI::WindowsAndMessaging::{GetClassNameW, GetForegroundWindow, GetShellWindow, GetWindowRect};
+    unsafe {
+        let hwnd = GetForegroundWindow();
+        if hwnd == 0 || hwnd == GetShellWindow() {
+            return false;
+        }
+        let mut class = [0u16; 32];
+        let len = GetClassNameW(hwnd, class.as_mut_ptr(), class.len() as i32).max(0) as usize;
+        let class = String::from_utf16_lossy(&class[..len]);
+        if class == "Progman" || class == "WorkerW" {
+            return false;
+        }
+        let mut window = RECT { left: 0, top: 0, right: 0, bottom: 0 };
+        if GetWindowRect(hwnd, &mut window) == 0 {
+            return false;
+        }
+        let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONULL);
+        if monitor == 0 {
+            return false;
+        }
+        let mut info: MONITORINFO = std::mem::zeroed();
+        info.cbSize = std::mem::size_of::<MONITORINFO>() as u32;
+        if GetMonitorInfoW(monitor, &mut info) == 0 {
+            return false;
+        }
+        let screen = info.rcMonitor;
+        window.left <= screen.left && window.top <= screen.top && window.right >= screen.right && window.bottom >= screen.bottom
+    }
+}
+
+#[cfg(not(windows))]
+pub fn foreground_is_fullscreen() -> bool {
+    false
+}
+
+// Opens a folder or URL with the desktop's default handler
+pub fn open(target: impl AsRef<OsStr>) {
+    let program = if cfg!(windows) {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    if let Err(e) = Command::new(program).arg(target).spawn() {
+        log::warn!("Failed to run {}: {}", program, e);
+    }
+}
+
+pub fn open_sound_settings() {
+    let result = if cfg!(windows) {
+        Command::new("control").arg("mmsys.cpl").spawn()
+    } else if cfg!(target_os = "macos") {
+        Command::new("open").arg("x-apple.systempreferences:com.apple.preference.sound").spawn()
+    } else {
+        Command::new("pavucontrol").spawn()
+    };
+    if let Err(e) = result {
+        log::warn!("Failed to open the sound settings: {}", e);
+    }
+}
+
+// Page where the OS lets the user grant or revoke microphone access
+pub fn open_microphone_privacy_settings() {
+    if cfg!(windows) {
+        open("ms-settings:privacy-microphone");
+    } else if cfg!(target_os = "macos") {
+        open("x-apple.systempreferences:com.apple.preference.security?Privacy_Microphone");
+    } else {
+        log::info!("No microphone privacy settings on this platform");
+    }
+}
@@ -0,0 +1,35 @@
+// Windows' microphone privacy switches. With any of them off, capture streams still open but
+// deliver nothing except zeros, so this is checked once the input turns out to be exact silence.
+#[cfg(windows)]
+use winreg::enums::{HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE};
+#[cfg(windows)]
+use winreg::RegKey;
+
+#[cfg(windows)]
+const CONSENT_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\CapabilityAccessManager\ConsentStore\microphone";
+
+// Which switch blocks capture, or None if access is allowed or the state can't be read
+#[cfg(windows)]
+pub fn microphone_access_blocked() -> Option<&'static str> {
+    let denied = |root: isize, path: &str| {
+        RegKey::predef(root)
+            .open_subkey(path)
+            .and_then(|key| key.get_value::<String, _>("Value"))
+            .map(|value| value.eq_ignore_ascii_case("Deny"))
+            .unwrap_or(false)
+    };
+    if denied(HKEY_LOCAL_MACHINE, CONSENT_KEY) {
+        Some("Microphone access is turned off for this device")
+    } else if denied(HKEY_CURRENT_USER, CONSENT_KEY) {
+        Some("Microphone access is turned off for apps")
+    } else if denied(HKEY_CURRENT_USER, &format!(r"{}\NonPackaged", CONSENT_KEY)) {
+        Some("Desktop apps aren't allowed to use the microphone")
+    } else {
+        None
+    }
+}
+
+#[cfg(not(windows))]
+pub fn microphone_access_blocked() -> Option<&'static str> {
+    None
+}
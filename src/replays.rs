@@ -0,0 +1,107 @@
+// Replay recordings (see silentstream_core::replay): where new ones go, the list in the
+// settings, and `--replay <file>`, which re-runs one and exits. The replay writes the output
+// next to the recording as WAV, plus a short report; the exit code says whether the output
+// matched the live run (0), differed (1) or couldn't be produced (2).
+use crate::crash;
+use silentstream_core::replay::{self, ReplayReport, EXTENSION};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const REPLAY_FLAG: &str = "--replay";
+
+pub struct ReplayFile {
+    pub path: PathBuf,
+    pub bytes: u64,
+}
+
+pub fn replay_dir(config_dir: &Path) -> PathBuf {
+    config_dir.join("replays")
+}
+
+// Named after the local start time, so it lines up with the time in a report
+pub fn new_path(config_dir: &Path) -> io::Result<PathBuf> {
+    let dir = replay_dir(config_dir);
+    fs::create_dir_all(&dir)?;
+    let name = chrono::Local::now().format("replay-%Y%m%d-%H%M%S").to_string();
+    Ok(dir.join(name).with_extension(EXTENSION))
+}
+
+// Newest first
+pub fn list(config_dir: &Path) -> Vec<ReplayFile> {
+    let Ok(entries) = fs::read_dir(replay_dir(config_dir)) else { return Vec::new() };
+    let mut files: Vec<ReplayFile> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == EXTENSION))
+        .map(|e| ReplayFile { path: e.path(), bytes: e.metadata().map_or(0, |m| m.len()) })
+        .collect();
+    files.sort_by(|a, b| b.path.cmp(&a.path));
+    files
+}
+
+pub fn delete(file: &Path) {
+    match fs::remove_file(file) {
+        Ok(()) => log::info!("Deleted replay recording '{}'", file.display()),
+        Err(e) => log::warn!("Could not delete '{}': {}", file.display(), e),
+    }
+}
+
+pub fn replay_arg() -> Option<PathBuf> {
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == REPLAY_FLAG {
+            return args.next().map(PathBuf::from);
+        }
+    }
+    None
+}
+
+// Runs from main() before any window opens; returns the exit code
+pub fn run(recording: &Path) -> i32 {
+    let output = recording.with_extension("wav");
+    log::info!("Replaying '{}'", recording.display());
+    match replay::replay(recording, &output) {
+        Ok(report) => {
+            let text = report_text(&report, &output);
+            for line in text.lines() {
+                log::info!("{}", line);
+            }
+            eprintln!("{}", text);
+            let report_path = recording.with_extension("txt");
+            if let Err(e) = fs::write(&report_path, &text) {
+                log::warn!("Could not write '{}': {}", report_path.display(), e);
+            }
+            if report.first_difference.is_some() { 1 } else { 0 }
+        }
+        Err(e) => {
+            crash::show_error("SilentStream replay", &format!("'{}' could not be replayed.\n\n{}", recording.display(), e));
+            2
+        }
+    }
+}
+
+fn report_text(report: &ReplayReport, output: &Path) -> String {
+    let started = chrono::DateTime::from_timestamp(report.started as i64, 0).map(|t| t.with_timezone(&chrono::Local));
+    // Wall-clock time of a point in the recording
+    let at = |ms: u64| {
+        let offset = format!("{:.1} s", ms as f64 / 1000.0);
+        started.map_or(offset.clone(), |t| format!("{} ({})", (t + chrono::Duration::milliseconds(ms as i64)).format("%H:%M:%S"), offset))
+    };
+    let mut lines = vec![
+        format!("Recorded {}", started.map_or_else(|| "at an unknown time".to_string(), |t| t.format("%Y-%m-%d %H:%M:%S").to_string())),
+        format!("{:.1} s processed, written to '{}'", report.seconds(), output.display()),
+    ];
+    lines.push(match report.first_difference {
+        None => "Output identical to the live run".to_string(),
+        Some(ms) => format!("Output differs from the live run from {} on", at(ms)),
+    });
+    if report.plugin {
+        lines.push("Recorded with an effect plugin, which the replay runs without".to_string());
+    }
+    if report.truncated {
+        lines.push("The recording ends partway through; the app probably closed while recording".to_string());
+    }
+    lines.push("Control changes:".to_string());
+    lines.extend(report.control_changes.iter().map(|(ms, controls)| format!("  {}  {}", at(*ms), controls)));
+    lines.join("\n")
+}
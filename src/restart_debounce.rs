@@ -0,0 +1,126 @@
+// Device picks from the UI restart the engine only once they've settled, so going through a
+// few devices (or changing input and output in a row) costs one restart, and picking what's
+// already running costs none. cpal doesn't always take quick stop/start cycles well.
+use silentstream_core::audio_engine::InputChannel;
+use std::time::{Duration, Instant};
+
+// Quiet time after the last pick before it's applied
+pub const DEVICE_CHANGE_SETTLE: Duration = Duration::from_millis(500);
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct DeviceSelection {
+    pub input: String,
+    pub output: String,
+    pub channel: InputChannel,
+}
+
+#[derive(Default)]
+pub struct RestartDebouncer {
+    due: Option<Instant>,
+    // What the engine was last started with
+    applied: Option<DeviceSelection>,
+}
+
+impl RestartDebouncer {
+    // Each pick pushes the restart back
+    pub fn changed(&mut self, now: Instant) {
+        self.due = Some(now + DEVICE_CHANGE_SETTLE);
+    }
+
+    // Any successful start, debounced or not
+    pub fn started(&mut self, selection: DeviceSelection) {
+        self.applied = Some(selection);
+        self.due = None;
+    }
+
+    // Time left until the pending restart, None when nothing is pending
+    pub fn remaining(&self, now: Instant) -> Option<Duration> {
+        self.due.map(|due| due.saturating_duration_since(now))
+    }
+
+    // True once the picks have settled on something other than what's running
+    pub fn take_due(&mut self, now: Instant, current: &DeviceSelection, running: bool) -> bool {
+        match self.due {
+            Some(due) if now >= due => {
+                self.due = None;
+                if running && self.applied.as_ref() == Some(current) {
+                    log::debug!("Device selection back to the running pair; no restart");
+                    return false;
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn selection(input: &str) -> DeviceSelection {
+        DeviceSelection { input: input.to_string(), output: "Cable".to_string(), channel: InputChannel::Mix }
+    }
+
+    // Polls every 10 ms from `start` to `end`, as the UI does, counting restarts
+    fn poll(debouncer: &mut RestartDebouncer, start: Instant, end: Instant, current: &DeviceSelection, running: bool) -> Vec<Instant> {
+        let mut restarts = Vec::new();
+        let mut now = start;
+        while now <= end {
+            if debouncer.take_due(now, current, running) {
+                restarts.push(now);
+            }
+            now += Duration::from_millis(10);
+        }
+        restarts
+    }
+
+    #[test]
+    fn rapid_burst_collapses_to_one_restart() {
+        let start = Instant::now();
+        let mut debouncer = RestartDebouncer::default();
+        debouncer.started(selection("Mic A"));
+        // Five picks 100 ms apart, each well inside the settle time of the one before
+        let mut now = start;
+        for _ in 0..5 {
+            debouncer.changed(now);
+            assert!(!debouncer.take_due(now, &selection("Mic B"), true));
+            now += Duration::from_millis(100);
+        }
+        let last_pick = start + Duration::from_millis(400);
+        assert_eq!(debouncer.remaining(last_pick), Some(DEVICE_CHANGE_SETTLE));
+
+        let restarts = poll(&mut debouncer, last_pick, last_pick + Duration::from_secs(3), &selection("Mic B"), true);
+        assert_eq!(restarts, vec![last_pick + DEVICE_CHANGE_SETTLE]);
+        assert_eq!(debouncer.remaining(last_pick + Duration::from_secs(3)), None);
+    }
+
+    #[test]
+    fn picking_the_running_pair_again_costs_nothing() {
+        let start = Instant::now();
+        let mut debouncer = RestartDebouncer::default();
+        debouncer.started(selection("Mic A"));
+        debouncer.changed(start);
+        debouncer.changed(start + Duration::from_millis(200));
+        assert!(poll(&mut debouncer, start, start + Duration::from_secs(2), &selection("Mic A"), true).is_empty());
+        assert_eq!(debouncer.remaining(start), None);
+    }
+
+    #[test]
+    fn stopped_engine_restarts_even_on_the_same_pair() {
+        let start = Instant::now();
+        let mut debouncer = RestartDebouncer::default();
+        debouncer.started(selection("Mic A"));
+        debouncer.changed(start);
+        assert_eq!(poll(&mut debouncer, start, start + Duration::from_secs(1), &selection("Mic A"), false).len(), 1);
+    }
+
+    #[test]
+    fn a_start_in_between_cancels_the_pending_restart() {
+        let start = Instant::now();
+        let mut debouncer = RestartDebouncer::default();
+        debouncer.changed(start);
+        debouncer.started(selection("Mic B"));
+        assert!(poll(&mut debouncer, start, start + Duration::from_secs(1), &selection("Mic B"), true).is_empty());
+    }
+}
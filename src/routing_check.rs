@@ -0,0 +1,186 @@
+// "Verify routing": plays a short tone on the processed output and listens for it on the
+// capture end of the virtual cable. Catches the cases where the right devices are picked but
+// the app on the other end still hears nothing: SilentStream turned down in the volume mixer,
+// a muted cable, the wrong capture endpoint, or cable ends running at different rates.
+use silentstream_core::audio_engine::{open_capture_stream, CaptureSource};
+use silentstream_core::dsp::rms;
+use crate::earcon;
+use silentstream_core::output_format;
+use silentstream_core::simd;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use eframe::egui;
+use std::f32::consts::TAU;
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Above most voices' fundamentals and inside any call codec's band
+const TONE_HZ: f32 = 1500.0;
+const TONE_SECONDS: f32 = 0.5;
+const TONE_VOLUME: f32 = 0.25;
+// Time for the capture stream to start delivering before the tone plays
+const SETTLE: Duration = Duration::from_millis(150);
+// How long to keep listening after the tone ends, for the cable's latency
+const LISTEN_MARGIN: Duration = Duration::from_millis(500);
+const WINDOW: usize = 2048;
+// Share of a window's energy at one frequency for it to count as the tone
+const TONAL_SHARE: f32 = 0.5;
+// Peak below this is treated as no signal at all
+const SILENCE_PEAK: f32 = 1e-4;
+// Arriving this much below what was sent suggests a volume turned down along the way
+const QUIET_LOSS_DB: f32 = 20.0;
+// Where the tone ends up when one end of the cable runs at 44.1 kHz and the other at 48 kHz
+const RATE_RATIOS: [f32; 2] = [44100.0 / 48000.0, 48000.0 / 44100.0];
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum RoutingResult {
+    Arrived,
+    // Received, but this many dB below what was sent
+    Quiet(f32),
+    // Received at the wrong pitch
+    RateMismatch,
+    // Sound on the capture side, but not the tone
+    OtherSound,
+    Silent,
+    Failed(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct RoutingReport {
+    pub result: RoutingResult,
+    pub output: String,
+    pub capture: String,
+}
+
+impl RoutingReport {
+    pub fn is_ok(&self) -> bool {
+        self.result == RoutingResult::Arrived
+    }
+
+    pub fn message(&self) -> String {
+        match &self.result {
+            RoutingResult::Arrived => format!("The test tone arrived on '{}'.", self.capture),
+            RoutingResult::Quiet(loss) => format!(
+                "The tone arrived {:.0} dB quieter than it was sent. Check SilentStream's level in the Windows volume mixer and the level of '{}' in Sound settings.",
+                loss, self.capture
+            ),
+            RoutingResult::RateMismatch => format!(
+                "The tone arrived at the wrong pitch: '{}' and '{}' run at different sample rates. Give both ends the same format in Sound settings or the cable's control panel.",
+                self.output, self.capture
+            ),
+            RoutingResult::OtherSound => format!(
+                "'{}' has sound, but not the test tone. It's probably not the other end of '{}'.",
+                self.capture, self.output
+            ),
+            RoutingResult::Silent => format!(
+                "Nothing arrived on '{}'. Check that SilentStream isn't muted or at 0 in the Windows volume mixer, that neither end of the cable is muted, and that '{}' is the other end of '{}'.",
+                self.capture, self.capture, self.output
+            ),
+            RoutingResult::Failed(e) => format!("The check couldn't run: {}", e),
+        }
+    }
+}
+
+pub struct RoutingCheck {
+    report: Receiver<RoutingReport>,
+}
+
+impl RoutingCheck {
+    pub fn start(output: &str, capture: &str, ctx: &egui::Context) -> Self {
+        let (tx, rx) = channel();
+        let (output, capture) = (output.to_string(), capture.to_string());
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let result = run(&output, &capture).unwrap_or_else(|e| RoutingResult::Failed(e.to_string()));
+            log::info!("Routing check '{}' -> '{}': {:?}", output, capture, result);
+            let _ = tx.send(RoutingReport { result, output, capture });
+            ctx.request_repaint();
+        });
+        Self { report: rx }
+    }
+
+    pub fn poll(&self) -> Option<RoutingReport> {
+        self.report.try_recv().ok()
+    }
+}
+
+fn run(output: &str, capture: &str) -> Result<RoutingResult, Box<dyn std::error::Error>> {
+    let host = cpal::default_host();
+    let device = host
+        .output_devices()?
+        .find(|d| d.name().map(|n| n == output).unwrap_or(false))
+        .ok_or_else(|| format!("Output device '{}' not found", output))?;
+
+    let source = CaptureSource { device: capture.to_string(), loopback: false };
+    let mut listen = open_capture_stream(&host, &source, "Routing check")?;
+    listen.stream.play()?;
+    thread::sleep(SETTLE);
+    listen.samples.clear();
+
+    let format = output_format::negotiate(&device)?;
+    let config = format.config();
+    let tone = earcon::tone(TONE_HZ, TONE_SECONDS, config.sample_rate.0, TONE_VOLUME);
+    let mut position = 0;
+    let stream = output_format::build_stream(
+        &device,
+        &format,
+        &config,
+        move |mono: &mut [f32]| {
+            for sample in mono {
+                *sample = tone.get(position).copied().unwrap_or(0.0);
+                position += 1;
+            }
+        },
+        |err| log::warn!("Routing check stream error: {}", err),
+    )?;
+    stream.play()?;
+
+    let mut captured = Vec::new();
+    let until = Instant::now() + Duration::from_secs_f32(TONE_SECONDS) + LISTEN_MARGIN;
+    while Instant::now() < until {
+        thread::sleep(Duration::from_millis(20));
+        captured.extend(listen.samples.pop_iter());
+    }
+    Ok(analyze(&captured, listen.sample_rate))
+}
+
+fn analyze(captured: &[f32], sample_rate: u32) -> RoutingResult {
+    let peak = captured.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+    if peak < SILENCE_PEAK {
+        return RoutingResult::Silent;
+    }
+    let windows = || captured.chunks_exact(WINDOW);
+    let best = |frequency: f32| windows().map(|w| tonal_share(w, frequency, sample_rate)).fold(0.0f32, f32::max);
+
+    if best(TONE_HZ) >= TONAL_SHARE {
+        // Loudest window where the tone dominates, against the tone's own RMS as sent
+        let level = windows()
+            .filter(|w| tonal_share(w, TONE_HZ, sample_rate) >= TONAL_SHARE)
+            .map(rms)
+            .fold(0.0f32, f32::max);
+        let sent = TONE_VOLUME / 2f32.sqrt();
+        let loss = 20.0 * (sent / level.max(1e-9)).log10();
+        return if loss > QUIET_LOSS_DB { RoutingResult::Quiet(loss) } else { RoutingResult::Arrived };
+    }
+    if RATE_RATIOS.iter().any(|r| best(TONE_HZ * r) >= TONAL_SHARE) {
+        return RoutingResult::RateMismatch;
+    }
+    RoutingResult::OtherSound
+}
+
+// Share of the window's energy at `frequency` (Goertzel); close to 1 for a pure tone
+fn tonal_share(window: &[f32], frequency: f32, sample_rate: u32) -> f32 {
+    let coeff = 2.0 * (TAU * frequency / sample_rate as f32).cos();
+    let (mut s1, mut s2) = (0.0f32, 0.0f32);
+    for &x in window {
+        let s0 = x + coeff * s1 - s2;
+        s2 = s1;
+        s1 = s0;
+    }
+    let power = s1 * s1 + s2 * s2 - coeff * s1 * s2;
+    let energy = simd::sum_of_squares(window);
+    if energy <= 0.0 {
+        return 0.0;
+    }
+    (2.0 * power / (window.len() as f32 * energy)).min(1.0)
+}
@@ -0,0 +1,158 @@
+// Session lock/unlock, sleep/resume and Explorer restart notifications. eframe owns the main window's
+// message loop, so a hidden window on its own thread receives these broadcasts instead.
+// Other platforms get no events for now.
+use eframe::egui;
+use std::sync::mpsc::{channel, Receiver};
+#[cfg(windows)]
+use {
+    std::cell::{Cell, RefCell},
+    std::sync::mpsc::Sender,
+    std::thread,
+    windows_sys::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM},
+    windows_sys::Win32::System::LibraryLoader::GetModuleHandleW,
+    windows_sys::Win32::System::RemoteDesktop::{WTSRegisterSessionNotification, NOTIFY_FOR_THIS_SESSION},
+    windows_sys::Win32::UI::WindowsAndMessaging::{
+        CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, RegisterClassW, RegisterWindowMessageW, TranslateMessage, MSG,
+        PBT_APMRESUMEAUTOMATIC, PBT_APMSUSPEND, WM_POWERBROADCAST, WM_QUERYENDSESSION, WM_WTSSESSION_CHANGE, WNDCLASSW, WS_OVERLAPPED,
+        WTS_SESSION_LOCK, WTS_SESSION_UNLOCK,
+    },
+};
+
+// Only the watcher on Windows produces these
+#[cfg_attr(not(windows), allow(dead_code))]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SessionEvent {
+    Locked,
+    Unlocked,
+    Suspending,
+    Resumed,
+    // Explorer restarted and recreated the taskbar, so tray icons can be added again
+    ShellRestarted,
+    // Windows is shutting down or the user is logging off
+    EndingSession,
+}
+
+#[cfg(windows)]
+thread_local! {
+    // Only touched on the watcher thread, which is where the window procedure runs
+    static SINK: RefCell<Option<(Sender<SessionEvent>, egui::Context)>> = const { RefCell::new(None) };
+    // Id of the registered "TaskbarCreated" message, 0 until registered
+    static TASKBAR_CREATED: Cell<u32> = const { Cell::new(0) };
+}
+
+pub struct SessionWatcher {
+    events: Receiver<SessionEvent>,
+}
+
+impl SessionWatcher {
+    #[cfg(windows)]
+    pub fn start(ctx: &egui::Context) -> Self {
+        let (tx, rx) = channel();
+        let ctx = ctx.clone();
+
+        thread::spawn(move || {
+            SINK.with(|s| *s.borrow_mut() = Some((tx, ctx)));
+            if let Err(e) = run_message_window() {
+                log::warn!("Session notifications unavailable: {}", e);
+            }
+        });
+
+        Self { events: rx }
+    }
+
+    // The sender is dropped right away, so poll() never returns anything
+    #[cfg(not(windows))]
+    pub fn start(_ctx: &egui::Context) -> Self {
+        log::info!("Session notifications are not available on this platform");
+        let (_, rx) = channel();
+        Self { events: rx }
+    }
+
+    pub fn poll(&self) -> Vec<SessionEvent> {
+        self.events.try_iter().collect()
+    }
+}
+
+#[cfg(windows)]
+fn wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+#[cfg(windows)]
+fn run_message_window() -> Result<(), String> {
+    let class_name = wide("SilentStreamSessionWatcher");
+    unsafe {
+        let instance = GetModuleHandleW(std::ptr::null());
+        let class = WNDCLASSW {
+            style: 0,
+            lpfnWndProc: Some(window_proc),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: instance,
+            hIcon: 0,
+            hCursor: 0,
+            hbrBackground: 0,
+            lpszMenuName: std::ptr::null(),
+            lpszClassName: class_name.as_ptr(),
+        };
+        if RegisterClassW(&class) == 0 {
+            return Err("RegisterClassW failed".to_string());
+        }
+
+        // A hidden top-level window: message-only windows don't get WM_POWERBROADCAST
+        let hwnd = CreateWindowExW(
+            0,
+            class_name.as_ptr(),
+            class_name.as_ptr(),
+            WS_OVERLAPPED,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            instance,
+            std::ptr::null(),
+        );
+        if hwnd == 0 {
+            return Err("CreateWindowExW failed".to_string());
+        }
+        if WTSRegisterSessionNotification(hwnd, NOTIFY_FOR_THIS_SESSION) == 0 {
+            log::warn!("WTSRegisterSessionNotification failed; lock detection disabled");
+        }
+        // Broadcast to every top-level window when the taskbar comes back
+        let taskbar_created = wide("TaskbarCreated");
+        TASKBAR_CREATED.with(|id| id.set(RegisterWindowMessageW(taskbar_created.as_ptr())));
+
+        let mut msg: MSG = std::mem::zeroed();
+        while GetMessageW(&mut msg, 0, 0, 0) > 0 {
+            TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+unsafe extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    let event = match (msg, wparam as u32) {
+        (WM_WTSSESSION_CHANGE, WTS_SESSION_LOCK) => Some(SessionEvent::Locked),
+        (WM_WTSSESSION_CHANGE, WTS_SESSION_UNLOCK) => Some(SessionEvent::Unlocked),
+        (WM_POWERBROADCAST, PBT_APMSUSPEND) => Some(SessionEvent::Suspending),
+        // Sent on every resume, whether or not a user triggered it
+        (WM_POWERBROADCAST, PBT_APMRESUMEAUTOMATIC) => Some(SessionEvent::Resumed),
+        // Answered right away by DefWindowProcW (TRUE); the UI exits on its own time
+        (WM_QUERYENDSESSION, _) => Some(SessionEvent::EndingSession),
+        _ if msg != 0 && msg == TASKBAR_CREATED.with(Cell::get) => Some(SessionEvent::ShellRestarted),
+        _ => None,
+    };
+    if let Some(event) = event {
+        SINK.with(|s| {
+            if let Some((tx, ctx)) = s.borrow().as_ref() {
+                let _ = tx.send(event);
+                ctx.request_repaint();
+            }
+        });
+    }
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
@@ -0,0 +1,902 @@
+use silentstream_core::audio_engine::{CaptureSource, InputChannel, OverflowPolicy, SessionStats, BLOCK_FRAMES, LATENCY_CAPS_MS};
+use crate::autostart::AutostartBackend;
+use crate::automation::{format_time_of_day, parse_time_of_day, AppWatchConfig, IdlePauseConfig, ScheduleConfig, TriggerAction};
+use crate::default_device::DefaultDeviceConfig;
+use crate::device_filter::VirtualInputFilter;
+use crate::hotkey::DEFAULT_HOTKEY;
+use silentstream_core::dsp::{
+    BoostConfig, ClickConfig, DeEsserConfig, GateConfig, MeterMode, MusicConfig, PlosiveConfig, SuppressionMode,
+    BOOST_RANGE, DE_ESSER_FREQUENCY_RANGE, DE_ESSER_THRESHOLD_RANGE, GATE_ATTACK_RANGE, GATE_ENERGY_FLOOR_RANGE, GATE_HOLD_RANGE, GATE_PRE_ROLL_RANGE, GATE_RELEASE_RANGE,
+    GATE_STEEPNESS_RANGE,
+};
+use silentstream_core::noise_print::NoisePrintConfig;
+use silentstream_core::overload::OverloadPolicy;
+use crate::obs::ObsConfig;
+use silentstream_core::pipeline::ChainConfig;
+use silentstream_core::plugin_host::PluginConfig;
+use crate::notifier::NotificationPolicy;
+use crate::osd::OsdCorner;
+use silentstream_core::resample::ResamplerQuality;
+use crate::theme::{AnimationMode, Appearance};
+use log::LevelFilter;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+#[derive(Clone)]
+pub struct Settings {
+    pub input_device: Option<String>,
+    pub output_device: Option<String>,
+    pub vad_threshold: f32,
+    pub suppression_mode: SuppressionMode,
+    pub gate: GateConfig,
+    pub start_with_windows: bool,
+    pub autostart_backend: AutostartBackend,
+    // Start processing on launch instead of waiting for the Start button
+    pub start_processing: bool,
+    // Come back stopped or bypassed if that's how the last session was left, instead of
+    // following start_processing
+    pub restore_state: bool,
+    // Runtime state as of its last change, read back when restore_state is on
+    pub last_stopped: bool,
+    pub last_bypassed: bool,
+    // Seconds to wait before starting when launched at login, so slow drivers can catch up
+    pub startup_delay: u32,
+    // Global shortcut that shows or hides the window, e.g. "Ctrl+Alt+S"; empty = none
+    pub window_hotkey: String,
+    pub obs: ObsConfig,
+    pub app_watch: AppWatchConfig,
+    pub schedule: ScheduleConfig,
+    pub idle_pause: IdlePauseConfig,
+    pub default_device: DefaultDeviceConfig,
+    // All-time totals; per-session numbers are never written
+    pub lifetime_stats: SessionStats,
+    pub metrics_enabled: bool,
+    // JSON mic state for overlays; an empty path means status.json in the settings folder
+    pub status_file_enabled: bool,
+    pub status_file_path: String,
+    pub log_level: LevelFilter,
+    pub update_check_enabled: bool,
+    // Release the devices while the session is locked or the PC sleeps
+    pub pause_when_locked: bool,
+    pub animations: AnimationMode,
+    pub appearance: Appearance,
+    // Toast after a mute or bypass change made while the window isn't in front
+    pub osd_enabled: bool,
+    pub osd_corner: OsdCorner,
+    // When toasts and sound cues are held back
+    pub notifications: NotificationPolicy,
+    // Which level drives the volume-reactive orb
+    pub meter_mode: MeterMode,
+    // Mic level on the orb while stopped, from a capture-only stream
+    pub input_monitor: bool,
+    // Unix time of the last completed update check, 0 if never
+    pub last_update_check: i64,
+    pub aec_enabled: bool,
+    // Output device the echo is taken from; empty = default output
+    pub aec_reference: String,
+    pub de_esser: DeEsserConfig,
+    pub plosive: PlosiveConfig,
+    pub click: ClickConfig,
+    pub boost: BoostConfig,
+    // Strength of the subtraction; the prints themselves live in noise_prints.txt
+    pub noise_print: NoisePrintConfig,
+    pub music: MusicConfig,
+    // Order and on/off state of the processing stages
+    pub dsp_chain: ChainConfig,
+    pub plugin: PluginConfig,
+    pub resampler_quality: ResamplerQuality,
+    // RNNoise frames per processing wakeup, one of BLOCK_FRAMES
+    pub block_frames: usize,
+    pub overflow_policy: OverflowPolicy,
+    // One of LATENCY_CAPS_MS; 0 = no cap
+    pub latency_cap_ms: u32,
+    // What gives way when processing can't keep up with real time
+    pub overload_policy: OverloadPolicy,
+    // Input channel per input device name; devices not listed use the first channel
+    pub input_channels: BTreeMap<String, InputChannel>,
+    // Tuning remembered per input device name; other devices use the global values above
+    pub device_profiles: BTreeMap<String, DeviceTuning>,
+    // Two pinned input/output pairs for the quick swap
+    pub favorites: [Option<DevicePair>; 2],
+    // Go back to the saved devices as soon as they reappear instead of asking first
+    pub return_to_saved_devices: bool,
+    // The first-run note about the OS microphone permission prompt was dismissed
+    pub permission_notice_seen: bool,
+    // Virtual capture devices hidden from the input list
+    pub virtual_input_filter: VirtualInputFilter,
+    // Ask before exiting while processing is on
+    pub confirm_exit: bool,
+    // Tones on the monitoring device when the mic goes live or quiet
+    pub earcons_enabled: bool,
+    pub earcon_volume: f32,
+    pub monitor_enabled: bool,
+    // Empty = default output
+    pub monitor_device: String,
+    pub monitor_gain: f32,
+    pub mix_enabled: bool,
+    pub mix_source: CaptureSource,
+    pub mix_gain: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            input_device: None,
+            output_device: None,
+            vad_threshold: 0.1,
+            suppression_mode: SuppressionMode::Normal,
+            gate: GateConfig::default(),
+            start_with_windows: false,
+            autostart_backend: AutostartBackend::Registry,
+            start_processing: true,
+            restore_state: false,
+            last_stopped: false,
+            last_bypassed: false,
+            startup_delay: 0,
+            window_hotkey: DEFAULT_HOTKEY.to_string(),
+            obs: ObsConfig::default(),
+            app_watch: AppWatchConfig::default(),
+            schedule: ScheduleConfig::default(),
+            idle_pause: IdlePauseConfig::default(),
+            default_device: DefaultDeviceConfig::default(),
+            lifetime_stats: SessionStats::default(),
+            metrics_enabled: false,
+            status_file_enabled: false,
+            status_file_path: String::new(),
+            log_level: LevelFilter::Info,
+            update_check_enabled: false,
+            pause_when_locked: true,
+            animations: AnimationMode::system_default(),
+            appearance: Appearance::system_default(),
+            osd_enabled: true,
+            osd_corner: OsdCorner::BottomRight,
+            notifications: NotificationPolicy::default(),
+            meter_mode: MeterMode::Rms,
+            input_monitor: true,
+            last_update_check: 0,
+            aec_enabled: false,
+            aec_reference: String::new(),
+            de_esser: DeEsserConfig::default(),
+            plosive: PlosiveConfig::default(),
+            click: ClickConfig::default(),
+            boost: BoostConfig::default(),
+            noise_print: NoisePrintConfig::default(),
+            music: MusicConfig::default(),
+            dsp_chain: ChainConfig::default(),
+            plugin: PluginConfig::default(),
+            resampler_quality: ResamplerQuality::Balanced,
+            block_frames: 1,
+            overflow_policy: OverflowPolicy::DropOldest,
+            latency_cap_ms: 150,
+            overload_policy: OverloadPolicy::PreferQuality,
+            input_channels: BTreeMap::new(),
+            device_profiles: BTreeMap::new(),
+            favorites: [None, None],
+            return_to_saved_devices: false,
+            permission_notice_seen: false,
+            virtual_input_filter: VirtualInputFilter::default(),
+            confirm_exit: true,
+            earcons_enabled: true,
+            earcon_volume: 0.5,
+            monitor_enabled: false,
+            monitor_device: String::new(),
+            monitor_gain: 1.0,
+            mix_enabled: false,
+            mix_source: CaptureSource::default(),
+            mix_gain: 0.5,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct DevicePair {
+    pub input: String,
+    pub output: String,
+}
+
+// The part of the settings that depends on the microphone
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DeviceTuning {
+    pub vad_threshold: f32,
+    pub gate: GateConfig,
+    pub de_esser: DeEsserConfig,
+    pub plosive: PlosiveConfig,
+    pub click: ClickConfig,
+    pub boost: BoostConfig,
+}
+
+impl DeviceTuning {
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self {
+            vad_threshold: settings.vad_threshold,
+            gate: settings.gate,
+            de_esser: settings.de_esser,
+            plosive: settings.plosive,
+            click: settings.click,
+            boost: settings.boost,
+        }
+    }
+
+    pub fn apply_to(&self, settings: &mut Settings) {
+        settings.vad_threshold = self.vad_threshold;
+        settings.gate = self.gate;
+        settings.de_esser = self.de_esser;
+        settings.plosive = self.plosive;
+        settings.click = self.click;
+        settings.boost = self.boost;
+    }
+
+    // Same keys as the global values
+    fn values(&self) -> [(&'static str, String); 17] {
+        [
+            ("vad_threshold", self.vad_threshold.to_string()),
+            ("gate_mode", self.gate.mode.as_str().to_string()),
+            ("gate_steepness", self.gate.steepness.to_string()),
+            ("gate_hold_ms", self.gate.hold_ms.to_string()),
+            ("gate_attack_ms", self.gate.attack_ms.to_string()),
+            ("gate_release_ms", self.gate.release_ms.to_string()),
+            ("gate_pre_roll_ms", self.gate.pre_roll_ms.to_string()),
+            ("gate_energy_floor_db", self.gate.energy_floor_db.to_string()),
+            ("de_esser_enabled", self.de_esser.enabled.to_string()),
+            ("de_esser_frequency", self.de_esser.frequency.to_string()),
+            ("de_esser_threshold", self.de_esser.threshold_db.to_string()),
+            ("de_esser_amount", self.de_esser.amount.to_string()),
+            ("plosive_enabled", self.plosive.enabled.to_string()),
+            ("plosive_strength", self.plosive.strength.to_string()),
+            ("click_enabled", self.click.enabled.to_string()),
+            ("click_sensitivity", self.click.sensitivity.to_string()),
+            ("boost_db", self.boost.gain_db.to_string()),
+        ]
+    }
+}
+
+impl Default for DeviceTuning {
+    fn default() -> Self {
+        Self::from_settings(&Settings::default())
+    }
+}
+
+pub const CONFIG_DIR_ENV: &str = "SILENTSTREAM_CONFIG_DIR";
+pub const CONFIG_DIR_FLAG: &str = "--config-dir";
+
+// Directory holding settings and everything else the app writes (logs, crashes, metrics):
+// --config-dir, then SILENTSTREAM_CONFIG_DIR, then %APPDATA%\SilentStream
+pub fn get_config_dir() -> Option<PathBuf> {
+    config_dir_override().or_else(platform_config_dir)
+}
+
+#[cfg(windows)]
+fn platform_config_dir() -> Option<PathBuf> {
+    std::env::var_os("APPDATA").map(|app_data| PathBuf::from(app_data).join("SilentStream"))
+}
+
+#[cfg(target_os = "macos")]
+fn platform_config_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support/SilentStream"))
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn platform_config_dir() -> Option<PathBuf> {
+    xdg_config_home().map(|dir| dir.join("silentstream"))
+}
+
+// $XDG_CONFIG_HOME, or ~/.config when it's unset or not absolute (per the XDG spec)
+#[cfg(not(any(windows, target_os = "macos")))]
+pub fn xdg_config_home() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+}
+
+// Directory given on the command line or in the environment, if any
+pub fn config_dir_override() -> Option<PathBuf> {
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == CONFIG_DIR_FLAG {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(dir) = arg.to_str().and_then(|a| a.strip_prefix("--config-dir=")) {
+            return Some(PathBuf::from(dir));
+        }
+    }
+    std::env::var_os(CONFIG_DIR_ENV).filter(|dir| !dir.is_empty()).map(PathBuf::from)
+}
+
+// Creates the directory and checks that files can be written in it
+pub fn prepare_config_dir(dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    let probe = dir.join(".write-test");
+    fs::write(&probe, b"").map_err(|e| format!("Cannot write to {}: {}", dir.display(), e))?;
+    let _ = fs::remove_file(probe);
+    Ok(())
+}
+
+// Get config path
+pub fn get_config_path() -> Option<PathBuf> {
+    get_config_dir().map(|dir| dir.join("settings.txt"))
+}
+
+// Modification time of the settings file as this process last read or wrote it,
+// so the app's own saves aren't mistaken for external edits
+static KNOWN_MODIFIED: Mutex<Option<SystemTime>> = Mutex::new(None);
+
+fn settings_modified() -> Option<SystemTime> {
+    fs::metadata(get_config_path()?).ok()?.modified().ok()
+}
+
+fn remember_modified() {
+    if let Ok(mut known) = KNOWN_MODIFIED.lock() {
+        *known = settings_modified();
+    }
+}
+
+// True when something other than this process has rewritten the settings file
+pub fn changed_externally() -> bool {
+    let modified = settings_modified();
+    modified.is_some() && KNOWN_MODIFIED.lock().map(|known| *known != modified).unwrap_or(false)
+}
+
+// The first lines keep the original positional layout so older versions can still
+// read the file; everything added later is stored as `key=value` lines after them.
+const POSITIONAL_LINES: usize = 5;
+// Top of the threshold slider; anything outside 0..=this breaks its math
+pub const VAD_THRESHOLD_MAX: f32 = 0.5;
+// Longest wait before processing starts when launched at login
+pub const STARTUP_DELAY_MAX: u32 = 60;
+
+// "NaN" and "inf" parse as f32, and clamp() passes NaN straight through
+fn parse_finite(value: &str) -> Option<f32> {
+    value.trim().parse::<f32>().ok().filter(|v| v.is_finite())
+}
+
+pub fn load_settings() -> Settings {
+    let content = get_config_path().filter(|path| path.exists()).and_then(|path| fs::read_to_string(path).ok());
+    let settings = content.as_deref().map_or_else(Settings::default, parse_settings);
+    remember_modified();
+    settings
+}
+
+// What settings_to_string wrote; lines that can't be read keep their defaults
+fn parse_settings(content: &str) -> Settings {
+    let mut settings = Settings::default();
+    let lines: Vec<&str> = content.lines().collect();
+    if lines.len() >= 4 {
+        settings.input_device = if lines[0].is_empty() { None } else { Some(lines[0].to_string()) };
+        settings.output_device = if lines[1].is_empty() { None } else { Some(lines[1].to_string()) };
+        settings.vad_threshold = parse_finite(lines[2]).map_or(0.1, |t| t.clamp(0.0, VAD_THRESHOLD_MAX));
+        settings.suppression_mode = if lines[3] == "true" { SuppressionMode::Normal } else { SuppressionMode::Off };
+    }
+    if lines.len() >= 5 {
+        settings.start_with_windows = lines[4] == "true";
+    }
+
+    // A `device_profile=<device name>` line starts a profile; the
+    // `device_profile.<key>=<value>` lines after it belong to that device
+    let mut profile: Option<String> = None;
+    for line in lines.iter().skip(POSITIONAL_LINES) {
+        if let Some((key, value)) = line.split_once('=') {
+            let key = key.trim();
+            if key == "device_profile" {
+                settings.device_profiles.insert(value.to_string(), DeviceTuning::default());
+                profile = Some(value.to_string());
+            } else if let Some(key) = key.strip_prefix("device_profile.") {
+                if let Some(tuning) = profile.as_ref().and_then(|d| settings.device_profiles.get_mut(d)) {
+                    let mut scratch = Settings::default();
+                    tuning.apply_to(&mut scratch);
+                    apply_value(&mut scratch, key, value);
+                    *tuning = DeviceTuning::from_settings(&scratch);
+                }
+            } else {
+                apply_value(&mut settings, key, value);
+            }
+        }
+    }
+    settings
+}
+
+fn apply_value(settings: &mut Settings, key: &str, value: &str) {
+    match key {
+        // Only written for device profiles; the global threshold is positional
+        "vad_threshold" => {
+            if let Some(t) = parse_finite(value) {
+                settings.vad_threshold = t.clamp(0.0, VAD_THRESHOLD_MAX);
+            }
+        }
+        // Refines the positional on/off line; older versions only read that line
+        "suppression_mode" => settings.suppression_mode = value.parse().unwrap_or(settings.suppression_mode),
+        "gate_mode" => settings.gate.mode = value.parse().unwrap_or(settings.gate.mode),
+        "gate_steepness" => {
+            if let Some(k) = parse_finite(value) {
+                settings.gate.steepness = k.clamp(*GATE_STEEPNESS_RANGE.start(), *GATE_STEEPNESS_RANGE.end());
+            }
+        }
+        "gate_hold_ms" => {
+            if let Some(ms) = parse_finite(value) {
+                settings.gate.hold_ms = ms.clamp(*GATE_HOLD_RANGE.start(), *GATE_HOLD_RANGE.end());
+            }
+        }
+        "gate_attack_ms" => {
+            if let Some(ms) = parse_finite(value) {
+                settings.gate.attack_ms = ms.clamp(*GATE_ATTACK_RANGE.start(), *GATE_ATTACK_RANGE.end());
+            }
+        }
+        "gate_release_ms" => {
+            if let Some(ms) = parse_finite(value) {
+                settings.gate.release_ms = ms.clamp(*GATE_RELEASE_RANGE.start(), *GATE_RELEASE_RANGE.end());
+            }
+        }
+        "gate_pre_roll_ms" => {
+            if let Some(ms) = parse_finite(value) {
+                settings.gate.pre_roll_ms = ms.clamp(*GATE_PRE_ROLL_RANGE.start(), *GATE_PRE_ROLL_RANGE.end());
+            }
+        }
+        "gate_energy_floor_db" => {
+            if let Some(db) = parse_finite(value) {
+                settings.gate.energy_floor_db = db.clamp(*GATE_ENERGY_FLOOR_RANGE.start(), *GATE_ENERGY_FLOOR_RANGE.end());
+            }
+        }
+        "window_hotkey" => settings.window_hotkey = value.to_string(),
+        "obs_enabled" => settings.obs.enabled = value == "true",
+        "obs_host" => settings.obs.host = value.to_string(),
+        "obs_port" => settings.obs.port = value.parse().unwrap_or(settings.obs.port),
+        "obs_password" => settings.obs.password = value.to_string(),
+        "obs_on_active" => settings.obs.on_active = TriggerAction::from_str(value).unwrap_or(settings.obs.on_active),
+        "obs_on_inactive" => settings.obs.on_inactive = TriggerAction::from_str(value).unwrap_or(settings.obs.on_inactive),
+        "app_watch_enabled" => settings.app_watch.enabled = value == "true",
+        "app_watch_apps" => {
+            settings.app_watch.apps = value.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect()
+        }
+        "app_watch_on_exit" => {
+            settings.app_watch.on_exit = TriggerAction::from_str(value).unwrap_or(settings.app_watch.on_exit)
+        }
+        "schedule_enabled" => settings.schedule.enabled = value == "true",
+        "schedule_start" => settings.schedule.start = parse_time_of_day(value).unwrap_or(settings.schedule.start),
+        "schedule_end" => settings.schedule.end = parse_time_of_day(value).unwrap_or(settings.schedule.end),
+        "schedule_days" => settings.schedule.days = ScheduleConfig::days_from_str(value).unwrap_or(settings.schedule.days),
+        "idle_pause_enabled" => settings.idle_pause.enabled = value == "true",
+        "idle_pause_minutes" => {
+            if let Ok(minutes) = value.parse::<u32>() {
+                settings.idle_pause.minutes = minutes.clamp(1, 120);
+            }
+        }
+        "idle_pause_release" => settings.idle_pause.release_devices = value == "true",
+        "default_device_enabled" => settings.default_device.enabled = value == "true",
+        "default_device_name" => settings.default_device.device = value.to_string(),
+        "default_device_auto_fix" => settings.default_device.auto_fix = value == "true",
+        "stats_frames_forwarded" => settings.lifetime_stats.frames_forwarded = value.parse().unwrap_or(0),
+        "stats_frames_gated" => settings.lifetime_stats.frames_gated = value.parse().unwrap_or(0),
+        "stats_frames_muted" => settings.lifetime_stats.frames_muted = value.parse().unwrap_or(0),
+        "stats_clip_events" => settings.lifetime_stats.clip_events = value.parse().unwrap_or(0),
+        "stats_vad_sum" => {
+            settings.lifetime_stats.vad_sum = value.parse().ok().filter(|v: &f64| v.is_finite() && *v >= 0.0).unwrap_or(0.0)
+        }
+        "stats_vad_frames" => settings.lifetime_stats.vad_frames = value.parse().unwrap_or(0),
+        "metrics_enabled" => settings.metrics_enabled = value == "true",
+        "status_file_enabled" => settings.status_file_enabled = value == "true",
+        "status_file_path" => settings.status_file_path = value.to_string(),
+        "log_level" => settings.log_level = value.parse().unwrap_or(settings.log_level),
+        "update_check_enabled" => settings.update_check_enabled = value == "true",
+        "animations" => settings.animations = AnimationMode::from_str(value).unwrap_or(settings.animations),
+        "appearance" => settings.appearance = Appearance::from_str(value).unwrap_or(settings.appearance),
+        "meter_mode" => settings.meter_mode = value.parse().unwrap_or(settings.meter_mode),
+        "input_monitor_when_stopped" => settings.input_monitor = value == "true",
+        "pause_when_locked" => settings.pause_when_locked = value == "true",
+        "return_to_saved_devices" => settings.return_to_saved_devices = value == "true",
+        "permission_notice_seen" => settings.permission_notice_seen = value == "true",
+        "confirm_exit" => settings.confirm_exit = value == "true",
+        "earcons_enabled" => settings.earcons_enabled = value == "true",
+        "earcon_volume" => {
+            if let Some(v) = parse_finite(value) {
+                settings.earcon_volume = v.clamp(0.0, 1.0);
+            }
+        }
+        "hide_virtual_inputs" => settings.virtual_input_filter.enabled = value == "true",
+        "virtual_input_patterns" => {
+            settings.virtual_input_filter.patterns =
+                value.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect()
+        }
+        "start_processing_on_launch" => settings.start_processing = value == "true",
+        "restore_state_on_launch" => settings.restore_state = value == "true",
+        "last_stopped_by_user" => settings.last_stopped = value == "true",
+        "last_bypassed" => settings.last_bypassed = value == "true",
+        "autostart_backend" => {
+            settings.autostart_backend = AutostartBackend::from_str(value).unwrap_or(settings.autostart_backend)
+        }
+        "startup_delay" => {
+            if let Ok(secs) = value.parse::<u32>() {
+                settings.startup_delay = secs.min(STARTUP_DELAY_MAX);
+            }
+        }
+        "last_update_check" => settings.last_update_check = value.parse().unwrap_or(0),
+        "aec_enabled" => settings.aec_enabled = value == "true",
+        "aec_reference" => settings.aec_reference = value.to_string(),
+        "de_esser_enabled" => settings.de_esser.enabled = value == "true",
+        "de_esser_frequency" => {
+            if let Some(f) = parse_finite(value) {
+                settings.de_esser.frequency = f.clamp(*DE_ESSER_FREQUENCY_RANGE.start(), *DE_ESSER_FREQUENCY_RANGE.end());
+            }
+        }
+        "de_esser_threshold" => {
+            if let Some(t) = parse_finite(value) {
+                settings.de_esser.threshold_db = t.clamp(*DE_ESSER_THRESHOLD_RANGE.start(), *DE_ESSER_THRESHOLD_RANGE.end());
+            }
+        }
+        "de_esser_amount" => {
+            if let Some(a) = parse_finite(value) {
+                settings.de_esser.amount = a.clamp(0.0, 1.0);
+            }
+        }
+        "plosive_enabled" => settings.plosive.enabled = value == "true",
+        "boost_db" => {
+            if let Some(db) = parse_finite(value) {
+                settings.boost.gain_db = db.clamp(*BOOST_RANGE.start(), *BOOST_RANGE.end());
+            }
+        }
+        "noise_print_amount" => {
+            if let Some(a) = parse_finite(value) {
+                settings.noise_print.amount = a.clamp(0.0, 1.0);
+            }
+        }
+        "plosive_strength" => {
+            if let Some(s) = parse_finite(value) {
+                settings.plosive.strength = s.clamp(0.0, 1.0);
+            }
+        }
+        "resampler_quality" => {
+            settings.resampler_quality = value.parse().unwrap_or(settings.resampler_quality)
+        }
+        "block_frames" => {
+            if let Some(n) = value.parse().ok().filter(|n| BLOCK_FRAMES.contains(n)) {
+                settings.block_frames = n;
+            }
+        }
+        "overflow_policy" => {
+            settings.overflow_policy = value.parse().unwrap_or(settings.overflow_policy)
+        }
+        "latency_cap_ms" => {
+            if let Some(ms) = value.parse().ok().filter(|ms| LATENCY_CAPS_MS.contains(ms)) {
+                settings.latency_cap_ms = ms;
+            }
+        }
+        "overload_policy" => {
+            settings.overload_policy = value.parse().unwrap_or(settings.overload_policy)
+        }
+        "monitor_enabled" => settings.monitor_enabled = value == "true",
+        "monitor_device" => settings.monitor_device = value.to_string(),
+        "monitor_gain" => {
+            if let Some(g) = parse_finite(value) {
+                settings.monitor_gain = g.clamp(0.0, 1.0);
+            }
+        }
+        "mix_enabled" => settings.mix_enabled = value == "true",
+        "mix_device" => settings.mix_source.device = value.to_string(),
+        "mix_loopback" => settings.mix_source.loopback = value == "true",
+        "mix_gain" => {
+            if let Some(g) = parse_finite(value) {
+                settings.mix_gain = g.clamp(0.0, 1.0);
+            }
+        }
+        // One line per device: `input_channel=<channel>:<device name>`
+        "input_channel" => {
+            if let Some((channel, device)) = value.split_once(':') {
+                if let Ok(channel) = channel.parse::<InputChannel>() {
+                    settings.input_channels.insert(device.to_string(), channel);
+                }
+            }
+        }
+        "click_enabled" => settings.click.enabled = value == "true",
+        "click_sensitivity" => {
+            if let Some(s) = parse_finite(value) {
+                settings.click.sensitivity = s.clamp(0.0, 1.0);
+            }
+        }
+        "favorite_1_input" => favorite(settings, 0).input = value.to_string(),
+        "favorite_1_output" => favorite(settings, 0).output = value.to_string(),
+        "favorite_2_input" => favorite(settings, 1).input = value.to_string(),
+        "favorite_2_output" => favorite(settings, 1).output = value.to_string(),
+        "music_enabled" => settings.music.enabled = value == "true",
+        "osd_enabled" => settings.osd_enabled = value == "true",
+        "osd_corner" => settings.osd_corner = OsdCorner::from_str(value).unwrap_or(settings.osd_corner),
+        "quiet_hours_enabled" => settings.notifications.quiet_hours = value == "true",
+        "quiet_hours_start" => {
+            settings.notifications.quiet_start = parse_time_of_day(value).unwrap_or(settings.notifications.quiet_start)
+        }
+        "quiet_hours_end" => settings.notifications.quiet_end = parse_time_of_day(value).unwrap_or(settings.notifications.quiet_end),
+        "quiet_when_fullscreen" => settings.notifications.quiet_when_fullscreen = value == "true",
+        "critical_always_notify" => settings.notifications.critical_always = value == "true",
+        "music_sensitivity" => {
+            if let Some(s) = parse_finite(value) {
+                settings.music.sensitivity = s.clamp(0.0, 1.0);
+            }
+        }
+        "dsp_chain" => settings.dsp_chain = ChainConfig::from_setting(value),
+        "plugin_enabled" => settings.plugin.enabled = value == "true",
+        "plugin_path" => settings.plugin.path = value.to_string(),
+        "plugin_params" => settings.plugin.params = PluginConfig::parse_params(value),
+        _ => {}
+    }
+}
+
+fn favorite(settings: &mut Settings, index: usize) -> &mut DevicePair {
+    settings.favorites[index].get_or_insert_with(DevicePair::default)
+}
+
+// The exact file contents save_settings writes
+pub fn settings_to_string(settings: &Settings) -> String {
+    let mut content = format!(
+        "{}\n{}\n{}\n{}\n{}",
+        settings.input_device.as_deref().unwrap_or(""),
+        settings.output_device.as_deref().unwrap_or(""),
+        settings.vad_threshold,
+        settings.suppression_mode != SuppressionMode::Off,
+        settings.start_with_windows
+    );
+
+    let extra = [
+        ("suppression_mode", settings.suppression_mode.as_str().to_string()),
+        ("gate_mode", settings.gate.mode.as_str().to_string()),
+        ("gate_steepness", settings.gate.steepness.to_string()),
+        ("gate_hold_ms", settings.gate.hold_ms.to_string()),
+        ("gate_attack_ms", settings.gate.attack_ms.to_string()),
+        ("gate_release_ms", settings.gate.release_ms.to_string()),
+        ("gate_pre_roll_ms", settings.gate.pre_roll_ms.to_string()),
+        ("gate_energy_floor_db", settings.gate.energy_floor_db.to_string()),
+        ("window_hotkey", settings.window_hotkey.clone()),
+        ("obs_enabled", settings.obs.enabled.to_string()),
+        ("obs_host", settings.obs.host.clone()),
+        ("obs_port", settings.obs.port.to_string()),
+        ("obs_password", settings.obs.password.clone()),
+        ("obs_on_active", settings.obs.on_active.as_str().to_string()),
+        ("obs_on_inactive", settings.obs.on_inactive.as_str().to_string()),
+        ("app_watch_enabled", settings.app_watch.enabled.to_string()),
+        ("app_watch_apps", settings.app_watch.apps.join(",")),
+        ("app_watch_on_exit", settings.app_watch.on_exit.as_str().to_string()),
+        ("schedule_enabled", settings.schedule.enabled.to_string()),
+        ("schedule_start", format_time_of_day(settings.schedule.start)),
+        ("schedule_end", format_time_of_day(settings.schedule.end)),
+        ("schedule_days", settings.schedule.days_to_string()),
+        ("idle_pause_enabled", settings.idle_pause.enabled.to_string()),
+        ("idle_pause_minutes", settings.idle_pause.minutes.to_string()),
+        ("idle_pause_release", settings.idle_pause.release_devices.to_string()),
+        ("default_device_enabled", settings.default_device.enabled.to_string()),
+        ("default_device_name", settings.default_device.device.clone()),
+        ("default_device_auto_fix", settings.default_device.auto_fix.to_string()),
+        ("stats_frames_forwarded", settings.lifetime_stats.frames_forwarded.to_string()),
+        ("stats_frames_gated", settings.lifetime_stats.frames_gated.to_string()),
+        ("stats_frames_muted", settings.lifetime_stats.frames_muted.to_string()),
+        ("stats_clip_events", settings.lifetime_stats.clip_events.to_string()),
+        ("stats_vad_sum", settings.lifetime_stats.vad_sum.to_string()),
+        ("stats_vad_frames", settings.lifetime_stats.vad_frames.to_string()),
+        ("metrics_enabled", settings.metrics_enabled.to_string()),
+        ("status_file_enabled", settings.status_file_enabled.to_string()),
+        ("status_file_path", settings.status_file_path.clone()),
+        ("log_level", settings.log_level.to_string()),
+        ("update_check_enabled", settings.update_check_enabled.to_string()),
+        ("animations", settings.animations.as_str().to_string()),
+        ("appearance", settings.appearance.as_str().to_string()),
+        ("osd_enabled", settings.osd_enabled.to_string()),
+        ("osd_corner", settings.osd_corner.as_str().to_string()),
+        ("quiet_hours_enabled", settings.notifications.quiet_hours.to_string()),
+        ("quiet_hours_start", format_time_of_day(settings.notifications.quiet_start)),
+        ("quiet_hours_end", format_time_of_day(settings.notifications.quiet_end)),
+        ("quiet_when_fullscreen", settings.notifications.quiet_when_fullscreen.to_string()),
+        ("critical_always_notify", settings.notifications.critical_always.to_string()),
+        ("meter_mode", settings.meter_mode.as_str().to_string()),
+        ("input_monitor_when_stopped", settings.input_monitor.to_string()),
+        ("pause_when_locked", settings.pause_when_locked.to_string()),
+        ("return_to_saved_devices", settings.return_to_saved_devices.to_string()),
+        ("permission_notice_seen", settings.permission_notice_seen.to_string()),
+        ("confirm_exit", settings.confirm_exit.to_string()),
+        ("earcons_enabled", settings.earcons_enabled.to_string()),
+        ("earcon_volume", settings.earcon_volume.to_string()),
+        ("hide_virtual_inputs", settings.virtual_input_filter.enabled.to_string()),
+        ("virtual_input_patterns", settings.virtual_input_filter.patterns.join(",")),
+        ("start_processing_on_launch", settings.start_processing.to_string()),
+        ("restore_state_on_launch", settings.restore_state.to_string()),
+        ("last_stopped_by_user", settings.last_stopped.to_string()),
+        ("last_bypassed", settings.last_bypassed.to_string()),
+        ("autostart_backend", settings.autostart_backend.as_str().to_string()),
+        ("startup_delay", settings.startup_delay.to_string()),
+        ("last_update_check", settings.last_update_check.to_string()),
+        ("aec_enabled", settings.aec_enabled.to_string()),
+        ("aec_reference", settings.aec_reference.clone()),
+        ("de_esser_enabled", settings.de_esser.enabled.to_string()),
+        ("de_esser_frequency", settings.de_esser.frequency.to_string()),
+        ("de_esser_threshold", settings.de_esser.threshold_db.to_string()),
+        ("de_esser_amount", settings.de_esser.amount.to_string()),
+        ("plosive_enabled", settings.plosive.enabled.to_string()),
+        ("plosive_strength", settings.plosive.strength.to_string()),
+        ("boost_db", settings.boost.gain_db.to_string()),
+        ("noise_print_amount", settings.noise_print.amount.to_string()),
+        ("click_enabled", settings.click.enabled.to_string()),
+        ("click_sensitivity", settings.click.sensitivity.to_string()),
+        ("music_enabled", settings.music.enabled.to_string()),
+        ("music_sensitivity", settings.music.sensitivity.to_string()),
+        ("dsp_chain", settings.dsp_chain.as_setting()),
+        ("plugin_enabled", settings.plugin.enabled.to_string()),
+        ("plugin_path", settings.plugin.path.clone()),
+        ("plugin_params", settings.plugin.params_setting()),
+        ("resampler_quality", settings.resampler_quality.as_str().to_string()),
+        ("block_frames", settings.block_frames.to_string()),
+        ("overflow_policy", settings.overflow_policy.as_str().to_string()),
+        ("latency_cap_ms", settings.latency_cap_ms.to_string()),
+        ("overload_policy", settings.overload_policy.as_str().to_string()),
+        ("monitor_enabled", settings.monitor_enabled.to_string()),
+        ("monitor_device", settings.monitor_device.clone()),
+        ("monitor_gain", settings.monitor_gain.to_string()),
+        ("mix_enabled", settings.mix_enabled.to_string()),
+        ("mix_device", settings.mix_source.device.clone()),
+        ("mix_loopback", settings.mix_source.loopback.to_string()),
+        ("mix_gain", settings.mix_gain.to_string()),
+    ];
+    for (key, value) in extra.iter() {
+        content.push_str(&format!("\n{}={}", key, value));
+    }
+    for (device, channel) in settings.input_channels.iter() {
+        content.push_str(&format!("\ninput_channel={}:{}", channel.as_str(), device));
+    }
+    for (i, pair) in settings.favorites.iter().enumerate() {
+        if let Some(pair) = pair {
+            content.push_str(&format!("\nfavorite_{}_input={}", i + 1, pair.input));
+            content.push_str(&format!("\nfavorite_{}_output={}", i + 1, pair.output));
+        }
+    }
+    for (device, tuning) in settings.device_profiles.iter() {
+        content.push_str(&format!("\ndevice_profile={}", device));
+        for (key, value) in tuning.values().iter() {
+            content.push_str(&format!("\ndevice_profile.{}={}", key, value));
+        }
+    }
+    content
+}
+
+pub fn save_settings(settings: &Settings) {
+    if let Some(path) = get_config_path() {
+        if let Some(parent) = path.parent() {
+            if let Err(e) = fs::create_dir_all(parent) {
+                log::warn!("Could not create config directory {}: {}", parent.display(), e);
+            }
+        }
+        let content = settings_to_string(settings);
+        if let Err(e) = fs::write(&path, content) {
+            log::error!("Failed to write settings to {}: {}", path.display(), e);
+        }
+        remember_modified();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    // Every key apply_value reads, so generated lines hit real branches
+    fn known_keys() -> Vec<String> {
+        let defaults = settings_to_string(&Settings::default());
+        let written = defaults.lines().skip(POSITIONAL_LINES).filter_map(|line| line.split_once('=').map(|(key, _)| key.to_string()));
+        let extra = ["vad_threshold", "input_channel", "device_profile", "device_profile.vad_threshold", "favorite_1_input"];
+        written.chain(extra.map(String::from)).collect()
+    }
+
+    fn nasty_value() -> impl Strategy<Value = String> {
+        prop_oneof![
+            Just("NaN".to_string()),
+            Just("inf".to_string()),
+            Just("-inf".to_string()),
+            Just("1e39".to_string()),
+            Just("-1e39".to_string()),
+            Just("99999999999999999999999".to_string()),
+            Just("-0".to_string()),
+            Just(String::new()),
+            any::<f64>().prop_map(|v| v.to_string()),
+            any::<i64>().prop_map(|v| v.to_string()),
+            "[ -~]{0,24}",
+        ]
+    }
+
+    fn in_range(value: f32, range: std::ops::RangeInclusive<f32>) -> bool {
+        value.is_finite() && range.contains(&value)
+    }
+
+    fn assert_valid(settings: &Settings) {
+        let tunings = std::iter::once(DeviceTuning::from_settings(settings)).chain(settings.device_profiles.values().copied());
+        for tuning in tunings {
+            assert!(in_range(tuning.vad_threshold, 0.0..=VAD_THRESHOLD_MAX), "threshold {}", tuning.vad_threshold);
+            assert!(in_range(tuning.gate.steepness, GATE_STEEPNESS_RANGE));
+            assert!(in_range(tuning.gate.hold_ms, GATE_HOLD_RANGE));
+            assert!(in_range(tuning.gate.attack_ms, GATE_ATTACK_RANGE));
+            assert!(in_range(tuning.gate.release_ms, GATE_RELEASE_RANGE));
+            assert!(in_range(tuning.gate.pre_roll_ms, GATE_PRE_ROLL_RANGE));
+            assert!(in_range(tuning.gate.energy_floor_db, GATE_ENERGY_FLOOR_RANGE));
+            assert!(in_range(tuning.de_esser.frequency, DE_ESSER_FREQUENCY_RANGE));
+            assert!(in_range(tuning.de_esser.threshold_db, DE_ESSER_THRESHOLD_RANGE));
+            assert!(in_range(tuning.de_esser.amount, 0.0..=1.0));
+            assert!(in_range(tuning.plosive.strength, 0.0..=1.0));
+            assert!(in_range(tuning.click.sensitivity, 0.0..=1.0));
+            assert!(in_range(tuning.boost.gain_db, BOOST_RANGE));
+        }
+        assert!(in_range(settings.earcon_volume, 0.0..=1.0));
+        assert!(in_range(settings.noise_print.amount, 0.0..=1.0));
+        assert!(in_range(settings.music.sensitivity, 0.0..=1.0));
+        assert!(in_range(settings.monitor_gain, 0.0..=1.0));
+        assert!(in_range(settings.mix_gain, 0.0..=1.0));
+        assert!(BLOCK_FRAMES.contains(&settings.block_frames));
+        assert!(LATENCY_CAPS_MS.contains(&settings.latency_cap_ms));
+        assert!(settings.startup_delay <= STARTUP_DELAY_MAX);
+        assert!((1..=120).contains(&settings.idle_pause.minutes));
+        assert!(settings.lifetime_stats.vad_sum.is_finite() && settings.lifetime_stats.vad_sum >= 0.0);
+    }
+
+    // A name that survives a line of its own: no line breaks, no surrounding whitespace
+    fn device_name() -> impl Strategy<Value = String> {
+        "[A-Za-z0-9][A-Za-z0-9 ()_-]{0,20}[A-Za-z0-9)]"
+    }
+
+    // Settings with every value in range, as save_settings would write them
+    fn saved_settings() -> impl Strategy<Value = String> {
+        (
+            (proptest::option::of(device_name()), proptest::option::of(device_name()), 0.0f32..=VAD_THRESHOLD_MAX, any::<bool>()),
+            (GATE_STEEPNESS_RANGE, GATE_HOLD_RANGE, GATE_ATTACK_RANGE, GATE_RELEASE_RANGE, GATE_PRE_ROLL_RANGE, GATE_ENERGY_FLOOR_RANGE),
+            (DE_ESSER_FREQUENCY_RANGE, 0.0f32..=1.0, BOOST_RANGE, 0.0f32..=1.0, 0.0f32..=1.0),
+            (proptest::sample::select(BLOCK_FRAMES.to_vec()), proptest::sample::select(LATENCY_CAPS_MS.to_vec()), 0..=STARTUP_DELAY_MAX),
+            proptest::collection::btree_map(device_name(), 0.0f32..=VAD_THRESHOLD_MAX, 0..3),
+        )
+            .prop_map(|(positional, gate, dsp, engine, profiles)| {
+                let mut settings = Settings::default();
+                (settings.input_device, settings.output_device, settings.vad_threshold, settings.start_with_windows) = positional;
+                let (steepness, hold_ms, attack_ms, release_ms, pre_roll_ms, energy_floor_db) = gate;
+                settings.gate = GateConfig { steepness, hold_ms, attack_ms, release_ms, pre_roll_ms, energy_floor_db, ..settings.gate };
+                (settings.de_esser.frequency, settings.de_esser.amount, settings.boost.gain_db, settings.mix_gain, settings.earcon_volume) = dsp;
+                (settings.block_frames, settings.latency_cap_ms, settings.startup_delay) = engine;
+                for (device, vad_threshold) in profiles {
+                    settings.device_profiles.insert(device, DeviceTuning { vad_threshold, ..DeviceTuning::default() });
+                }
+                settings_to_string(&settings)
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn random_bytes_never_panic(bytes in proptest::collection::vec(any::<u8>(), 0..512)) {
+            assert_valid(&parse_settings(&String::from_utf8_lossy(&bytes)));
+        }
+
+        #[test]
+        fn bad_values_are_rejected_or_clamped(
+            threshold in nasty_value(),
+            lines in proptest::collection::vec((proptest::sample::select(known_keys()), nasty_value()), 0..40),
+        ) {
+            let mut content = format!("mic\nspeakers\n{}\ntrue\nfalse", threshold);
+            for (key, value) in lines {
+                content.push_str(&format!("\n{}={}", key, value));
+            }
+            assert_valid(&parse_settings(&content));
+        }
+
+        #[test]
+        fn truncated_files_load(content in saved_settings(), cut in any::<prop::sample::Index>()) {
+            let boundaries: Vec<usize> = content.char_indices().map(|(i, _)| i).collect();
+            let at = boundaries.get(cut.index(boundaries.len().max(1))).copied().unwrap_or(0);
+            assert_valid(&parse_settings(&content[..at]));
+        }
+
+        #[test]
+        fn save_then_load_round_trips(saved in saved_settings()) {
+            let loaded = parse_settings(&saved);
+            assert_valid(&loaded);
+            prop_assert_eq!(settings_to_string(&loaded), saved);
+        }
+    }
+
+    #[test]
+    fn non_finite_and_negative_thresholds_fall_back() {
+        for (line, expected) in [("NaN", 0.1), ("inf", 0.1), ("-inf", 0.1), ("-0.3", 0.0), ("7", VAD_THRESHOLD_MAX), ("0.25", 0.25)] {
+            let settings = parse_settings(&format!("\n\n{}\ntrue", line));
+            assert_eq!(settings.vad_threshold, expected, "threshold line {:?}", line);
+        }
+    }
+}
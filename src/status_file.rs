@@ -0,0 +1,149 @@
+// Small JSON file with the mic state for stream overlays and scripts to poll. Written from
+// its own thread, since the UI hardly updates while hidden in the tray, and replaced
+// atomically (temp file + rename) so a reader never sees half a file.
+use silentstream_core::audio_engine::{AtomicF32, AudioEngine, EngineCounters};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+pub const STATUS_FILE: &str = "status.json";
+// At most this many writes per second, and only when something changed
+const WRITE_INTERVAL: Duration = Duration::from_millis(250);
+
+pub fn default_path(config_dir: &Path) -> PathBuf {
+    config_dir.join(STATUS_FILE)
+}
+
+// Input and output device, None while stopped
+type Devices = Option<(Arc<str>, Arc<str>)>;
+
+// Engine flags the writer reads itself; device names come from the UI through set_devices
+struct Sources {
+    running: Arc<AtomicBool>,
+    bypass: Arc<AtomicBool>,
+    system_muted: Arc<AtomicBool>,
+    idle: Arc<AtomicBool>,
+    level: Arc<AtomicF32>,
+    counters: Arc<EngineCounters>,
+}
+
+#[derive(Default)]
+pub struct StatusFile {
+    path: Option<PathBuf>,
+    stop_flag: Option<Arc<AtomicBool>>,
+    thread: Option<JoinHandle<()>>,
+    devices: Arc<Mutex<Devices>>,
+}
+
+impl StatusFile {
+    pub fn is_running(&self) -> bool {
+        self.thread.is_some()
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn start(&mut self, path: PathBuf, engine: &AudioEngine) {
+        self.stop();
+        let sources = Sources {
+            running: engine.is_running.clone(),
+            bypass: engine.bypass_flag(),
+            system_muted: engine.system_muted.clone(),
+            idle: engine.idle.clone(),
+            level: engine.current_volume.clone(),
+            counters: engine.counters.clone(),
+        };
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let stop = stop_flag.clone();
+        let devices = self.devices.clone();
+        let target = path.clone();
+        log::info!("Writing status file to {}", path.display());
+
+        let thread = thread::spawn(move || {
+            let mut written = String::new();
+            loop {
+                let stopping = stop.load(Ordering::SeqCst);
+                let text = if stopping {
+                    render(&sources, None, true)
+                } else {
+                    render(&sources, devices.lock().ok().and_then(|d| d.clone()), false)
+                };
+                if text != written {
+                    match write_atomically(&target, &text) {
+                        Ok(()) => written = text,
+                        Err(e) => log::debug!("Failed to write status file: {}", e),
+                    }
+                }
+                if stopping {
+                    return;
+                }
+                thread::park_timeout(WRITE_INTERVAL);
+            }
+        });
+
+        self.path = Some(path);
+        self.stop_flag = Some(stop_flag);
+        self.thread = Some(thread);
+    }
+
+    // Leaves a final "stopped" state in the file before returning
+    pub fn stop(&mut self) {
+        if let (Some(flag), Some(thread)) = (self.stop_flag.take(), self.thread.take()) {
+            flag.store(true, Ordering::SeqCst);
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+        self.path = None;
+    }
+
+    pub fn set_devices(&self, devices: Devices) {
+        if let Ok(mut current) = self.devices.lock() {
+            *current = devices;
+        }
+    }
+}
+
+fn render(sources: &Sources, devices: Devices, stopped: bool) -> String {
+    let running = !stopped && sources.running.load(Ordering::Relaxed) && devices.is_some();
+    let state = if !running {
+        "stopped"
+    } else if sources.system_muted.load(Ordering::Relaxed) {
+        "muted"
+    } else if sources.bypass.load(Ordering::Relaxed) {
+        "bypassed"
+    } else if sources.idle.load(Ordering::Relaxed) {
+        "idle"
+    } else if sources.counters.gate_open.load(Ordering::Relaxed) {
+        "live"
+    } else {
+        "gated"
+    };
+    let live = matches!(state, "live" | "bypassed");
+    // Rounded so a steady level doesn't rewrite the file for noise in the last digits
+    let level = if running { (sources.level.load() * 100.0).round() / 100.0 } else { 0.0 };
+    let (input, output) = match &devices {
+        Some((input, output)) if running => (Some(input.as_ref()), Some(output.as_ref())),
+        _ => (None, None),
+    };
+    let status = serde_json::json!({
+        "state": state,
+        "live": live,
+        "level": level,
+        "input_device": input,
+        "output_device": output,
+    });
+    status.to_string()
+}
+
+fn write_atomically(path: &Path, text: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let temp = path.with_extension("json.tmp");
+    fs::write(&temp, text)?;
+    fs::rename(&temp, path)
+}
@@ -0,0 +1,110 @@
+// Overlay badge on the taskbar button (ITaskbarList3), shown while the mic is muted or
+// processing has failed. Must be used from the UI thread, which owns the window. There is
+// no equivalent elsewhere, so the stand-in at the bottom does nothing.
+#[cfg(windows)]
+use windows::{
+    core::{HSTRING, PCWSTR},
+    Win32::Foundation::{HINSTANCE, HWND},
+    Win32::System::Com::{CoCreateInstance, CLSCTX_INPROC_SERVER},
+    Win32::UI::Shell::{ITaskbarList3, TaskbarList},
+    Win32::UI::WindowsAndMessaging::{CreateIcon, DestroyIcon, HICON},
+};
+
+#[cfg(windows)]
+const BADGE_SIZE: i32 = 16;
+
+#[cfg(windows)]
+pub struct TaskbarOverlay {
+    taskbar: Option<ITaskbarList3>,
+    badge: Option<HICON>,
+    // Description of the badge currently shown; None when cleared
+    shown: Option<String>,
+}
+
+#[cfg(windows)]
+impl TaskbarOverlay {
+    pub fn new() -> Self {
+        let taskbar = unsafe {
+            CoCreateInstance::<_, ITaskbarList3>(&TaskbarList, None, CLSCTX_INPROC_SERVER)
+                .and_then(|taskbar| taskbar.HrInit().map(|_| taskbar))
+        };
+        let taskbar = match taskbar {
+            Ok(taskbar) => Some(taskbar),
+            Err(e) => {
+                log::warn!("Taskbar overlay unavailable: {}", e);
+                None
+            }
+        };
+        Self { taskbar, badge: red_badge(), shown: None }
+    }
+
+    // `alert` is the badge's accessible description, None to clear it. Only calls into the
+    // shell when the state changes.
+    pub fn set(&mut self, hwnd: isize, alert: Option<&str>) {
+        if self.shown.as_deref() == alert {
+            return;
+        }
+        let (Some(taskbar), Some(badge)) = (&self.taskbar, self.badge) else { return };
+        let result = unsafe {
+            match alert {
+                Some(text) => taskbar.SetOverlayIcon(HWND(hwnd), badge, &HSTRING::from(text)),
+                None => taskbar.SetOverlayIcon(HWND(hwnd), HICON(0), PCWSTR::null()),
+            }
+        };
+        match result {
+            Ok(()) => self.shown = alert.map(str::to_string),
+            Err(e) => log::debug!("Failed to set taskbar overlay: {}", e),
+        }
+    }
+}
+
+#[cfg(windows)]
+impl Drop for TaskbarOverlay {
+    fn drop(&mut self) {
+        if let Some(badge) = self.badge.take() {
+            unsafe {
+                let _ = DestroyIcon(badge);
+            }
+        }
+    }
+}
+
+// Filled red circle, 32-bit BGRA with alpha
+#[cfg(windows)]
+fn red_badge() -> Option<HICON> {
+    let size = BADGE_SIZE as usize;
+    let radius = BADGE_SIZE as f32 / 2.0;
+    let mut color = Vec::with_capacity(size * size * 4);
+    for y in 0..size {
+        for x in 0..size {
+            let dx = x as f32 + 0.5 - radius;
+            let dy = y as f32 + 0.5 - radius;
+            // One pixel of anti-aliasing at the edge
+            let alpha = (radius - (dx * dx + dy * dy).sqrt()).clamp(0.0, 1.0);
+            let a = (alpha * 255.0) as u8;
+            // Premultiplied blue, green, red, alpha
+            color.extend_from_slice(&[(40.0 * alpha) as u8, (40.0 * alpha) as u8, (220.0 * alpha) as u8, a]);
+        }
+    }
+    // All-zero AND mask: transparency comes from the alpha channel
+    let mask = vec![0u8; size * size / 8];
+    match unsafe { CreateIcon(HINSTANCE(0), BADGE_SIZE, BADGE_SIZE, 1, 32, mask.as_ptr(), color.as_ptr()) } {
+        Ok(icon) => Some(icon),
+        Err(e) => {
+            log::warn!("Failed to create taskbar badge: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(not(windows))]
+pub struct TaskbarOverlay;
+
+#[cfg(not(windows))]
+impl TaskbarOverlay {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn set(&mut self, _hwnd: isize, _alert: Option<&str>) {}
+}
@@ -0,0 +1,105 @@
+// Appearance preferences and what Windows says about them. Other platforms report the
+// defaults (animations on, no high contrast) until the user picks otherwise.
+#[cfg(windows)]
+use windows_sys::Win32::{
+    Foundation::BOOL,
+    UI::Accessibility::{HCF_HIGHCONTRASTON, HIGHCONTRASTW},
+    UI::WindowsAndMessaging::{SystemParametersInfoW, SPI_GETCLIENTAREAANIMATION, SPI_GETHIGHCONTRAST},
+};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AnimationMode {
+    On,
+    // Volume-reactive orb only, no idle breathing
+    Reduced,
+    // No decorative painting at all
+    Off,
+}
+
+impl AnimationMode {
+    pub const ALL: [AnimationMode; 3] = [AnimationMode::On, AnimationMode::Reduced, AnimationMode::Off];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnimationMode::On => "on",
+            AnimationMode::Reduced => "reduced",
+            AnimationMode::Off => "off",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|m| m.as_str() == s)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AnimationMode::On => "On",
+            AnimationMode::Reduced => "Reduced",
+            AnimationMode::Off => "Off",
+        }
+    }
+
+    // Used until the user picks a mode: honour "Show animations in Windows" being off
+    pub fn system_default() -> Self {
+        if prefers_reduced_motion() { AnimationMode::Reduced } else { AnimationMode::On }
+    }
+}
+
+#[cfg(windows)]
+pub fn prefers_reduced_motion() -> bool {
+    let mut enabled: BOOL = 1;
+    let ok = unsafe { SystemParametersInfoW(SPI_GETCLIENTAREAANIMATION, 0, &mut enabled as *mut BOOL as *mut _, 0) };
+    ok != 0 && enabled == 0
+}
+
+#[cfg(not(windows))]
+pub fn prefers_reduced_motion() -> bool {
+    false
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Appearance {
+    Standard,
+    // Opaque black cards, white text, thick outlines, larger text and no background animation
+    HighContrast,
+}
+
+impl Appearance {
+    pub const ALL: [Appearance; 2] = [Appearance::Standard, Appearance::HighContrast];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Appearance::Standard => "standard",
+            Appearance::HighContrast => "high_contrast",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|a| a.as_str() == s)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Appearance::Standard => "Standard",
+            Appearance::HighContrast => "High contrast",
+        }
+    }
+
+    // Used until the user picks one: follow Windows' high contrast mode
+    pub fn system_default() -> Self {
+        if high_contrast_active() { Appearance::HighContrast } else { Appearance::Standard }
+    }
+}
+
+#[cfg(windows)]
+pub fn high_contrast_active() -> bool {
+    let mut info: HIGHCONTRASTW = unsafe { std::mem::zeroed() };
+    info.cbSize = std::mem::size_of::<HIGHCONTRASTW>() as u32;
+    let ok = unsafe { SystemParametersInfoW(SPI_GETHIGHCONTRAST, info.cbSize, &mut info as *mut HIGHCONTRASTW as *mut _, 0) };
+    ok != 0 && info.dwFlags & HCF_HIGHCONTRASTON != 0
+}
+
+#[cfg(not(windows))]
+pub fn high_contrast_active() -> bool {
+    false
+}
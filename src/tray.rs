@@ -0,0 +1,344 @@
+// Notification-area icon (the status bar on macOS) and its menu. On other platforms new()
+// returns None and the window minimizes normally instead of hiding to the tray.
+use silentstream_core::dsp::VadPreset;
+use eframe::egui;
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Whether this platform has a tray at all; when it does and new() still fails, that's worth telling the user
+pub const SUPPORTED: bool = cfg!(any(windows, target_os = "macos"));
+
+// Menu entry that swaps between the favorite device pairs
+#[cfg(any(windows, target_os = "macos"))]
+const SWAP_ID: &str = "swap_devices";
+#[cfg(any(windows, target_os = "macos"))]
+const EXIT_ID: &str = "exit";
+// Followed by VadPreset::as_str()
+#[cfg(any(windows, target_os = "macos"))]
+const PRESET_ID_PREFIX: &str = "preset_";
+
+// The alert icon blinks this many times when an error first needs attention, then stays
+pub const ALERT_FLASHES: u32 = 3;
+// How long each half of a blink lasts
+pub const ALERT_FLASH_PERIOD: Duration = Duration::from_millis(500);
+
+// Whether the tray shows the alert icon. An error that turns up while the window is hidden
+// raises it; restoring the window acknowledges it and resolving the error clears it. The
+// same error isn't raised again once acknowledged.
+#[derive(Default)]
+pub struct TrayAlert {
+    // Summary of the unrecovered error, if any
+    error: Option<String>,
+    acknowledged: bool,
+    raised_at: Option<Instant>,
+}
+
+impl TrayAlert {
+    // Returns true when the alert has just been raised
+    pub fn update(&mut self, error: Option<&str>, window_visible: bool, now: Instant) -> bool {
+        let Some(error) = error else {
+            *self = Self::default();
+            return false;
+        };
+        if self.error.as_deref() != Some(error) {
+            self.error = Some(error.to_string());
+            self.acknowledged = window_visible;
+            self.raised_at = (!window_visible).then_some(now);
+            return !window_visible;
+        }
+        if window_visible && !self.acknowledged {
+            self.acknowledged = true;
+            self.raised_at = None;
+        }
+        false
+    }
+
+    pub fn active(&self) -> bool {
+        self.error.is_some() && !self.acknowledged
+    }
+
+    // Alternates between the two icons for the first few periods, then holds the alert icon
+    pub fn shows_alert_icon(&self, now: Instant) -> bool {
+        if !self.active() {
+            return false;
+        }
+        let Some(raised_at) = self.raised_at else { return true };
+        let half_periods = (now.saturating_duration_since(raised_at).as_millis() / ALERT_FLASH_PERIOD.as_millis()) as u32;
+        half_periods >= ALERT_FLASHES * 2 || half_periods.is_multiple_of(2)
+    }
+}
+
+// The app icon with a red dot in the bottom-right corner, for the alert state
+#[cfg(any(windows, target_os = "macos"))]
+fn alert_icon_rgba(rgba: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let mut out = rgba.to_vec();
+    let radius = width.min(height) as f32 * 0.22;
+    let (cx, cy) = (width as f32 - radius - 1.0, height as f32 - radius - 1.0);
+    for y in 0..height {
+        for x in 0..width {
+            let distance = ((x as f32 + 0.5 - cx).powi(2) + (y as f32 + 0.5 - cy).powi(2)).sqrt();
+            // One pixel of antialiasing at the edge
+            let coverage = (radius + 0.5 - distance).clamp(0.0, 1.0);
+            if coverage > 0.0 {
+                let i = ((y * width + x) * 4) as usize;
+                for (channel, target) in out[i..i + 3].iter_mut().zip([237.0, 66.0, 69.0]) {
+                    *channel = (*channel as f32 * (1.0 - coverage) + target * coverage).round() as u8;
+                }
+                out[i + 3] = out[i + 3].max((coverage * 255.0).round() as u8);
+            }
+        }
+    }
+    out
+}
+
+// What the tray listener thread is asked to do with the window
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum WindowRequest {
+    // Icon click or "Open": bring it back from the tray
+    #[cfg_attr(not(any(windows, target_os = "macos")), allow(dead_code))]
+    Show,
+    // The global hotkey: bring it back, or hide it to the tray if it's showing
+    Toggle,
+}
+
+// Menu choices waiting for the UI, which picks them up on its next frame
+#[derive(Default)]
+pub struct TrayRequests {
+    pub swap: AtomicBool,
+    pub exit: AtomicBool,
+    pub preset: Mutex<Option<VadPreset>>,
+}
+
+#[cfg(any(windows, target_os = "macos"))]
+pub struct Tray {
+    // Kept alive here; TrayIcon is not Send so it must stay on the UI thread
+    icon: tray_icon::TrayIcon,
+    normal_icon: tray_icon::Icon,
+    // None if the alert variant couldn't be built; the tooltip still carries the error
+    alert_icon: Option<tray_icon::Icon>,
+    swap_item: tray_icon::menu::MenuItem,
+    preset_items: Vec<(VadPreset, tray_icon::menu::CheckMenuItem)>,
+}
+
+#[cfg(any(windows, target_os = "macos"))]
+impl Tray {
+    pub fn new(rgba: Vec<u8>, width: u32, height: u32) -> Option<Self> {
+        use tray_icon::menu::{CheckMenuItem, IsMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu};
+
+        let menu = Menu::new();
+        let open_item = MenuItem::new("Open SilentStream", true, None);
+        let swap_item = MenuItem::with_id(SWAP_ID, "Swap favorite devices", false, None);
+        let preset_items: Vec<(VadPreset, CheckMenuItem)> = VadPreset::ALL
+            .iter()
+            .map(|&preset| {
+                let id = format!("{}{}", PRESET_ID_PREFIX, preset.as_str());
+                (preset, CheckMenuItem::with_id(id, preset.label(), true, false, None))
+            })
+            .collect();
+        let preset_refs: Vec<&dyn IsMenuItem> = preset_items.iter().map(|(_, item)| item as &dyn IsMenuItem).collect();
+        let exit_item = MenuItem::with_id(EXIT_ID, "Exit", true, None);
+        let built = Submenu::with_items("Sensitivity", true, &preset_refs).and_then(|presets| {
+            menu.append_items(&[&open_item, &swap_item, &presets, &PredefinedMenuItem::separator(), &exit_item])
+        });
+        if let Err(e) = built {
+            log::warn!("Failed to build tray menu: {}", e);
+        }
+
+        let alert_icon = tray_icon::Icon::from_rgba(alert_icon_rgba(&rgba, width, height), width, height)
+            .map_err(|e| log::warn!("Invalid tray alert icon: {}", e))
+            .ok();
+        let normal_icon = match tray_icon::Icon::from_rgba(rgba, width, height) {
+            Ok(icon) => icon,
+            Err(e) => {
+                log::error!("Invalid tray icon image: {}", e);
+                return None;
+            }
+        };
+        match tray_icon::TrayIconBuilder::new()
+            .with_menu(Box::new(menu))
+            .with_tooltip("SilentStream")
+            .with_icon(normal_icon.clone())
+            .build()
+        {
+            Ok(icon) => Some(Self { icon, normal_icon, alert_icon, swap_item, preset_items }),
+            Err(e) => {
+                log::error!("Failed to create tray icon: {}", e);
+                None
+            }
+        }
+    }
+
+    pub fn set_tooltip(&self, tooltip: &str) {
+        let _ = self.icon.set_tooltip(Some(tooltip));
+    }
+
+    pub fn set_swap_enabled(&self, enabled: bool) {
+        self.swap_item.set_enabled(enabled);
+    }
+
+    pub fn set_alert(&self, alert: bool) {
+        let icon = match (&self.alert_icon, alert) {
+            (Some(alert_icon), true) => alert_icon,
+            _ => &self.normal_icon,
+        };
+        if let Err(e) = self.icon.set_icon(Some(icon.clone())) {
+            log::debug!("Failed to swap tray icon: {}", e);
+        }
+    }
+
+    // Ticks the active preset; none while the values are custom
+    pub fn set_preset(&self, active: Option<VadPreset>) {
+        for (preset, item) in &self.preset_items {
+            item.set_checked(Some(*preset) == active);
+        }
+    }
+}
+
+// Icon clicks and "Open" send to `open`; the other entries are left in `requests` and wake the UI
+#[cfg(any(windows, target_os = "macos"))]
+pub fn listen(open: Sender<WindowRequest>, requests: Arc<TrayRequests>, ctx: &egui::Context) {
+    use std::sync::atomic::Ordering;
+    use tray_icon::menu::MenuEvent;
+    use tray_icon::TrayIconEvent;
+
+    let menu_open = open.clone();
+    let ctx = ctx.clone();
+    MenuEvent::set_event_handler(Some(move |event: MenuEvent| {
+        if event.id == SWAP_ID {
+            requests.swap.store(true, Ordering::SeqCst);
+        } else if event.id == EXIT_ID {
+            requests.exit.store(true, Ordering::SeqCst);
+        } else if let Some(preset) = event.id.as_ref().strip_prefix(PRESET_ID_PREFIX).and_then(VadPreset::from_str) {
+            if let Ok(mut p) = requests.preset.lock() {
+                *p = Some(preset);
+            }
+        } else {
+            let _ = menu_open.send(WindowRequest::Show);
+            return;
+        }
+        ctx.request_repaint();
+    }));
+    TrayIconEvent::set_event_handler(Some(move |event: TrayIconEvent| {
+        if let TrayIconEvent::Click { .. } = event {
+            let _ = open.send(WindowRequest::Show);
+        }
+    }));
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+pub enum Tray {}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+impl Tray {
+    pub fn new(_rgba: Vec<u8>, _width: u32, _height: u32) -> Option<Self> {
+        log::info!("No tray icon on this platform; hiding minimizes the window instead");
+        None
+    }
+
+    pub fn set_tooltip(&self, _tooltip: &str) {
+        match *self {}
+    }
+
+    pub fn set_swap_enabled(&self, _enabled: bool) {
+        match *self {}
+    }
+
+    pub fn set_alert(&self, _alert: bool) {
+        match *self {}
+    }
+
+    pub fn set_preset(&self, _active: Option<VadPreset>) {
+        match *self {}
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+pub fn listen(_open: Sender<WindowRequest>, _requests: Arc<TrayRequests>, _ctx: &egui::Context) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ERROR: &str = "Device not found";
+
+    fn after(start: Instant, half_periods: u32) -> Instant {
+        start + ALERT_FLASH_PERIOD * half_periods
+    }
+
+    #[test]
+    fn error_while_hidden_raises_the_alert() {
+        let now = Instant::now();
+        let mut alert = TrayAlert::default();
+        assert!(alert.update(Some(ERROR), false, now));
+        assert!(alert.active());
+        assert!(alert.shows_alert_icon(now));
+        // Raised once, not on every update
+        assert!(!alert.update(Some(ERROR), false, now));
+    }
+
+    #[test]
+    fn error_while_visible_needs_no_alert() {
+        let now = Instant::now();
+        let mut alert = TrayAlert::default();
+        assert!(!alert.update(Some(ERROR), true, now));
+        assert!(!alert.active());
+        assert!(!alert.update(Some(ERROR), false, now));
+        assert!(!alert.shows_alert_icon(now));
+    }
+
+    #[test]
+    fn showing_the_window_acknowledges() {
+        let now = Instant::now();
+        let mut alert = TrayAlert::default();
+        alert.update(Some(ERROR), false, now);
+        assert!(!alert.update(Some(ERROR), true, now));
+        assert!(!alert.active());
+        assert!(!alert.shows_alert_icon(now));
+    }
+
+    #[test]
+    fn acknowledged_error_is_not_raised_again() {
+        let now = Instant::now();
+        let mut alert = TrayAlert::default();
+        alert.update(Some(ERROR), false, now);
+        alert.update(Some(ERROR), true, now);
+        // Hidden again with the same error still there
+        assert!(!alert.update(Some(ERROR), false, after(now, 10)));
+        assert!(!alert.active());
+        // A different error is news
+        assert!(alert.update(Some("Device in use"), false, after(now, 11)));
+        assert!(alert.active());
+    }
+
+    #[test]
+    fn resolving_the_error_clears_the_alert() {
+        let now = Instant::now();
+        let mut alert = TrayAlert::default();
+        alert.update(Some(ERROR), false, now);
+        assert!(!alert.update(None, false, now));
+        assert!(!alert.active());
+        assert!(!alert.shows_alert_icon(now));
+        // The same error coming back later is raised again
+        assert!(alert.update(Some(ERROR), false, after(now, 1)));
+    }
+
+    #[test]
+    fn alert_icon_flashes_then_holds() {
+        let raised = Instant::now();
+        let mut alert = TrayAlert::default();
+        alert.update(Some(ERROR), false, raised);
+        for half_period in 0..ALERT_FLASHES * 2 {
+            assert_eq!(alert.shows_alert_icon(after(raised, half_period)), half_period % 2 == 0, "half period {}", half_period);
+        }
+        // The last "off" half ends right at the boundary, where the icon starts holding
+        let boundary = after(raised, ALERT_FLASHES * 2);
+        assert!(!alert.shows_alert_icon(boundary - Duration::from_millis(1)));
+        for half_period in ALERT_FLASHES * 2..ALERT_FLASHES * 2 + 6 {
+            assert!(alert.shows_alert_icon(after(raised, half_period)), "half period {}", half_period);
+        }
+        // A clock that went backwards counts as just raised
+        assert!(alert.shows_alert_icon(raised - Duration::from_secs(1)));
+    }
+}
@@ -0,0 +1,137 @@
+// Opt-in check for a newer GitHub release. Only ever notifies; nothing is downloaded.
+use eframe::egui;
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/yyyutakaaa/SilentStream/releases/latest";
+const CHECK_INTERVAL_SECS: i64 = 24 * 60 * 60;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Clone)]
+pub struct Release {
+    pub version: String,
+    pub url: String,
+}
+
+pub enum UpdateEvent {
+    // A check finished (successfully or not) at this Unix time
+    Checked(i64),
+    Available(Release),
+}
+
+pub struct UpdateChecker {
+    stop_flag: Option<Arc<AtomicBool>>,
+    events: Option<Receiver<UpdateEvent>>,
+}
+
+impl UpdateChecker {
+    pub fn new() -> Self {
+        Self { stop_flag: None, events: None }
+    }
+
+    // `last_check` is the persisted Unix time of the previous check (0 = never)
+    pub fn start(&mut self, last_check: i64, ctx: &egui::Context) {
+        self.stop();
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = channel();
+        let stop = stop_flag.clone();
+        let ctx = ctx.clone();
+
+        thread::spawn(move || check_loop(last_check, stop, tx, ctx));
+
+        self.stop_flag = Some(stop_flag);
+        self.events = Some(rx);
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(flag) = self.stop_flag.take() {
+            flag.store(true, Ordering::SeqCst);
+        }
+        self.events = None;
+    }
+
+    pub fn poll(&self) -> Vec<UpdateEvent> {
+        self.events.as_ref().map(|rx| rx.try_iter().collect()).unwrap_or_default()
+    }
+}
+
+impl Drop for UpdateChecker {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn check_loop(mut last_check: i64, stop: Arc<AtomicBool>, tx: Sender<UpdateEvent>, ctx: egui::Context) {
+    loop {
+        let now = chrono::Utc::now().timestamp();
+        if now - last_check >= CHECK_INTERVAL_SECS {
+            let release = fetch_latest_release();
+            if stop.load(Ordering::SeqCst) {
+                return;
+            }
+            last_check = now;
+            let _ = tx.send(UpdateEvent::Checked(now));
+            if let Some(release) = release.filter(|r| is_newer(&r.version, env!("CARGO_PKG_VERSION"))) {
+                log::info!("Update available: {}", release.version);
+                let _ = tx.send(UpdateEvent::Available(release));
+            }
+            ctx.request_repaint();
+        }
+
+        // Wake up now and then so a long-running session still checks once a day
+        for _ in 0..60 {
+            if stop.load(Ordering::SeqCst) {
+                return;
+            }
+            thread::sleep(Duration::from_secs(1));
+        }
+    }
+}
+
+// Any failure (offline, rate limited, unexpected JSON) is silently treated as "nothing new"
+fn fetch_latest_release() -> Option<Release> {
+    // System TLS (SChannel) so no certificate bundle ships with the app
+    let tls = native_tls::TlsConnector::new().ok()?;
+    let agent = ureq::AgentBuilder::new().tls_connector(Arc::new(tls)).timeout(REQUEST_TIMEOUT).build();
+    let body = agent
+        .get(LATEST_RELEASE_URL)
+        .set("User-Agent", concat!("SilentStream/", env!("CARGO_PKG_VERSION")))
+        .set("Accept", "application/vnd.github+json")
+        .call()
+        .map_err(|e| log::debug!("Update check failed: {}", e))
+        .ok()?
+        .into_string()
+        .ok()?;
+    let json: Value = serde_json::from_str(&body).ok()?;
+    if json["draft"].as_bool() == Some(true) || json["prerelease"].as_bool() == Some(true) {
+        return None;
+    }
+    Some(Release {
+        version: json["tag_name"].as_str()?.to_string(),
+        url: json["html_url"].as_str()?.to_string(),
+    })
+}
+
+// "v1.2.3" -> [1, 2, 3]; anything after a '-' or '+' is ignored
+fn version_parts(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches(['v', 'V'])
+        .split(['-', '+'])
+        .next()
+        .unwrap_or("")
+        .split('.')
+        .map(|p| p.parse().unwrap_or(0))
+        .collect()
+}
+
+fn is_newer(candidate: &str, current: &str) -> bool {
+    let (mut a, mut b) = (version_parts(candidate), version_parts(current));
+    let len = a.len().max(b.len());
+    a.resize(len, 0);
+    b.resize(len, 0);
+    a > b
+}
@@ -0,0 +1,115 @@
+// How long processing has been running and why the engine was restarted along the way, for
+// the advanced settings and the diagnostic report. Kept in memory only; each restart is also
+// logged. Recovering from a device disconnect or restarting for a settings change doesn't
+// reset the uptime, stopping processing does.
+use chrono::{DateTime, Local};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// Restarts listed in the report; the count covers all of them
+const MAX_RECENT_RESTARTS: usize = 20;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RestartReason {
+    // Started again once a device that went missing came back
+    DeviceDisconnect,
+    // A different device, device pair or input channel was picked
+    DeviceChange,
+    // A setting that only applies when the streams are opened
+    SettingsChange,
+    // The user asked for it
+    Manual,
+}
+
+impl RestartReason {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RestartReason::DeviceDisconnect => "device disconnect",
+            RestartReason::DeviceChange => "device change",
+            RestartReason::SettingsChange => "settings change",
+            RestartReason::Manual => "manual restart",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Restart {
+    pub reason: RestartReason,
+    pub at: DateTime<Local>,
+}
+
+#[derive(Clone, Default)]
+pub struct Uptime {
+    // None while processing is stopped
+    started: Option<Instant>,
+    total_restarts: u32,
+    // Oldest first
+    recent: VecDeque<Restart>,
+}
+
+impl Uptime {
+    // Keeps the original start time if processing is already running
+    pub fn start(&mut self) {
+        self.started.get_or_insert_with(Instant::now);
+    }
+
+    pub fn stop(&mut self) {
+        self.started = None;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.started.is_some()
+    }
+
+    pub fn restarted(&mut self, reason: RestartReason) {
+        log::info!("Engine restarted ({})", reason.label());
+        self.start();
+        self.total_restarts += 1;
+        if self.recent.len() == MAX_RECENT_RESTARTS {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(Restart { reason, at: Local::now() });
+    }
+
+    pub fn uptime(&self) -> Option<Duration> {
+        self.started.map(|s| s.elapsed())
+    }
+
+    pub fn total_restarts(&self) -> u32 {
+        self.total_restarts
+    }
+
+    pub fn recent(&self) -> impl DoubleEndedIterator<Item = &Restart> {
+        self.recent.iter()
+    }
+
+    // "Uptime: 6 h 12 m, engine restarts: 3 (last: device disconnect at 14:32)"
+    pub fn summary(&self) -> String {
+        let uptime = match self.uptime() {
+            Some(d) => format!("Uptime: {}", format_uptime(d)),
+            None => "Not running".to_string(),
+        };
+        match self.recent.back() {
+            Some(last) => format!(
+                "{}, engine restarts: {} (last: {} at {})",
+                uptime,
+                self.total_restarts,
+                last.reason.label(),
+                last.at.format("%H:%M")
+            ),
+            None => format!("{}, engine restarts: 0", uptime),
+        }
+    }
+}
+
+fn format_uptime(duration: Duration) -> String {
+    let total = duration.as_secs();
+    let (d, h, m) = (total / 86400, (total / 3600) % 24, (total / 60) % 60);
+    if d > 0 {
+        format!("{} d {} h", d, h)
+    } else if h > 0 {
+        format!("{} h {} m", h, m)
+    } else {
+        format!("{} m", m)
+    }
+}